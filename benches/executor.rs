@@ -0,0 +1,15 @@
+use cfrs::bench::{measure, workloads};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+fn executor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("executor");
+    group.sample_size(10);
+    for workload in workloads() {
+        group.throughput(Throughput::Elements(measure(&workload).steps));
+        group.bench_function(workload.name, |b| b.iter(|| measure(&workload)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, executor);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+fn main() {
+    // Node.js addons resolve the N-API symbols at load time.
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -0,0 +1,112 @@
+//! A Cloudflare Worker that renders CFRS programs straight from a URL, e.g.
+//! `https://<worker>/?program=[[[[[[[[[[F]]]]R]]RR]]RRCC]]&format=png`.
+//!
+//! Supported query parameters are `program`, `width`, `height`, `background`, `interval`, and
+//! `format` (`png` or `gif`). Renders are deterministic, so responses are cached forever.
+//!
+//! Build it for `wasm32-unknown-unknown` without the default features, which pull in the full
+//! `image` stack:
+//!
+//! ```sh
+//! cargo build --release --example worker --target wasm32-unknown-unknown \
+//!     --no-default-features --features worker
+//! ```
+
+use cfrs::encode::{encode_png, write_gif, GifOptions};
+use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+use std::collections::HashMap;
+use std::str::FromStr;
+use worker::{event, Context, Env, Request, Response, Result};
+
+const MAX_STEPS: u64 = 5_000_000;
+const MAX_SIZE: u32 = 1024;
+const MAX_FRAMES: usize = 300;
+
+#[event(fetch)]
+async fn fetch(req: Request, _env: Env, _ctx: Context) -> Result<Response> {
+    let url = req.url()?;
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    let Some(program) = params.get("program") else {
+        return Response::error("Missing `program` query parameter", 400);
+    };
+    let width = params
+        .get("width")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(256);
+    let height = params
+        .get("height")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(256);
+    let interval = params
+        .get("interval")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(100);
+    let background = match params.get("background") {
+        Some(s) => match CFRColor::from_str(s) {
+            Ok(color) => color,
+            Err(e) => return Response::error(e, 400),
+        },
+        None => CFRColor::Black,
+    };
+    let animation = params.get("format").map(String::as_str) == Some("gif");
+
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return Response::error(format!("Canvas must be 1 to {} pixels wide", MAX_SIZE), 400);
+    }
+    if interval < 20 {
+        return Response::error("Interval must be at least 20 ms", 400);
+    }
+
+    let mut buffer = CFRBuffer::new(width, height);
+    buffer.data.iter_mut().for_each(|c| *c = background);
+
+    let mut steps = 0;
+    let mut time = 0;
+    let mut frames = Vec::new();
+
+    let mut executor = CommandExecutor::new(program.clone(), &mut buffer);
+    while let Ok((sleep, buf)) = executor.step() {
+        steps += 1;
+        if steps > MAX_STEPS {
+            return Response::error(format!("Program exceeds {} steps", MAX_STEPS), 422);
+        }
+        if sleep && animation {
+            time += 20;
+            if time >= interval {
+                time -= interval;
+                if frames.len() >= MAX_FRAMES {
+                    return Response::error(
+                        format!("Animation exceeds {} frames", MAX_FRAMES),
+                        422,
+                    );
+                }
+                frames.push(buf.clone());
+            }
+        }
+    }
+
+    let (bytes, content_type) = if animation {
+        let options = GifOptions {
+            delay: interval,
+            ..GifOptions::default()
+        };
+        let mut bytes = Vec::new();
+        if let Err(e) = write_gif(&mut bytes, &frames, &options) {
+            return Response::error(e.to_string(), 500);
+        }
+        (bytes, "image/gif")
+    } else {
+        match encode_png(&buffer) {
+            Ok(bytes) => (bytes, "image/png"),
+            Err(e) => return Response::error(e.to_string(), 500),
+        }
+    };
+
+    let mut response = Response::from_bytes(bytes)?;
+    response.headers_mut().set("content-type", content_type)?;
+    response
+        .headers_mut()
+        .set("cache-control", "public, max-age=31536000, immutable")?;
+    Ok(response)
+}
@@ -0,0 +1,65 @@
+#ifndef CFRS_H
+#define CFRS_H
+
+/* Generated with cbindgen. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Returned by `cfrs_step` when a command was executed.
+#define CFRS_STEP_OK 0
+
+// Returned by `cfrs_step` when the executed command was `S` (sleep).
+#define CFRS_STEP_SLEEP 1
+
+// Returned by `cfrs_step` when there are no more commands to execute.
+#define CFRS_STEP_END -1
+
+// Returned by `cfrs_step` when the program is invalid (e.g. an unmatched `]`).
+#define CFRS_STEP_ERROR -2
+
+// An opaque interpreter instance that owns its program and canvas.
+typedef struct CfrsInstance CfrsInstance;
+
+// Creates a new interpreter instance for a NUL-terminated UTF-8 program.
+//
+// Returns a null pointer if `program` is null or not valid UTF-8, if either dimension is zero,
+// or if the canvas has more than `u32::MAX` pixels.
+// The instance must be released with `cfrs_free`.
+//
+// # Safety
+//
+// `program` must be null or point to a valid NUL-terminated string.
+CfrsInstance *cfrs_new(const char *program, uint32_t width, uint32_t height);
+
+// Executes the next command.
+//
+// Returns `CFRS_STEP_OK`, `CFRS_STEP_SLEEP`, `CFRS_STEP_END` or `CFRS_STEP_ERROR`.
+//
+// # Safety
+//
+// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+int cfrs_step(CfrsInstance *instance);
+
+// Returns a pointer to the canvas pixels, `width * height` bytes in row-major order.
+//
+// Each byte is a color index following the painter's color cycle:
+// 0 = white, 1 = black, 2 = blue, 3 = green, 4 = cyan, 5 = red, 6 = magenta, 7 = yellow.
+// Builds with the `transparent` feature also use 8 = transparent.
+// The pointer stays valid until `instance` is freed.
+//
+// # Safety
+//
+// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+const uint8_t *cfrs_buffer_ptr(const CfrsInstance *instance);
+
+// Releases an instance created by `cfrs_new`. Passing a null pointer is a no-op.
+//
+// # Safety
+//
+// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+void cfrs_free(CfrsInstance *instance);
+
+#endif /* CFRS_H */
@@ -0,0 +1,315 @@
+use crate::enums::CFRDirection;
+use crate::error::CFRError;
+use crate::painter::CFRPainter;
+use crate::validator::bracket_pairs;
+
+/// The result of `analyze`.
+///
+/// Every CFRS[] program terminates, because every block runs exactly twice, so the counts
+/// are exact rather than estimates. They are `None` when they don't fit in a `u64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// Deepest nesting of blocks; 0 when the program has no blocks.
+    pub max_depth: usize,
+    /// Number of matched blocks.
+    pub blocks: usize,
+    /// Number of steps until the program finishes or fails, like `CommandExecutor::step`
+    /// counts them.
+    pub steps: Option<u64>,
+    pub sleeps: Option<u64>,
+    /// Number of steps before the first `S`, or `None` if the program never sleeps.
+    pub first_sleep: Option<u64>,
+    /// Why the program fails, if it does.
+    pub error: Option<CFRError>,
+    /// Where the painter draws, relative to its start, ignoring wrapping.
+    pub bounds: Option<Bounds>,
+    /// How many 45° rotations the painter ends up turned from its start, from 0 to 7.
+    pub rotation: usize,
+    /// How many colors the painter ends up ahead of its start in the color cycle, from 0 to 7.
+    pub color_shift: usize,
+}
+
+/// The smallest rectangle around every drawn pixel, as offsets from the start of the painter.
+///
+/// Offsets saturate at the limits of `i64`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i64,
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+}
+
+impl Analysis {
+    /// Returns whether the program draws on a `width`×`height` canvas without ever wrapping
+    /// around its edges.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        let Some(bounds) = self.bounds else {
+            return true;
+        };
+        let x = (width.saturating_sub(1) / 2) as i64;
+        let y = (height.saturating_sub(1) / 2) as i64;
+        x.saturating_add(bounds.left) >= 0
+            && y.saturating_add(bounds.top) >= 0
+            && x.saturating_add(bounds.right) < width as i64
+            && y.saturating_add(bounds.bottom) < height as i64
+    }
+}
+
+/// Analyzes a program without running it.
+///
+/// This takes time linear in the length of the program, even for programs that would run
+/// for billions of steps, which makes it suitable for filtering submissions.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::analyze;
+///
+/// let analysis = analyze("[[[[[[[[[[FS]]]]R]]RR]]RRCC]]");
+/// assert_eq!(analysis.max_depth, 10);
+/// assert_eq!(analysis.sleeps, Some(1024));
+/// assert_eq!(analysis.first_sleep, Some(11));
+/// assert!(analysis.error.is_none());
+/// assert!(analysis.fits(256, 256));
+/// assert!(!analysis.fits(64, 64));
+/// ```
+pub fn analyze(program: &str) -> Analysis {
+    analyze_with(program, false)
+}
+
+/// Like `analyze`, with `#` commenting out the rest of its line if `comments` is set, as
+/// `CommandExecutor::set_comments` does.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::analysis::analyze_with;
+///
+/// assert_eq!(analyze_with("F # FS", false).sleeps, Some(1));
+/// assert_eq!(analyze_with("F # FS", true).sleeps, Some(0));
+/// ```
+pub fn analyze_with(program: &str, comments: bool) -> Analysis {
+    let pairs = bracket_pairs(program, comments);
+    let bytes = program.as_bytes();
+
+    // One sequence per open block, innermost last.
+    let mut open = vec![Summary::identity()];
+    let mut max_depth = 0;
+    let mut error = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' if pairs.binary_search_by_key(&i, |p| p.0).is_ok() => {
+                open.push(Summary::identity());
+                max_depth = max_depth.max(open.len() - 1);
+            }
+            b']' if open.len() > 1 => {
+                let body = open.pop().unwrap();
+                let block = Summary::steps(1)
+                    .then(&body)
+                    .then(&body)
+                    .then(&Summary::steps(2));
+                let parent = open.last_mut().unwrap();
+                *parent = parent.then(&block);
+            }
+            b']' => {
+                error = Some(CFRError::UnmatchedBlockEnd { index: i });
+                break;
+            }
+            byte => {
+                let parent = open.last_mut().unwrap();
+                *parent = parent.then(&Summary::command(byte));
+                if comments && byte == b'#' {
+                    // The comment and the end of its line are a single step.
+                    i = program[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                }
+            }
+        }
+        i += 1;
+    }
+    let summary = open.swap_remove(0);
+
+    Analysis {
+        max_depth,
+        blocks: pairs.len(),
+        steps: summary.steps,
+        sleeps: summary.sleeps,
+        first_sleep: summary.first_sleep,
+        error,
+        bounds: summary.effects[CFRDirection::Up as usize].bounds,
+        rotation: summary.effects[CFRDirection::Up as usize].rotation,
+        color_shift: summary.colors,
+    }
+}
+
+/// What a sequence of commands does when it starts in a given direction.
+#[derive(Debug, Copy, Clone)]
+struct Effect {
+    /// Number of 45° rotations.
+    rotation: usize,
+    dx: i64,
+    dy: i64,
+    bounds: Option<Bounds>,
+}
+
+/// A sequence of commands, with its effect for every starting direction.
+#[derive(Debug, Clone)]
+struct Summary {
+    steps: Option<u64>,
+    sleeps: Option<u64>,
+    first_sleep: Option<u64>,
+    /// Number of color changes, modulo the length of the color cycle.
+    colors: usize,
+    /// Indexed by `CFRDirection as usize`.
+    effects: [Effect; 8],
+}
+
+impl Summary {
+    fn identity() -> Self {
+        Self::steps(0)
+    }
+
+    /// Commands that take `steps` steps and have no other effect.
+    fn steps(steps: u64) -> Self {
+        Self {
+            steps: Some(steps),
+            sleeps: Some(0),
+            first_sleep: None,
+            colors: 0,
+            effects: [Effect {
+                rotation: 0,
+                dx: 0,
+                dy: 0,
+                bounds: None,
+            }; 8],
+        }
+    }
+
+    fn command(byte: u8) -> Self {
+        let mut summary = Self::steps(1);
+        match byte {
+            b'F' => {
+                for (effect, direction) in summary.effects.iter_mut().zip(CFRDirection::ALL) {
+                    let mut painter = CFRPainter::new();
+                    painter.direction = direction;
+                    (painter.x, painter.y) = (1, 1);
+                    painter
+                        .move_forward(3, 3)
+                        .expect("The painter starts on the canvas");
+                    (effect.dx, effect.dy) = (painter.x as i64 - 1, painter.y as i64 - 1);
+                    effect.bounds = Some(Bounds {
+                        left: effect.dx,
+                        top: effect.dy,
+                        right: effect.dx,
+                        bottom: effect.dy,
+                    });
+                }
+            }
+            b'C' => summary.colors = 1,
+            b'R' => summary.effects.iter_mut().for_each(|e| e.rotation = 1),
+            b'S' => {
+                summary.sleeps = Some(1);
+                summary.first_sleep = Some(0);
+            }
+            _ => {}
+        }
+        summary
+    }
+
+    /// Returns the summary of `self` followed by `next`.
+    fn then(&self, next: &Summary) -> Summary {
+        let effects = std::array::from_fn(|direction| {
+            let first = self.effects[direction];
+            let second = next.effects[(direction + first.rotation) % 8];
+            let shifted = second.bounds.map(|b| Bounds {
+                left: b.left.saturating_add(first.dx),
+                top: b.top.saturating_add(first.dy),
+                right: b.right.saturating_add(first.dx),
+                bottom: b.bottom.saturating_add(first.dy),
+            });
+            Effect {
+                rotation: (first.rotation + second.rotation) % 8,
+                dx: first.dx.saturating_add(second.dx),
+                dy: first.dy.saturating_add(second.dy),
+                bounds: match (first.bounds, shifted) {
+                    (Some(a), Some(b)) => Some(Bounds {
+                        left: a.left.min(b.left),
+                        top: a.top.min(b.top),
+                        right: a.right.max(b.right),
+                        bottom: a.bottom.max(b.bottom),
+                    }),
+                    (a, b) => a.or(b),
+                },
+            }
+        });
+        Summary {
+            steps: self
+                .steps
+                .zip(next.steps)
+                .and_then(|(a, b)| a.checked_add(b)),
+            sleeps: self
+                .sleeps
+                .zip(next.sleeps)
+                .and_then(|(a, b)| a.checked_add(b)),
+            first_sleep: self.first_sleep.or_else(|| {
+                let before = self.steps.unwrap_or(u64::MAX);
+                next.first_sleep.map(|n| before.saturating_add(n))
+            }),
+            colors: (self.colors + next.colors) % 8,
+            effects,
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn matches_execution() {
+        use crate::analysis::analyze_with;
+        use crate::stats::{collect, StatsOptions};
+        use crate::{analyze, CFRBuffer, CommandExecutor};
+
+        let programs = [
+            "[[[[[[[[[[FS]]]]R]]RR]]RRCC]][[[[[[[[S]]]]]]]]",
+            "F # FF [\nRR[F]]xé[[S",
+            "F # S]\n[FS] # [S\nS",
+            "[RRF[FS]RF]]F",
+            "",
+        ];
+        for (program, comments) in programs.iter().flat_map(|p| [(*p, false), (*p, true)]) {
+            let analysis = analyze_with(program, comments);
+            let options = StatsOptions {
+                comments,
+                ..Default::default()
+            };
+            let stats = collect(program, &options);
+            assert_eq!(analysis.steps, Some(stats.steps), "{}", program);
+            assert_eq!(analysis.sleeps, Some(stats.sleeps), "{}", program);
+            assert_eq!(
+                analysis.error.is_some(),
+                stats.error.is_some(),
+                "{}",
+                program
+            );
+
+            let mut buffer = CFRBuffer::new(256, 256);
+            let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+            executor.set_comments(comments);
+            let mut steps = 0;
+            while !matches!(executor.step(), Ok((true, _)) | Err(_)) {
+                steps += 1;
+            }
+            if analysis.first_sleep.is_some() {
+                assert_eq!(analysis.first_sleep, Some(steps), "{}", program);
+            }
+        }
+
+        let analysis = analyze("RR[[FF]]");
+        assert_eq!(analysis.bounds.map(|b| (b.left, b.right)), Some((1, 8)));
+        assert!(analysis.fits(16, 1));
+        assert!(!analysis.fits(15, 1));
+        assert!(analyze("CRS").fits(0, 0));
+        let analysis = analyze("C[[[CR]]R]C");
+        assert_eq!((analysis.rotation, analysis.color_shift), (2, 2));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::buffer::CFRBuffer;
+#[cfg(feature = "encode")]
+use crate::encode::{encode_apng, write_gif_frames, GifOptions, LoopCount};
+use crate::executor::CommandExecutor;
+use crate::sink::AsPixelSink;
+use crate::timing::{FrameClock, Timing};
+#[cfg(feature = "encode")]
+use std::io::Write;
+#[cfg(any(feature = "encode", feature = "webp"))]
+use std::path::Path;
+
+/// Frames of a run, every one with its own delay.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::animation::Animation;
+/// use cfrs::{CFRBuffer, CommandExecutor};
+///
+/// let mut executor = CommandExecutor::new("[[[[FS]]]]".to_string(), CFRBuffer::new(16, 16));
+/// let animation = Animation::capture(&mut executor, 100).unwrap();
+/// // 16 sleeps of 20ms make 3 frames of 100ms.
+/// assert_eq!(animation.frames.len(), 3);
+/// assert_eq!(animation.delays, vec![100; 3]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    pub frames: Vec<CFRBuffer>,
+    /// How long the frame at the same index is shown, in milliseconds.
+    pub delays: Vec<u32>,
+}
+
+impl Animation {
+    /// Creates an empty animation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the program of `executor` to the end, capturing the canvas every `interval`
+    /// milliseconds of sleeps (every `S` sleeps 20ms), like the `cfrs` command does.
+    ///
+    /// Returns an error if the program fails, e.g. at an unmatched `]`.
+    pub fn capture<B: AsPixelSink<Sink = CFRBuffer>>(
+        executor: &mut CommandExecutor<B>,
+        interval: u32,
+    ) -> Result<Self, &'static str> {
+        Self::capture_with_timing(executor, interval, Timing::default())
+    }
+
+    /// Like `capture`, but every command takes as long as `timing` says, so the frames are
+    /// captured every `interval` milliseconds of program time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::animation::Animation;
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("[[[[F]]]]".to_string(), CFRBuffer::new(32, 32));
+    /// let timing = "F=10".parse().unwrap();
+    /// let animation = Animation::capture_with_timing(&mut executor, 50, timing).unwrap();
+    /// // 16 moves of 10ms make 3 frames of 50ms.
+    /// assert_eq!(animation.frames.len(), 3);
+    /// ```
+    pub fn capture_with_timing<B: AsPixelSink<Sink = CFRBuffer>>(
+        executor: &mut CommandExecutor<B>,
+        interval: u32,
+        timing: Timing,
+    ) -> Result<Self, &'static str> {
+        let mut animation = Self::new();
+        let mut clock = FrameClock::new(timing, interval);
+        loop {
+            let command = executor.view().next_command();
+            match executor.step() {
+                Ok((_, buffer)) => {
+                    if let Some(delay) = command.and_then(|c| clock.tick(c)) {
+                        animation.push_frame(buffer.clone(), delay);
+                    }
+                }
+                Err("End of commands") => return Ok(animation),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Runs the program of `executor` to the end, capturing the canvas every `pixels` drawn
+    /// pixels, each shown for `delay` milliseconds, which animates programs that rarely sleep.
+    ///
+    /// Returns an error if the program fails, e.g. at an unmatched `]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::animation::Animation;
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("[[[[F]]]]".to_string(), CFRBuffer::new(32, 32));
+    /// let animation = Animation::capture_every_pixels(&mut executor, 5, 100).unwrap();
+    /// assert_eq!(animation.frames.len(), 3);
+    /// ```
+    pub fn capture_every_pixels<B: AsPixelSink<Sink = CFRBuffer>>(
+        executor: &mut CommandExecutor<B>,
+        pixels: u32,
+        delay: u32,
+    ) -> Result<Self, &'static str> {
+        let mut animation = Self::new();
+        let mut drawn = 0;
+        loop {
+            let draws = executor.view().next_command() == Some('F');
+            match executor.step() {
+                Ok((_, buffer)) => {
+                    if draws {
+                        drawn += 1;
+                        if drawn % pixels.max(1) == 0 {
+                            animation.push_frame(buffer.clone(), delay);
+                        }
+                    }
+                }
+                Err("End of commands") => return Ok(animation),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Appends a frame shown for `delay` milliseconds.
+    pub fn push_frame(&mut self, frame: CFRBuffer, delay: u32) {
+        self.frames.push(frame);
+        self.delays.push(delay);
+    }
+
+    /// Merges runs of identical frames into one frame shown for their total delay.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::animation::Animation;
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut animation = Animation::new();
+    /// let mut frame = CFRBuffer::new(4, 4);
+    /// animation.push_frame(frame.clone(), 100);
+    /// animation.push_frame(frame.clone(), 100);
+    /// frame.data[0] = CFRColor::Red;
+    /// animation.push_frame(frame, 50);
+    ///
+    /// animation.dedup();
+    /// assert_eq!(animation.delays, vec![200, 50]);
+    /// ```
+    pub fn dedup(&mut self) {
+        let mut frames: Vec<CFRBuffer> = Vec::with_capacity(self.frames.len());
+        let mut delays: Vec<u32> = Vec::with_capacity(self.delays.len());
+        for (frame, delay) in self.frames.drain(..).zip(self.delays.drain(..)) {
+            match frames.last() {
+                Some(last) if last.data == frame.data => {
+                    let total = delays.last_mut().unwrap();
+                    *total = total.saturating_add(delay);
+                }
+                _ => {
+                    frames.push(frame);
+                    delays.push(delay);
+                }
+            }
+        }
+        self.frames = frames;
+        self.delays = delays;
+    }
+
+    /// Returns the total duration in milliseconds.
+    pub fn duration(&self) -> u64 {
+        self.delays.iter().map(|&d| d as u64).sum()
+    }
+
+    /// Writes the animation as a GIF. The frame delays replace `options.delay`.
+    #[cfg(feature = "encode")]
+    pub fn write_gif<W: Write>(
+        &self,
+        writer: W,
+        options: &GifOptions,
+    ) -> Result<(), gif::EncodingError> {
+        write_gif_frames(writer, &self.frames, &self.delays, options)
+    }
+
+    #[cfg(feature = "encode")]
+    pub fn save_gif<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &GifOptions,
+    ) -> Result<(), gif::EncodingError> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_gif(file, options)
+    }
+
+    /// Saves the animation as an animated PNG, which keeps the exact frame delays that GIF
+    /// rounds to 10ms.
+    #[cfg(feature = "encode")]
+    pub fn save_apng<P: AsRef<Path>>(
+        &self,
+        path: P,
+        loop_count: LoopCount,
+    ) -> Result<(), png::EncodingError> {
+        let bytes = encode_apng(&self.frames, &self.delays, loop_count)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Saves the animation as a lossless animated WebP.
+    #[cfg(feature = "webp")]
+    pub fn save_webp<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        use std::io;
+        use webp_animation::{Encoder, EncoderOptions, EncodingConfig, EncodingType};
+
+        let Some(first) = self.frames.first() else {
+            return Ok(());
+        };
+        let error = |e: webp_animation::Error| io::Error::new(io::ErrorKind::Other, e);
+        let options = EncoderOptions {
+            encoding_config: Some(EncodingConfig {
+                encoding_type: EncodingType::Lossless,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut encoder =
+            Encoder::new_with_options((first.width, first.height), options).map_err(error)?;
+        let mut timestamp = 0i32;
+        for (frame, &delay) in self.frames.iter().zip(&self.delays) {
+            encoder
+                .add_frame(&frame.to_rgba_bytes(), timestamp)
+                .map_err(error)?;
+            timestamp = timestamp.saturating_add(delay.min(i32::MAX as u32) as i32);
+        }
+        let webp = encoder.finalize(timestamp).map_err(error)?;
+        std::fs::write(path, &*webp)
+    }
+}
+
+mod tests {
+    #[test]
+    fn capture_matches_stepping() {
+        use crate::animation::Animation;
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let program = "[[[[[FS]]]]R]";
+        let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(16, 16));
+        let mut animation = Animation::capture(&mut executor, 60).unwrap();
+        // 32 sleeps of 20ms make 10 frames of 60ms, and the last two sleeps are dropped.
+        assert_eq!(animation.frames.len(), 10);
+        assert_eq!(animation.duration(), 10 * 60);
+        let last = animation.frames.last().unwrap();
+        assert_ne!(last.data, executor.buffer().data);
+
+        animation.push_frame(executor.buffer().clone(), 60);
+        animation.push_frame(executor.buffer().clone(), 60);
+        animation.dedup();
+        assert_eq!(animation.frames.len(), 11);
+        assert_eq!(animation.delays.last(), Some(&120));
+
+        let mut executor = CommandExecutor::new("F]F".to_string(), CFRBuffer::new(16, 16));
+        assert_eq!(
+            Animation::capture(&mut executor, 60).unwrap_err(),
+            "Unmatched ]"
+        );
+    }
+}
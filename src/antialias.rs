@@ -0,0 +1,195 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::sink::{AsPixelSink, PixelSink};
+
+/// A `PixelSink` that draws the path of the painter as smooth lines on a canvas `factor`
+/// times larger, and averages every `factor`×`factor` square back into one pixel for
+/// anti-aliased exports. The logical canvas is kept as well, as crisp as ever.
+///
+/// Every move of the painter is a line from the center of the previous pixel to the center
+/// of the new one, drawn with a round brush one pixel wide, so diagonals come out smooth. A
+/// pixel that doesn't continue a line, after a wrap or a color change, is a full square.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::antialias::Supersampled;
+/// use cfrs::{CFRColor, CommandExecutor};
+///
+/// let mut canvas = Supersampled::new(8, 8, 4, CFRColor::Black);
+/// CommandExecutor::new("RFF".to_string(), &mut canvas).run().unwrap();
+/// assert_eq!(canvas.buffer().data[8 + 5], CFRColor::White);
+///
+/// let rgba = canvas.to_rgba_bytes();
+/// let red = |x: usize, y: usize| rgba[(y * 8 + x) * 4];
+/// assert_eq!(red(4, 2), 255);
+/// // The pixels along the diagonal are partly painted.
+/// assert!(red(4, 1) > 0 && red(4, 1) < 255);
+/// assert_eq!(red(3, 2), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Supersampled {
+    factor: u32,
+    buffer: CFRBuffer,
+    samples: Vec<CFRColor>,
+    /// The last painted pixel and its color.
+    last: Option<(u32, u32, CFRColor)>,
+}
+
+impl Supersampled {
+    /// Creates a `width`×`height` canvas filled with `background`, drawn `factor` times
+    /// larger. A `factor` of 0 counts as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the larger canvas has more than `u32::MAX` pixels.
+    pub fn new(width: u32, height: u32, factor: u32, background: CFRColor) -> Self {
+        let factor = factor.max(1);
+        let samples = (width as u64 * factor as u64)
+            .checked_mul(height as u64 * factor as u64)
+            .filter(|&samples| samples <= u32::MAX as u64)
+            .expect("The supersampled canvas is too large");
+        let mut buffer = CFRBuffer::new(width, height);
+        buffer.data.fill(background);
+        Self {
+            factor,
+            buffer,
+            samples: vec![background; samples as usize],
+            last: None,
+        }
+    }
+
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+
+    /// Returns the logical canvas, as the program drew it without anti-aliasing.
+    pub fn buffer(&self) -> &CFRBuffer {
+        &self.buffer
+    }
+
+    /// Returns the anti-aliased canvas as RGBA bytes: every pixel is the average of the
+    /// `factor`×`factor` samples it covers.
+    pub fn to_rgba_bytes(&self) -> Vec<u8> {
+        let (width, height) = (self.buffer.width as usize, self.buffer.height as usize);
+        let factor = self.factor as usize;
+        let count = (factor * factor) as u32;
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 4];
+                for sy in y * factor..(y + 1) * factor {
+                    let row = sy * width * factor;
+                    for sample in &self.samples[row + x * factor..row + (x + 1) * factor] {
+                        for (total, channel) in sum.iter_mut().zip(sample.to_rgba()) {
+                            *total += channel as u32;
+                        }
+                    }
+                }
+                rgba.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+            }
+        }
+        rgba
+    }
+
+    /// Paints the samples of the pixel at (`x`, `y`) as a full square.
+    fn fill_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        let factor = self.factor as usize;
+        let row_len = self.buffer.width as usize * factor;
+        for sy in y as usize * factor..(y as usize + 1) * factor {
+            let start = sy * row_len + x as usize * factor;
+            self.samples[start..start + factor].fill(color);
+        }
+    }
+
+    /// Paints the samples within half a pixel of the line between the centers of two
+    /// neighboring pixels.
+    fn draw_line(&mut self, from: (u32, u32), to: (u32, u32), color: CFRColor) {
+        let factor = self.factor as f64;
+        let center = |(x, y): (u32, u32)| ((x as f64 + 0.5) * factor, (y as f64 + 0.5) * factor);
+        let ((ax, ay), (bx, by)) = (center(from), center(to));
+        let radius = factor / 2.0;
+        let (dx, dy) = (bx - ax, by - ay);
+        let length = dx * dx + dy * dy;
+
+        let (width, height) = (
+            self.buffer.width * self.factor,
+            self.buffer.height * self.factor,
+        );
+        let range = |a: f64, b: f64, max: u32| {
+            let start = (a.min(b) - radius).floor().max(0.0) as u32;
+            let end = ((a.max(b) + radius).ceil() as u32).min(max);
+            start..end
+        };
+        for sy in range(ay, by, height) {
+            for sx in range(ax, bx, width) {
+                let (px, py) = (sx as f64 + 0.5, sy as f64 + 0.5);
+                let t = if length > 0.0 {
+                    (((px - ax) * dx + (py - ay) * dy) / length).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (ex, ey) = (px - (ax + t * dx), py - (ay + t * dy));
+                if ex * ex + ey * ey <= radius * radius {
+                    self.samples[(sy * width + sx) as usize] = color;
+                }
+            }
+        }
+    }
+}
+
+impl PixelSink for Supersampled {
+    fn dimensions(&self) -> (u32, u32) {
+        self.buffer.dimensions()
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        self.buffer.set_pixel(x, y, color);
+        match self.last.replace((x, y, color)) {
+            Some((lx, ly, last)) if last == color && lx.abs_diff(x).max(ly.abs_diff(y)) <= 1 => {
+                self.draw_line((lx, ly), (x, y), color)
+            }
+            _ => self.fill_pixel(x, y, color),
+        }
+    }
+}
+
+impl AsPixelSink for Supersampled {
+    type Sink = Self;
+
+    fn sink(&self) -> &Self {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn straight_lines_stay_crisp() {
+        use crate::antialias::Supersampled;
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let program = "RRFFFFCRRFFF";
+        let mut canvas = Supersampled::new(16, 16, 3, CFRColor::Black);
+        CommandExecutor::new(program.to_string(), &mut canvas)
+            .run()
+            .unwrap();
+        let mut crisp = CFRBuffer::new(16, 16);
+        CommandExecutor::new(program.to_string(), &mut crisp)
+            .run()
+            .unwrap();
+        assert_eq!(canvas.buffer().data, crisp.data);
+
+        // Only the round ends of the lines are softer than the crisp canvas.
+        let rgba = canvas.to_rgba_bytes();
+        let differ = rgba
+            .chunks(4)
+            .zip(crisp.to_rgba_bytes().chunks(4))
+            .filter(|(a, b)| a != b)
+            .count();
+        assert!(differ <= 2, "{} pixels differ", differ);
+    }
+}
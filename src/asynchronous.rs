@@ -0,0 +1,89 @@
+use crate::executor::CommandExecutor;
+use crate::sink::AsPixelSink;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl<'a, B: AsPixelSink> CommandExecutor<'a, B> {
+    /// Executes all the remaining commands, yielding to the async runtime after every
+    /// `steps_per_yield` steps.
+    ///
+    /// This works with any runtime. Dropping the future between two yields, e.g. in the
+    /// losing branch of `tokio::select!`, cancels the run and leaves the executor where it
+    /// stopped, so the run can be resumed by calling this again.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    /// use std::time::Duration;
+    ///
+    /// let mut executor = CommandExecutor::new(program, CFRBuffer::new(256, 256));
+    /// tokio::select! {
+    ///     result = executor.run_with_budget(4096) => result?,
+    ///     _ = tokio::time::sleep(Duration::from_secs(5)) => return Err("Render timed out"),
+    /// }
+    /// ```
+    pub async fn run_with_budget(&mut self, steps_per_yield: usize) -> Result<(), &'static str> {
+        let steps_per_yield = steps_per_yield.max(1);
+        loop {
+            for _ in 0..steps_per_yield {
+                match self.step() {
+                    Ok(_) => {}
+                    Err("End of commands") => return Ok(()),
+                    Err(e) => return Err(e),
+                }
+            }
+            YieldNow(false).await;
+        }
+    }
+}
+
+/// Returns `Pending` once, after waking the task, so the runtime can run other tasks.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+mod tests {
+    #[test]
+    fn yield_and_resume() {
+        use crate::{CFRBuffer, CommandExecutor};
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        let mut expected = CFRBuffer::new(16, 16);
+        CommandExecutor::new("[[[[FR]]]]".to_string(), &mut expected)
+            .run()
+            .unwrap();
+
+        let mut buffer = CFRBuffer::new(16, 16);
+        let mut executor = CommandExecutor::new("[[[[FR]]]]".to_string(), &mut buffer);
+        let mut cx = Context::from_waker(Waker::noop());
+        {
+            // Cancel the run after its first yield.
+            let mut run = Box::pin(executor.run_with_budget(10));
+            assert_eq!(run.as_mut().poll(&mut cx), Poll::Pending);
+        }
+        assert_eq!(executor.view().index, 7);
+
+        let mut run = Box::pin(executor.run_with_budget(10));
+        let mut yields = 0;
+        while run.as_mut().poll(&mut cx).is_pending() {
+            yields += 1;
+        }
+        assert_eq!(yields, 6);
+        drop(run);
+        assert_eq!(buffer.data, expected.data);
+    }
+}
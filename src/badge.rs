@@ -0,0 +1,101 @@
+use crate::parser::{tokenize, TokenKind};
+use crate::stats::{collect, StatsOptions};
+
+/// The statistics of a program shown on its badge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    /// Number of commands and brackets, without comments and whitespace.
+    pub length: usize,
+    /// Number of colors on the canvas other than the background.
+    pub colors: usize,
+    /// Percentage of the canvas that differs from the background.
+    pub coverage: f64,
+}
+
+impl Badge {
+    /// Runs a program and collects the statistics for its badge.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::badge::Badge;
+    /// use cfrs::stats::StatsOptions;
+    ///
+    /// let options = StatsOptions { width: 4, height: 4, ..Default::default() };
+    /// let badge = Badge::new("[FF] # two lines", &options);
+    /// assert_eq!((badge.length, badge.colors, badge.coverage), (4, 1, 25.0));
+    /// assert_eq!(badge.message(), "4 commands · 1 color · 25% painted");
+    /// ```
+    pub fn new(program: &str, options: &StatsOptions) -> Self {
+        let length = tokenize(program, options.comments)
+            .iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Invalid))
+            .count();
+        let options = StatsOptions {
+            coverage: false,
+            profile: false,
+            ..options.clone()
+        };
+        let stats = collect(program, &options);
+        let pixels = options.width as f64 * options.height as f64;
+        Self {
+            length,
+            colors: stats.colors,
+            coverage: stats.painted as f64 / pixels * 100.0,
+        }
+    }
+
+    /// Returns the text on the right of the badge.
+    pub fn message(&self) -> String {
+        let plural =
+            |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        format!(
+            "{} · {} · {}% painted",
+            plural(self.length, "command"),
+            plural(self.colors, "color"),
+            (self.coverage * 10.0).round() / 10.0
+        )
+    }
+
+    /// Renders the badge as a flat SVG in the style of shields.io, to embed next to the art.
+    pub fn to_svg(&self) -> String {
+        const LABEL: &str = "cfrs";
+        let message = self.message();
+        // Verdana at 11px is about 7px per character.
+        let text_width = |s: &str| s.chars().count() as u32 * 7 + 10;
+        let (label_width, message_width) = (text_width(LABEL), text_width(&message));
+        let width = label_width + message_width;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {message}">
+<title>{LABEL}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="#007ec6"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{LABEL}</text><text x="{message_x}" y="14">{message}</text></g>
+</svg>
+"##,
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        )
+    }
+}
+
+mod tests {
+    #[test]
+    fn badge_fits_its_message() {
+        use crate::badge::Badge;
+
+        let badge = Badge {
+            length: 1200,
+            colors: 8,
+            coverage: 100.0,
+        };
+        let svg = badge.to_svg();
+        assert!(svg.contains(">1200 commands · 8 colors · 100% painted</text>"));
+        let width = 4 * 7 + 10 + badge.message().chars().count() as u32 * 7 + 10;
+        assert!(svg.starts_with(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\"",
+            width
+        )));
+    }
+}
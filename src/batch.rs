@@ -0,0 +1,281 @@
+use crate::buffer::CFRBuffer;
+use crate::encode::{base64, encode_png, write_gif_frames, GifOptions};
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::limits::{Meter, ResourceLimits};
+use crate::timing::{FrameClock, Timing};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The output format of a job.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobFormat {
+    #[default]
+    Png,
+    Gif,
+    /// Only the statistics, without an image.
+    Json,
+}
+
+/// A render job, read as one line of JSON, e.g.
+/// `{"id": 7, "program": "[[FS]]", "width": 64, "height": 64, "format": "gif"}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Job {
+    /// Any JSON value, returned with the result to match it to the job.
+    #[serde(default)]
+    pub id: serde_json::Value,
+    pub program: String,
+    #[serde(default = "default_size")]
+    pub width: u32,
+    #[serde(default = "default_size")]
+    pub height: u32,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub format: JobFormat,
+    /// Delay between GIF frames in milliseconds.
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// How many milliseconds every command takes in GIFs, e.g. `F=1,S=20`, like `--timing`.
+    #[serde(default)]
+    pub timing: Option<String>,
+    /// Write the image to this path instead of returning it as base64.
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+fn default_size() -> u32 {
+    256
+}
+
+fn default_interval() -> u32 {
+    100
+}
+
+/// The resources a job used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct JobStats {
+    pub width: u32,
+    pub height: u32,
+    pub steps: u64,
+    pub sleeps: u64,
+    pub frames: u64,
+    pub wall_time_ms: u64,
+}
+
+/// The result of a job, written as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobResult {
+    pub id: serde_json::Value,
+    pub ok: bool,
+    /// The path the image was written to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// The image as base64, when the job has no `output`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub stats: JobStats,
+}
+
+/// Runs a job within `limits`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::batch::{run_job, Job};
+/// use cfrs::ResourceLimits;
+///
+/// let job: Job = serde_json::from_str(r#"{"program": "[[FS]]", "width": 8}"#).unwrap();
+/// let result = run_job(job, &ResourceLimits::default());
+/// assert!(result.ok);
+/// assert!(result.data.unwrap().starts_with("iVBORw0KGgo"));
+/// assert_eq!((result.stats.width, result.stats.steps), (8, 17));
+/// ```
+pub fn run_job(job: Job, limits: &ResourceLimits) -> JobResult {
+    let mut meter = Meter::new(limits);
+    let (width, height) = (job.width, job.height);
+    let result = render(&job, &mut meter).and_then(|image| match (image, &job.output) {
+        (Some(image), Some(path)) => std::fs::write(path, image)
+            .map(|_| (Some(path.clone()), None))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        (Some(image), None) => Ok((None, Some(base64(&image)))),
+        (None, _) => Ok((None, None)),
+    });
+    let usage = meter.usage();
+    let stats = JobStats {
+        width,
+        height,
+        steps: usage.steps,
+        sleeps: usage.sleeps,
+        frames: usage.frames,
+        wall_time_ms: usage.wall_time.unwrap_or_default().as_millis() as u64,
+    };
+    match result {
+        Ok((output, data)) => JobResult {
+            id: job.id,
+            ok: true,
+            output,
+            data,
+            error: None,
+            stats,
+        },
+        Err(error) => JobResult {
+            id: job.id,
+            ok: false,
+            output: None,
+            data: None,
+            error: Some(error),
+            stats,
+        },
+    }
+}
+
+/// Runs the program of a job and encodes the image, or returns `None` for `JobFormat::Json`.
+fn render(job: &Job, meter: &mut Meter) -> Result<Option<Vec<u8>>, String> {
+    let background = match &job.background {
+        Some(s) => CFRColor::from_str(s)?,
+        None => CFRColor::Black,
+    };
+    let timing = match &job.timing {
+        Some(s) => Timing::from_str(s)?,
+        None => Timing::default(),
+    };
+    if job.format == JobFormat::Gif && job.interval < 20 {
+        return Err("Interval must be at least 20 ms".to_string());
+    }
+    meter
+        .check_program(job.program.as_bytes())
+        .map_err(|e| e.to_string())?;
+    meter
+        .check_canvas(job.width, job.height)
+        .map_err(|e| e.to_string())?;
+
+    let mut buffer = CFRBuffer::new(job.width, job.height);
+    buffer.data.iter_mut().for_each(|c| *c = background);
+    let animation = job.format == JobFormat::Gif;
+    let mut frames = Vec::new();
+    let mut delays = Vec::new();
+    let mut clock = FrameClock::new(timing, job.interval);
+    let mut executor = CommandExecutor::new(job.program.clone(), &mut buffer);
+    executor.set_max_wraps(meter.limits().max_wraps);
+    loop {
+        let command = executor.view().next_command();
+        match executor.step() {
+            Ok((sleep, canvas)) => {
+                meter.step(sleep).map_err(|e| e.to_string())?;
+                if let Some(delay) = command.filter(|_| animation).and_then(|c| clock.tick(c)) {
+                    meter.frame().map_err(|e| e.to_string())?;
+                    frames.push(canvas.clone());
+                    delays.push(delay);
+                }
+            }
+            Err("End of commands") => break,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    match job.format {
+        JobFormat::Png => encode_png(&buffer).map(Some).map_err(|e| e.to_string()),
+        JobFormat::Gif => {
+            if frames.last().map(|f| &f.data) != Some(&buffer.data) {
+                meter.frame().map_err(|e| e.to_string())?;
+                frames.push(buffer);
+                delays.push(job.interval);
+            }
+            let mut bytes = Vec::new();
+            write_gif_frames(&mut bytes, &frames, &delays, &GifOptions::default())
+                .map_err(|e| e.to_string())?;
+            Ok(Some(bytes))
+        }
+        JobFormat::Json => Ok(None),
+    }
+}
+
+/// Reads jobs from `input`, one JSON object per line, and writes the result of every job
+/// to `output` as one line of JSON, in the same order. Lines that are not valid jobs get a
+/// result with `"ok": false`, and empty lines are skipped.
+///
+/// Returns the number of jobs when `input` ends.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::batch::run_worker;
+/// use cfrs::ResourceLimits;
+///
+/// let input = "{\"id\": \"a\", \"program\": \"F\", \"format\": \"json\"}\n\nnot json\n";
+/// let mut output = Vec::new();
+/// assert_eq!(run_worker(input.as_bytes(), &mut output, &ResourceLimits::default()).unwrap(), 2);
+/// let output = String::from_utf8(output).unwrap();
+/// let lines: Vec<&str> = output.lines().collect();
+/// assert!(lines[0].starts_with(r#"{"id":"a","ok":true,"stats":{"#));
+/// assert!(lines[1].starts_with(r#"{"id":null,"ok":false,"error":"#));
+/// ```
+pub fn run_worker<R: BufRead, W: Write>(
+    input: R,
+    mut output: W,
+    limits: &ResourceLimits,
+) -> io::Result<u64> {
+    let mut jobs = 0;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        jobs += 1;
+        let result = match serde_json::from_str::<Job>(&line) {
+            Ok(job) => run_job(job, limits),
+            Err(e) => JobResult {
+                id: serde_json::Value::Null,
+                ok: false,
+                output: None,
+                data: None,
+                error: Some(format!("Invalid job: {}", e)),
+                stats: JobStats::default(),
+            },
+        };
+        serde_json::to_writer(&mut output, &result)?;
+        output.write_all(b"\n")?;
+        // Flush every result so that the queue sees it before the next job finishes.
+        output.flush()?;
+    }
+    Ok(jobs)
+}
+
+mod tests {
+    #[test]
+    fn jobs_report_errors() {
+        use crate::batch::{run_job, Job, JobFormat};
+        use crate::ResourceLimits;
+
+        let job = |json: &str| -> Job { serde_json::from_str(json).unwrap() };
+        let limits = ResourceLimits {
+            max_steps: 100,
+            ..Default::default()
+        };
+
+        let result = run_job(job(r#"{"id": 1, "program": "[[[[[[[[F]]]]]]]]"}"#), &limits);
+        assert!(!result.ok);
+        assert_eq!(result.id, serde_json::json!(1));
+        assert_eq!(result.stats.steps, 101);
+
+        let gif = job(r#"{"program": "[[FS]]", "format": "gif", "interval": 40}"#);
+        assert_eq!(gif.format, JobFormat::Gif);
+        let result = run_job(gif, &limits);
+        assert_eq!(result.stats.frames, 2);
+        assert!(result.data.unwrap().starts_with("R0lGODlh"));
+
+        let timed = r#"{"program": "[[FF]]", "format": "gif", "interval": 40, "timing": "F=10"}"#;
+        assert_eq!(run_job(job(timed), &limits).stats.frames, 2);
+
+        let result = run_job(job(r#"{"program": "F", "background": "pink"}"#), &limits);
+        assert!(result.error.is_some());
+        let result = run_job(job(r#"{"program": "F", "timing": "X=1"}"#), &limits);
+        assert_eq!(result.error.as_deref(), Some("Invalid timing: X=1"));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use std::time::{Duration, Instant};
+
+/// A program and canvas size to benchmark.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub name: &'static str,
+    pub program: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The result of running a `Workload` once.
+#[derive(Debug, Copy, Clone)]
+pub struct Measurement {
+    /// Number of executed steps.
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl Measurement {
+    /// Returns the throughput in steps per second.
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// A program with `depth` nested blocks and a command at every level, so most steps are
+/// block bookkeeping: `[F[F[F...]]]`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bench::deep_nesting;
+///
+/// assert_eq!(deep_nesting(3), "[F[F[F]]]");
+/// ```
+pub fn deep_nesting(depth: usize) -> String {
+    format!("{}{}", "[F".repeat(depth), "]".repeat(depth))
+}
+
+/// A program that repeatedly redraws a small patch while changing colors, executing
+/// `2^depth` iterations of a loop body that draws 8 pixels.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bench::dense_drawing;
+///
+/// assert_eq!(dense_drawing(2), "[[FRFRFRFRFRFRFRFRC]]");
+/// ```
+pub fn dense_drawing(depth: usize) -> String {
+    format!(
+        "{}{}C{}",
+        "[".repeat(depth),
+        "FR".repeat(8),
+        "]".repeat(depth)
+    )
+}
+
+/// A flat program of `length` `F` commands without any block.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bench::straight_run;
+///
+/// assert_eq!(straight_run(4), "FFFF");
+/// ```
+pub fn straight_run(length: usize) -> String {
+    "F".repeat(length)
+}
+
+/// Returns the representative workloads used by the benchmarks.
+pub fn workloads() -> Vec<Workload> {
+    vec![
+        Workload {
+            name: "deep_nesting",
+            program: deep_nesting(18),
+            width: 256,
+            height: 256,
+        },
+        Workload {
+            name: "dense_drawing",
+            program: dense_drawing(14),
+            width: 256,
+            height: 256,
+        },
+        Workload {
+            name: "straight_run",
+            program: straight_run(1 << 18),
+            width: 256,
+            height: 256,
+        },
+        Workload {
+            name: "large_canvas",
+            program: "[[[[[[[[[[FS]]]]R]]RR]]RRCC]]".to_string(),
+            width: 4096,
+            height: 4096,
+        },
+    ]
+}
+
+/// Runs a workload to the end and measures it, including the canvas allocation.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bench::{measure, straight_run, Workload};
+///
+/// let workload = Workload {
+///     name: "example",
+///     program: straight_run(1000),
+///     width: 64,
+///     height: 64,
+/// };
+/// assert_eq!(measure(&workload).steps, 1000);
+/// ```
+pub fn measure(workload: &Workload) -> Measurement {
+    let start = Instant::now();
+    let mut buffer = CFRBuffer::new(workload.width, workload.height);
+    let mut executor = CommandExecutor::new(workload.program.clone(), &mut buffer);
+    let mut steps = 0;
+    while executor.step().is_ok() {
+        steps += 1;
+    }
+    Measurement {
+        steps,
+        elapsed: start.elapsed(),
+    }
+}
@@ -0,0 +1,128 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+
+/// A Bevy plugin that advances every `CfrsProgram` once per frame and uploads its canvas
+/// to the program's `Image` asset.
+///
+/// # Examples
+///
+/// ```ignore
+/// use bevy::prelude::*;
+/// use cfrs::bevy_cfrs::{CfrsPlugin, CfrsProgram};
+///
+/// fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+///     let program = CfrsProgram::new("[[[[[[[[[[FS]]]]R]]RR]]RRCC]]", 256, 256, &mut images);
+///     commands.spawn(SpriteBundle {
+///         texture: program.image.clone(),
+///         ..default()
+///     });
+///     commands.spawn(program);
+/// }
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, CfrsPlugin))
+///     .add_systems(Startup, setup)
+///     .run();
+/// ```
+pub struct CfrsPlugin;
+
+impl Plugin for CfrsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, run_programs);
+    }
+}
+
+/// A CFRS program rendering into an `Image` asset.
+///
+/// Each frame the program runs until its next `S` (sleep) command, so one sleep corresponds to
+/// one frame. `steps_per_frame` caps the number of commands executed in a single frame.
+#[derive(Component)]
+pub struct CfrsProgram {
+    /// The texture the canvas is uploaded to.
+    pub image: Handle<Image>,
+    /// The maximum number of commands executed per frame.
+    pub steps_per_frame: usize,
+    executor: CommandExecutor<'static, CFRBuffer>,
+    finished: bool,
+}
+
+impl CfrsProgram {
+    /// Creates a program on a canvas of the given size, along with the `Image` asset it draws to.
+    pub fn new(
+        program: impl Into<String>,
+        width: u32,
+        height: u32,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let buffer = CFRBuffer::new(width, height);
+        let image = images.add(create_image(&buffer));
+        Self {
+            image,
+            steps_per_frame: 100_000,
+            executor: CommandExecutor::new(program.into(), buffer),
+            finished: false,
+        }
+    }
+
+    /// Returns the current canvas.
+    pub fn buffer(&self) -> &CFRBuffer {
+        self.executor.buffer()
+    }
+
+    /// Returns `true` once the program has run to completion (or failed).
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Creates an `Image` holding the buffer's pixels, sampled with nearest filtering.
+pub fn create_image(buffer: &CFRBuffer) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: buffer.width,
+            height: buffer.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        buffer.to_rgba_bytes(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn run_programs(mut programs: Query<&mut CfrsProgram>, mut images: ResMut<Assets<Image>>) {
+    for mut program in &mut programs {
+        if program.finished {
+            continue;
+        }
+
+        let program = &mut *program;
+        let mut changed = false;
+        for _ in 0..program.steps_per_frame {
+            match program.executor.step() {
+                Ok((sleep, _)) => {
+                    changed = true;
+                    if sleep {
+                        break;
+                    }
+                }
+                Err(_) => {
+                    program.finished = true;
+                    break;
+                }
+            }
+        }
+
+        if changed {
+            if let Some(image) = images.get_mut(&program.image) {
+                image.data = program.executor.buffer().to_rgba_bytes();
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::buffer::CFRBuffer;
+use std::io::{self, Write};
+
+/// Writes the buffer as an uncompressed 24-bit BMP image.
+///
+/// This needs no image library, so it works in builds without the `image` and `encode`
+/// features.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bitmap::write_bmp;
+/// use cfrs::CFRBuffer;
+///
+/// let mut bytes = Vec::new();
+/// write_bmp(&mut bytes, &CFRBuffer::new(3, 2)).unwrap();
+/// assert_eq!(&bytes[0..2], b"BM");
+/// // Every row of 3 pixels is padded from 9 to 12 bytes.
+/// assert_eq!(bytes.len(), 54 + 2 * 12);
+/// ```
+pub fn write_bmp<W: Write>(mut writer: W, buffer: &CFRBuffer) -> io::Result<()> {
+    let row_size = (buffer.width as usize * 3).div_ceil(4) * 4;
+    let image_size = row_size * buffer.height as usize;
+    let file_size = u32::try_from(54 + image_size)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Image too large for BMP"))?;
+
+    // BITMAPFILEHEADER
+    writer.write_all(b"BM")?;
+    writer.write_all(&file_size.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&54u32.to_le_bytes())?;
+    // BITMAPINFOHEADER
+    writer.write_all(&40u32.to_le_bytes())?;
+    writer.write_all(&(buffer.width as i32).to_le_bytes())?;
+    writer.write_all(&(buffer.height as i32).to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&24u16.to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(&(image_size as u32).to_le_bytes())?;
+    // 2835 pixels per meter is 72 DPI.
+    writer.write_all(&2835i32.to_le_bytes())?;
+    writer.write_all(&2835i32.to_le_bytes())?;
+    writer.write_all(&[0; 8])?;
+
+    // Rows are stored bottom-up, in BGR order.
+    let mut row = vec![0; row_size];
+    for y in (0..buffer.height).rev() {
+        let start = (y * buffer.width) as usize;
+        for (x, color) in buffer.data[start..start + buffer.width as usize]
+            .iter()
+            .enumerate()
+        {
+            let [r, g, b] = color.to_rgb();
+            row[x * 3..x * 3 + 3].copy_from_slice(&[b, g, r]);
+        }
+        writer.write_all(&row)?;
+    }
+    Ok(())
+}
+
+/// Writes the buffer as a [farbfeld](https://tools.suckless.org/farbfeld/) image.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bitmap::write_farbfeld;
+/// use cfrs::CFRBuffer;
+///
+/// let mut bytes = Vec::new();
+/// write_farbfeld(&mut bytes, &CFRBuffer::new(2, 2)).unwrap();
+/// assert_eq!(&bytes[0..8], b"farbfeld");
+/// assert_eq!(bytes.len(), 16 + 4 * 8);
+/// ```
+pub fn write_farbfeld<W: Write>(mut writer: W, buffer: &CFRBuffer) -> io::Result<()> {
+    writer.write_all(b"farbfeld")?;
+    writer.write_all(&buffer.width.to_be_bytes())?;
+    writer.write_all(&buffer.height.to_be_bytes())?;
+    for color in &buffer.data {
+        let [r, g, b] = color.to_rgb();
+        for channel in [r, g, b, 255] {
+            // Scale 8-bit channels to 16 bits, so that 255 becomes 65535.
+            writer.write_all(&(channel as u16 * 257).to_be_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the buffer as a binary PGM (`P5`) grayscale image, with the luminance of every
+/// color, for monochrome displays, laser engravers, and thresholding tools.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bitmap::write_pgm;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(3, 1);
+/// buffer.data[0] = CFRColor::White;
+/// buffer.data[1] = CFRColor::Red;
+/// let mut bytes = Vec::new();
+/// write_pgm(&mut bytes, &buffer).unwrap();
+/// assert_eq!(bytes, b"P5\n3 1\n255\n\xff\x36\x00");
+/// ```
+pub fn write_pgm<W: Write>(mut writer: W, buffer: &CFRBuffer) -> io::Result<()> {
+    write!(writer, "P5\n{} {}\n255\n", buffer.width, buffer.height)?;
+    writer.write_all(&buffer.to_luma_bytes())
+}
@@ -1,6 +1,40 @@
+use crate::bufferfile::{read_buffer, write_buffer};
 use crate::enums::CFRColor;
+use crate::error::CFRError;
+use crate::tiles::{downsample, max_level, Tile};
 #[cfg(feature = "image")]
-use image::{ImageBuffer, Rgb, Rgba};
+use image::{ImageBuffer, Luma, Rgb, Rgba};
+use std::io;
+use std::path::Path;
+
+/// Returns the index in `data` of the pixel at (`x`, `y`) of a canvas `width` pixels wide.
+#[cfg(not(feature = "checked-math"))]
+#[inline]
+pub(crate) fn pixel_index(x: u32, y: u32, width: u32) -> usize {
+    (y * width + x) as usize
+}
+
+/// Returns the index in `data` of the pixel at (`x`, `y`) of a canvas `width` pixels wide,
+/// or panics with the coordinates if `x` is not on the canvas or the index overflows `u32`,
+/// instead of landing on another row or wrapping around.
+#[cfg(feature = "checked-math")]
+pub(crate) fn pixel_index(x: u32, y: u32, width: u32) -> usize {
+    assert!(
+        x < width,
+        "Pixel ({}, {}) is outside a canvas {} pixels wide",
+        x,
+        y,
+        width
+    );
+    y.checked_mul(width)
+        .and_then(|row| row.checked_add(x))
+        .unwrap_or_else(|| {
+            panic!(
+                "Index of pixel ({}, {}) of a canvas {} pixels wide overflows u32",
+                x, y, width
+            )
+        }) as usize
+}
 
 /// The `CFRBuffer` struct represents a buffer that stores color data.
 ///
@@ -24,14 +58,394 @@ pub struct CFRBuffer {
 }
 
 impl CFRBuffer {
+    /// Creates a black `width`×`height` canvas.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero or the canvas has more than `u32::MAX` pixels; use
+    /// `try_new` for dimensions from an untrusted source.
     pub fn new(width: u32, height: u32) -> CFRBuffer {
+        match Self::try_new(width, height) {
+            Ok(buffer) => buffer,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a black `width`×`height` canvas, or returns an error if either dimension is
+    /// zero or the canvas has more than `u32::MAX` pixels (pixel indices are `u32`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRError};
+    ///
+    /// assert_eq!(CFRBuffer::try_new(1, 5).unwrap().data.len(), 5);
+    /// assert_eq!(
+    ///     CFRBuffer::try_new(0, 5).unwrap_err(),
+    ///     CFRError::InvalidDimensions { width: 0, height: 5 }
+    /// );
+    /// assert!(CFRBuffer::try_new(u32::MAX, 2).is_err());
+    /// ```
+    pub fn try_new(width: u32, height: u32) -> Result<CFRBuffer, CFRError> {
+        if width == 0 || height == 0 {
+            return Err(CFRError::InvalidDimensions { width, height });
+        }
+        let pixels = width as u64 * height as u64;
+        if pixels > u32::MAX as u64 {
+            return Err(CFRError::CanvasTooLarge {
+                pixels,
+                max: u32::MAX as u64,
+            });
+        }
+        Ok(CFRBuffer {
+            width,
+            height,
+            data: vec![CFRColor::Black; pixels as usize],
+        })
+    }
+
+    /// Convert the buffer to raw RGBA bytes (4 bytes per pixel, row-major).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::buffer::CFRBuffer;
+    ///
+    /// let buffer = CFRBuffer::new(2, 2);
+    /// let bytes = buffer.to_rgba_bytes();
+    /// assert_eq!(bytes.len(), 2 * 2 * 4);
+    /// assert_eq!(&bytes[0..4], &[0, 0, 0, 255]);
+    /// ```
+    pub fn to_rgba_bytes(&self) -> Vec<u8> {
+        self.data.iter().flat_map(|color| color.to_rgba()).collect()
+    }
+
+    /// Convert the buffer to one luminance byte per pixel, row by row, for monochrome
+    /// displays and thresholding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(2, 1);
+    /// buffer.data[1] = CFRColor::White;
+    /// assert_eq!(buffer.to_luma_bytes(), vec![0, 255]);
+    /// ```
+    pub fn to_luma_bytes(&self) -> Vec<u8> {
+        self.data.iter().map(CFRColor::luma).collect()
+    }
+
+    /// Pack the palette indices of every row into `u32` words, 10 pixels (3 bits each) per word.
+    ///
+    /// Pixel `x` of a row is stored in bits `3 * (x % 10)` of word `x / 10` of that row, and
+    /// every row starts with a new word. `Transparent` is packed as black.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(11, 2);
+    /// buffer.data[10] = CFRColor::Yellow;
+    /// let packed = buffer.to_packed_indices();
+    /// assert_eq!(packed.len(), 2 * 2);
+    /// assert_eq!(packed[0], 0o1111111111);
+    /// assert_eq!(packed[1], 0o7);
+    /// ```
+    pub fn to_packed_indices(&self) -> Vec<u32> {
+        self.data
+            .chunks(self.width.max(1) as usize)
+            .flat_map(|row| row.chunks(10))
+            .map(|pixels| {
+                pixels.iter().enumerate().fold(0, |word, (i, &color)| {
+                    #[cfg(feature = "transparent")]
+                    let color = match color {
+                        CFRColor::Transparent => CFRColor::Black,
+                        color => color,
+                    };
+                    word | (color as u32) << (3 * i)
+                })
+            })
+            .collect()
+    }
+
+    /// Copy a region of the buffer into a new buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not inside the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(4, 4);
+    /// buffer.data[5] = CFRColor::Red;
+    /// let region = buffer.crop(1, 1, 2, 1);
+    /// assert_eq!(region.data, vec![CFRColor::Red, CFRColor::Black]);
+    /// ```
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> CFRBuffer {
+        assert!(
+            x.checked_add(width)
+                .is_some_and(|right| right <= self.width)
+                && y.checked_add(height)
+                    .is_some_and(|bottom| bottom <= self.height),
+            "Region is outside the buffer"
+        );
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for row in y..y + height {
+            let start = pixel_index(x, row, self.width);
+            data.extend_from_slice(&self.data[start..start + width as usize]);
+        }
+        CFRBuffer {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Resizes the buffer to `width`×`height` with nearest-neighbor sampling, so the palette
+    /// colors stay crisp.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has no pixels but the new size does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(2, 1);
+    /// buffer.data[1] = CFRColor::Red;
+    /// let scaled = buffer.scale(4, 2);
+    /// assert_eq!(scaled.data[2..4], [CFRColor::Red, CFRColor::Red]);
+    /// assert_eq!(scaled.data[4..], scaled.data[..4]);
+    /// ```
+    pub fn scale(&self, width: u32, height: u32) -> CFRBuffer {
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            let row = (y as u64 * self.height as u64 / height as u64) as u32 * self.width;
+            data.extend((0..width).map(|x| {
+                let x = (x as u64 * self.width as u64 / width as u64) as u32;
+                self.data[(row + x) as usize]
+            }));
+        }
+        CFRBuffer {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Shrinks the buffer with `scale` to fit in `size`×`size`, keeping its aspect ratio,
+    /// e.g. for the thumbnails of a gallery. Smaller buffers are returned as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::CFRBuffer;
+    ///
+    /// let thumbnail = CFRBuffer::new(256, 128).thumbnail(64);
+    /// assert_eq!((thumbnail.width, thumbnail.height), (64, 32));
+    /// assert_eq!(CFRBuffer::new(16, 16).thumbnail(64).width, 16);
+    /// ```
+    pub fn thumbnail(&self, size: u32) -> CFRBuffer {
+        let size = size.max(1) as u64;
+        let longest = self.width.max(self.height) as u64;
+        if longest <= size {
+            return self.clone();
+        }
+        let fit = |side: u32| ((side as u64 * size / longest) as u32).max(1);
+        self.scale(fit(self.width), fit(self.height))
+    }
+
+    /// Copies `source` into the buffer with its top-left corner at (`x`, `y`), clipping the
+    /// parts outside the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(3, 3);
+    /// let mut source = CFRBuffer::new(2, 2);
+    /// source.data.fill(CFRColor::Red);
+    /// buffer.blit(&source, 2, 1);
+    /// assert_eq!(buffer.data.iter().filter(|&&c| c == CFRColor::Red).count(), 2);
+    /// ```
+    pub fn blit(&mut self, source: &CFRBuffer, x: u32, y: u32) {
+        let width = source.width.min(self.width.saturating_sub(x)) as usize;
+        let height = source.height.min(self.height.saturating_sub(y));
+        if width == 0 {
+            return;
+        }
+        for row in 0..height {
+            let from = pixel_index(0, row, source.width);
+            let to = pixel_index(x, y + row, self.width);
+            self.data[to..to + width].copy_from_slice(&source.data[from..from + width]);
+        }
+    }
+
+    /// Draws `top` over the buffer, like a layer: the buffer shows through wherever `top` is
+    /// `CFRColor::Transparent`. Both must have the same size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut bottom = CFRBuffer::new(2, 1);
+    /// bottom.data.fill(CFRColor::Red);
+    /// let mut top = CFRBuffer::new(2, 1);
+    /// top.data[0] = CFRColor::Transparent;
+    /// bottom.layer(&top);
+    /// assert_eq!(bottom.data, [CFRColor::Red, CFRColor::Black]);
+    /// ```
+    #[cfg(feature = "transparent")]
+    pub fn layer(&mut self, top: &CFRBuffer) {
+        assert_eq!((self.width, self.height), (top.width, top.height));
+        for (pixel, &color) in self.data.iter_mut().zip(&top.data) {
+            if color != CFRColor::Transparent {
+                *pixel = color;
+            }
+        }
+    }
+
+    /// Repeats the buffer in a grid of `columns`×`rows` copies, which shows whether a
+    /// drawing tiles seamlessly across the wrapping edges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the repeated size does not fit in a `u32`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(2, 1);
+    /// buffer.data[1] = CFRColor::Red;
+    /// let tiled = buffer.repeat(3, 2);
+    /// assert_eq!((tiled.width, tiled.height), (6, 2));
+    /// assert_eq!(tiled.data[..6], [CFRColor::Black, CFRColor::Red].repeat(3));
+    /// assert_eq!(tiled.data[6..], tiled.data[..6]);
+    /// ```
+    pub fn repeat(&self, columns: u32, rows: u32) -> CFRBuffer {
+        let width = self.width.checked_mul(columns);
+        let height = self.height.checked_mul(rows);
+        let (Some(width), Some(height)) = (width, height) else {
+            panic!("Repeated buffer is too large");
+        };
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for _ in 0..rows {
+            for row in self.data.chunks_exact(self.width.max(1) as usize) {
+                for _ in 0..columns {
+                    data.extend_from_slice(row);
+                }
+            }
+        }
         CFRBuffer {
             width,
             height,
-            data: vec![CFRColor::Black; (width * height) as usize],
+            data,
         }
     }
 
+    /// Returns a binary mask of where the buffer has `color`: white there and black elsewhere.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    ///
+    /// let mut buffer = CFRBuffer::new(3, 1);
+    /// buffer.data[1] = CFRColor::Red;
+    /// let mask = buffer.mask(CFRColor::Red);
+    /// assert_eq!(mask.data, vec![CFRColor::Black, CFRColor::White, CFRColor::Black]);
+    /// ```
+    pub fn mask(&self, color: CFRColor) -> CFRBuffer {
+        CFRBuffer {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .map(|&c| {
+                    if c == color {
+                        CFRColor::White
+                    } else {
+                        CFRColor::Black
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Split the buffer into a Deep Zoom image pyramid of `tile_size`×`tile_size` tiles.
+    ///
+    /// The highest level holds the buffer at full resolution, and every level below halves
+    /// it (nearest-neighbor) down to a single pixel at level 0. Tiles on the right and bottom
+    /// edges are smaller. Write the tiles to `<name>_files/<level>/<column>_<row>.<format>`
+    /// next to the descriptor from `tiles::dzi_descriptor` to view huge renders with a
+    /// zoomable web viewer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::CFRBuffer;
+    ///
+    /// let buffer = CFRBuffer::new(600, 300);
+    /// let tiles = buffer.export_tiles(256);
+    /// let full = tiles.iter().filter(|t| t.level == 10).count();
+    /// assert_eq!(full, 3 * 2);
+    /// assert_eq!(tiles.last().unwrap().path(), "0/0_0");
+    /// ```
+    pub fn export_tiles(&self, tile_size: u32) -> Vec<Tile> {
+        let tile_size = tile_size.max(1);
+        let mut tiles = Vec::new();
+        let mut level_buffer = self.clone();
+        for level in (0..=max_level(self.width, self.height)).rev() {
+            for row in 0..level_buffer.height.div_ceil(tile_size) {
+                for column in 0..level_buffer.width.div_ceil(tile_size) {
+                    let (x, y) = (column * tile_size, row * tile_size);
+                    let width = tile_size.min(level_buffer.width - x);
+                    let height = tile_size.min(level_buffer.height - y);
+                    tiles.push(Tile {
+                        level,
+                        column,
+                        row,
+                        buffer: level_buffer.crop(x, y, width, height),
+                    });
+                }
+            }
+            level_buffer = downsample(&level_buffer);
+        }
+        tiles
+    }
+
+    /// Saves the buffer as a `.cfrsbuf` file, which keeps the exact colors for later runs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cfrs::CFRBuffer;
+    ///
+    /// CFRBuffer::new(8, 8).save("canvas.cfrsbuf").unwrap();
+    /// assert_eq!(CFRBuffer::load("canvas.cfrsbuf").unwrap().width, 8);
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = io::BufWriter::new(std::fs::File::create(path)?);
+        write_buffer(file, self)
+    }
+
+    /// Loads a buffer from a `.cfrsbuf` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<CFRBuffer> {
+        read_buffer(io::BufReader::new(std::fs::File::open(path)?))
+    }
+
     #[cfg(feature = "image")]
     /// Get the color at the specified coordinates as an `Rgb<u8>` value.
     ///
@@ -55,17 +469,7 @@ impl CFRBuffer {
     /// assert_eq!(color, Rgb([0, 0, 0]));
     /// ```
     pub fn get_rgb(&self, x: u32, y: u32) -> Rgb<u8> {
-        let color = self.data[(y * self.width + x) as usize];
-        match color {
-            CFRColor::White => Rgb([255, 255, 255]),
-            CFRColor::Black => Rgb([0, 0, 0]),
-            CFRColor::Blue => Rgb([0, 0, 255]),
-            CFRColor::Green => Rgb([0, 255, 0]),
-            CFRColor::Cyan => Rgb([0, 255, 255]),
-            CFRColor::Red => Rgb([255, 0, 0]),
-            CFRColor::Magenta => Rgb([255, 0, 255]),
-            CFRColor::Yellow => Rgb([255, 255, 0]),
-        }
+        Rgb(self.data[pixel_index(x, y, self.width)].to_rgb())
     }
 
     #[cfg(feature = "image")]
@@ -91,17 +495,7 @@ impl CFRBuffer {
     /// assert_eq!(color, Rgba([0, 0, 0, 255]));
     /// ```
     pub fn get_rgba(&self, x: u32, y: u32) -> Rgba<u8> {
-        let color = self.data[(y * self.width + x) as usize];
-        match color {
-            CFRColor::White => Rgba([255, 255, 255, 255]),
-            CFRColor::Black => Rgba([0, 0, 0, 255]),
-            CFRColor::Blue => Rgba([0, 0, 255, 255]),
-            CFRColor::Green => Rgba([0, 255, 0, 255]),
-            CFRColor::Cyan => Rgba([0, 255, 255, 255]),
-            CFRColor::Red => Rgba([255, 0, 0, 255]),
-            CFRColor::Magenta => Rgba([255, 0, 255, 255]),
-            CFRColor::Yellow => Rgba([255, 255, 0, 255]),
-        }
+        Rgba(self.data[pixel_index(x, y, self.width)].to_rgba())
     }
 
     #[cfg(feature = "image")]
@@ -143,4 +537,25 @@ impl CFRBuffer {
     pub fn to_rgba_image(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
         ImageBuffer::from_fn(self.width, self.height, |x, y| self.get_rgba(x, y))
     }
+
+    #[cfg(feature = "image")]
+    /// Convert the buffer to image crate's grayscale `ImageBuffer<Luma<u8>, Vec<u8>>` value,
+    /// with the luminance of every color.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor};
+    /// use image::Luma;
+    ///
+    /// let mut buffer = CFRBuffer::new(2, 2);
+    /// buffer.data[3] = CFRColor::Green;
+    /// let image = buffer.to_luma_image();
+    /// assert_eq!(image.get_pixel(1, 1), &Luma([182]));
+    /// ```
+    pub fn to_luma_image(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            Luma([self.data[pixel_index(x, y, self.width)].luma()])
+        })
+    }
 }
@@ -0,0 +1,234 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::{CFRColor, CFRDirection};
+use crate::painter::CFRPainter;
+use std::io::{self, Read, Write};
+
+/// The first bytes of every `.cfrsbuf` file.
+pub const MAGIC: &[u8; 8] = b"CFRSBUF\0";
+
+/// The current version of the `.cfrsbuf` format.
+///
+/// Version 1 had no flags and no painter; it is still read.
+pub const VERSION: u16 = 2;
+
+/// Set in the flags when the painter follows the pixels.
+const HAS_PAINTER: u8 = 1;
+
+/// The contents of a `.cfrsbuf` file: a canvas with its exact colors, and optionally the
+/// painter that drew it, so that later runs can continue drawing on it.
+///
+/// After the magic, a little-endian `u16` version, `u32` width and height, and a flags byte,
+/// the pixels follow in rows with two pixels per byte (high nibble first). If bit 0 of the
+/// flags is set, the painter follows: its direction and color as bytes, then its `u32` x and
+/// y.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bufferfile::BufferFile;
+/// use cfrs::{CFRBuffer, CFRColor, CFRPainter};
+///
+/// let mut buffer = CFRBuffer::new(3, 3);
+/// buffer.data[4] = CFRColor::Cyan;
+/// let file = BufferFile { buffer, painter: Some(CFRPainter::new()) };
+/// let mut bytes = Vec::new();
+/// file.write(&mut bytes).unwrap();
+/// assert_eq!(bytes.len(), 19 + 5 + 10);
+/// let read = BufferFile::read(bytes.as_slice()).unwrap();
+/// assert_eq!(read.buffer.data, file.buffer.data);
+/// assert_eq!(read.painter.map(|p| (p.x, p.y)), Some((0, 0)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BufferFile {
+    pub buffer: CFRBuffer,
+    pub painter: Option<CFRPainter>,
+}
+
+impl BufferFile {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let buffer = &self.buffer;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&buffer.width.to_le_bytes())?;
+        writer.write_all(&buffer.height.to_le_bytes())?;
+        let flags = if self.painter.is_some() {
+            HAS_PAINTER
+        } else {
+            0
+        };
+        writer.write_all(&[flags])?;
+        let packed: Vec<u8> = buffer
+            .data
+            .chunks(2)
+            .map(|pair| (pair[0] as u8) << 4 | pair.get(1).map_or(0, |c| *c as u8))
+            .collect();
+        writer.write_all(&packed)?;
+        if let Some(painter) = &self.painter {
+            writer.write_all(&[painter.direction as u8, painter.color as u8])?;
+            writer.write_all(&painter.x.to_le_bytes())?;
+            writer.write_all(&painter.y.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a `.cfrsbuf` file of this or an earlier version.
+    ///
+    /// The pixels are read as they arrive, so a file that claims a huge canvas but ends
+    /// early fails without allocating the whole canvas.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 18];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid_data("Not a CFRS buffer".to_string()));
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version > VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported CFRS buffer version: {}",
+                version
+            )));
+        }
+        let width = u32::from_le_bytes(header[10..14].try_into().unwrap());
+        let height = u32::from_le_bytes(header[14..18].try_into().unwrap());
+        let pixels = width as u64 * height as u64;
+        if pixels > u32::MAX as u64 {
+            return Err(invalid_data(format!(
+                "The canvas is too large: {}x{}",
+                width, height
+            )));
+        }
+        let [flags] = if version >= 2 {
+            read_array(&mut reader)?
+        } else {
+            [0]
+        };
+
+        let mut packed = Vec::new();
+        let len = pixels.div_ceil(2);
+        reader.by_ref().take(len).read_to_end(&mut packed)?;
+        if packed.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let data = packed
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0xf])
+            .take(pixels as usize)
+            .map(to_color)
+            .collect::<io::Result<Vec<CFRColor>>>()?;
+
+        let painter = if flags & HAS_PAINTER != 0 {
+            let [direction, color] = read_array(&mut reader)?;
+            let x = u32::from_le_bytes(read_array(&mut reader)?);
+            let y = u32::from_le_bytes(read_array(&mut reader)?);
+            if x >= width.max(1) || y >= height.max(1) {
+                return Err(invalid_data("Painter out of the canvas".to_string()));
+            }
+            Some(CFRPainter {
+                direction: *CFRDirection::ALL
+                    .get(direction as usize)
+                    .ok_or_else(|| invalid_data(format!("Invalid direction: {}", direction)))?,
+                color: to_color(color)?,
+                x,
+                y,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            buffer: CFRBuffer {
+                width,
+                height,
+                data,
+            },
+            painter,
+        })
+    }
+}
+
+/// Writes a canvas without a painter as a `.cfrsbuf` file.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::bufferfile::{read_buffer, write_buffer};
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(3, 3);
+/// buffer.data[4] = CFRColor::Cyan;
+/// let mut bytes = Vec::new();
+/// write_buffer(&mut bytes, &buffer).unwrap();
+/// assert_eq!(read_buffer(bytes.as_slice()).unwrap().data, buffer.data);
+/// ```
+pub fn write_buffer<W: Write>(writer: W, buffer: &CFRBuffer) -> io::Result<()> {
+    let file = BufferFile {
+        buffer: buffer.clone(),
+        painter: None,
+    };
+    file.write(writer)
+}
+
+/// Reads the canvas of a `.cfrsbuf` file, ignoring the painter.
+pub fn read_buffer<R: Read>(reader: R) -> io::Result<CFRBuffer> {
+    BufferFile::read(reader).map(|file| file.buffer)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn to_color(index: u8) -> io::Result<CFRColor> {
+    CFRColor::ALL
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| invalid_data(format!("Invalid color: {}", index)))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+mod tests {
+    #[test]
+    fn rejects_invalid_files() {
+        use crate::bufferfile::{read_buffer, write_buffer, BufferFile};
+        use crate::{CFRBuffer, CFRPainter};
+
+        let mut bytes = Vec::new();
+        write_buffer(&mut bytes, &CFRBuffer::new(5, 2)).unwrap();
+        assert_eq!(read_buffer(bytes.as_slice()).unwrap().width, 5);
+
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        assert!(read_buffer(truncated.as_slice()).is_err());
+
+        let mut huge = bytes.clone();
+        huge[10..18].copy_from_slice(&[0xff; 8]);
+        assert!(read_buffer(huge.as_slice()).is_err());
+
+        let mut invalid = bytes.clone();
+        invalid[19] = 0xf0;
+        assert!(read_buffer(invalid.as_slice()).is_err());
+
+        let mut newer = bytes.clone();
+        newer[8] = 9;
+        assert!(read_buffer(newer.as_slice()).is_err());
+
+        // Version 1 had no flags.
+        let mut version_1 = bytes.clone();
+        version_1[8] = 1;
+        version_1.remove(18);
+        assert_eq!(read_buffer(version_1.as_slice()).unwrap().data.len(), 10);
+
+        let mut painter = CFRPainter::new();
+        painter.x = 5;
+        let file = BufferFile {
+            buffer: CFRBuffer::new(5, 2),
+            painter: Some(painter),
+        };
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+        assert!(BufferFile::read(bytes.as_slice()).is_err());
+    }
+}
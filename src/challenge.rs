@@ -0,0 +1,251 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use std::str::FromStr;
+
+/// The color of the walls on the canvas of a maze.
+pub const WALL: CFRColor = CFRColor::Blue;
+/// The color of the goal on the canvas of a maze.
+pub const GOAL: CFRColor = CFRColor::Green;
+
+/// A maze where every character is a pixel: `#` is a wall, `S` the start of the painter, `G`
+/// the goal, and `.` or a space open floor. Shorter lines are padded with floor.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::challenge::Maze;
+///
+/// let maze: Maze = "#####\n#S.G#\n#####".parse().unwrap();
+/// assert_eq!((maze.width, maze.height), (5, 3));
+/// assert_eq!((maze.start, maze.goal), ((1, 1), (3, 1)));
+/// assert!(maze.is_wall(0, 0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Maze {
+    pub width: u32,
+    pub height: u32,
+    walls: Vec<bool>,
+    pub start: (u32, u32),
+    pub goal: (u32, u32),
+}
+
+impl FromStr for Maze {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().map(|l| l.trim_end()).collect();
+        let height = lines.len() as u32;
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+        if width == 0 {
+            return Err("The maze is empty".to_string());
+        }
+        let mut walls = vec![false; width as usize * height as usize];
+        let (mut start, mut goal) = (None, None);
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let position = (x as u32, y as u32);
+                let marker = match c {
+                    '#' => {
+                        walls[y * width as usize + x] = true;
+                        continue;
+                    }
+                    '.' | ' ' => continue,
+                    'S' => &mut start,
+                    'G' => &mut goal,
+                    c => return Err(format!("Unexpected '{}' on line {} of the maze", c, y + 1)),
+                };
+                if marker.replace(position).is_some() {
+                    return Err(format!("The maze has more than one '{}'", c));
+                }
+            }
+        }
+        Ok(Maze {
+            width,
+            height,
+            walls,
+            start: start.ok_or("The maze has no start 'S'")?,
+            goal: goal.ok_or("The maze has no goal 'G'")?,
+        })
+    }
+}
+
+impl Maze {
+    pub fn is_wall(&self, x: u32, y: u32) -> bool {
+        self.walls[(y * self.width + x) as usize]
+    }
+
+    /// Returns the canvas of the maze: the walls in `WALL` and the goal in `GOAL` on
+    /// `background`.
+    pub fn to_buffer(&self, background: CFRColor) -> CFRBuffer {
+        let mut buffer = CFRBuffer::new(self.width, self.height);
+        for (pixel, &wall) in buffer.data.iter_mut().zip(&self.walls) {
+            *pixel = if wall { WALL } else { background };
+        }
+        buffer.data[(self.goal.1 * self.width + self.goal.0) as usize] = GOAL;
+        buffer
+    }
+}
+
+/// What a program has to draw or where it has to go.
+#[derive(Debug, Clone)]
+pub enum Challenge {
+    /// Draw this picture, starting from a black canvas of the same size.
+    Target(CFRBuffer),
+    /// Paint a path from the start to the goal of the maze without painting over a wall.
+    Maze(Maze),
+}
+
+/// How well a program did in a `Challenge`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    /// The number of commands the program executed.
+    pub steps: u64,
+    /// Why the program stopped, if it failed or ran out of steps.
+    pub error: Option<&'static str>,
+    /// The percentage of pixels that match the target, for `Challenge::Target`.
+    pub matched: Option<f64>,
+    /// Whether the painter reached the goal, for `Challenge::Maze`.
+    pub reached_goal: Option<bool>,
+    /// The first wall the painter painted over, if any.
+    pub wall_hit: Option<(u32, u32)>,
+}
+
+impl Score {
+    /// Returns whether the program drew the target exactly, or reached the goal of the maze
+    /// without touching a wall.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+            && !matches!(self.matched, Some(m) if m < 100.0)
+            && self.reached_goal != Some(false)
+            && self.wall_hit.is_none()
+    }
+
+    /// Returns the score as a JSON object.
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"passed\":{},\"steps\":{},\"error\":{},\"matched\":{},\"reached_goal\":{},\"wall_hit\":{}}}",
+            self.passed(),
+            self.steps,
+            optional(self.error.map(|e| format!("\"{}\"", e))),
+            optional(self.matched.map(|m| format!("{:.2}", m))),
+            optional(self.reached_goal.map(|r| r.to_string())),
+            optional(self.wall_hit.map(|(x, y)| format!("[{},{}]", x, y))),
+        )
+    }
+}
+
+impl Challenge {
+    /// Runs `program` for at most `max_steps` steps and scores it.
+    ///
+    /// In a maze, the painter starts at the start facing up, and the program stops as soon as
+    /// it paints the goal or a wall.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::challenge::Challenge;
+    ///
+    /// let maze = "#####\n#S.G#\n#####".parse().unwrap();
+    /// let challenge = Challenge::Maze(maze);
+    /// assert!(challenge.run("RRFF", 1000).passed());
+    ///
+    /// let score = challenge.run("F", 1000);
+    /// assert_eq!(score.wall_hit, Some((1, 0)));
+    /// assert!(!score.passed());
+    /// ```
+    pub fn run(&self, program: &str, max_steps: u64) -> Score {
+        let mut score = Score {
+            steps: 0,
+            error: None,
+            matched: None,
+            reached_goal: None,
+            wall_hit: None,
+        };
+        let mut buffer = match self {
+            Challenge::Target(target) => CFRBuffer::new(target.width, target.height),
+            Challenge::Maze(maze) => maze.to_buffer(CFRColor::Black),
+        };
+        let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+        if let Challenge::Maze(maze) = self {
+            score.reached_goal = Some(false);
+            let painter = executor.painter_mut();
+            (painter.x, painter.y) = maze.start;
+        }
+        loop {
+            if score.steps >= max_steps {
+                score.error = Some("Step limit exceeded");
+                break;
+            }
+            let draws = executor.view().next_command() == Some('F');
+            match executor.step() {
+                Ok(_) => score.steps += 1,
+                Err("End of commands") => break,
+                Err(e) => {
+                    score.error = Some(e);
+                    break;
+                }
+            }
+            if let (Challenge::Maze(maze), true) = (self, draws) {
+                let (x, y) = (executor.painter().x, executor.painter().y);
+                if maze.is_wall(x, y) {
+                    score.wall_hit = Some((x, y));
+                    break;
+                }
+                if (x, y) == maze.goal {
+                    score.reached_goal = Some(true);
+                    break;
+                }
+            }
+        }
+        if let Challenge::Target(target) = self {
+            let same = buffer.data.iter().zip(&target.data).filter(|(a, b)| a == b);
+            score.matched = Some(100.0 * same.count() as f64 / target.data.len().max(1) as f64);
+        }
+        score
+    }
+}
+
+mod tests {
+    #[test]
+    fn scores_targets_and_mazes() {
+        use crate::challenge::{Challenge, Maze};
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let mut target = CFRBuffer::new(4, 4);
+        CommandExecutor::new("FF".to_string(), &mut target)
+            .run()
+            .unwrap();
+        let challenge = Challenge::Target(target);
+        let score = challenge.run("FF", 100);
+        assert_eq!(score.matched, Some(100.0));
+        assert!(score.passed());
+        let score = challenge.run("F", 100);
+        assert_eq!(score.matched, Some(93.75));
+        assert!(!score.passed());
+        assert_eq!(
+            challenge.run("[[[[[[[[[[[[F]]]]]]]]]]]]", 10).error,
+            Some("Step limit exceeded")
+        );
+
+        let maze: Maze = "#####\n#S..#\n###G#\n#####".parse().unwrap();
+        assert_eq!(
+            maze.to_buffer(CFRColor::Black).data[3 * 5 - 2],
+            CFRColor::Green
+        );
+        let challenge = Challenge::Maze(maze);
+        assert!(challenge.run("RRFFF", 100).wall_hit == Some((4, 1)));
+        let score = challenge.run("RRFFRRF", 100);
+        assert_eq!(score.reached_goal, Some(true));
+        assert_eq!(
+            score.to_json(),
+            "{\"passed\":true,\"steps\":7,\"error\":null,\"matched\":null,\"reached_goal\":true,\"wall_hit\":null}"
+        );
+        assert_eq!(challenge.run("", 100).reached_goal, Some(false));
+
+        assert!("#S#".parse::<Maze>().is_err());
+        assert!("SGS".parse::<Maze>().is_err());
+        assert!("S?G".parse::<Maze>().is_err());
+    }
+}
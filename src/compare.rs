@@ -0,0 +1,142 @@
+use crate::buffer::CFRBuffer;
+
+/// How two renders of the same size differ.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    pub width: u32,
+    pub height: u32,
+    /// Number of pixels with different colors.
+    pub differing: usize,
+    /// The smallest rectangle around every differing pixel, as `(left, top, right, bottom)`
+    /// with inclusive edges, or `None` if the renders are identical.
+    pub bounds: Option<(u32, u32, u32, u32)>,
+}
+
+impl Comparison {
+    pub fn is_identical(&self) -> bool {
+        self.differing == 0
+    }
+
+    /// Returns the fraction of pixels that differ, from 0 to 1.
+    pub fn ratio(&self) -> f64 {
+        let total = self.width as f64 * self.height as f64;
+        if total == 0.0 {
+            0.0
+        } else {
+            self.differing as f64 / total
+        }
+    }
+}
+
+/// Compares two renders pixel by pixel.
+///
+/// # Panics
+///
+/// Panics if the buffers have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::compare::compare;
+/// use cfrs::{CFRBuffer, CommandExecutor};
+///
+/// let mut a = CFRBuffer::new(8, 8);
+/// CommandExecutor::new("FF".to_string(), &mut a).run().unwrap();
+/// let mut b = CFRBuffer::new(8, 8);
+/// CommandExecutor::new("FRRF".to_string(), &mut b).run().unwrap();
+///
+/// let comparison = compare(&a, &b);
+/// assert_eq!(comparison.differing, 2);
+/// assert_eq!(comparison.bounds, Some((3, 1, 4, 2)));
+/// ```
+pub fn compare(a: &CFRBuffer, b: &CFRBuffer) -> Comparison {
+    assert_same_size(a, b);
+    let mut comparison = Comparison {
+        width: a.width,
+        height: a.height,
+        differing: 0,
+        bounds: None,
+    };
+    for (i, _) in a
+        .data
+        .iter()
+        .zip(&b.data)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+    {
+        let (x, y) = (i as u32 % a.width, i as u32 / a.width);
+        comparison.differing += 1;
+        comparison.bounds = Some(match comparison.bounds {
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+    comparison
+}
+
+/// Returns for every pixel whether it differs between `a` and `b`.
+///
+/// # Panics
+///
+/// Panics if the buffers have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::compare::diff_mask;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let a = CFRBuffer::new(3, 1);
+/// let mut b = a.clone();
+/// b.data[2] = CFRColor::Red;
+/// assert_eq!(diff_mask(&a, &b), vec![false, false, true]);
+/// ```
+pub fn diff_mask(a: &CFRBuffer, b: &CFRBuffer) -> Vec<bool> {
+    assert_same_size(a, b);
+    a.data.iter().zip(&b.data).map(|(a, b)| a != b).collect()
+}
+
+/// Returns an image of `a` with the pixels that differ in `b` highlighted, as raw RGBA bytes
+/// like `CFRBuffer::to_rgba_bytes`.
+///
+/// Differing pixels are red, and the other pixels are dimmed to a quarter of their
+/// brightness, so the changes stand out even on red drawings.
+///
+/// # Panics
+///
+/// Panics if the buffers have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::compare::diff_image;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let a = CFRBuffer::new(2, 1);
+/// let mut b = a.clone();
+/// b.data[1] = CFRColor::White;
+/// assert_eq!(diff_image(&a, &b), vec![0, 0, 0, 255, 255, 0, 0, 255]);
+/// ```
+pub fn diff_image(a: &CFRBuffer, b: &CFRBuffer) -> Vec<u8> {
+    a.data
+        .iter()
+        .zip(diff_mask(a, b))
+        .flat_map(|(a, differs)| {
+            if differs {
+                [255, 0, 0, 255]
+            } else {
+                let [r, g, b] = a.to_rgb();
+                [r / 4, g / 4, b / 4, 255]
+            }
+        })
+        .collect()
+}
+
+fn assert_same_size(a: &CFRBuffer, b: &CFRBuffer) {
+    assert!(
+        a.width == b.width && a.height == b.height,
+        "Buffers have different dimensions"
+    );
+}
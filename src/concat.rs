@@ -0,0 +1,138 @@
+use crate::analysis::analyze;
+use crate::validator::{validate, Diagnostic};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// What goes between two programs joined by `concat`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Separator {
+    /// Nothing.
+    None,
+    /// A line break, which keeps the parts readable and runs nothing.
+    #[default]
+    Newline,
+    /// An `S`, which pauses animations for a frame between scenes.
+    Sleep,
+}
+
+/// Converts `none`, `newline`, or `sleep` to a `Separator`.
+impl FromStr for Separator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Separator::None),
+            "newline" => Ok(Separator::Newline),
+            "sleep" => Ok(Separator::Sleep),
+            _ => Err(format!("Invalid separator: {}", s)),
+        }
+    }
+}
+
+/// Options of `concat`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ConcatOptions {
+    pub separator: Separator,
+    /// After every part but the last, add the `C`s and `R`s that bring the painter back to
+    /// white and facing up, so that every part starts with the color and direction it was
+    /// written for. The painter keeps its position, since it can't move without drawing.
+    pub reset: bool,
+}
+
+/// A part that can't be joined, because one of its brackets is unmatched and would pair up
+/// with a bracket of another part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcatError {
+    /// The index of the part.
+    pub part: usize,
+    pub diagnostic: Diagnostic,
+}
+
+impl Display for ConcatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "part {}: {}", self.part + 1, self.diagnostic)
+    }
+}
+
+/// Joins programs into one that runs them one after the other, e.g. to assemble an
+/// animation from scenes written separately.
+///
+/// A part that ends in a `#` comment gets a line break, so that the comment doesn't hide
+/// the next part.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::concat::{concat, ConcatOptions, Separator};
+///
+/// let options = ConcatOptions {
+///     separator: Separator::Sleep,
+///     reset: true,
+/// };
+/// assert_eq!(concat(&["C[FR]", "F # two"], &options).unwrap(), "C[FR]CCCCCCCRRRRRRSF # two\n");
+/// assert_eq!(concat(&["F # one", "F"], &ConcatOptions::default()).unwrap(), "F # one\n\nF");
+///
+/// let error = concat(&["F", "[F"], &options).unwrap_err();
+/// assert_eq!((error.part, error.diagnostic.span), (1, 0..1));
+/// ```
+pub fn concat<S: AsRef<str>>(parts: &[S], options: &ConcatOptions) -> Result<String, ConcatError> {
+    let mut program = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        let part = part.as_ref();
+        if let Some(diagnostic) = validate(part, false)
+            .into_iter()
+            .find(|d| matches!(&part[d.span.clone()], "[" | "]"))
+        {
+            return Err(ConcatError {
+                part: i,
+                diagnostic,
+            });
+        }
+
+        if i > 0 {
+            match options.separator {
+                Separator::None => {}
+                Separator::Newline => program.push('\n'),
+                Separator::Sleep => program.push('S'),
+            }
+        }
+        program.push_str(part);
+        if part
+            .rsplit('\n')
+            .next()
+            .is_some_and(|line| line.contains('#'))
+        {
+            program.push('\n');
+        }
+        if options.reset && i + 1 < parts.len() {
+            let analysis = analyze(part);
+            program.push_str(&"C".repeat((8 - analysis.color_shift) % 8));
+            program.push_str(&"R".repeat((8 - analysis.rotation) % 8));
+        }
+    }
+    Ok(program)
+}
+
+mod tests {
+    #[test]
+    fn reset_restores_the_painter() {
+        use crate::concat::{concat, ConcatOptions, Separator};
+        use crate::{CFRBuffer, CFRPainter, CommandExecutor};
+
+        let options = ConcatOptions {
+            separator: Separator::None,
+            reset: true,
+        };
+        let program = concat(&["C[[CR]]RF", "[CCC]"], &options).unwrap();
+        let mut buffer = CFRBuffer::new(8, 8);
+        let mut executor = CommandExecutor::new(program.clone(), &mut buffer);
+        let start = CFRPainter::new();
+        let second = program.rfind('[').unwrap();
+        while executor.view().index < second {
+            executor.step().unwrap();
+        }
+        assert_eq!(executor.painter().color, start.color);
+        assert_eq!(executor.painter().direction, start.direction);
+        assert!("pause".parse::<Separator>().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+
+/// The expected result of a `TestVector`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// The program finishes and the canvas has this `digest`.
+    Digest(u64),
+    /// The program fails with this error.
+    Error(&'static str),
+}
+
+/// A program with its reference result.
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    pub name: &'static str,
+    pub program: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub expected: Expected,
+}
+
+/// The result of running a `TestVector`.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub vector: &'static TestVector,
+    pub actual: Expected,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.actual == self.vector.expected
+    }
+}
+
+/// The reference programs: the examples from the README, plus edge cases of the semantics
+/// (wrapping, ignored characters including `#`, and errors).
+pub const VECTORS: &[TestVector] = &[
+    TestVector {
+        name: "flower",
+        program: "[[[[[[[[[[F]]]]R]]RR]]RRCC]]",
+        width: 256,
+        height: 256,
+        expected: Expected::Digest(0x31b7379cbb973ae2),
+    },
+    TestVector {
+        name: "flower-animation",
+        program: "[[[[[[[[[[FS]]]]R]]RR]]RRCC]][[[[[[[[S]]]]]]]]",
+        width: 256,
+        height: 256,
+        expected: Expected::Digest(0x31b7379cbb973ae2),
+    },
+    TestVector {
+        name: "sky",
+        program:
+            "[[CC[C[C[[[[[[[[[[[F]]]]]][[[R[[[[[[F]]R[[F]]]]]]]]]RS][[R]]]]R]]]RF]]][[[[[[[S]]]]]]]",
+        width: 512,
+        height: 512,
+        expected: Expected::Digest(0xf7d2b2ae29c8c78c),
+    },
+    TestVector {
+        name: "frames",
+        program: "[[[CR[[[[[[[[[[F]]]]FF]FF]FF]R]FF]FF]FF]FFR]FF]",
+        width: 256,
+        height: 256,
+        expected: Expected::Digest(0xdc1d1288cbb2868a),
+    },
+    TestVector {
+        name: "wrap-around",
+        program: "RRR[[[[[[F]]]]]]",
+        width: 16,
+        height: 12,
+        expected: Expected::Digest(0x011833b01ad3b419),
+    },
+    TestVector {
+        name: "color-cycle",
+        program: "[[[CFR]]]F",
+        width: 8,
+        height: 8,
+        expected: Expected::Digest(0x3a90f64d6712bd82),
+    },
+    TestVector {
+        name: "hash-is-not-a-comment",
+        program: "F # FFFF [\nRRF#",
+        width: 8,
+        height: 8,
+        expected: Expected::Digest(0xae834c4254e3b8f7),
+    },
+    TestVector {
+        name: "ignored-characters",
+        program: "xFé yR[F]\t",
+        width: 8,
+        height: 8,
+        expected: Expected::Digest(0x5cc5d353c8d7cc54),
+    },
+    TestVector {
+        name: "empty",
+        program: "",
+        width: 4,
+        height: 4,
+        expected: Expected::Digest(0xc6a438a2986878d5),
+    },
+    TestVector {
+        name: "unmatched-block-end",
+        program: "F]F",
+        width: 4,
+        height: 4,
+        expected: Expected::Error("Unmatched ]"),
+    },
+];
+
+/// Returns the 64-bit FNV-1a hash of the dimensions (little-endian) and the palette index
+/// of every pixel.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::conformance::digest;
+/// use cfrs::CFRBuffer;
+///
+/// assert_ne!(digest(&CFRBuffer::new(2, 1)), digest(&CFRBuffer::new(1, 2)));
+/// ```
+pub fn digest(buffer: &CFRBuffer) -> u64 {
+    let bytes = buffer
+        .width
+        .to_le_bytes()
+        .into_iter()
+        .chain(buffer.height.to_le_bytes())
+        .chain(buffer.data.iter().map(|c| *c as u8));
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Runs a single test vector.
+pub fn run(vector: &'static TestVector) -> Outcome {
+    let mut buffer = CFRBuffer::new(vector.width, vector.height);
+    let mut executor = CommandExecutor::new(vector.program.to_string(), &mut buffer);
+    let actual = match executor.run() {
+        Ok(()) => Expected::Digest(digest(&buffer)),
+        Err(e) => Expected::Error(e),
+    };
+    Outcome { vector, actual }
+}
+
+/// Runs every test vector in `VECTORS`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::conformance::run_all;
+///
+/// assert!(run_all().iter().all(|outcome| outcome.passed()));
+/// ```
+pub fn run_all() -> Vec<Outcome> {
+    VECTORS.iter().map(run).collect()
+}
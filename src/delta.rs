@@ -0,0 +1,358 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use std::io::{self, Read, Write};
+
+/// The first bytes of every binary delta animation.
+pub const MAGIC: &[u8; 8] = b"CFRSDLT\0";
+
+/// The current version of the binary delta animation format.
+pub const VERSION: u16 = 1;
+
+/// Maximum number of canvas pixels `read_binary` accepts.
+pub const MAX_PIXELS: usize = 1 << 28;
+
+/// Maximum number of deltas `read_binary` accepts.
+pub const MAX_FRAMES: u32 = 1 << 20;
+
+/// An animation stored as a keyframe plus the pixels that change from one frame to the next.
+///
+/// Players draw the keyframe once and then apply one delta per frame, which is much smaller
+/// than full frames when only a few pixels change per sleep.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::delta::DeltaAnimation;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(4, 4);
+/// let mut animation = DeltaAnimation::new(&frame, 100);
+/// frame.data[5] = CFRColor::Red;
+/// animation.push(&frame);
+///
+/// assert_eq!(animation.deltas, vec![vec![(5, CFRColor::Red)]]);
+/// assert_eq!(animation.frames().last().unwrap().data, frame.data);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeltaAnimation {
+    pub width: u32,
+    pub height: u32,
+    /// Delay between frames in milliseconds.
+    pub delay: u32,
+    /// The first frame.
+    pub keyframe: Vec<CFRColor>,
+    /// For every following frame, the pixel indices that changed and their new colors.
+    pub deltas: Vec<Vec<(u32, CFRColor)>>,
+    last: Vec<CFRColor>,
+}
+
+impl DeltaAnimation {
+    /// Creates an animation that starts with `keyframe`.
+    pub fn new(keyframe: &CFRBuffer, delay: u32) -> Self {
+        Self {
+            width: keyframe.width,
+            height: keyframe.height,
+            delay,
+            keyframe: keyframe.data.clone(),
+            deltas: Vec::new(),
+            last: keyframe.data.clone(),
+        }
+    }
+
+    /// Appends a frame, storing only the pixels that differ from the previous frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame does not have the dimensions of the keyframe.
+    pub fn push(&mut self, frame: &CFRBuffer) {
+        assert_eq!(
+            (frame.width, frame.height),
+            (self.width, self.height),
+            "Frame dimensions differ from the keyframe"
+        );
+        let changes = self
+            .last
+            .iter_mut()
+            .zip(&frame.data)
+            .enumerate()
+            .filter(|(_, (last, color))| **last != **color)
+            .map(|(i, (last, color))| {
+                *last = *color;
+                (i as u32, *color)
+            })
+            .collect();
+        self.deltas.push(changes);
+    }
+
+    /// Reconstructs every frame, starting with the keyframe.
+    pub fn frames(&self) -> Vec<CFRBuffer> {
+        let mut frame = CFRBuffer {
+            width: self.width,
+            height: self.height,
+            data: self.keyframe.clone(),
+        };
+        let mut frames = vec![frame.clone()];
+        for delta in &self.deltas {
+            for &(i, color) in delta {
+                frame.data[i as usize] = color;
+            }
+            frames.push(frame.clone());
+        }
+        frames
+    }
+
+    /// Writes the animation as JSON.
+    ///
+    /// The keyframe is a string with one palette index per pixel, and every delta is a flat
+    /// array of `pixel index, palette index` pairs:
+    ///
+    /// ```json
+    /// {"width":2,"height":1,"delay":100,"palette":["#ffffff",...],"keyframe":"11","deltas":[[0,5]]}
+    /// ```
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "{{\"width\":{},\"height\":{},\"delay\":{},\"palette\":[",
+            self.width, self.height, self.delay
+        )?;
+        for (i, color) in CFRColor::ALL.iter().enumerate() {
+            let [r, g, b] = color.to_rgb();
+            let separator = if i == 0 { "" } else { "," };
+            write!(writer, "{}\"#{:02x}{:02x}{:02x}\"", separator, r, g, b)?;
+        }
+        let keyframe: String = self
+            .keyframe
+            .iter()
+            .map(|c| char::from(b'0' + *c as u8))
+            .collect();
+        write!(writer, "],\"keyframe\":\"{}\",\"deltas\":[", keyframe)?;
+        for (i, delta) in self.deltas.iter().enumerate() {
+            writer.write_all(if i == 0 { b"[" } else { b",[" })?;
+            for (j, (pixel, color)) in delta.iter().enumerate() {
+                let separator = if j == 0 { "" } else { "," };
+                write!(writer, "{}{},{}", separator, pixel, *color as u8)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        writer.write_all(b"]}")?;
+        writer.flush()
+    }
+
+    /// Writes the animation in the compact binary format.
+    ///
+    /// After the magic, a little-endian `u16` version and `u32` width, height, delay and delta
+    /// count, the keyframe follows with two pixels per byte (high nibble first). Every delta is
+    /// a LEB128 change count followed by, per change, the LEB128 distance from the previous
+    /// changed pixel (plus one) and a palette index byte.
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        for value in [
+            self.width,
+            self.height,
+            self.delay,
+            self.deltas.len() as u32,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        let packed: Vec<u8> = self
+            .keyframe
+            .chunks(2)
+            .map(|pair| (pair[0] as u8) << 4 | pair.get(1).map_or(0, |c| *c as u8))
+            .collect();
+        writer.write_all(&packed)?;
+
+        for delta in &self.deltas {
+            write_varint(&mut writer, delta.len() as u64)?;
+            let mut next = 0;
+            for &(pixel, color) in delta {
+                write_varint(&mut writer, (pixel - next) as u64)?;
+                writer.write_all(&[color as u8])?;
+                next = pixel + 1;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Reads an animation in the binary format written by `write_binary`.
+    pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 26];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid_data("Not a delta animation".to_string()));
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version > VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported delta animation version: {}",
+                version
+            )));
+        }
+        let field =
+            |i: usize| u32::from_le_bytes(header[10 + i * 4..14 + i * 4].try_into().unwrap());
+        let (width, height, delay, count) = (field(0), field(1), field(2), field(3));
+
+        let pixels = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|&pixels| pixels <= MAX_PIXELS)
+            .ok_or_else(|| {
+                invalid_data(format!("The canvas is too large: {}x{}", width, height))
+            })?;
+        if count > MAX_FRAMES {
+            return Err(invalid_data(format!("Too many frames: {}", count)));
+        }
+
+        let mut packed = Vec::new();
+        let len = pixels.div_ceil(2);
+        reader.by_ref().take(len as u64).read_to_end(&mut packed)?;
+        if packed.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let keyframe = packed
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0xf])
+            .take(pixels)
+            .map(to_color)
+            .collect::<io::Result<Vec<CFRColor>>>()?;
+
+        let mut deltas = Vec::new();
+        for _ in 0..count {
+            let changes = read_varint(&mut reader)?;
+            if changes > pixels as u64 {
+                return Err(invalid_data(format!("Too many changes: {}", changes)));
+            }
+            let mut delta = Vec::new();
+            let mut next: u64 = 0;
+            for _ in 0..changes {
+                let pixel = next.saturating_add(read_varint(&mut reader)?);
+                if pixel >= pixels as u64 {
+                    return Err(invalid_data(format!("Pixel out of range: {}", pixel)));
+                }
+                let mut color = [0; 1];
+                reader.read_exact(&mut color)?;
+                delta.push((pixel as u32, to_color(color[0])?));
+                next = pixel + 1;
+            }
+            deltas.push(delta);
+        }
+
+        let mut last = keyframe.clone();
+        for &(i, color) in deltas.iter().flatten() {
+            last[i as usize] = color;
+        }
+        Ok(Self {
+            width,
+            height,
+            delay,
+            keyframe,
+            deltas,
+            last,
+        })
+    }
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("Varint too long".to_string()))
+}
+
+fn to_color(index: u8) -> io::Result<CFRColor> {
+    CFRColor::ALL
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| invalid_data(format!("Invalid color: {}", index)))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+mod tests {
+    #[test]
+    fn binary_round_trip() {
+        use crate::delta::DeltaAnimation;
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let mut buffer = CFRBuffer::new(15, 9);
+        let mut frames = Vec::new();
+        let mut executor = CommandExecutor::new("[[[[[[FSC]]]R]]]".to_string(), &mut buffer);
+        while let Ok((sleep, buf)) = executor.step() {
+            if sleep {
+                frames.push(buf.clone());
+            }
+        }
+
+        let mut animation = DeltaAnimation::new(&frames[0], 20);
+        frames[1..].iter().for_each(|f| animation.push(f));
+        let mut bytes = Vec::new();
+        animation.write_binary(&mut bytes).unwrap();
+        let mut read = DeltaAnimation::read_binary(bytes.as_slice()).unwrap();
+
+        assert_eq!(read.delay, 20);
+        assert_eq!(read.deltas, animation.deltas);
+        let decoded: Vec<_> = read.frames().into_iter().map(|f| f.data).collect();
+        let expected: Vec<_> = frames.iter().map(|f| f.data.clone()).collect();
+        assert_eq!(decoded, expected);
+
+        // Reading restores the last frame, so more frames can be appended.
+        read.push(&buffer);
+        assert!(read.deltas.last().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reject_oversized_header() {
+        use crate::delta::{DeltaAnimation, MAGIC, VERSION};
+
+        let header = |width: u32, height: u32, count: u32| {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend_from_slice(&VERSION.to_le_bytes());
+            for field in [width, height, 100, count] {
+                bytes.extend_from_slice(&field.to_le_bytes());
+            }
+            bytes
+        };
+        for bytes in [header(u32::MAX, u32::MAX, 0), header(2, 2, u32::MAX)] {
+            let error = DeltaAnimation::read_binary(bytes.as_slice()).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn json() {
+        use crate::delta::DeltaAnimation;
+        use crate::{CFRBuffer, CFRColor};
+
+        let mut frame = CFRBuffer::new(2, 1);
+        let mut animation = DeltaAnimation::new(&frame, 100);
+        frame.data[1] = CFRColor::Red;
+        animation.push(&frame);
+        animation.push(&frame);
+
+        let mut json = Vec::new();
+        animation.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(
+            json.starts_with("{\"width\":2,\"height\":1,\"delay\":100,\"palette\":[\"#ffffff\",")
+        );
+        assert!(json.ends_with("\"keyframe\":\"11\",\"deltas\":[[1,5],[]]}"));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::bitmap::{write_bmp, write_pgm};
+use crate::buffer::CFRBuffer;
+use crate::conformance;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::terminal::ColorMode;
+use std::fmt::Display;
+
+/// Returns every feature of the crate, and whether it is enabled in this build.
+pub fn features() -> Vec<(&'static str, bool)> {
+    vec![
+        ("arbitrary", cfg!(feature = "arbitrary")),
+        ("async", cfg!(feature = "async")),
+        ("batch", cfg!(feature = "batch")),
+        ("bevy_cfrs", cfg!(feature = "bevy_cfrs")),
+        ("checked-math", cfg!(feature = "checked-math")),
+        ("ctrlc", cfg!(feature = "ctrlc")),
+        ("egui", cfg!(feature = "egui")),
+        ("embedded-graphics", cfg!(feature = "embedded-graphics")),
+        ("encode", cfg!(feature = "encode")),
+        ("evcxr", cfg!(feature = "evcxr")),
+        ("ffi", cfg!(feature = "ffi")),
+        ("image", cfg!(feature = "image")),
+        ("lsp", cfg!(feature = "lsp")),
+        ("node", cfg!(feature = "node")),
+        ("ratatui", cfg!(feature = "ratatui")),
+        ("scripting", cfg!(feature = "scripting")),
+        ("server", cfg!(feature = "server")),
+        ("transparent", cfg!(feature = "transparent")),
+        ("webp", cfg!(feature = "webp")),
+        ("wgpu", cfg!(feature = "wgpu")),
+        ("worker", cfg!(feature = "worker")),
+    ]
+}
+
+/// What the terminal can show, as far as its environment variables tell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminal {
+    /// `TERM_PROGRAM`, or `TERM` if it is not set.
+    pub name: Option<String>,
+    pub colors: ColorMode,
+    /// Whether the terminal is known to show sixel graphics. Only a few terminals say so in
+    /// their environment, so this can be `false` for terminals that do.
+    pub sixel: bool,
+    /// Whether stdout is a terminal rather than a file or a pipe.
+    pub is_terminal: bool,
+}
+
+impl Terminal {
+    /// Detects the terminal of stdout.
+    pub fn detect() -> Self {
+        Self::detect_from(
+            |name| std::env::var(name).ok(),
+            std::io::IsTerminal::is_terminal(&std::io::stdout()),
+        )
+    }
+
+    /// Like `detect`, with the environment variables of `env`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::doctor::Terminal;
+    /// use cfrs::terminal::ColorMode;
+    ///
+    /// let terminal = Terminal::detect_from(
+    ///     |name| match name {
+    ///         "TERM" => Some("xterm-256color".to_string()),
+    ///         "TERM_PROGRAM" => Some("WezTerm".to_string()),
+    ///         _ => None,
+    ///     },
+    ///     true,
+    /// );
+    /// assert_eq!(terminal.colors, ColorMode::Ansi256);
+    /// assert!(terminal.sixel);
+    ///
+    /// let terminal = Terminal::detect_from(|_| None, false);
+    /// assert_eq!((terminal.colors, terminal.sixel), (ColorMode::Ansi16, false));
+    /// ```
+    pub fn detect_from(env: impl Fn(&str) -> Option<String>, is_terminal: bool) -> Self {
+        let term = env("TERM").unwrap_or_default();
+        let program = env("TERM_PROGRAM").unwrap_or_default();
+        let sixel = term.contains("sixel")
+            || ["mlterm", "foot", "yaft", "contour"]
+                .iter()
+                .any(|t| term.starts_with(t))
+            || ["WezTerm", "iTerm.app", "mintty", "contour"].contains(&program.as_str());
+        Self {
+            name: [program, term.clone()].into_iter().find(|s| !s.is_empty()),
+            colors: ColorMode::detect_from(env("COLORTERM").as_deref(), Some(&term)),
+            sixel,
+            is_terminal,
+        }
+    }
+}
+
+/// A step of the self-test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    /// What the step produced, or why it failed.
+    pub result: Result<String, String>,
+}
+
+/// The features, encoders, and terminal of this build, and a self-test that runs the
+/// reference programs and encodes a small drawing in every format, to tell why an output
+/// doesn't work on a machine.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::doctor::Report;
+///
+/// let report = Report::collect();
+/// assert!(report.healthy());
+/// assert!(report.to_string().contains("Self-test"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub version: &'static str,
+    pub features: Vec<(&'static str, bool)>,
+    /// The animation encoders of `--format`.
+    pub encoders: Vec<&'static str>,
+    pub terminal: Terminal,
+    pub checks: Vec<Check>,
+}
+
+/// The program that the self-test draws.
+const PROGRAM: &str = "[[[[FFCRS]]]]";
+
+impl Report {
+    pub fn collect() -> Self {
+        #[cfg(feature = "encode")]
+        let encoders = crate::encoder::EncoderRegistry::builtin().names().collect();
+        #[cfg(not(feature = "encode"))]
+        let encoders = Vec::new();
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            features: features(),
+            encoders,
+            terminal: Terminal::detect(),
+            checks: self_test(),
+        }
+    }
+
+    /// Returns whether every step of the self-test passed.
+    pub fn healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.result.is_ok())
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = |enabled: bool| {
+            let names: Vec<_> = self
+                .features
+                .iter()
+                .filter(|(_, e)| *e == enabled)
+                .map(|(name, _)| *name)
+                .collect();
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        };
+        writeln!(f, "cfrs {}", self.version)?;
+        writeln!(f, "Enabled features: {}", names(true))?;
+        writeln!(f, "Disabled features: {}", names(false))?;
+        if self.encoders.is_empty() {
+            writeln!(f, "Encoders: none (enable the encode feature)")?;
+        } else {
+            writeln!(f, "Encoders: {}", self.encoders.join(", "))?;
+        }
+
+        let terminal = &self.terminal;
+        let colors = match terminal.colors {
+            ColorMode::TrueColor => "24-bit colors",
+            ColorMode::Ansi256 => "256 colors",
+            ColorMode::Ansi16 => "16 colors",
+        };
+        writeln!(
+            f,
+            "Terminal: {}, {}{}",
+            terminal.name.as_deref().unwrap_or("unknown"),
+            colors,
+            if terminal.is_terminal {
+                ""
+            } else {
+                " (stdout is not a terminal)"
+            }
+        )?;
+        writeln!(
+            f,
+            "Sixel: {} (terminal previews use colored half blocks, which need no sixel)",
+            if terminal.sixel {
+                "yes"
+            } else {
+                "not detected"
+            }
+        )?;
+
+        writeln!(f, "Self-test:")?;
+        for check in &self.checks {
+            match &check.result {
+                Ok(details) => writeln!(f, "  ok     {:<8} {}", check.name, details)?,
+                Err(error) => writeln!(f, "  FAILED {:<8} {}", check.name, error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the reference programs, draws a small program, and encodes the drawing in every
+/// format of this build.
+pub fn self_test() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let outcomes = conformance::run_all();
+    let failed = outcomes.iter().filter(|o| !o.passed()).count();
+    checks.push(Check {
+        name: "executor",
+        result: if failed == 0 {
+            Ok(format!("{} reference programs passed", outcomes.len()))
+        } else {
+            Err(format!(
+                "{} of {} reference programs failed; run `cfrs conformance`",
+                failed,
+                outcomes.len()
+            ))
+        },
+    });
+
+    let mut buffer = CFRBuffer::new(16, 16);
+    let mut frames = Vec::new();
+    let mut executor = CommandExecutor::new(PROGRAM.to_string(), &mut buffer);
+    let drawn = loop {
+        match executor.step() {
+            Ok((true, buffer)) => frames.push(buffer.clone()),
+            Ok(_) => {}
+            Err("End of commands") => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    let drawn = match drawn {
+        Ok(()) if buffer.data.iter().any(|c| *c != CFRColor::Black) => Ok(buffer),
+        Ok(()) => Err("The program drew nothing".to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    checks.push(Check {
+        name: "render",
+        result: drawn
+            .as_ref()
+            .map(|_| format!("{} drew {} frames", PROGRAM, frames.len()))
+            .map_err(Clone::clone),
+    });
+    let Ok(buffer) = drawn else {
+        return checks;
+    };
+
+    let mut encode = |name, magic: &[u8], bytes: std::io::Result<Vec<u8>>| {
+        let result = match bytes {
+            Ok(bytes) if bytes.starts_with(magic) => Ok(format!("{} bytes", bytes.len())),
+            Ok(_) => Err("Unexpected file header".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        checks.push(Check { name, result });
+    };
+    let write = |writer: fn(&mut Vec<u8>, &CFRBuffer) -> std::io::Result<()>| {
+        let mut bytes = Vec::new();
+        writer(&mut bytes, &buffer).map(|_| bytes)
+    };
+    encode("bmp", b"BM", write(|w, b| write_bmp(w, b)));
+    encode("pgm", b"P5", write(|w, b| write_pgm(w, b)));
+
+    #[cfg(feature = "encode")]
+    {
+        use crate::encoder::{encode_frames, EncoderOptions, EncoderRegistry};
+
+        let png = crate::encode::encode_png(&buffer).map_err(std::io::Error::other);
+        encode("png", b"\x89PNG", png);
+        let registry = EncoderRegistry::builtin();
+        let delays = vec![20; frames.len()];
+        for name in registry.names() {
+            let mut encoder = registry.get(name, &EncoderOptions::default()).unwrap();
+            let magic: &[u8] = match name {
+                "gif" => b"GIF89a",
+                "apng" | "strip" => b"\x89PNG",
+                "webp" => b"RIFF",
+                _ => b"",
+            };
+            encode(
+                name,
+                magic,
+                encode_frames(encoder.as_mut(), &frames, &delays),
+            );
+        }
+    }
+
+    checks
+}
+
+mod tests {
+    #[test]
+    fn failed_checks_make_the_report_unhealthy() {
+        use crate::doctor::{features, self_test, Check, Report, Terminal};
+
+        let mut report = Report {
+            version: "1.0.0",
+            features: features(),
+            encoders: Vec::new(),
+            terminal: Terminal::detect_from(|_| None, false),
+            checks: self_test(),
+        };
+        assert!(report.healthy());
+        assert!(report.checks.iter().any(|c| c.name == "pgm"));
+
+        report.checks.push(Check {
+            name: "gif",
+            result: Err("Unexpected file header".to_string()),
+        });
+        assert!(!report.healthy());
+        let text = report.to_string();
+        assert!(text.contains("Encoders: none"));
+        assert!(text.contains("unknown, 16 colors (stdout is not a terminal)"));
+        assert!(text.contains("  FAILED gif      Unexpected file header"));
+    }
+}
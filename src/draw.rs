@@ -0,0 +1,311 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+
+// Shapes may stick out of the canvas; the pixels outside of it are skipped rather than
+// wrapped like the painter does.
+
+/// Fills the `width` × `height` rectangle with its top-left corner at (`x`, `y`).
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::draw::fill_rect;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(4, 4);
+/// fill_rect(&mut buffer, -1, 2, 2, 5, CFRColor::Red);
+/// let red = buffer.data.iter().filter(|&&c| c == CFRColor::Red).count();
+/// assert_eq!(red, 2);
+/// assert_eq!(buffer.data[2 * 4], CFRColor::Red);
+/// ```
+pub fn fill_rect(buffer: &mut CFRBuffer, x: i32, y: i32, width: u32, height: u32, color: CFRColor) {
+    let clamp = |v: i64, max: u32| v.clamp(0, max as i64) as u32;
+    let (x0, x1) = (
+        clamp(x as i64, buffer.width),
+        clamp(x as i64 + width as i64, buffer.width),
+    );
+    let (y0, y1) = (
+        clamp(y as i64, buffer.height),
+        clamp(y as i64 + height as i64, buffer.height),
+    );
+    for row in y0..y1 {
+        let start = (row * buffer.width) as usize;
+        buffer.data[start + x0 as usize..start + x1 as usize].fill(color);
+    }
+}
+
+/// Draws the one-pixel outline of the `width` × `height` rectangle with its top-left corner
+/// at (`x`, `y`).
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::draw::draw_rect_outline;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(5, 5);
+/// draw_rect_outline(&mut buffer, 1, 1, 3, 3, CFRColor::White);
+/// let white = buffer.data.iter().filter(|&&c| c == CFRColor::White).count();
+/// assert_eq!(white, 8);
+/// assert_eq!(buffer.data[2 * 5 + 2], CFRColor::Black);
+/// ```
+pub fn draw_rect_outline(
+    buffer: &mut CFRBuffer,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    color: CFRColor,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (right, bottom) = (x as i64 + width as i64 - 1, y as i64 + height as i64 - 1);
+    for px in x as i64..=right {
+        plot(buffer, px, y as i64, color);
+        plot(buffer, px, bottom, color);
+    }
+    for py in y as i64..=bottom {
+        plot(buffer, x as i64, py, color);
+        plot(buffer, right, py, color);
+    }
+}
+
+/// Draws the one-pixel outline of the circle around (`cx`, `cy`) with `radius`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::draw::draw_circle;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(9, 9);
+/// draw_circle(&mut buffer, 4, 4, 3, CFRColor::Green);
+/// for (x, y) in [(4, 1), (7, 4), (4, 7), (1, 4)] {
+///     assert_eq!(buffer.data[y * 9 + x], CFRColor::Green);
+/// }
+/// assert_eq!(buffer.data[4 * 9 + 4], CFRColor::Black);
+/// ```
+pub fn draw_circle(buffer: &mut CFRBuffer, cx: i32, cy: i32, radius: u32, color: CFRColor) {
+    // The midpoint circle algorithm, mirroring one octant into the other seven.
+    let (cx, cy) = (cx as i64, cy as i64);
+    let (mut x, mut y) = (radius as i64, 0i64);
+    let mut error = 1 - x;
+    while x >= y {
+        for (dx, dy) in [(x, y), (y, x)] {
+            for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                plot(buffer, cx + sx * dx, cy + sy * dy, color);
+            }
+        }
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Draws a line from (`x0`, `y0`) to (`x1`, `y1`), both ends included.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::draw::draw_line;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(4, 4);
+/// draw_line(&mut buffer, 0, 0, 3, 3, CFRColor::Blue);
+/// assert!((0..4).all(|i| buffer.data[i * 4 + i] == CFRColor::Blue));
+/// ```
+pub fn draw_line(buffer: &mut CFRBuffer, x0: i32, y0: i32, x1: i32, y1: i32, color: CFRColor) {
+    // Bresenham's algorithm, for lines in every direction.
+    let (mut x, mut y) = (x0 as i64, y0 as i64);
+    let (x1, y1) = (x1 as i64, y1 as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+    loop {
+        plot(buffer, x, y, color);
+        if x == x1 && y == y1 {
+            return;
+        }
+        if 2 * error >= dy {
+            error += dy;
+            x += sx;
+        }
+        if 2 * error <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+/// The width of a character of `draw_text`, without the column between characters.
+pub const GLYPH_WIDTH: u32 = 3;
+/// The height of a line of `draw_text`, without the row between lines.
+pub const GLYPH_HEIGHT: u32 = 5;
+
+/// Returns the 3×5 glyph of `c`, one row of 3 bits per byte, leftmost pixel first.
+/// Lowercase letters look like uppercase ones, and characters without a glyph like `?`.
+pub(crate) fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Draws `text` in a 3×5 pixel font with its top-left corner at (`x`, `y`). Characters are
+/// one pixel apart, lines one pixel apart, and `\n` starts a new line. Only the pixels of the
+/// characters are drawn, so the text is transparent around them.
+///
+/// Digits, letters, and common punctuation have glyphs; lowercase letters are drawn as
+/// uppercase ones, and other characters as `?`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::draw::{draw_text, text_size};
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(16, 8);
+/// draw_text(&mut buffer, 1, 1, "Hi!", CFRColor::Yellow);
+/// assert_eq!(text_size("Hi!"), (11, 5));
+/// // The top-left pixel of the H.
+/// assert_eq!(buffer.data[16 + 1], CFRColor::Yellow);
+/// ```
+pub fn draw_text(buffer: &mut CFRBuffer, x: i32, y: i32, text: &str, color: CFRColor) {
+    for (row, line) in text.lines().enumerate() {
+        let top = y as i64 + row as i64 * (GLYPH_HEIGHT + 1) as i64;
+        for (column, c) in line.chars().enumerate() {
+            let left = x as i64 + column as i64 * (GLYPH_WIDTH + 1) as i64;
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> dx) != 0 {
+                        plot(buffer, left + dx as i64, top + dy as i64, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the width and height in pixels of `text` drawn with `draw_text`.
+pub fn text_size(text: &str) -> (u32, u32) {
+    let lines = text.lines().count() as u32;
+    let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+    let size = |count: u32, glyph: u32| (count * (glyph + 1)).saturating_sub(1);
+    (size(columns, GLYPH_WIDTH), size(lines, GLYPH_HEIGHT))
+}
+
+/// Sets the pixel at (`x`, `y`) if it is on the canvas.
+fn plot(buffer: &mut CFRBuffer, x: i64, y: i64, color: CFRColor) {
+    if (0..buffer.width as i64).contains(&x) && (0..buffer.height as i64).contains(&y) {
+        buffer.data[(y * buffer.width as i64 + x) as usize] = color;
+    }
+}
+
+mod tests {
+    #[test]
+    fn shapes_are_clipped_to_the_canvas() {
+        use crate::draw::{
+            draw_circle, draw_line, draw_rect_outline, draw_text, fill_rect, text_size,
+        };
+        use crate::{CFRBuffer, CFRColor};
+
+        let count = |buffer: &CFRBuffer| {
+            buffer
+                .data
+                .iter()
+                .filter(|&&c| c != CFRColor::Black)
+                .count()
+        };
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        fill_rect(&mut buffer, -10, -10, 100, 100, CFRColor::Red);
+        assert_eq!(count(&buffer), 64);
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        draw_rect_outline(&mut buffer, -1, -1, 4, 4, CFRColor::Red);
+        assert_eq!(count(&buffer), 5);
+        fill_rect(&mut buffer, 100, 100, 5, 5, CFRColor::Red);
+        draw_rect_outline(&mut buffer, 0, 0, 0, 5, CFRColor::Red);
+        assert_eq!(count(&buffer), 5);
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        draw_line(&mut buffer, 7, 0, -7, 0, CFRColor::Red);
+        assert_eq!(count(&buffer), 8);
+        draw_line(&mut buffer, 3, 3, 3, 3, CFRColor::Red);
+        assert_eq!(count(&buffer), 9);
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        draw_circle(&mut buffer, 0, 0, 0, CFRColor::Red);
+        assert_eq!(count(&buffer), 1);
+        draw_circle(&mut buffer, 0, 0, 100, CFRColor::Red);
+        assert_eq!(count(&buffer), 1);
+
+        let mut buffer = CFRBuffer::new(8, 12);
+        draw_text(&mut buffer, -2, 0, "-\n..", CFRColor::Red);
+        assert_eq!(count(&buffer), 2);
+        assert_eq!(buffer.data[10 * 8 + 3], CFRColor::Red);
+        assert_eq!(text_size("ab\nc"), (7, 11));
+        assert_eq!(text_size(""), (0, 0));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::buffer::CFRBuffer;
+use std::str::FromStr;
+
+/// A recursive "Droste effect": the canvas shrunk into a region of itself, again and again.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::droste::Droste;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(8, 8);
+/// buffer.data[0] = CFRColor::Red;
+/// let droste: Droste = "4,4,4,4,2".parse().unwrap();
+/// let result = droste.apply(&buffer);
+/// // The canvas at half size, and inside it at a quarter size.
+/// assert_eq!(result.data[4 * 8 + 4], CFRColor::Red);
+/// assert_eq!(result.data[6 * 8 + 6], CFRColor::Red);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Droste {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// How many times the canvas is nested in itself.
+    pub levels: u32,
+}
+
+impl Droste {
+    /// Returns the buffer with itself, including the nested copies, drawn shrunk into the
+    /// region `levels` times.
+    pub fn apply(&self, buffer: &CFRBuffer) -> CFRBuffer {
+        let mut result = buffer.clone();
+        if self.width == 0 || self.height == 0 || buffer.data.is_empty() {
+            return result;
+        }
+        for _ in 0..self.levels {
+            let nested = result.scale(self.width, self.height);
+            result.clone_from(buffer);
+            result.blit(&nested, self.x, self.y);
+        }
+        result
+    }
+}
+
+/// Converts `x,y,width,height,levels` to a `Droste`.
+impl FromStr for Droste {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers = s
+            .split(',')
+            .map(|n| n.trim().parse())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("Invalid Droste region: {}", s))?;
+        let [x, y, width, height, levels] = numbers[..] else {
+            return Err(format!("Expected x,y,width,height,levels: {}", s));
+        };
+        Ok(Droste {
+            x,
+            y,
+            width,
+            height,
+            levels,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use core::convert::Infallible;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Dimensions, OriginDimensions, Point, Size};
+use embedded_graphics_core::image::ImageDrawable;
+use embedded_graphics_core::pixelcolor::{PixelColor, Rgb888};
+use embedded_graphics_core::primitives::{PointsIter, Rectangle};
+use embedded_graphics_core::Pixel;
+
+impl PixelColor for CFRColor {
+    type Raw = ();
+}
+
+impl From<CFRColor> for Rgb888 {
+    fn from(color: CFRColor) -> Self {
+        let [r, g, b] = color.to_rgb();
+        Rgb888::new(r, g, b)
+    }
+}
+
+impl OriginDimensions for CFRBuffer {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+/// Lets `embedded-graphics` primitives draw onto the canvas in CFRS colors,
+/// e.g. to pre-populate it before running a program.
+/// Pixels outside the canvas are ignored.
+impl DrawTarget for CFRBuffer {
+    type Color = CFRColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0
+                && point.y >= 0
+                && (point.x as u32) < self.width
+                && (point.y as u32) < self.height
+            {
+                self.data[(point.y as u32 * self.width + point.x as u32) as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Draws the canvas as an image onto any `embedded-graphics` display.
+///
+/// Displays that don't use `Rgb888` can be targeted through `color_converted()`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use embedded_graphics::{image::Image, prelude::*};
+///
+/// Image::new(&buffer, Point::zero()).draw(&mut display.color_converted())?;
+/// ```
+impl ImageDrawable for CFRBuffer {
+    type Color = Rgb888;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.draw_sub_image(target, &self.bounding_box())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let area = area.intersection(&self.bounding_box());
+        let colors = area.points().map(|p| {
+            let index = (p.y as u32 * self.width + p.x as u32) as usize;
+            Rgb888::from(self.data[index])
+        });
+        target.fill_contiguous(&Rectangle::new(Point::zero(), area.size), colors)
+    }
+}
+
+mod tests {
+    #[test]
+    fn draw_into_buffer() {
+        use crate::{CFRBuffer, CFRColor};
+        use embedded_graphics_core::draw_target::DrawTarget;
+        use embedded_graphics_core::geometry::Point;
+        use embedded_graphics_core::Pixel;
+
+        let mut buffer = CFRBuffer::new(4, 4);
+        buffer
+            .draw_iter([
+                Pixel(Point::new(1, 2), CFRColor::Red),
+                Pixel(Point::new(-1, 0), CFRColor::Red),
+                Pixel(Point::new(4, 0), CFRColor::Red),
+            ])
+            .unwrap();
+        assert_eq!(buffer.data[2 * 4 + 1], CFRColor::Red);
+        assert_eq!(
+            buffer.data.iter().filter(|c| **c == CFRColor::Red).count(),
+            1
+        );
+    }
+}
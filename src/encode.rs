@@ -0,0 +1,573 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use gif::{DisposalMethod, Encoder, EncodingError, Frame, Repeat};
+use std::io::Write;
+use std::str::FromStr;
+
+/// Repeat behavior of an animated GIF.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LoopCount {
+    Infinite,
+    None,
+    Count(u16),
+}
+
+/// Converts a string (`infinite`, `none`, or a number) to a `LoopCount`.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use cfrs::encode::LoopCount;
+///
+/// assert_eq!(LoopCount::from_str("infinite"), Ok(LoopCount::Infinite));
+/// assert_eq!(LoopCount::from_str("3"), Ok(LoopCount::Count(3)));
+/// assert_eq!(LoopCount::from_str("0"), Ok(LoopCount::None));
+/// ```
+impl FromStr for LoopCount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "infinite" => Ok(LoopCount::Infinite),
+            "none" | "0" => Ok(LoopCount::None),
+            n => n
+                .parse()
+                .map(LoopCount::Count)
+                .map_err(|_| format!("Invalid loop count: {}", s)),
+        }
+    }
+}
+
+/// Disposal method applied to every GIF frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Disposal {
+    Any,
+    Keep,
+    Background,
+    Previous,
+}
+
+impl FromStr for Disposal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(Disposal::Any),
+            "keep" => Ok(Disposal::Keep),
+            "background" => Ok(Disposal::Background),
+            "previous" => Ok(Disposal::Previous),
+            _ => Err(format!("Invalid disposal method: {}", s)),
+        }
+    }
+}
+
+impl From<Disposal> for DisposalMethod {
+    fn from(disposal: Disposal) -> Self {
+        match disposal {
+            Disposal::Any => DisposalMethod::Any,
+            Disposal::Keep => DisposalMethod::Keep,
+            Disposal::Background => DisposalMethod::Background,
+            Disposal::Previous => DisposalMethod::Previous,
+        }
+    }
+}
+
+/// Options for encoding an animated GIF.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GifOptions {
+    /// Delay between frames in milliseconds.
+    pub delay: u32,
+    pub loop_count: LoopCount,
+    pub disposal: Disposal,
+    /// Encode only the area that changed since the previous frame, with the unchanged pixels
+    /// in it transparent. Only applies with `keep` or `any` disposal and palettes of less
+    /// than 256 colors, which leave a free index for transparency.
+    pub optimize: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            delay: 100,
+            loop_count: LoopCount::Infinite,
+            disposal: Disposal::Keep,
+            optimize: true,
+        }
+    }
+}
+
+/// Writes the frames as an animated GIF.
+///
+/// Frames are encoded with a global palette of the 8 CFRS colors, so no quantization is needed.
+/// Nothing is written if `frames` is empty, and an error is returned if the frames are wider
+/// or taller than the 65535 pixels a GIF can hold.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::encode::{write_gif, GifOptions};
+///
+/// let frames = vec![CFRBuffer::new(16, 16); 2];
+/// let mut bytes = Vec::new();
+/// write_gif(&mut bytes, &frames, &GifOptions::default()).unwrap();
+/// assert_eq!(&bytes[0..6], b"GIF89a");
+/// ```
+pub fn write_gif<W: Write>(
+    writer: W,
+    frames: &[CFRBuffer],
+    options: &GifOptions,
+) -> Result<(), EncodingError> {
+    let Some(first) = frames.first() else {
+        return Ok(());
+    };
+    let palette: Vec<[u8; 3]> = CFRColor::ALL.iter().map(|c| c.to_rgb()).collect();
+    let indices: Vec<Vec<u8>> = frames
+        .iter()
+        .map(|buffer| buffer.data.iter().map(|c| *c as u8).collect())
+        .collect();
+    write_indexed_gif(
+        writer,
+        first.width,
+        first.height,
+        &palette,
+        &indices,
+        options,
+    )
+}
+
+/// Writes frames of palette indices as an animated GIF with a global `palette` of at most
+/// 256 colors.
+///
+/// Nothing is written if `frames` is empty.
+pub fn write_indexed_gif<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    palette: &[[u8; 3]],
+    frames: &[Vec<u8>],
+    options: &GifOptions,
+) -> Result<(), EncodingError> {
+    let frames = frames.iter().map(|indices| (&indices[..], options.delay));
+    write_gif_indices(writer, width, height, palette, frames, options)
+}
+
+/// Writes the frames as an animated GIF, showing every frame for the delay at the same index
+/// in `delays` (in milliseconds) instead of `options.delay`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::encode::{write_gif_frames, GifOptions};
+///
+/// let frames = vec![CFRBuffer::new(16, 16); 2];
+/// let mut bytes = Vec::new();
+/// write_gif_frames(&mut bytes, &frames, &[100, 500], &GifOptions::default()).unwrap();
+/// assert_eq!(&bytes[0..6], b"GIF89a");
+/// ```
+pub fn write_gif_frames<W: Write>(
+    writer: W,
+    frames: &[CFRBuffer],
+    delays: &[u32],
+    options: &GifOptions,
+) -> Result<(), EncodingError> {
+    let Some(first) = frames.first() else {
+        return Ok(());
+    };
+    let palette: Vec<[u8; 3]> = CFRColor::ALL.iter().map(|c| c.to_rgb()).collect();
+    let indices: Vec<Vec<u8>> = frames
+        .iter()
+        .map(|buffer| buffer.data.iter().map(|c| *c as u8).collect())
+        .collect();
+    let frames = indices.iter().map(|i| &i[..]).zip(delays.iter().copied());
+    write_gif_indices(writer, first.width, first.height, &palette, frames, options)
+}
+
+/// Writes RGBA frames, e.g. after a `FrameFilter`, as an animated GIF, showing every frame
+/// for the delay at the same index in `delays` (in milliseconds). Every frame gets its own
+/// palette of at most 256 colors.
+///
+/// Nothing is written if `frames` is empty.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::encode::{write_rgba_gif, GifOptions};
+///
+/// let frames = vec![vec![128; 4 * 4 * 4]; 2];
+/// let mut bytes = Vec::new();
+/// write_rgba_gif(&mut bytes, 4, 4, &frames, &[100, 100], &GifOptions::default()).unwrap();
+/// assert_eq!(&bytes[0..6], b"GIF89a");
+/// ```
+pub fn write_rgba_gif<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    frames: &[Vec<u8>],
+    delays: &[u32],
+    options: &GifOptions,
+) -> Result<(), EncodingError> {
+    if frames.is_empty() {
+        return Ok(());
+    }
+    let (width, height) = gif_dimensions(width, height)?;
+    let mut encoder = Encoder::new(writer, width, height, &[])?;
+    match options.loop_count {
+        LoopCount::Infinite => encoder.set_repeat(Repeat::Infinite)?,
+        LoopCount::Count(n) => encoder.set_repeat(Repeat::Finite(n))?,
+        LoopCount::None => {}
+    }
+
+    for (rgba, &delay) in frames.iter().zip(delays) {
+        let mut rgba = rgba.clone();
+        let mut frame = Frame::from_rgba_speed(width, height, &mut rgba, 10);
+        frame.delay = (delay / 10).min(u16::MAX as u32) as u16;
+        frame.dispose = options.disposal.into();
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+fn write_gif_indices<'a, W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    palette: &[[u8; 3]],
+    frames: impl Iterator<Item = (&'a [u8], u32)>,
+    options: &GifOptions,
+) -> Result<(), EncodingError> {
+    let mut frames = frames.peekable();
+    if frames.peek().is_none() {
+        return Ok(());
+    }
+    let (width, height) = gif_dimensions(width, height)?;
+    let optimize = options.optimize
+        && matches!(options.disposal, Disposal::Keep | Disposal::Any)
+        && palette.len() < 256;
+    let transparent = palette.len() as u8;
+
+    let mut palette: Vec<u8> = palette.iter().flatten().copied().collect();
+    if optimize {
+        palette.extend([0, 0, 0]);
+    }
+    let mut encoder = Encoder::new(writer, width, height, &palette)?;
+    match options.loop_count {
+        LoopCount::Infinite => encoder.set_repeat(Repeat::Infinite)?,
+        LoopCount::Count(n) => encoder.set_repeat(Repeat::Finite(n))?,
+        LoopCount::None => {}
+    }
+
+    let mut previous: Option<&[u8]> = None;
+    for (indices, delay) in frames {
+        let mut frame = match previous.filter(|_| optimize) {
+            Some(previous) => {
+                let delta = delta_frame(previous, indices, width, transparent);
+                let mut frame = Frame::from_indexed_pixels(
+                    delta.width,
+                    delta.height,
+                    delta.indices,
+                    Some(transparent),
+                );
+                frame.left = delta.left;
+                frame.top = delta.top;
+                frame
+            }
+            None => Frame::from_indexed_pixels(width, height, indices, None::<u8>),
+        };
+        frame.delay = (delay / 10).min(u16::MAX as u32) as u16;
+        frame.dispose = options.disposal.into();
+        encoder.write_frame(&frame)?;
+        previous = Some(indices);
+    }
+    Ok(())
+}
+
+/// Returns the dimensions as the 16-bit numbers of a GIF, or an error if they don't fit.
+fn gif_dimensions(width: u32, height: u32) -> Result<(u16, u16), EncodingError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "A GIF is at most {0}x{0} pixels, not {1}x{2}",
+                u16::MAX,
+                width,
+                height
+            ),
+        )
+        .into()),
+    }
+}
+
+/// The part of a GIF frame that changed since the previous one.
+#[derive(Debug, PartialEq, Eq)]
+struct DeltaFrame {
+    left: u16,
+    top: u16,
+    width: u16,
+    height: u16,
+    indices: Vec<u8>,
+}
+
+/// Crops the frame to the bounding box of the pixels that differ from `previous`, replacing
+/// the unchanged ones in it with `transparent`. A frame without changes becomes a single
+/// transparent pixel, which still has to be written for its delay.
+fn delta_frame(previous: &[u8], next: &[u8], width: u16, transparent: u8) -> DeltaFrame {
+    let width = width as usize;
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+    for (i, (a, b)) in previous.iter().zip(next).enumerate() {
+        if a != b {
+            let (x, y) = (i % width, i / width);
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+    }
+    if left == usize::MAX {
+        return DeltaFrame {
+            left: 0,
+            top: 0,
+            width: 1,
+            height: 1,
+            indices: vec![transparent],
+        };
+    }
+    let mut indices = Vec::with_capacity((right - left) * (bottom - top));
+    for y in top..bottom {
+        let row = y * width + left..y * width + right;
+        indices.extend(previous[row.clone()].iter().zip(&next[row]).map(|(a, &b)| {
+            if *a == b {
+                transparent
+            } else {
+                b
+            }
+        }));
+    }
+    DeltaFrame {
+        left: left as u16,
+        top: top as u16,
+        width: (right - left) as u16,
+        height: (bottom - top) as u16,
+        indices,
+    }
+}
+
+/// Encodes the buffer as an indexed-color PNG image.
+///
+/// The palette only has the colors used in the buffer, with as few bits per pixel as
+/// possible, which makes the file several times smaller than `encode_rgba_png`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::encode::{encode_png, encode_rgba_png};
+///
+/// let buffer = CFRBuffer::new(16, 16);
+/// let bytes = encode_png(&buffer).unwrap();
+/// assert_eq!(&bytes[1..4], b"PNG");
+/// assert!(bytes.len() < encode_rgba_png(&buffer).unwrap().len());
+/// ```
+pub fn encode_png(buffer: &CFRBuffer) -> Result<Vec<u8>, png::EncodingError> {
+    let mut used = [false; CFRColor::ALL.len()];
+    buffer.data.iter().for_each(|&c| used[c as usize] = true);
+    let palette: Vec<[u8; 3]> = CFRColor::ALL
+        .iter()
+        .filter(|&&c| used[c as usize])
+        .map(|c| c.to_rgb())
+        .collect();
+    // Position of every used color in the palette.
+    let mut remap = [0; CFRColor::ALL.len()];
+    let mut next = 0;
+    for (color, used) in used.iter().enumerate() {
+        if *used {
+            remap[color] = next;
+            next += 1;
+        }
+    }
+    let indices: Vec<u8> = buffer.data.iter().map(|&c| remap[c as usize]).collect();
+    encode_indexed_png(buffer.width, buffer.height, &palette, &indices)
+}
+
+/// Encodes palette indices as an indexed-color PNG image with a `palette` of at most 256
+/// colors.
+///
+/// The bit depth is the smallest one that fits every palette index: 1, 2, 4, or 8 bits.
+pub fn encode_indexed_png(
+    width: u32,
+    height: u32,
+    palette: &[[u8; 3]],
+    indices: &[u8],
+) -> Result<Vec<u8>, png::EncodingError> {
+    let (bits, depth) = match palette.len() {
+        0..=2 => (1, png::BitDepth::One),
+        3..=4 => (2, png::BitDepth::Two),
+        5..=16 => (4, png::BitDepth::Four),
+        _ => (8, png::BitDepth::Eight),
+    };
+    let data = pack_indices(width, height, bits, indices);
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(depth);
+    encoder.set_palette(palette.iter().flatten().copied().collect::<Vec<u8>>());
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// Encodes the frames as an animated PNG, showing every frame for the delay at the same
+/// index in `delays` (in milliseconds, at most 65535).
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::encode::{encode_apng, LoopCount};
+///
+/// let frames = vec![CFRBuffer::new(16, 16); 2];
+/// let bytes = encode_apng(&frames, &[100, 500], LoopCount::Infinite).unwrap();
+/// assert_eq!(&bytes[1..4], b"PNG");
+/// ```
+pub fn encode_apng(
+    frames: &[CFRBuffer],
+    delays: &[u32],
+    loop_count: LoopCount,
+) -> Result<Vec<u8>, png::EncodingError> {
+    let Some(first) = frames.first() else {
+        return Ok(Vec::new());
+    };
+    let plays = match loop_count {
+        LoopCount::Infinite => 0,
+        LoopCount::None => 1,
+        // GIF counts repeats, APNG counts plays.
+        LoopCount::Count(n) => n as u32 + 1,
+    };
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, first.width, first.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Four);
+    let palette: Vec<u8> = CFRColor::ALL.iter().flat_map(|c| c.to_rgb()).collect();
+    encoder.set_palette(palette);
+    encoder.set_animated(frames.len().min(delays.len()) as u32, plays)?;
+    let mut writer = encoder.write_header()?;
+    for (frame, &delay) in frames.iter().zip(delays) {
+        writer.set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)?;
+        let indices: Vec<u8> = frame.data.iter().map(|&c| c as u8).collect();
+        writer.write_image_data(&pack_indices(frame.width, frame.height, 4, &indices))?;
+    }
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// Packs palette indices of `bits` bits into rows of bytes, from the most significant bit.
+fn pack_indices(width: u32, height: u32, bits: usize, indices: &[u8]) -> Vec<u8> {
+    let pixels_per_byte = 8 / bits;
+    let row_size = (width as usize).div_ceil(pixels_per_byte);
+    let mut data = vec![0u8; row_size * height as usize];
+    if width > 0 {
+        for (y, row) in indices.chunks(width as usize).enumerate() {
+            let out = &mut data[y * row_size..(y + 1) * row_size];
+            for (x, &index) in row.iter().enumerate() {
+                let shift = 8 - bits * (x % pixels_per_byte + 1);
+                out[x / pixels_per_byte] |= index << shift;
+            }
+        }
+    }
+    data
+}
+
+/// Encodes bytes as standard base64 with padding, e.g. to embed an image in JSON or HTML.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::encode::base64;
+///
+/// assert_eq!(base64(b"CFRS"), "Q0ZSUw==");
+/// ```
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Encodes the buffer as an RGBA PNG image.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::encode::encode_rgba_png;
+///
+/// let bytes = encode_rgba_png(&CFRBuffer::new(16, 16)).unwrap();
+/// assert_eq!(&bytes[1..4], b"PNG");
+/// ```
+pub fn encode_rgba_png(buffer: &CFRBuffer) -> Result<Vec<u8>, png::EncodingError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, buffer.width, buffer.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&buffer.to_rgba_bytes())?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+mod tests {
+    #[test]
+    fn delta_frames_crop_changes() {
+        use crate::encode::{delta_frame, DeltaFrame};
+
+        let previous = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let next = [0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 5, 5];
+        assert_eq!(
+            delta_frame(&previous, &next, 4, 8),
+            DeltaFrame {
+                left: 1,
+                top: 1,
+                width: 3,
+                height: 2,
+                indices: vec![3, 8, 8, 8, 5, 5],
+            }
+        );
+        assert_eq!(delta_frame(&next, &next, 4, 8).indices, vec![8]);
+    }
+
+    #[test]
+    fn gifs_reject_canvases_wider_than_u16() {
+        use crate::encode::{write_indexed_gif, write_rgba_gif, GifOptions};
+
+        let width = u16::MAX as u32 + 1;
+        let options = GifOptions::default();
+        let frames = vec![vec![0; width as usize]];
+        let mut bytes = Vec::new();
+        let result = write_indexed_gif(&mut bytes, width, 1, &[[0; 3]], &frames, &options);
+        assert!(result.is_err());
+        assert!(bytes.is_empty());
+
+        let frames = vec![vec![0; width as usize * 4]];
+        let result = write_rgba_gif(&mut bytes, 1, width, &frames, &[100], &options);
+        assert!(result.is_err());
+        assert!(bytes.is_empty());
+    }
+}
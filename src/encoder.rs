@@ -0,0 +1,366 @@
+use crate::buffer::CFRBuffer;
+use crate::encode::{encode_apng, write_gif_frames, Disposal, GifOptions, LoopCount};
+use crate::labels::contact_sheet;
+use std::io;
+
+/// Options that every encoder gets; formats ignore the ones they don't support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EncoderOptions {
+    pub loop_count: LoopCount,
+    pub disposal: Disposal,
+    /// Encode only the changed area of every GIF frame, see `GifOptions::optimize`.
+    pub optimize: bool,
+    /// The number of commands between the frames, which labels the frames of a film strip.
+    pub every: Option<u64>,
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        Self {
+            loop_count: LoopCount::Infinite,
+            disposal: Disposal::Keep,
+            optimize: true,
+            every: None,
+        }
+    }
+}
+
+/// An output format for animations.
+///
+/// The frames arrive one at a time after `begin`, so an encoder can stream them or collect
+/// them until `finish`. Implement this and add it to an `EncoderRegistry` to support a new
+/// format.
+pub trait Encoder {
+    /// Starts an animation of `width`×`height` frames.
+    fn begin(&mut self, width: u32, height: u32) -> io::Result<()>;
+
+    /// Adds a frame shown for `delay` milliseconds.
+    fn frame(&mut self, frame: &CFRBuffer, delay: u32) -> io::Result<()>;
+
+    /// Finishes the animation and returns the encoded file.
+    fn finish(&mut self) -> io::Result<Vec<u8>>;
+}
+
+/// Creates an encoder with the given options.
+pub type EncoderFactory = fn(&EncoderOptions) -> Box<dyn Encoder>;
+
+/// Encoders by name, which the `cfrs` command selects with `--format` or the extension of
+/// the output.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::encoder::{encode_frames, EncoderOptions, EncoderRegistry};
+/// use cfrs::CFRBuffer;
+///
+/// let registry = EncoderRegistry::builtin();
+/// let mut encoder = registry.get("gif", &EncoderOptions::default()).unwrap();
+/// let frames = vec![CFRBuffer::new(16, 16); 2];
+/// let bytes = encode_frames(encoder.as_mut(), &frames, &[100, 100]).unwrap();
+/// assert_eq!(&bytes[0..6], b"GIF89a");
+/// assert!(registry.get("avif", &EncoderOptions::default()).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EncoderRegistry {
+    encoders: Vec<(&'static str, EncoderFactory)>,
+}
+
+impl EncoderRegistry {
+    /// Creates a registry without any encoders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoders of this crate: `gif`, `apng`, `strip`, and `webp` with the
+    /// `webp` feature.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("gif", |options| Box::new(GifEncoder::new(options)));
+        registry.register("apng", |options| Box::new(ApngEncoder::new(options)));
+        registry.register("strip", |options| Box::new(StripEncoder::new(options)));
+        #[cfg(feature = "webp")]
+        registry.register("webp", |_| Box::new(WebpEncoder::new()));
+        registry
+    }
+
+    /// Adds an encoder, replacing the one with the same name.
+    pub fn register(&mut self, name: &'static str, factory: EncoderFactory) {
+        self.encoders.retain(|(n, _)| *n != name);
+        self.encoders.push((name, factory));
+    }
+
+    /// Creates the encoder called `name`, if there is one.
+    pub fn get(&self, name: &str, options: &EncoderOptions) -> Option<Box<dyn Encoder>> {
+        let (_, factory) = self.encoders.iter().find(|(n, _)| *n == name)?;
+        Some(factory(options))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.encoders.iter().any(|(n, _)| *n == name)
+    }
+
+    /// Returns the names of the encoders, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.encoders.iter().map(|(name, _)| *name)
+    }
+}
+
+/// Encodes the frames with `encoder`, showing every frame for the delay at the same index in
+/// `delays` (in milliseconds).
+pub fn encode_frames(
+    encoder: &mut dyn Encoder,
+    frames: &[CFRBuffer],
+    delays: &[u32],
+) -> io::Result<Vec<u8>> {
+    let (width, height) = frames.first().map_or((0, 0), |f| (f.width, f.height));
+    encoder.begin(width, height)?;
+    for (frame, &delay) in frames.iter().zip(delays) {
+        encoder.frame(frame, delay)?;
+    }
+    encoder.finish()
+}
+
+/// The frames that an encoder collects until `finish`.
+#[derive(Debug, Clone, Default)]
+struct Frames {
+    frames: Vec<CFRBuffer>,
+    delays: Vec<u32>,
+}
+
+impl Frames {
+    fn push(&mut self, frame: &CFRBuffer, delay: u32) {
+        self.frames.push(frame.clone());
+        self.delays.push(delay);
+    }
+}
+
+fn other_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::other(e)
+}
+
+/// Encodes an animated GIF with the 8 CFRS colors as its palette, like `write_gif_frames`.
+#[derive(Debug, Clone)]
+pub struct GifEncoder {
+    options: GifOptions,
+    frames: Frames,
+}
+
+impl GifEncoder {
+    pub fn new(options: &EncoderOptions) -> Self {
+        Self {
+            options: GifOptions {
+                loop_count: options.loop_count,
+                disposal: options.disposal,
+                optimize: options.optimize,
+                ..Default::default()
+            },
+            frames: Frames::default(),
+        }
+    }
+}
+
+impl Encoder for GifEncoder {
+    fn begin(&mut self, _width: u32, _height: u32) -> io::Result<()> {
+        self.frames = Frames::default();
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &CFRBuffer, delay: u32) -> io::Result<()> {
+        self.frames.push(frame, delay);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        let Frames { frames, delays } = std::mem::take(&mut self.frames);
+        let mut bytes = Vec::new();
+        write_gif_frames(&mut bytes, &frames, &delays, &self.options).map_err(other_error)?;
+        Ok(bytes)
+    }
+}
+
+/// Encodes an animated PNG, which keeps the exact frame delays, like `encode_apng`.
+#[derive(Debug, Clone)]
+pub struct ApngEncoder {
+    loop_count: LoopCount,
+    frames: Frames,
+}
+
+impl ApngEncoder {
+    pub fn new(options: &EncoderOptions) -> Self {
+        Self {
+            loop_count: options.loop_count,
+            frames: Frames::default(),
+        }
+    }
+}
+
+impl Encoder for ApngEncoder {
+    fn begin(&mut self, _width: u32, _height: u32) -> io::Result<()> {
+        self.frames = Frames::default();
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &CFRBuffer, delay: u32) -> io::Result<()> {
+        self.frames.push(frame, delay);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        let Frames { frames, delays } = std::mem::take(&mut self.frames);
+        encode_apng(&frames, &delays, self.loop_count).map_err(other_error)
+    }
+}
+
+/// Encodes the frames side by side in a PNG film strip, each labeled with the number of
+/// executed commands when `EncoderOptions::every` is set, or with its frame number otherwise.
+#[derive(Debug, Clone)]
+pub struct StripEncoder {
+    every: Option<u64>,
+    cells: Vec<(u32, CFRBuffer)>,
+}
+
+impl StripEncoder {
+    pub fn new(options: &EncoderOptions) -> Self {
+        Self {
+            every: options.every,
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl Encoder for StripEncoder {
+    fn begin(&mut self, _width: u32, _height: u32) -> io::Result<()> {
+        self.cells.clear();
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &CFRBuffer, _delay: u32) -> io::Result<()> {
+        let n = self.cells.len() as u64 + 1;
+        let label = self.every.map_or(n, |every| n.saturating_mul(every));
+        self.cells
+            .push((label.min(u32::MAX as u64) as u32, frame.clone()));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        let cells = std::mem::take(&mut self.cells);
+        if cells.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No frames were captured for the film strip",
+            ));
+        }
+        let strip = contact_sheet(&cells, cells.len() as u32, 1);
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, strip.width, strip.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(other_error)?;
+        writer.write_image_data(&strip.rgba).map_err(other_error)?;
+        writer.finish().map_err(other_error)?;
+        Ok(bytes)
+    }
+}
+
+/// Encodes a lossless animated WebP, streaming every frame to the encoder.
+#[cfg(feature = "webp")]
+#[derive(Default)]
+pub struct WebpEncoder {
+    encoder: Option<webp_animation::Encoder>,
+    frames: usize,
+    timestamp: i32,
+}
+
+#[cfg(feature = "webp")]
+impl WebpEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "webp")]
+impl Encoder for WebpEncoder {
+    fn begin(&mut self, width: u32, height: u32) -> io::Result<()> {
+        use webp_animation::{EncoderOptions, EncodingConfig, EncodingType};
+
+        let options = EncoderOptions {
+            encoding_config: Some(EncodingConfig {
+                encoding_type: EncodingType::Lossless,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let encoder = webp_animation::Encoder::new_with_options((width, height), options)
+            .map_err(other_error)?;
+        *self = Self {
+            encoder: Some(encoder),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    fn frame(&mut self, frame: &CFRBuffer, delay: u32) -> io::Result<()> {
+        let Some(encoder) = &mut self.encoder else {
+            return Err(io::Error::other("The encoder has not begun"));
+        };
+        encoder
+            .add_frame(&frame.to_rgba_bytes(), self.timestamp)
+            .map_err(other_error)?;
+        self.frames += 1;
+        self.timestamp = self
+            .timestamp
+            .saturating_add(delay.min(i32::MAX as u32) as i32);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match self.encoder.take() {
+            Some(encoder) if self.frames > 0 => {
+                let webp = encoder.finalize(self.timestamp).map_err(other_error)?;
+                Ok(webp.to_vec())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn register_custom_encoders() {
+        use crate::encoder::{encode_frames, Encoder, EncoderOptions, EncoderRegistry};
+        use crate::CFRBuffer;
+        use std::io;
+
+        /// Writes the delays of the frames as text.
+        struct Delays(String);
+
+        impl Encoder for Delays {
+            fn begin(&mut self, width: u32, height: u32) -> io::Result<()> {
+                self.0 = format!("{}x{}", width, height);
+                Ok(())
+            }
+
+            fn frame(&mut self, _frame: &CFRBuffer, delay: u32) -> io::Result<()> {
+                self.0 += &format!(" {}", delay);
+                Ok(())
+            }
+
+            fn finish(&mut self) -> io::Result<Vec<u8>> {
+                Ok(std::mem::take(&mut self.0).into_bytes())
+            }
+        }
+
+        let mut registry = EncoderRegistry::builtin();
+        registry.register("delays", |_| Box::new(Delays(String::new())));
+        registry.register("gif", |_| Box::new(Delays(String::new())));
+        assert_eq!(registry.names().filter(|&n| n == "gif").count(), 1);
+
+        let mut encoder = registry.get("gif", &EncoderOptions::default()).unwrap();
+        let frames = vec![CFRBuffer::new(4, 3); 2];
+        let bytes = encode_frames(encoder.as_mut(), &frames, &[100, 250]).unwrap();
+        assert_eq!(bytes, b"4x3 100 250");
+
+        let mut strip = registry.get("strip", &EncoderOptions::default()).unwrap();
+        assert!(encode_frames(strip.as_mut(), &[], &[]).is_err());
+    }
+}
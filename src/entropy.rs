@@ -0,0 +1,262 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+
+/// Where random numbers come from, for generators and dialects with random commands.
+///
+/// Seeded sources give the same numbers on every platform, and a `Recorder` saves the
+/// numbers of any source so that a `Replay` can give them again, e.g. to reproduce a run
+/// that used `OsEntropy`.
+pub trait EntropySource {
+    /// Returns the next 64 random bits.
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a number from `0` to `n - 1`, or `0` if `n` is `0`.
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+}
+
+impl<S: EntropySource + ?Sized> EntropySource for &mut S {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+impl<S: EntropySource + ?Sized> EntropySource for Box<S> {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+/// The PCG-XSH-RR generator with 64 bits of state, the default seeded source.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::entropy::{EntropySource, Pcg32};
+///
+/// let mut a = Pcg32::new(7);
+/// let mut b = Pcg32::new(7);
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// assert!(a.below(8) < 8);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    /// Creates a generator of the default stream starting from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0xda3e39cb94b95bdb)
+    }
+
+    /// Creates a generator of one of 2<sup>63</sup> independent streams, starting from `seed`.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut pcg = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        pcg.next_u32();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.next_u32();
+        pcg
+    }
+
+    /// Returns the next 32 random bits.
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(self.increment);
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+}
+
+impl Default for Pcg32 {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl EntropySource for Pcg32 {
+    fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+}
+
+/// The SplitMix64 generator, which `random(n)` of the `scripting` generator has always used,
+/// so that existing seeds keep generating the same programs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl EntropySource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Different numbers on every run, from the randomness the standard library seeds its hash
+/// maps with. Wrap it in a `Recorder` to be able to replay a run.
+#[derive(Debug, Clone, Default)]
+pub struct OsEntropy {
+    state: RandomState,
+    counter: u64,
+}
+
+impl OsEntropy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EntropySource for OsEntropy {
+    fn next_u64(&mut self) -> u64 {
+        self.counter += 1;
+        let mut hasher = self.state.build_hasher();
+        hasher.write_u64(self.counter);
+        hasher.finish()
+    }
+}
+
+/// Passes on the numbers of another source, and keeps them so that they can be replayed.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::entropy::{EntropySource, OsEntropy, Recorder, Replay};
+///
+/// let mut recorder = Recorder::new(OsEntropy::new());
+/// let rolls: Vec<u64> = (0..3).map(|_| recorder.below(6)).collect();
+///
+/// let mut replay: Replay = recorder.log().parse().unwrap();
+/// assert_eq!(rolls, (0..3).map(|_| replay.below(6)).collect::<Vec<_>>());
+/// assert_eq!(replay.missing(), 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder<S> {
+    pub source: S,
+    pub values: Vec<u64>,
+}
+
+impl<S: EntropySource> Recorder<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            values: Vec::new(),
+        }
+    }
+
+    /// Returns the numbers so far as text, one hexadecimal number per line, which
+    /// `Replay::from_str` reads.
+    pub fn log(&self) -> String {
+        self.values
+            .iter()
+            .map(|v| format!("{:016x}\n", v))
+            .collect()
+    }
+}
+
+impl<S: EntropySource> EntropySource for Recorder<S> {
+    fn next_u64(&mut self) -> u64 {
+        let value = self.source.next_u64();
+        self.values.push(value);
+        value
+    }
+}
+
+/// Gives the numbers saved by a `Recorder`, in the same order. Once they run out, it gives
+/// `0` and counts the numbers that were missing, since the run no longer matches the one
+/// that was recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    values: Vec<u64>,
+    position: usize,
+    missing: u64,
+}
+
+impl Replay {
+    pub fn new(values: Vec<u64>) -> Self {
+        Self {
+            values,
+            position: 0,
+            missing: 0,
+        }
+    }
+
+    /// Returns how many numbers were asked for after the recorded ones ran out.
+    pub fn missing(&self) -> u64 {
+        self.missing
+    }
+}
+
+impl EntropySource for Replay {
+    fn next_u64(&mut self) -> u64 {
+        match self.values.get(self.position) {
+            Some(&value) => {
+                self.position += 1;
+                value
+            }
+            None => {
+                self.missing += 1;
+                0
+            }
+        }
+    }
+}
+
+/// Reads the log of a `Recorder`: hexadecimal numbers separated by whitespace.
+impl FromStr for Replay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(|v| u64::from_str_radix(v, 16).map_err(|_| format!("Invalid entropy: {}", v)))
+            .collect::<Result<_, _>>()
+            .map(Replay::new)
+    }
+}
+
+mod tests {
+    #[test]
+    fn seeded_sources_match_their_references() {
+        use crate::entropy::{EntropySource, Pcg32, Replay, SplitMix64};
+
+        // The first outputs of the PCG reference implementation for seed 42, stream 54.
+        let mut pcg = Pcg32::with_stream(42, 54);
+        let outputs: Vec<u32> = (0..6).map(|_| pcg.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+
+        let mut splitmix = SplitMix64::new(1234567);
+        assert_eq!(splitmix.next_u64(), 6457827717110365317);
+        assert_eq!(splitmix.next_u64(), 3203168211198807973);
+
+        let mut replay: Replay = "2a\n ff".parse().unwrap();
+        assert_eq!((replay.next_u64(), replay.below(16)), (42, 15));
+        assert_eq!((replay.next_u64(), replay.missing()), (0, 1));
+        assert!("xyz".parse::<Replay>().is_err());
+        assert_eq!(Pcg32::default().below(0), 0);
+    }
+}
@@ -2,7 +2,10 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 /// Represents the direction in which the painter moves.
+///
+/// The discriminants (`Up = 0` through `UpLeft = 7`) follow the painter's rotation order.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
 pub enum CFRDirection {
     Up,
     UpRight,
@@ -30,6 +33,43 @@ impl Display for CFRDirection {
     }
 }
 
+impl CFRDirection {
+    /// All directions, in the order of the painter's rotation.
+    pub const ALL: [CFRDirection; 8] = [
+        CFRDirection::Up,
+        CFRDirection::UpRight,
+        CFRDirection::Right,
+        CFRDirection::DownRight,
+        CFRDirection::Down,
+        CFRDirection::DownLeft,
+        CFRDirection::Left,
+        CFRDirection::UpLeft,
+    ];
+
+    /// Returns how far one step in this direction moves, in pixels along x and y.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::enums::CFRDirection;
+    ///
+    /// assert_eq!(CFRDirection::Up.offset(), (0, -1));
+    /// assert_eq!(CFRDirection::DownLeft.offset(), (-1, 1));
+    /// ```
+    pub fn offset(self) -> (i64, i64) {
+        match self {
+            CFRDirection::Up => (0, -1),
+            CFRDirection::UpRight => (1, -1),
+            CFRDirection::Right => (1, 0),
+            CFRDirection::DownRight => (1, 1),
+            CFRDirection::Down => (0, 1),
+            CFRDirection::DownLeft => (-1, 1),
+            CFRDirection::Left => (-1, 0),
+            CFRDirection::UpLeft => (-1, -1),
+        }
+    }
+}
+
 /// Converts a string to a `CFRDirection` enum variant.
 ///
 /// # Arguments
@@ -71,8 +111,55 @@ impl FromStr for CFRDirection {
     }
 }
 
+/// How many times a block `[...]` runs.
+///
+/// Standard CFRS[] runs every block twice; some variants repeat blocks a different number of
+/// times, and `Repeat` renders programs written for them.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Every block runs twice.
+    #[default]
+    Standard,
+    /// Every block runs this many times. Values below 1 count as 1.
+    Repeat(u32),
+}
+
+/// Converts `standard` or `repeat:<count>` to a `Dialect`.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use cfrs::enums::Dialect;
+///
+/// assert_eq!(Dialect::from_str("standard"), Ok(Dialect::Standard));
+/// assert_eq!(Dialect::from_str("repeat:3"), Ok(Dialect::Repeat(3)));
+/// assert!(Dialect::from_str("repeat").is_err());
+/// ```
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.split_once(':') {
+            None if s == "standard" => Ok(Dialect::Standard),
+            Some(("repeat", count)) => count
+                .parse()
+                .map(Dialect::Repeat)
+                .map_err(|_| format!("Invalid repeat count: {}", count)),
+            _ => Err(format!("Invalid dialect: {}", s)),
+        }
+    }
+}
+
 /// Represents the color of the painter.
+///
+/// The discriminants (`White = 0` through `Yellow = 7`) follow the painter's color cycle.
+///
+/// With the `transparent` feature, `Transparent = 8` is a ninth color that the painter only
+/// cycles to after `CommandExecutor::set_transparent`, so standard programs are unchanged.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
 pub enum CFRColor {
     White,
     Black,
@@ -82,6 +169,9 @@ pub enum CFRColor {
     Red,
     Magenta,
     Yellow,
+    /// No color at all: fully transparent in RGBA exports, and black elsewhere.
+    #[cfg(feature = "transparent")]
+    Transparent,
 }
 
 impl Display for CFRColor {
@@ -95,11 +185,120 @@ impl Display for CFRColor {
             CFRColor::Red => "Red",
             CFRColor::Magenta => "Magenta",
             CFRColor::Yellow => "Yellow",
+            #[cfg(feature = "transparent")]
+            CFRColor::Transparent => "Transparent",
         };
         write!(f, "{}", s)
     }
 }
 
+impl CFRColor {
+    /// All colors, in the order of the painter's color cycle.
+    #[cfg(not(feature = "transparent"))]
+    pub const ALL: [CFRColor; 8] = [
+        CFRColor::White,
+        CFRColor::Black,
+        CFRColor::Blue,
+        CFRColor::Green,
+        CFRColor::Cyan,
+        CFRColor::Red,
+        CFRColor::Magenta,
+        CFRColor::Yellow,
+    ];
+
+    /// All colors, in the order of the painter's color cycle with `Transparent` last.
+    #[cfg(feature = "transparent")]
+    pub const ALL: [CFRColor; 9] = [
+        CFRColor::White,
+        CFRColor::Black,
+        CFRColor::Blue,
+        CFRColor::Green,
+        CFRColor::Cyan,
+        CFRColor::Red,
+        CFRColor::Magenta,
+        CFRColor::Yellow,
+        CFRColor::Transparent,
+    ];
+
+    /// Returns the RGB components of the color.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::enums::CFRColor;
+    ///
+    /// assert_eq!(CFRColor::Magenta.to_rgb(), [255, 0, 255]);
+    /// ```
+    pub fn to_rgb(&self) -> [u8; 3] {
+        match self {
+            CFRColor::White => [255, 255, 255],
+            CFRColor::Black => [0, 0, 0],
+            CFRColor::Blue => [0, 0, 255],
+            CFRColor::Green => [0, 255, 0],
+            CFRColor::Cyan => [0, 255, 255],
+            CFRColor::Red => [255, 0, 0],
+            CFRColor::Magenta => [255, 0, 255],
+            CFRColor::Yellow => [255, 255, 0],
+            #[cfg(feature = "transparent")]
+            CFRColor::Transparent => [0, 0, 0],
+        }
+    }
+
+    /// Returns the RGBA components of the color, which are only transparent for
+    /// `Transparent`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::enums::CFRColor;
+    ///
+    /// assert_eq!(CFRColor::Cyan.to_rgba(), [0, 255, 255, 255]);
+    /// ```
+    pub fn to_rgba(&self) -> [u8; 4] {
+        let [r, g, b] = self.to_rgb();
+        #[cfg(feature = "transparent")]
+        if *self == CFRColor::Transparent {
+            return [r, g, b, 0];
+        }
+        [r, g, b, 255]
+    }
+
+    /// Returns the luminance of the color, with the Rec. 709 weights of sRGB, from 0 for
+    /// black to 255 for white.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::enums::CFRColor;
+    ///
+    /// assert_eq!(CFRColor::Red.luma(), 54);
+    /// assert!(CFRColor::Blue.luma() < CFRColor::Red.luma());
+    /// assert!(CFRColor::Yellow.luma() < CFRColor::White.luma());
+    /// ```
+    pub fn luma(&self) -> u8 {
+        let [r, g, b] = self.to_rgb().map(u32::from);
+        ((2126 * r + 7152 * g + 722 * b + 5000) / 10000) as u8
+    }
+
+    /// Returns the color closest to `rgb`, where every component counts as on from 128.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::enums::CFRColor;
+    ///
+    /// assert_eq!(CFRColor::nearest([200, 30, 240]), CFRColor::Magenta);
+    /// assert_eq!(CFRColor::nearest([90, 90, 90]), CFRColor::Black);
+    /// ```
+    pub fn nearest(rgb: [u8; 3]) -> CFRColor {
+        let on = rgb.map(|c| if c >= 128 { 255 } else { 0 });
+        CFRColor::ALL
+            .into_iter()
+            .find(|color| color.to_rgb() == on)
+            .expect("Every combination of full components is a color")
+    }
+}
+
 /// Converts a string to a `CFRColor` enum variant.
 ///
 /// # Arguments
@@ -136,6 +335,8 @@ impl FromStr for CFRColor {
             "red" => Ok(CFRColor::Red),
             "magenta" => Ok(CFRColor::Magenta),
             "yellow" => Ok(CFRColor::Yellow),
+            #[cfg(feature = "transparent")]
+            "transparent" => Ok(CFRColor::Transparent),
             _ => Err(format!("Invalid color: {}", s)),
         }
     }
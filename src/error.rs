@@ -0,0 +1,58 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+/// An error from running a program with `run_untrusted`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CFRError {
+    /// A `]` without a matching `[`, at the given byte offset.
+    UnmatchedBlockEnd { index: usize },
+    /// The canvas has no pixels.
+    InvalidDimensions { width: u32, height: u32 },
+    /// The program is longer than allowed.
+    ProgramTooLong { len: usize, max: usize },
+    /// The canvas has more pixels than allowed.
+    CanvasTooLarge { pixels: u64, max: u64 },
+    /// The program did not finish within the allowed number of steps.
+    StepLimitExceeded { max: u64 },
+    /// The painter wrapped around the edges of the canvas more often than allowed.
+    WrapLimitExceeded { max: u64 },
+    /// The program did not finish within the allowed wall time.
+    TimeLimitExceeded { max: Duration },
+    /// The program produced more frames than allowed.
+    FrameLimitExceeded { max: u64 },
+}
+
+impl Display for CFRError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CFRError::UnmatchedBlockEnd { index } => write!(f, "Unmatched ] at {}", index),
+            CFRError::InvalidDimensions { width, height } => {
+                write!(f, "Invalid canvas size: {}x{}", width, height)
+            }
+            CFRError::ProgramTooLong { len, max } => {
+                write!(f, "Program too long: {} bytes (max {})", len, max)
+            }
+            CFRError::CanvasTooLarge { pixels, max } => {
+                write!(f, "Canvas too large: {} pixels (max {})", pixels, max)
+            }
+            CFRError::StepLimitExceeded { max } => {
+                write!(f, "Program did not finish within {} steps", max)
+            }
+            CFRError::WrapLimitExceeded { max } => {
+                write!(
+                    f,
+                    "Painter wrapped around the edges more than {} times",
+                    max
+                )
+            }
+            CFRError::TimeLimitExceeded { max } => {
+                write!(f, "Program did not finish within {:?}", max)
+            }
+            CFRError::FrameLimitExceeded { max } => {
+                write!(f, "Program produced more than {} frames", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CFRError {}
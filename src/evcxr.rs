@@ -0,0 +1,35 @@
+use crate::animation::Animation;
+use crate::buffer::CFRBuffer;
+use crate::encode::{base64, encode_png, GifOptions};
+
+impl CFRBuffer {
+    /// Shows the canvas as a PNG in an [evcxr](https://github.com/evcxr/evcxr) Jupyter
+    /// notebook, when a cell evaluates to a `CFRBuffer`.
+    pub fn evcxr_display(&self) {
+        match encode_png(self) {
+            Ok(png) => display("image/png", &png),
+            Err(e) => eprintln!("Failed to encode PNG: {}", e),
+        }
+    }
+}
+
+impl Animation {
+    /// Shows the animation as a GIF in an [evcxr](https://github.com/evcxr/evcxr) Jupyter
+    /// notebook, when a cell evaluates to an `Animation`.
+    pub fn evcxr_display(&self) {
+        let mut gif = Vec::new();
+        match self.write_gif(&mut gif, &GifOptions::default()) {
+            Ok(()) => display("image/gif", &gif),
+            Err(e) => eprintln!("Failed to encode GIF: {}", e),
+        }
+    }
+}
+
+/// Prints `content` in evcxr's display protocol.
+fn display(mime: &str, content: &[u8]) {
+    println!(
+        "EVCXR_BEGIN_CONTENT {}\n{}\nEVCXR_END_CONTENT",
+        mime,
+        base64(content)
+    );
+}
@@ -1,29 +1,250 @@
+use crate::analysis::Bounds;
 use crate::buffer::CFRBuffer;
-use crate::painter::CFRPainter;
+use crate::enums::{CFRColor, Dialect};
+use crate::history::History;
+use crate::painter::{CFRPainter, Wraps};
+use crate::plot::{PathSegment, Trail};
+use crate::sink::{AsPixelSink, PixelSink};
+use crate::validator::{Diagnostic, Severity};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::ops::Range;
 
+/// The execution state of a `CommandExecutor`.
+///
+/// `index` and `block_starts` are byte offsets into `commands`.
 #[derive(Debug, Clone)]
 pub struct CommandExecutorState {
     pub commands: String,
     pub index: usize,
     pub block_starts: Vec<usize>,
+    /// How many times blocks run. Change it only before the first step.
+    pub dialect: Dialect,
+    /// With `Dialect::Repeat`, how many more times every open block runs, innermost last.
+    pub repeats: Vec<u32>,
+    /// Whether `#` comments out the rest of the line. Otherwise it is ignored like any other
+    /// unknown character, as the spec says. Change it only before the first step.
+    pub comments: bool,
+}
+
+impl CommandExecutorState {
+    /// Executes the command at `index` and moves on, calling `forward` for every `F`.
+    ///
+    /// Returns whether the command was a sleep.
+    pub(crate) fn advance(
+        &mut self,
+        painter: &mut CFRPainter,
+        forward: impl FnOnce(&mut CFRPainter) -> Result<(), &'static str>,
+    ) -> Result<bool, &'static str> {
+        if self.index >= self.commands.len() {
+            return Err("End of commands");
+        }
+
+        let mut sleep = false;
+        let c = self.commands.as_bytes()[self.index] as char;
+        match c {
+            'C' => {
+                painter.change_color();
+            }
+            'F' => {
+                forward(painter)?;
+            }
+            'R' => {
+                painter.rotate();
+            }
+            'S' => {
+                sleep = true;
+            }
+            '[' => {
+                self.block_starts.push(self.index + 1);
+                if let Dialect::Repeat(count) = self.dialect {
+                    self.repeats.push(count.max(1) - 1);
+                }
+            }
+            ']' if matches!(self.dialect, Dialect::Repeat(_)) => match self.repeats.last_mut() {
+                Some(0) => {
+                    self.repeats.pop();
+                    self.block_starts.pop();
+                }
+                Some(remaining) => {
+                    *remaining -= 1;
+                    self.index = *self.block_starts.last().unwrap();
+                    return Ok(sleep);
+                }
+                None => return Err("Unmatched ]"),
+            },
+            ']' => {
+                if let Some(block_start) = self.block_starts.pop() {
+                    self.commands.replace_range(self.index..=self.index, "|");
+                    self.index = block_start;
+                    return Ok(sleep);
+                } else {
+                    return Err("Unmatched ]");
+                }
+            }
+            '|' => {
+                self.commands.replace_range(self.index..=self.index, "]");
+            }
+            '#' if self.comments => {
+                // Skip the comment; the index then moves past the end of the line, or to the
+                // end of the program.
+                self.index = self.commands[self.index..]
+                    .find('\n')
+                    .map_or(self.commands.len() - 1, |n| self.index + n);
+            }
+            _ => {}
+        }
+
+        self.index += 1;
+        Ok(sleep)
+    }
+}
+
+/// A watched pixel being painted, from `CommandExecutor::run_to_watchpoint`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WatchHit {
+    pub x: u32,
+    pub y: u32,
+    pub color: CFRColor,
+    /// The byte offset of the `F` that painted the pixel.
+    pub index: usize,
+}
+
+/// The result of `CommandExecutor::dry_run`.
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    /// Where the painter would draw, grouped by color and pen state like
+    /// `CommandExecutor::path`.
+    pub path: Vec<PathSegment>,
+    /// Where the painter would draw relative to where it is, ignoring wrapping, or `None` if
+    /// it wouldn't draw.
+    pub bounds: Option<Bounds>,
+    pub steps: u64,
+    pub sleeps: u64,
+    /// How many times the painter would wrap around the edges of the canvas.
+    pub wraps: Wraps,
+    /// Why the run would stop early, if it would not reach the end of the program.
+    pub error: Option<&'static str>,
+}
+
+/// A read-only view of where a `CommandExecutor` is, for debuggers and UIs.
+#[derive(Debug, Copy, Clone)]
+pub struct ExecutorView<'e> {
+    /// The byte offset of the next command.
+    pub index: usize,
+    /// The byte offsets just after the `[` of every open block, innermost last.
+    pub block_starts: &'e [usize],
+    pub painter: CFRPainter,
+    /// The number of `S` commands executed so far.
+    pub sleeps: u64,
+    commands: &'e str,
+}
+
+impl<'e> ExecutorView<'e> {
+    /// Returns how many blocks are open.
+    pub fn depth(&self) -> usize {
+        self.block_starts.len()
+    }
+
+    /// Returns the next command, or `None` at the end of the program.
+    ///
+    /// Non-ASCII characters do nothing and run a byte at a time, so every byte of them is
+    /// returned as `char::REPLACEMENT_CHARACTER`.
+    pub fn next_command(&self) -> Option<char> {
+        match self.commands.as_bytes().get(self.index)? {
+            b'|' => Some(']'),
+            &byte if byte.is_ascii() => Some(byte as char),
+            _ => Some(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    /// Returns the program from the next command to the end.
+    pub fn remaining(&self) -> String {
+        let start = (self.index..self.commands.len())
+            .find(|&i| self.commands.is_char_boundary(i))
+            .unwrap_or(self.commands.len());
+        self.commands[start..].replace('|', "]")
+    }
 }
 
 /// The `CommandExecutor` struct represents an executor for a set of commands.
 /// It keeps track of the current state, buffer, and painter.
+///
+/// The buffer is borrowed (`&mut CFRBuffer`) by default, but an executor can also own
+/// its `CFRBuffer`, which is useful when it has to outlive the scope that created it.
+/// Any other `PixelSink`, owned or borrowed, can be drawn on as well (see `cfrs::sink`).
 #[derive(Debug)]
-pub struct CommandExecutor<'a> {
+pub struct CommandExecutor<'a, B = &'a mut CFRBuffer> {
+    #[deprecated(
+        since = "1.1.0",
+        note = "use `view`, `state`, `set_dialect`, or `push_commands` instead"
+    )]
     pub state: CommandExecutorState,
-    pub buffer: &'a mut CFRBuffer,
+    #[deprecated(
+        since = "1.1.0",
+        note = "use `buffer`, `buffer_mut`, or `into_buffer` instead"
+    )]
+    pub buffer: B,
+    #[deprecated(since = "1.1.0", note = "use `painter` or `painter_mut` instead")]
     pub painter: CFRPainter,
+    #[deprecated(since = "1.1.0", note = "use `wraps` or `wraps_mut` instead")]
+    pub wraps: Wraps,
+    #[deprecated(since = "1.1.0", note = "use `max_wraps` or `set_max_wraps` instead")]
+    pub max_wraps: Option<u64>,
+    last_wraps: Wraps,
+    max_depth: Option<usize>,
+    #[deprecated(
+        since = "1.1.0",
+        note = "use `enable_history`, `history`, or `history_mut` instead"
+    )]
+    pub history: Option<History>,
+    #[deprecated(
+        since = "1.1.0",
+        note = "use `record_path`, `trail`, or `trail_mut` instead"
+    )]
+    pub trail: Option<Trail>,
+    sleeps: u64,
+    watchpoints: Vec<(u32, u32)>,
+    watch_hit: Option<WatchHit>,
+    lenient: Option<Lenient>,
+    /// The length of the program and the depth of each `[` in it, for `max_depth`.
+    block_depths: Option<(usize, HashMap<usize, usize>)>,
+    #[cfg(feature = "transparent")]
+    transparent: bool,
+    _buffer: PhantomData<&'a mut CFRBuffer>,
+}
+
+/// What lenient mode has skipped so far.
+#[derive(Debug, Default)]
+struct Lenient {
+    warnings: Vec<Diagnostic>,
+    warned: HashSet<usize>,
+    /// How many of the open blocks were skipped for being too deep.
+    skipped_blocks: usize,
+}
+
+impl Lenient {
+    fn warn(&mut self, span: Range<usize>, message: String) {
+        if self.warned.insert(span.start) {
+            self.warnings.push(Diagnostic {
+                span,
+                severity: Severity::Warning,
+                message,
+            });
+        }
+    }
 }
 
-impl<'a> CommandExecutor<'a> {
+// The executor itself uses the fields that are deprecated for everyone else.
+#[allow(deprecated)]
+impl<'a, B: AsPixelSink> CommandExecutor<'a, B> {
     /// Creates a new `CommandExecutor` instance.
     ///
     /// # Arguments
     ///
     /// * `commands` - A string containing the commands to be executed.
-    /// * `buffer` - A mutable reference to the `CFRBuffer` instance.
+    /// * `buffer` - A mutable reference to the `CFRBuffer` instance, or an owned `CFRBuffer`,
+    ///   or any other `PixelSink`.
     ///
     /// # Returns
     ///
@@ -41,23 +262,245 @@ impl<'a> CommandExecutor<'a> {
     ///     // Do something with the buffer
     ///     // ...
     /// }
+    ///
+    /// // The executor can also take ownership of the buffer
+    /// let mut executor = CommandExecutor::new("[CFRS]".to_string(), CFRBuffer::new(256, 256));
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.buffer().width, 256);
     /// ```
-    pub fn new(commands: String, buffer: &'a mut CFRBuffer) -> Self {
+    pub fn new(commands: String, buffer: B) -> Self {
+        let (width, height) = buffer.sink().dimensions();
         let mut painter = CFRPainter::new();
-        painter.x = (buffer.width - 1) / 2;
-        painter.y = (buffer.height - 1) / 2;
+        painter.x = width.saturating_sub(1) / 2;
+        painter.y = height.saturating_sub(1) / 2;
 
         Self {
             state: CommandExecutorState {
                 commands,
                 index: 0,
                 block_starts: Vec::new(),
+                dialect: Dialect::Standard,
+                repeats: Vec::new(),
+                comments: false,
             },
             buffer,
             painter,
+            wraps: Wraps::default(),
+            max_wraps: None,
+            last_wraps: Wraps::default(),
+            max_depth: None,
+            history: None,
+            trail: None,
+            sleeps: 0,
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            lenient: None,
+            block_depths: None,
+            #[cfg(feature = "transparent")]
+            transparent: false,
+            _buffer: PhantomData,
         }
     }
 
+    /// Creates a `CommandExecutor` that continues from a previously captured state and painter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FFSFF".to_string(), CFRBuffer::new(8, 8));
+    /// while let Ok((false, _)) = executor.step() {}
+    ///
+    /// let state = executor.state().clone();
+    /// let mut resumed = CommandExecutor::from_parts(state, *executor.painter(), executor.buffer().clone());
+    /// resumed.run().unwrap();
+    /// assert_eq!(resumed.position(), (3, 7));
+    /// ```
+    pub fn from_parts(state: CommandExecutorState, painter: CFRPainter, buffer: B) -> Self {
+        Self {
+            state,
+            buffer,
+            painter,
+            wraps: Wraps::default(),
+            max_wraps: None,
+            last_wraps: Wraps::default(),
+            max_depth: None,
+            history: None,
+            trail: None,
+            sleeps: 0,
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            lenient: None,
+            block_depths: None,
+            #[cfg(feature = "transparent")]
+            transparent: false,
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Returns the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("F".to_string(), CFRBuffer::new(8, 8));
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.buffer().data[2 * 8 + 3], CFRColor::White);
+    /// ```
+    pub fn buffer(&self) -> &B::Sink {
+        self.buffer.sink()
+    }
+
+    /// Returns the canvas for drawing on it between steps.
+    pub fn buffer_mut(&mut self) -> &mut B::Sink {
+        self.buffer.sink_mut()
+    }
+
+    /// Returns the buffer the executor was created with.
+    pub fn into_buffer(self) -> B {
+        self.buffer
+    }
+
+    /// Returns the painter.
+    pub fn painter(&self) -> &CFRPainter {
+        &self.painter
+    }
+
+    /// Returns the painter for moving or recoloring it between steps.
+    pub fn painter_mut(&mut self) -> &mut CFRPainter {
+        &mut self.painter
+    }
+
+    /// Returns how many times the painter has wrapped around the edges of the canvas.
+    pub fn wraps(&self) -> Wraps {
+        self.wraps
+    }
+
+    /// Returns the edges the painter wrapped around in the last `step`, which are all zero
+    /// unless that step moved it off the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FRRF".to_string(), CFRBuffer::new(2, 2));
+    /// executor.step().unwrap();
+    /// assert_eq!(executor.last_wraps().top, 1);
+    /// executor.step().unwrap();
+    /// assert_eq!(executor.last_wraps().total(), 0);
+    /// ```
+    pub fn last_wraps(&self) -> Wraps {
+        self.last_wraps
+    }
+
+    /// Returns the wrap counts, e.g. to reset them before `set_max_wraps` applies again.
+    pub fn wraps_mut(&mut self) -> &mut Wraps {
+        &mut self.wraps
+    }
+
+    /// Stops with an error once the painter has wrapped around the edges more than
+    /// `max_wraps` times, which catches runaway drawings that scribble over the whole canvas.
+    /// `None`, the default, allows any number of wraps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FFFFFFFF".to_string(), CFRBuffer::new(4, 4));
+    /// executor.set_max_wraps(Some(1));
+    /// assert_eq!(executor.max_wraps(), Some(1));
+    /// assert_eq!(executor.run(), Err("Wrap limit exceeded"));
+    /// assert_eq!(executor.wraps().top, 2);
+    /// ```
+    pub fn set_max_wraps(&mut self, max_wraps: Option<u64>) {
+        self.max_wraps = max_wraps;
+    }
+
+    /// Returns the most wraps allowed, set with `set_max_wraps`.
+    pub fn max_wraps(&self) -> Option<u64> {
+        self.max_wraps
+    }
+
+    /// Returns the canvas at every recent sleep, if enabled with `enable_history`.
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Returns the history, if enabled, e.g. to record pixels drawn with `buffer_mut`.
+    pub fn history_mut(&mut self) -> Option<&mut History> {
+        self.history.as_mut()
+    }
+
+    /// Returns the path of the painter, if enabled with `record_path`.
+    pub fn trail(&self) -> Option<&Trail> {
+        self.trail.as_ref()
+    }
+
+    /// Returns the path of the painter, if enabled, e.g. to draw on it between steps.
+    pub fn trail_mut(&mut self) -> Option<&mut Trail> {
+        self.trail.as_mut()
+    }
+
+    /// Returns the execution state, e.g. to save a checkpoint for `from_parts`.
+    pub fn state(&self) -> &CommandExecutorState {
+        &self.state
+    }
+
+    /// Returns the state, painter, and buffer, which `from_parts` puts back together.
+    pub fn into_parts(self) -> (CommandExecutorState, CFRPainter, B) {
+        (self.state, self.painter, self.buffer)
+    }
+
+    /// Sets how many times blocks run. Call it only before the first step.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.state.dialect = dialect;
+    }
+
+    /// Appends commands to the end of the program, which runs them after the current ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("F".to_string(), CFRBuffer::new(8, 8));
+    /// executor.run().unwrap();
+    /// executor.push_commands("RRF");
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.position(), (4, 2));
+    /// ```
+    pub fn push_commands(&mut self, commands: &str) {
+        self.state.commands.push_str(commands);
+    }
+
+    /// Makes `#` comment out the rest of its line, for programs that explain themselves.
+    /// Without it, `#` is ignored and the commands after it run, as in standard CFRS[].
+    /// Call it only before the first step.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let program = "F # First\nF";
+    /// let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(8, 8));
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.position(), (3, 0));
+    ///
+    /// let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(8, 8));
+    /// executor.set_comments(true);
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.position(), (3, 1));
+    /// ```
+    pub fn set_comments(&mut self, comments: bool) {
+        self.state.comments = comments;
+    }
+
     /// Returns the current position of the painter.
     ///
     /// # Returns
@@ -78,11 +521,40 @@ impl<'a> CommandExecutor<'a> {
         (self.painter.x, self.painter.y)
     }
 
+    /// Returns a read-only view of the program position, open blocks, painter, and sleeps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("S[[FS]]".to_string(), CFRBuffer::new(8, 8));
+    /// for _ in 0..4 {
+    ///     executor.step().unwrap();
+    /// }
+    /// let view = executor.view();
+    /// assert_eq!(view.index, 4);
+    /// assert_eq!(view.next_command(), Some('S'));
+    /// assert_eq!(view.remaining(), "S]]");
+    /// assert_eq!((view.depth(), view.block_starts), (2, &[2, 3][..]));
+    /// assert_eq!(view.sleeps, 1);
+    /// assert_eq!((view.painter.x, view.painter.y), (3, 2));
+    /// ```
+    pub fn view(&self) -> ExecutorView<'_> {
+        ExecutorView {
+            index: self.state.index,
+            block_starts: &self.state.block_starts,
+            painter: self.painter,
+            sleeps: self.sleeps,
+            commands: &self.state.commands,
+        }
+    }
+
     /// Executes the next step in the command sequence.
     ///
     /// # Returns
     ///
-    /// - `Ok((bool, &CFRBuffer))` if the step was executed successfully. The boolean value indicates whether the executor should sleep after the step, and the reference to the `CFRBuffer` is returned.
+    /// - `Ok((bool, &CFRBuffer))` if the step was executed successfully. The boolean value indicates whether the executor should sleep after the step, and the reference to the `CFRBuffer` (or other `PixelSink`) is returned.
     /// - `Err(&'static str)` if an error occurred during execution.
     ///
     /// # Examples
@@ -106,50 +578,292 @@ impl<'a> CommandExecutor<'a> {
     ///     assert_eq!(e, "End of commands");
     /// }
     /// ```
-    pub fn step(&mut self) -> Result<(bool, &CFRBuffer), &'static str> {
-        if self.state.index >= self.state.commands.len() {
-            return Err("End of commands");
+    pub fn step(&mut self) -> Result<(bool, &B::Sink), &'static str> {
+        if self.max_wraps.is_some_and(|max| self.wraps.total() > max) {
+            return Err("Wrap limit exceeded");
         }
-
-        let mut sleep = false;
-        let c = self.state.commands.chars().nth(self.state.index).unwrap();
-        match c {
-            'C' => {
-                self.painter.change_color();
+        self.last_wraps = Wraps::default();
+        if self.skip_invalid()? {
+            return Ok((false, self.buffer.sink()));
+        }
+        #[cfg(feature = "transparent")]
+        let transparent = self.turns_transparent();
+        let sink = self.buffer.sink_mut();
+        let (wraps, last_wraps) = (&mut self.wraps, &mut self.last_wraps);
+        let history = &mut self.history;
+        let trail = &mut self.trail;
+        let (watchpoints, watch_hit) = (&self.watchpoints, &mut self.watch_hit);
+        let index = self.state.index;
+        let sleep = self.state.advance(&mut self.painter, |painter| {
+            let (width, height) = sink.dimensions();
+            if width == 0 || height == 0 {
+                return Ok(());
             }
-            'F' => {
-                self.painter.move_forward_and_draw(self.buffer);
+            *last_wraps = painter.move_forward(width, height)?;
+            *wraps += *last_wraps;
+            sink.set_pixel(painter.x, painter.y, painter.color);
+            if let Some(history) = history {
+                history.record(painter.y * width + painter.x, painter.color);
             }
-            'R' => {
-                self.painter.rotate();
+            if let Some(trail) = trail {
+                trail.set_pixel(painter.x, painter.y, painter.color);
             }
-            'S' => {
-                sleep = true;
+            if watchpoints.contains(&(painter.x, painter.y)) {
+                *watch_hit = Some(WatchHit {
+                    x: painter.x,
+                    y: painter.y,
+                    color: painter.color,
+                    index,
+                });
             }
-            '[' => {
-                self.state.block_starts.push(self.state.index + 1);
+            Ok(())
+        })?;
+        #[cfg(feature = "transparent")]
+        if transparent {
+            self.painter.color = CFRColor::Transparent;
+        }
+        if sleep {
+            self.sleeps += 1;
+            if let Some(history) = &mut self.history {
+                history.end_frame();
             }
-            ']' => {
-                if let Some(block_start) = self.state.block_starts.pop() {
-                    self.state
-                        .commands
-                        .replace_range(self.state.index..=self.state.index, "|");
-                    self.state.index = block_start;
-                    return Ok((sleep, self.buffer));
-                } else {
-                    return Err("Unmatched ]");
+        }
+        Ok((sleep, self.buffer.sink()))
+    }
+
+    /// Switches lenient mode on or off. In lenient mode, like in forgiving online playgrounds,
+    /// an unmatched `]` is skipped, and blocks nested deeper than `max_depth` run once; both
+    /// are collected in `warnings` along with unknown commands, and execution goes on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("F]x[[F]]".to_string(), CFRBuffer::new(8, 8));
+    /// executor.set_max_depth(Some(1));
+    /// executor.set_lenient(true);
+    /// executor.run().unwrap();
+    /// let warnings: Vec<String> = executor.warnings().iter().map(|w| w.to_string()).collect();
+    /// assert_eq!(
+    ///     warnings,
+    ///     vec![
+    ///         "warning at 1: Unmatched ] is skipped",
+    ///         "warning at 2: Unknown command 'x' is ignored",
+    ///         "warning at 4: Blocks nested deeper than 1 run once",
+    ///     ]
+    /// );
+    /// // The outer block runs twice, the inner one once each time.
+    /// assert_eq!(executor.position(), (3, 0));
+    /// ```
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient.then(Lenient::default);
+    }
+
+    /// Stops with an error when blocks are nested deeper than `max_depth`, or in lenient mode
+    /// runs the deeper blocks once. `None`, the default, allows any depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("[[F]]".to_string(), CFRBuffer::new(8, 8));
+    /// executor.set_max_depth(Some(1));
+    /// assert_eq!(executor.max_depth(), Some(1));
+    /// assert_eq!(executor.run(), Err("Nesting too deep"));
+    /// ```
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Returns the deepest nesting of blocks allowed, set with `set_max_depth`.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Returns the warnings of lenient mode so far, each reported once, in the order they
+    /// were found.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        self.lenient
+            .as_ref()
+            .map_or(&[], |lenient| &lenient.warnings)
+    }
+
+    /// Lets `C` cycle from yellow to `CFRColor::Transparent`, and from there back to white,
+    /// for layers that leave holes where lower layers show through.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("CCCCCCCCF".to_string(), CFRBuffer::new(8, 8));
+    /// executor.set_transparent(true);
+    /// executor.run().unwrap();
+    /// assert_eq!(executor.painter().color, CFRColor::Transparent);
+    /// assert_eq!(executor.buffer().to_rgba_bytes()[(2 * 8 + 3) * 4 + 3], 0);
+    /// ```
+    #[cfg(feature = "transparent")]
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    /// Returns whether the next command is a `C` that cycles to `CFRColor::Transparent`.
+    #[cfg(feature = "transparent")]
+    fn turns_transparent(&self) -> bool {
+        self.transparent
+            && self.painter.color == CFRColor::Yellow
+            && self.state.commands.as_bytes().get(self.state.index) == Some(&b'C')
+    }
+
+    /// Checks the next command against lenient mode and `max_depth`, and moves past it if it
+    /// has to be skipped. Returns whether it was skipped.
+    fn skip_invalid(&mut self) -> Result<bool, &'static str> {
+        let index = self.state.index;
+        let Some(&byte) = self.state.commands.as_bytes().get(index) else {
+            return Ok(false);
+        };
+        let too_deep = byte == b'['
+            && self
+                .max_depth
+                .is_some_and(|max| self.depth_at(index) >= max);
+        let state = &mut self.state;
+        let Some(lenient) = &mut self.lenient else {
+            return if too_deep {
+                Err("Nesting too deep")
+            } else {
+                Ok(false)
+            };
+        };
+        match byte {
+            b'[' if lenient.skipped_blocks > 0 || too_deep => {
+                lenient.skipped_blocks += 1;
+                lenient.warn(
+                    index..index + 1,
+                    format!(
+                        "Blocks nested deeper than {} run once",
+                        self.max_depth.unwrap_or(0)
+                    ),
+                );
+            }
+            b']' if lenient.skipped_blocks > 0 => lenient.skipped_blocks -= 1,
+            b']' if state.block_starts.is_empty() => {
+                lenient.warn(index..index + 1, "Unmatched ] is skipped".to_string());
+            }
+            b'C' | b'F' | b'R' | b'S' | b'[' | b']' | b'|' => return Ok(false),
+            b'#' if state.comments => return Ok(false),
+            _ => {
+                if let Some(c) = state.commands.get(index..).and_then(|s| s.chars().next()) {
+                    if !c.is_whitespace() {
+                        lenient.warn(
+                            index..index + c.len_utf8(),
+                            format!("Unknown command '{}' is ignored", c),
+                        );
+                    }
                 }
+                return Ok(false);
             }
-            '|' => {
-                self.state
-                    .commands
-                    .replace_range(self.state.index..=self.state.index, "]");
+        }
+        state.index += 1;
+        Ok(true)
+    }
+
+    /// Returns how many blocks enclose the `[` at `index` in the program.
+    ///
+    /// Blocks on their second run are no longer in `block_starts`, so the depth comes from
+    /// the program itself, scanned again only when it has grown.
+    fn depth_at(&mut self, index: usize) -> usize {
+        let commands = &self.state.commands;
+        let (_, depths) = match &mut self.block_depths {
+            Some(cached) if cached.0 == commands.len() => cached,
+            block_depths => {
+                let mut depths = HashMap::new();
+                let mut open = 0usize;
+                let mut comment = false;
+                for (i, c) in commands.char_indices() {
+                    match c {
+                        '\n' => comment = false,
+                        _ if comment => {}
+                        '#' if self.state.comments => comment = true,
+                        '[' => {
+                            depths.insert(i, open);
+                            open += 1;
+                        }
+                        ']' | '|' => open = open.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+                block_depths.insert((commands.len(), depths))
             }
-            _ => {}
+        };
+        depths.get(&index).copied().unwrap_or(0)
+    }
+
+    /// Pauses `run_to_watchpoint` whenever the pixel at (`x`, `y`) is painted, to find out
+    /// which command drew it.
+    pub fn watch_pixel(&mut self, x: u32, y: u32) {
+        if !self.watchpoints.contains(&(x, y)) {
+            self.watchpoints.push((x, y));
         }
+    }
+
+    /// Stops watching the pixel at (`x`, `y`).
+    pub fn unwatch_pixel(&mut self, x: u32, y: u32) {
+        self.watchpoints.retain(|&p| p != (x, y));
+    }
 
-        self.state.index += 1;
-        Ok((sleep, self.buffer))
+    /// Executes commands until one paints a pixel watched with `watch_pixel`, and returns
+    /// that pixel and command, or `None` at the end of the program. Call it again to resume.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FFRRRRFF".to_string(), CFRBuffer::new(8, 8));
+    /// executor.watch_pixel(3, 2);
+    /// let hits: Vec<usize> = std::iter::from_fn(|| executor.run_to_watchpoint().unwrap())
+    ///     .map(|hit| hit.index)
+    ///     .collect();
+    /// assert_eq!(hits, vec![0, 6]);
+    /// ```
+    pub fn run_to_watchpoint(&mut self) -> Result<Option<WatchHit>, &'static str> {
+        loop {
+            match self.step() {
+                Ok(_) => {}
+                Err("End of commands") => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if let Some(hit) = self.watch_hit.take() {
+                return Ok(Some(hit));
+            }
+        }
+    }
+
+    /// Starts recording the path of the painter for `path`.
+    pub fn record_path(&mut self) {
+        let (width, height) = self.buffer.sink().dimensions();
+        self.trail = Some(Trail::new(width, height));
+    }
+
+    /// Returns the path of the painter since `record_path` as runs of movement grouped by
+    /// color and pen state, or nothing if the path isn't recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FFRRFF".to_string(), CFRBuffer::new(8, 8));
+    /// executor.record_path();
+    /// executor.run().unwrap();
+    /// let path = executor.path();
+    /// assert_eq!(path.len(), 1);
+    /// assert_eq!(path[0].points, vec![(3, 2), (3, 1), (4, 1), (5, 1)]);
+    /// ```
+    pub fn path(&self) -> Vec<PathSegment> {
+        self.trail.as_ref().map(Trail::segments).unwrap_or_default()
     }
 
     /// Executes all the steps in the command sequence.
@@ -189,4 +903,366 @@ impl<'a> CommandExecutor<'a> {
 
         Ok(())
     }
+
+    /// Runs the rest of the program on a copy of the painter without touching the canvas,
+    /// stopping after `max_steps` steps, and returns where the painter would go. This helps
+    /// to pick a canvas size, or to preview a program before rendering it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("[[FFS]]RRFF".to_string(), CFRBuffer::new(8, 8));
+    /// let dry_run = executor.dry_run(1000);
+    /// let bounds = dry_run.bounds.unwrap();
+    /// assert_eq!((bounds.top, bounds.right), (-8, 2));
+    /// assert_eq!((dry_run.sleeps, dry_run.wraps.top), (4, 1));
+    /// assert_eq!(dry_run.path.len(), 3);
+    /// assert!(executor.buffer().data.iter().all(|&c| c == CFRColor::Black));
+    ///
+    /// assert_eq!(executor.dry_run(3).error, Some("Step limit exceeded"));
+    /// ```
+    pub fn dry_run(&self, max_steps: u64) -> DryRun {
+        let (width, height) = self.buffer.sink().dimensions();
+        let trail = Trail::new(width, height);
+        let mut ghost = CommandExecutor::from_parts(self.state.clone(), self.painter, trail);
+        ghost.max_wraps = self
+            .max_wraps
+            .map(|max| max.saturating_sub(self.wraps.total()));
+        ghost.max_depth = self.max_depth;
+        ghost.lenient = self.lenient.as_ref().map(|_| Lenient::default());
+        #[cfg(feature = "transparent")]
+        {
+            ghost.transparent = self.transparent;
+        }
+
+        let (mut x, mut y) = (0i64, 0i64);
+        let mut bounds: Option<Bounds> = None;
+        let (mut steps, mut sleeps) = (0, 0);
+        let error = loop {
+            if steps >= max_steps {
+                break Some("Step limit exceeded");
+            }
+            let direction = ghost.painter.direction;
+            let draws = ghost.view().next_command() == Some('F');
+            match ghost.step() {
+                Ok((sleep, _)) => {
+                    steps += 1;
+                    sleeps += sleep as u64;
+                }
+                Err("End of commands") => break None,
+                Err(e) => break Some(e),
+            }
+            if draws && width > 0 && height > 0 {
+                let (dx, dy) = direction.offset();
+                (x, y) = (x.saturating_add(dx), y.saturating_add(dy));
+                bounds = Some(match bounds {
+                    Some(b) => Bounds {
+                        left: b.left.min(x),
+                        top: b.top.min(y),
+                        right: b.right.max(x),
+                        bottom: b.bottom.max(y),
+                    },
+                    None => Bounds {
+                        left: x,
+                        top: y,
+                        right: x,
+                        bottom: y,
+                    },
+                });
+            }
+        };
+        DryRun {
+            path: ghost.buffer().segments(),
+            bounds,
+            steps,
+            sleeps,
+            wraps: ghost.wraps,
+            error,
+        }
+    }
+
+    /// Executes commands without drawing until execution first reaches the byte offset
+    /// `index`, so the painter, colors and blocks are as they would be there.
+    ///
+    /// Returns an error after `max_steps` commands, as getting there may take longer than the
+    /// caller is willing to wait.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("CFFRF".to_string(), CFRBuffer::new(8, 8));
+    /// executor.fast_forward(3, 1000).unwrap();
+    /// assert_eq!(executor.position(), (3, 1));
+    /// assert!(executor.buffer().data.iter().all(|&c| c == CFRColor::Black));
+    /// ```
+    pub fn fast_forward(&mut self, index: usize, max_steps: u64) -> Result<(), &'static str> {
+        let (width, height) = self.buffer.sink().dimensions();
+        let mut steps = 0;
+        while self.state.index < index {
+            if steps == max_steps {
+                return Err("Step limit exceeded");
+            }
+            steps += 1;
+            if self.skip_invalid()? {
+                continue;
+            }
+            #[cfg(feature = "transparent")]
+            let transparent = self.turns_transparent();
+            match self.state.advance(&mut self.painter, |painter| {
+                painter.move_forward(width, height).map(|_| ())
+            }) {
+                #[cfg(feature = "transparent")]
+                Ok(_) if transparent => self.painter.color = CFRColor::Transparent,
+                Ok(_) => {}
+                Err("End of commands") => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes only the commands at the byte offsets `range` of the program, to isolate
+    /// which part of it draws something.
+    ///
+    /// With `fast_forward`, the program first runs without drawing until it reaches
+    /// `range.start`, then draws until it first reaches `range.end`. Otherwise the slice runs
+    /// on its own, from the current painter, replacing the program.
+    ///
+    /// Returns an error if the range is out of bounds or splits a character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FFFRRFF".to_string(), CFRBuffer::new(8, 8));
+    /// executor.run_range(3..6, true).unwrap();
+    /// let drawn = executor.buffer().data.iter().filter(|&&c| c == CFRColor::White).count();
+    /// assert_eq!(drawn, 1);
+    /// assert_eq!(executor.position(), (4, 0));
+    /// ```
+    pub fn run_range(
+        &mut self,
+        range: Range<usize>,
+        fast_forward: bool,
+    ) -> Result<(), &'static str> {
+        let Some(slice) = self.state.commands.get(range.clone()) else {
+            return Err("Invalid range");
+        };
+        if !fast_forward {
+            self.state = CommandExecutorState {
+                commands: slice.to_string(),
+                index: 0,
+                block_starts: Vec::new(),
+                dialect: self.state.dialect,
+                repeats: Vec::new(),
+                comments: self.state.comments,
+            };
+            return self.run();
+        }
+        self.fast_forward(range.start, u64::MAX)?;
+        while self.state.index < range.end {
+            match self.step() {
+                Ok(_) => {}
+                Err("End of commands") => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[allow(deprecated)]
+impl<'a, B: AsPixelSink<Sink = CFRBuffer>> CommandExecutor<'a, B> {
+    /// Starts recording the canvas at every following sleep, keeping at most `max_bytes` of
+    /// history, so viewers can scrub back without running the program again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+    ///
+    /// let mut executor = CommandExecutor::new("FSFSFS".to_string(), CFRBuffer::new(8, 8));
+    /// executor.enable_history(1 << 20);
+    /// executor.run().unwrap();
+    /// let first = executor.buffer_at_sleep(0).unwrap();
+    /// assert_eq!(first.data.iter().filter(|&&c| c == CFRColor::White).count(), 1);
+    /// ```
+    pub fn enable_history(&mut self, max_bytes: usize) {
+        self.history = Some(History::new(self.buffer.sink().clone(), max_bytes));
+    }
+
+    /// Returns the canvas at the `n`th sleep since `enable_history` (counting from 0), or
+    /// `None` if it isn't in the history.
+    pub fn buffer_at_sleep(&self, n: usize) -> Option<CFRBuffer> {
+        self.history.as_ref()?.buffer_at_sleep(n)
+    }
+}
+
+mod tests {
+    #[test]
+    fn skip_non_ascii_characters() {
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        let mut executor = CommandExecutor::new("é[F]ü".to_string(), &mut buffer);
+        executor.run().unwrap();
+        assert_eq!(executor.position(), (3, 1));
+        assert_eq!(buffer.data[2 * 8 + 3], CFRColor::White);
+    }
+
+    #[test]
+    fn skip_comments() {
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        let mut executor = CommandExecutor::new("F # FFS\nR#F".to_string(), &mut buffer);
+        executor.set_comments(true);
+        let mut sleeps = 0;
+        while let Ok((sleep, _)) = executor.step() {
+            sleeps += sleep as u32;
+        }
+        assert_eq!(sleeps, 0);
+        assert_eq!(executor.position(), (3, 2));
+
+        // A comment at the end stops there, so pushed commands still run.
+        assert_eq!(executor.view().index, 11);
+        executor.push_commands("\nF");
+        executor.run().unwrap();
+        assert_eq!(executor.position(), (4, 1));
+    }
+
+    #[test]
+    fn run_range_alone_or_fast_forwarded() {
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let program = "F[RF]CF";
+        let mut alone = CommandExecutor::new(program.to_string(), CFRBuffer::new(8, 8));
+        alone.run_range(1..5, false).unwrap();
+        assert_eq!(alone.position(), (5, 2));
+        assert_eq!(alone.buffer().data[2 * 8 + 4], CFRColor::White);
+
+        let mut forwarded = CommandExecutor::new(program.to_string(), CFRBuffer::new(8, 8));
+        forwarded.run_range(1..5, true).unwrap();
+        assert_eq!(forwarded.position(), (5, 1));
+        assert_eq!(forwarded.buffer().data[2 * 8 + 3], CFRColor::Black);
+        assert_eq!(forwarded.buffer().data[8 + 4], CFRColor::White);
+
+        let mut executor = CommandExecutor::new("é".to_string(), CFRBuffer::new(8, 8));
+        assert_eq!(executor.run_range(0..1, false), Err("Invalid range"));
+        assert_eq!(executor.run_range(0..3, true), Err("Invalid range"));
+    }
+
+    #[test]
+    fn view_hides_block_markers() {
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let mut executor = CommandExecutor::new("[F]é".to_string(), CFRBuffer::new(8, 8));
+        for _ in 0..3 {
+            executor.step().unwrap();
+        }
+        assert_eq!(executor.state().commands, "[F|é");
+        assert_eq!(executor.view().remaining(), "F]é");
+        executor.step().unwrap();
+        executor.step().unwrap();
+        assert_eq!(
+            executor.view().next_command(),
+            Some(char::REPLACEMENT_CHARACTER)
+        );
+        executor.step().unwrap();
+        assert_eq!(executor.view().remaining(), "");
+        executor.run().unwrap();
+        assert_eq!(executor.view().next_command(), None);
+    }
+
+    #[test]
+    fn stop_after_max_wraps() {
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let mut buffer = CFRBuffer::new(4, 4);
+        let mut executor = CommandExecutor::new("FFFFFFFF".to_string(), &mut buffer);
+        executor.set_max_wraps(Some(1));
+        assert_eq!(executor.run(), Err("Wrap limit exceeded"));
+        assert_eq!(executor.wraps().top, 2);
+        assert_eq!(executor.view().index, 6);
+    }
+
+    #[test]
+    fn last_wraps_of_every_step() {
+        use crate::{CFRBuffer, CommandExecutor};
+
+        // Down-right from the center of a 3x3 canvas, the second `F` wraps through a corner.
+        let mut executor = CommandExecutor::new("RRRFFF".to_string(), CFRBuffer::new(3, 3));
+        let mut wraps = Vec::new();
+        while executor.step().is_ok() {
+            let last = executor.last_wraps();
+            wraps.push((last.right, last.bottom));
+        }
+        assert_eq!(wraps, vec![(0, 0), (0, 0), (0, 0), (0, 0), (1, 1), (0, 0)]);
+        assert_eq!(executor.wraps().total(), 2);
+    }
+
+    #[cfg(feature = "checked-math")]
+    #[test]
+    fn painter_outside_the_canvas() {
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let mut executor = CommandExecutor::new("FF".to_string(), CFRBuffer::new(4, 4));
+        executor.step().unwrap();
+        (executor.painter_mut().x, executor.painter_mut().y) = (9, 9);
+        assert_eq!(
+            executor.step().err(),
+            Some("Painter moved outside the canvas")
+        );
+        assert_eq!(executor.view().index, 1);
+    }
+
+    #[test]
+    fn repeat_dialect() {
+        use crate::{CFRBuffer, CommandExecutor, Dialect};
+
+        let run = |program: &str, dialect| {
+            let mut buffer = CFRBuffer::new(32, 32);
+            let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+            executor.set_dialect(dialect);
+            executor.run().map(|_| executor.position())
+        };
+        assert_eq!(
+            run("[[F]R]", Dialect::Standard),
+            run("[[F]R]", Dialect::Repeat(2))
+        );
+        assert_eq!(run("[F]", Dialect::Repeat(5)), Ok((15, 10)));
+        assert_eq!(run("[[F]]", Dialect::Repeat(3)), Ok((15, 6)));
+        assert_eq!(run("[F]", Dialect::Repeat(0)), Ok((15, 14)));
+        assert_eq!(run("[F]]", Dialect::Repeat(3)), Err("Unmatched ]"));
+    }
+
+    #[test]
+    fn lenient_mode() {
+        use crate::{CFRBuffer, CommandExecutor, Dialect};
+
+        let mut executor = CommandExecutor::new("[[[F]]]".to_string(), CFRBuffer::new(32, 32));
+        executor.set_max_depth(Some(2));
+        assert_eq!(executor.run(), Err("Nesting too deep"));
+        assert!(executor.warnings().is_empty());
+
+        let mut executor = CommandExecutor::new("[[[F]]]".to_string(), CFRBuffer::new(32, 32));
+        executor.set_max_depth(Some(2));
+        executor.set_lenient(true);
+        executor.run().unwrap();
+        assert_eq!(executor.position(), (15, 11));
+        assert_eq!(executor.warnings().len(), 1);
+
+        let mut executor = CommandExecutor::new("F]]é F".to_string(), CFRBuffer::new(32, 32));
+        executor.set_dialect(Dialect::Repeat(3));
+        executor.set_lenient(true);
+        executor.fast_forward(8, 1000).unwrap();
+        assert_eq!(executor.position(), (15, 13));
+        let spans: Vec<_> = executor.warnings().iter().map(|w| w.span.clone()).collect();
+        assert_eq!(spans, vec![1..2, 2..3, 3..5]);
+    }
 }
@@ -0,0 +1,138 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use std::ffi::{c_char, c_int, CStr};
+use std::ptr;
+
+/// Returned by `cfrs_step` when a command was executed.
+pub const CFRS_STEP_OK: c_int = 0;
+/// Returned by `cfrs_step` when the executed command was `S` (sleep).
+pub const CFRS_STEP_SLEEP: c_int = 1;
+/// Returned by `cfrs_step` when there are no more commands to execute.
+pub const CFRS_STEP_END: c_int = -1;
+/// Returned by `cfrs_step` when the program is invalid (e.g. an unmatched `]`).
+pub const CFRS_STEP_ERROR: c_int = -2;
+
+/// An opaque interpreter instance that owns its program and canvas.
+pub struct CfrsInstance {
+    executor: CommandExecutor<'static, CFRBuffer>,
+}
+
+/// Creates a new interpreter instance for a NUL-terminated UTF-8 program.
+///
+/// Returns a null pointer if `program` is null or not valid UTF-8, if either dimension is zero,
+/// or if the canvas has more than `u32::MAX` pixels.
+/// The instance must be released with `cfrs_free`.
+///
+/// # Safety
+///
+/// `program` must be null or point to a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cfrs_new(
+    program: *const c_char,
+    width: u32,
+    height: u32,
+) -> *mut CfrsInstance {
+    if program.is_null() {
+        return ptr::null_mut();
+    }
+    let commands = match CStr::from_ptr(program).to_str() {
+        Ok(s) => s.to_string(),
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let Ok(buffer) = CFRBuffer::try_new(width, height) else {
+        return ptr::null_mut();
+    };
+    let executor = CommandExecutor::new(commands, buffer);
+    Box::into_raw(Box::new(CfrsInstance { executor }))
+}
+
+/// Executes the next command.
+///
+/// Returns `CFRS_STEP_OK`, `CFRS_STEP_SLEEP`, `CFRS_STEP_END` or `CFRS_STEP_ERROR`.
+///
+/// # Safety
+///
+/// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cfrs_step(instance: *mut CfrsInstance) -> c_int {
+    let Some(instance) = instance.as_mut() else {
+        return CFRS_STEP_ERROR;
+    };
+
+    match instance.executor.step() {
+        Ok((true, _)) => CFRS_STEP_SLEEP,
+        Ok((false, _)) => CFRS_STEP_OK,
+        Err("End of commands") => CFRS_STEP_END,
+        Err(_) => CFRS_STEP_ERROR,
+    }
+}
+
+/// Returns a pointer to the canvas pixels, `width * height` bytes in row-major order.
+///
+/// Each byte is a color index following the painter's color cycle:
+/// 0 = white, 1 = black, 2 = blue, 3 = green, 4 = cyan, 5 = red, 6 = magenta, 7 = yellow.
+/// Builds with the `transparent` feature also use 8 = transparent.
+/// The pointer stays valid until `instance` is freed.
+///
+/// # Safety
+///
+/// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cfrs_buffer_ptr(instance: *const CfrsInstance) -> *const u8 {
+    match instance.as_ref() {
+        Some(instance) => instance.executor.buffer().data.as_ptr() as *const u8,
+        None => ptr::null(),
+    }
+}
+
+/// Releases an instance created by `cfrs_new`. Passing a null pointer is a no-op.
+///
+/// # Safety
+///
+/// `instance` must be null or a pointer returned by `cfrs_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cfrs_free(instance: *mut CfrsInstance) {
+    if !instance.is_null() {
+        drop(Box::from_raw(instance));
+    }
+}
+
+mod tests {
+    #[test]
+    fn step_and_read_buffer() {
+        use crate::ffi::*;
+        use std::ffi::CString;
+
+        let program = CString::new("FSF").unwrap();
+        unsafe {
+            let instance = cfrs_new(program.as_ptr(), 8, 8);
+            assert!(!instance.is_null());
+            assert_eq!(cfrs_step(instance), CFRS_STEP_OK);
+            assert_eq!(cfrs_step(instance), CFRS_STEP_SLEEP);
+            assert_eq!(cfrs_step(instance), CFRS_STEP_OK);
+            assert_eq!(cfrs_step(instance), CFRS_STEP_END);
+
+            let pixels = std::slice::from_raw_parts(cfrs_buffer_ptr(instance), 64);
+            assert_eq!(pixels[2 * 8 + 3], 0);
+            assert_eq!(pixels[8 + 3], 0);
+            assert_eq!(pixels[0], 1);
+            cfrs_free(instance);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        use crate::ffi::*;
+        use std::ffi::CString;
+
+        let program = CString::new("F").unwrap();
+        unsafe {
+            assert!(cfrs_new(std::ptr::null(), 8, 8).is_null());
+            assert!(cfrs_new(program.as_ptr(), 0, 8).is_null());
+            assert_eq!(cfrs_step(std::ptr::null_mut()), CFRS_STEP_ERROR);
+            assert!(cfrs_buffer_ptr(std::ptr::null()).is_null());
+            cfrs_free(std::ptr::null_mut());
+        }
+    }
+}
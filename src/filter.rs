@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+/// A post-processing effect applied to every captured frame before it is encoded.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::filter::FrameFilter;
+///
+/// /// Swaps the red and blue channels.
+/// struct SwapRedBlue;
+///
+/// impl FrameFilter for SwapRedBlue {
+///     fn apply(&self, frame: &mut [u8], _width: u32, _height: u32) {
+///         frame.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+///     }
+/// }
+///
+/// let mut frame = vec![255, 0, 0, 255];
+/// SwapRedBlue.apply(&mut frame, 1, 1);
+/// assert_eq!(frame, vec![0, 0, 255, 255]);
+/// ```
+pub trait FrameFilter {
+    /// Changes the RGBA `frame` of `width` × `height` pixels, in row-major order, in place.
+    fn apply(&self, frame: &mut [u8], width: u32, height: u32);
+}
+
+/// The built-in filters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Darkens every other row like the scanlines of a CRT monitor.
+    Crt,
+    /// Inverts the colors.
+    Invert,
+    /// Converts the colors to shades of gray.
+    Grayscale,
+    /// Averages every pixel with its neighbors up to this many pixels away.
+    Blur(u32),
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "crt" => Ok(Filter::Crt),
+            "invert" => Ok(Filter::Invert),
+            "grayscale" => Ok(Filter::Grayscale),
+            "blur" => Ok(Filter::Blur(1)),
+            other => other
+                .strip_prefix("blur:")
+                .and_then(|radius| radius.parse().ok())
+                .map(Filter::Blur)
+                .ok_or_else(|| format!("Invalid filter: {}", s)),
+        }
+    }
+}
+
+impl FrameFilter for Filter {
+    fn apply(&self, frame: &mut [u8], width: u32, height: u32) {
+        match *self {
+            Filter::Crt => {
+                for row in frame
+                    .chunks_exact_mut((width as usize * 4).max(1))
+                    .skip(1)
+                    .step_by(2)
+                {
+                    for pixel in row.chunks_exact_mut(4) {
+                        pixel[..3].iter_mut().for_each(|c| *c /= 2);
+                    }
+                }
+            }
+            Filter::Invert => {
+                for pixel in frame.chunks_exact_mut(4) {
+                    pixel[..3].iter_mut().for_each(|c| *c = 255 - *c);
+                }
+            }
+            Filter::Grayscale => {
+                for pixel in frame.chunks_exact_mut(4) {
+                    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as u32);
+                    let luma = ((299 * r + 587 * g + 114 * b) / 1000) as u8;
+                    pixel[..3].fill(luma);
+                }
+            }
+            Filter::Blur(radius) => {
+                blur_pass(frame, width, height, radius, 4, width as usize * 4);
+                blur_pass(frame, height, width, radius, width as usize * 4, 4);
+            }
+        }
+    }
+}
+
+/// Box-blurs `lines` lines of `length` pixels each, where consecutive pixels of a line are
+/// `step` bytes apart and consecutive lines `stride` bytes apart.
+fn blur_pass(frame: &mut [u8], length: u32, lines: u32, radius: u32, step: usize, stride: usize) {
+    let radius = radius as i64;
+    let mut line = vec![[0u8; 3]; length as usize];
+    for l in 0..lines as usize {
+        for (i, pixel) in line.iter_mut().enumerate() {
+            let start = l * stride + i * step;
+            pixel.copy_from_slice(&frame[start..start + 3]);
+        }
+        for i in 0..length as i64 {
+            let from = (i - radius).max(0);
+            let to = (i + radius).min(length as i64 - 1);
+            let mut sum = [0u32; 3];
+            for pixel in &line[from as usize..=to as usize] {
+                (0..3).for_each(|c| sum[c] += pixel[c] as u32);
+            }
+            let count = (to - from + 1) as u32;
+            let start = l * stride + i as usize * step;
+            (0..3).for_each(|c| frame[start + c] = (sum[c] / count) as u8);
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn builtin_filters() {
+        use crate::filter::{Filter, FrameFilter};
+        use std::str::FromStr;
+
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        let frame: Vec<u8> = [white, black, white, white].concat();
+
+        let mut crt = frame.clone();
+        Filter::Crt.apply(&mut crt, 2, 2);
+        assert_eq!(
+            crt,
+            [white, black, [127, 127, 127, 255], [127, 127, 127, 255]].concat()
+        );
+
+        let mut inverted = frame.clone();
+        Filter::Invert.apply(&mut inverted, 2, 2);
+        assert_eq!(inverted, [black, white, black, black].concat());
+
+        let mut blurred = frame.clone();
+        Filter::from_str("blur:1")
+            .unwrap()
+            .apply(&mut blurred, 2, 2);
+        assert!(blurred.chunks(4).all(|p| p == [191, 191, 191, 255]));
+
+        assert_eq!(Filter::from_str("blur"), Ok(Filter::Blur(1)));
+        assert!(Filter::from_str("sepia").is_err());
+    }
+}
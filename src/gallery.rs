@@ -0,0 +1,112 @@
+use crate::html::json_string;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// A rendered program of a gallery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GalleryEntry {
+    /// The program file.
+    pub program: PathBuf,
+    /// The rendered image or animation, relative to the gallery.
+    pub image: PathBuf,
+    /// The thumbnail, relative to the gallery, if thumbnails are made.
+    pub thumbnail: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    pub steps: u64,
+    pub sleeps: u64,
+    /// Number of animation frames, or 1 for a still image.
+    pub frames: usize,
+    /// Number of pixels that differ from the background.
+    pub painted: usize,
+    /// Number of distinct colors other than the background.
+    pub colors: usize,
+    /// Why the program stopped early, if it did.
+    pub error: Option<String>,
+}
+
+/// Writes the `index.json` manifest of a gallery, from which static site generators can
+/// build the gallery pages:
+///
+/// ```json
+/// {"entries":[{"program":"art/spiral.cfrs","image":"spiral.gif","thumbnail":"spiral_thumb.png",
+/// "width":256,"height":256,"steps":2047,"sleeps":1024,"frames":21,"painted":512,"colors":1,
+/// "error":null}]}
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::gallery::{write_index, GalleryEntry};
+///
+/// let entry = GalleryEntry {
+///     program: "dot.cfrs".into(),
+///     image: "dot.png".into(),
+///     thumbnail: None,
+///     width: 8,
+///     height: 8,
+///     steps: 1,
+///     sleeps: 0,
+///     frames: 1,
+///     painted: 1,
+///     colors: 1,
+///     error: None,
+/// };
+/// let mut json = Vec::new();
+/// write_index(&mut json, &[entry]).unwrap();
+/// assert!(String::from_utf8(json).unwrap().starts_with(r#"{"entries":[{"program":"dot.cfrs","#));
+/// ```
+pub fn write_index<W: Write>(mut writer: W, entries: &[GalleryEntry]) -> io::Result<()> {
+    let path = |path: &PathBuf| json_string(&path.to_string_lossy().replace('\\', "/"));
+    writer.write_all(b"{\"entries\":[")?;
+    for (i, entry) in entries.iter().enumerate() {
+        write!(
+            writer,
+            "{}{{\"program\":{},\"image\":{},\"thumbnail\":{},\"width\":{},\"height\":{},\"steps\":{},\"sleeps\":{},\"frames\":{},\"painted\":{},\"colors\":{},\"error\":{}}}",
+            if i == 0 { "" } else { "," },
+            path(&entry.program),
+            path(&entry.image),
+            entry.thumbnail.as_ref().map_or("null".to_string(), path),
+            entry.width,
+            entry.height,
+            entry.steps,
+            entry.sleeps,
+            entry.frames,
+            entry.painted,
+            entry.colors,
+            entry.error.as_deref().map_or("null".to_string(), json_string)
+        )?;
+    }
+    writer.write_all(b"]}")?;
+    writer.flush()
+}
+
+mod tests {
+    #[test]
+    fn index_json() {
+        use crate::gallery::{write_index, GalleryEntry};
+
+        let entry = GalleryEntry {
+            program: "art\\spiral.cfrs".into(),
+            image: "spiral.gif".into(),
+            thumbnail: Some("spiral_thumb.png".into()),
+            width: 64,
+            height: 32,
+            steps: 7,
+            sleeps: 2,
+            frames: 3,
+            painted: 2,
+            colors: 1,
+            error: Some("Step limit \"exceeded\"".to_string()),
+        };
+        let mut json = Vec::new();
+        write_index(&mut json, &[entry.clone(), entry]).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        let object = concat!(
+            r#"{"program":"art/spiral.cfrs","image":"spiral.gif","thumbnail":"spiral_thumb.png","#,
+            r#""width":64,"height":32,"steps":7,"sleeps":2,"frames":3,"painted":2,"colors":1,"#,
+            r#""error":"Step limit \"exceeded\""}"#
+        );
+        assert_eq!(json, format!(r#"{{"entries":[{},{}]}}"#, object, object));
+    }
+}
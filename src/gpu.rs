@@ -0,0 +1,246 @@
+use crate::buffer::CFRBuffer;
+use wgpu::util::DeviceExt;
+
+const SHADER: &str = r#"
+struct Canvas {
+    width: u32,
+    height: u32,
+}
+
+@group(0) @binding(0) var pixels: texture_2d<u32>;
+@group(0) @binding(1) var<uniform> canvas: Canvas;
+
+var<private> PALETTE: array<vec3<f32>, 8> = array<vec3<f32>, 8>(
+    vec3<f32>(1.0, 1.0, 1.0),
+    vec3<f32>(0.0, 0.0, 0.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, 1.0, 1.0),
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(1.0, 0.0, 1.0),
+    vec3<f32>(1.0, 1.0, 0.0),
+);
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// A single triangle covering the whole viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let x = min(u32(in.uv.x * f32(canvas.width)), canvas.width - 1u);
+    let y = min(u32(in.uv.y * f32(canvas.height)), canvas.height - 1u);
+    let word = textureLoad(pixels, vec2<u32>(x / 10u, y), 0).r;
+    let index = (word >> (3u * (x % 10u))) & 7u;
+    return vec4<f32>(PALETTE[index], 1.0);
+}
+"#;
+
+/// Draws a `CFRBuffer` with wgpu.
+///
+/// The buffer is uploaded as a texture of 3-bit palette indices (see
+/// `CFRBuffer::to_packed_indices`), a tenth of the size of an RGBA upload, and the palette is
+/// expanded in the fragment shader. The canvas is stretched over the viewport of the render
+/// pass with nearest-neighbor sampling; set the viewport to keep the aspect ratio.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn frame(device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+/// use cfrs::gpu::CfrsRenderer;
+/// use cfrs::CFRBuffer;
+///
+/// let buffer = CFRBuffer::new(3840, 2160);
+/// let renderer = CfrsRenderer::new(device, wgpu::TextureFormat::Bgra8UnormSrgb, 3840, 2160);
+/// renderer.update(queue, &buffer);
+///
+/// let mut encoder = device.create_command_encoder(&Default::default());
+/// {
+///     let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+///         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+///             view,
+///             resolve_target: None,
+///             ops: wgpu::Operations::default(),
+///         })],
+///         ..Default::default()
+///     });
+///     renderer.render(&mut pass);
+/// }
+/// queue.submit([encoder.finish()]);
+/// # }
+/// ```
+pub struct CfrsRenderer {
+    width: u32,
+    height: u32,
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl CfrsRenderer {
+    /// Creates a renderer for `width`×`height` canvases that draws into targets of `format`.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("cfrs canvas"),
+            size: packed_size(width, height),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let canvas = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("cfrs canvas size"),
+            contents: &[width.to_le_bytes(), height.to_le_bytes()].concat(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("cfrs"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("cfrs"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: canvas.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cfrs"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cfrs"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("cfrs"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            width,
+            height,
+            texture,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Uploads the pixels of `buffer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer does not have the dimensions the renderer was created with.
+    pub fn update(&self, queue: &wgpu::Queue, buffer: &CFRBuffer) {
+        assert_eq!(
+            (buffer.width, buffer.height),
+            (self.width, self.height),
+            "Buffer dimensions differ from the renderer"
+        );
+        let size = packed_size(self.width, self.height);
+        let bytes: Vec<u8> = buffer
+            .to_packed_indices()
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.width * 4),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+    }
+
+    /// Draws the last uploaded pixels into the render pass.
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn packed_size(width: u32, height: u32) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: width.div_ceil(10).max(1),
+        height: height.max(1),
+        depth_or_array_layers: 1,
+    }
+}
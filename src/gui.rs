@@ -0,0 +1,187 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use crate::throttle::Throttle;
+use crate::viewport::Viewport;
+use egui::{ColorImage, Key, Response, TextureHandle, TextureOptions, Ui};
+
+/// Maximum number of commands run per repaint, so a program that rarely sleeps doesn't
+/// freeze the UI. Playing goes on with the next repaint.
+pub const MAX_STEPS: u64 = 100_000;
+
+/// Converts the buffer to an egui `ColorImage`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let texture = ctx.load_texture("cfrs", cfrs::gui::to_color_image(&buffer), TextureOptions::NEAREST);
+/// ```
+pub fn to_color_image(buffer: &CFRBuffer) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied(
+        [buffer.width as usize, buffer.height as usize],
+        &buffer.to_rgba_bytes(),
+    )
+}
+
+/// An egui widget that runs a CFRS program and shows its canvas with play/pause/step controls.
+///
+/// While playing, the program runs in real time, with every `S` (sleep) command taking 20ms
+/// divided by the speed. "Step" runs the program until the next sleep while paused. The arrow
+/// keys pan the canvas, `+` and `-` zoom it, and `0` shows all of it again.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut viewer = CfrsViewer::new("[[[[[[[[[[FS]]]]R]]RR]]RRCC]]", 256, 256);
+///
+/// egui::CentralPanel::default().show(ctx, |ui| {
+///     viewer.show(ui);
+/// });
+/// ```
+pub struct CfrsViewer {
+    program: String,
+    executor: CommandExecutor<'static, CFRBuffer>,
+    texture: Option<TextureHandle>,
+    playing: bool,
+    finished: bool,
+    scale: f32,
+    throttle: Throttle,
+    viewport: Viewport,
+}
+
+impl CfrsViewer {
+    /// Creates a paused viewer for the program on a canvas of the given size.
+    pub fn new(program: impl Into<String>, width: u32, height: u32) -> Self {
+        let program = program.into();
+        let executor = CommandExecutor::new(program.clone(), CFRBuffer::new(width, height));
+        Self {
+            program,
+            executor,
+            texture: None,
+            playing: false,
+            finished: false,
+            scale: 1.0,
+            throttle: Throttle::new(1.0),
+            viewport: Viewport::default(),
+        }
+    }
+
+    /// Sets the size of one canvas pixel in points.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the playback speed, from 0.25 to 16 times the normal speed.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.throttle.set_speed(speed);
+        self
+    }
+
+    /// Returns the part of the canvas that is shown.
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Returns the current canvas.
+    pub fn buffer(&self) -> &CFRBuffer {
+        self.executor.buffer()
+    }
+
+    /// Restarts the program on a cleared canvas.
+    pub fn reset(&mut self) {
+        let buffer = CFRBuffer::new(self.executor.buffer().width, self.executor.buffer().height);
+        self.executor = CommandExecutor::new(self.program.clone(), buffer);
+        self.finished = false;
+    }
+
+    /// Runs the program until the next sleep, until it finishes, or for [`MAX_STEPS`] commands.
+    pub fn step(&mut self) {
+        for _ in 0..MAX_STEPS {
+            match self.executor.step() {
+                Ok((true, _)) => break,
+                Ok((false, _)) => {}
+                Err(_) => {
+                    self.finished = true;
+                    self.playing = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Draws the controls and the canvas.
+    pub fn show(&mut self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            let label = if self.playing { "Pause" } else { "Play" };
+            if ui
+                .add_enabled(!self.finished, egui::Button::new(label))
+                .clicked()
+            {
+                self.playing = !self.playing;
+                self.throttle.reset();
+            }
+            if ui
+                .add_enabled(!self.finished && !self.playing, egui::Button::new("Step"))
+                .clicked()
+            {
+                self.step();
+            }
+            if ui.button("Reset").clicked() {
+                self.reset();
+            }
+            if self.finished {
+                ui.label("Finished");
+            }
+        });
+
+        if self.playing {
+            let mut steps = 0;
+            while !self.finished && steps < MAX_STEPS && self.throttle.remaining().is_zero() {
+                steps += 1;
+                match self.executor.step() {
+                    Ok((sleep, _)) => self.throttle.tick(sleep),
+                    Err(_) => {
+                        self.finished = true;
+                        self.playing = false;
+                    }
+                }
+            }
+            ui.ctx().request_repaint_after(self.throttle.remaining());
+        }
+
+        let (width, height) = (self.executor.buffer().width, self.executor.buffer().height);
+        let (dx, dy) = self.viewport.pan_step(width, height);
+        ui.input(|input| {
+            let pressed = |key| input.key_pressed(key) as i64;
+            self.viewport.pan(
+                (pressed(Key::ArrowRight) - pressed(Key::ArrowLeft)) * dx,
+                (pressed(Key::ArrowDown) - pressed(Key::ArrowUp)) * dy,
+                width,
+                height,
+            );
+            if input.key_pressed(Key::Plus) || input.key_pressed(Key::Equals) {
+                self.viewport.zoom_in(width, height);
+            }
+            if input.key_pressed(Key::Minus) {
+                self.viewport.zoom_out(width, height);
+            }
+            if input.key_pressed(Key::Num0) {
+                self.viewport = Viewport::default();
+            }
+        });
+
+        let image = to_color_image(&self.viewport.apply(self.executor.buffer()));
+        let texture = match self.texture.take() {
+            Some(mut texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture
+            }
+            None => ui
+                .ctx()
+                .load_texture("cfrs-viewer", image, TextureOptions::NEAREST),
+        };
+        let response = ui.image((texture.id(), texture.size_vec2() * self.scale));
+        self.texture = Some(texture);
+        response
+    }
+}
@@ -0,0 +1,144 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/// The canvas at every sleep of a run, stored as a keyframe plus the pixels drawn between two
+/// sleeps, like `DeltaAnimation`.
+///
+/// When the deltas take more than the memory budget, the oldest ones are merged into the
+/// keyframe, so only the most recent sleeps can be retrieved. Enable it with
+/// `CommandExecutor::enable_history`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::history::History;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// // Room for two deltas of one pixel each.
+/// let mut history = History::new(CFRBuffer::new(4, 4), 16);
+/// for i in 0..3 {
+///     history.record(i, CFRColor::White);
+///     history.end_frame();
+/// }
+/// assert_eq!(history.sleeps(), 1..3);
+/// assert!(history.buffer_at_sleep(0).is_none());
+/// let frame = history.buffer_at_sleep(1).unwrap();
+/// assert_eq!(&frame.data[..3], &[CFRColor::White, CFRColor::White, CFRColor::Black]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct History {
+    keyframe: CFRBuffer,
+    /// The sleep that follows the keyframe.
+    first: usize,
+    deltas: VecDeque<Vec<(u32, CFRColor)>>,
+    pending: Vec<(u32, CFRColor)>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl History {
+    /// Creates a history that starts with `keyframe` and keeps at most `max_bytes` of deltas.
+    pub fn new(keyframe: CFRBuffer, max_bytes: usize) -> Self {
+        Self {
+            keyframe,
+            first: 0,
+            deltas: VecDeque::new(),
+            pending: Vec::new(),
+            bytes: 0,
+            max_bytes,
+        }
+    }
+
+    /// Records that the pixel at `index` was drawn in `color` since the last sleep.
+    pub fn record(&mut self, index: u32, color: CFRColor) {
+        self.pending.push((index, color));
+    }
+
+    /// Stores the pixels drawn since the last sleep as the delta of the next sleep.
+    pub fn end_frame(&mut self) {
+        let mut delta = std::mem::take(&mut self.pending);
+        // Keep only the last color of every pixel: after reversing, the stable sort puts the
+        // last write first, and `dedup_by_key` keeps the first of every run.
+        delta.reverse();
+        delta.sort_by_key(|&(index, _)| index);
+        delta.dedup_by_key(|&mut (index, _)| index);
+        delta.shrink_to_fit();
+
+        self.bytes += delta.len() * size_of::<(u32, CFRColor)>();
+        self.deltas.push_back(delta);
+        while self.bytes > self.max_bytes {
+            let Some(oldest) = self.deltas.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.len() * size_of::<(u32, CFRColor)>();
+            apply(&mut self.keyframe, &oldest);
+            self.first += 1;
+        }
+    }
+
+    /// Returns the range of sleeps that can still be retrieved, counting from 0.
+    pub fn sleeps(&self) -> std::ops::Range<usize> {
+        self.first..self.first + self.deltas.len()
+    }
+
+    /// Returns the number of bytes taken by the deltas.
+    pub fn memory(&self) -> usize {
+        self.bytes
+    }
+
+    /// Returns the canvas at the `n`th sleep (counting from 0), or `None` if that sleep hasn't
+    /// happened yet or was dropped to stay within the memory budget.
+    pub fn buffer_at_sleep(&self, n: usize) -> Option<CFRBuffer> {
+        if !self.sleeps().contains(&n) {
+            return None;
+        }
+        let mut buffer = self.keyframe.clone();
+        for delta in self.deltas.range(..=n - self.first) {
+            apply(&mut buffer, delta);
+        }
+        Some(buffer)
+    }
+}
+
+fn apply(buffer: &mut CFRBuffer, delta: &[(u32, CFRColor)]) {
+    for &(index, color) in delta {
+        buffer.data[index as usize] = color;
+    }
+}
+
+mod tests {
+    #[test]
+    fn matches_frames_of_a_run() {
+        use crate::CFRBuffer;
+        use crate::CommandExecutor;
+
+        let program = "[[[[[[FS]]]]R]]";
+        let mut frames = Vec::new();
+        let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(16, 16));
+        while let Ok((sleep, buffer)) = executor.step() {
+            if sleep {
+                frames.push(buffer.clone());
+            }
+        }
+
+        let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(16, 16));
+        executor.enable_history(usize::MAX);
+        executor.run().unwrap();
+        for (n, frame) in frames.iter().enumerate() {
+            assert_eq!(executor.buffer_at_sleep(n).unwrap().data, frame.data);
+        }
+        assert!(executor.buffer_at_sleep(frames.len()).is_none());
+
+        // Every delta is a single pixel, so a budget of 80 bytes keeps the last 10 sleeps.
+        let mut executor = CommandExecutor::new(program.to_string(), CFRBuffer::new(16, 16));
+        executor.enable_history(80);
+        executor.run().unwrap();
+        let history = executor.history().unwrap();
+        assert_eq!(history.sleeps(), frames.len() - 10..frames.len());
+        let n = frames.len() - 10;
+        assert_eq!(executor.buffer_at_sleep(n).unwrap().data, frames[n].data);
+        assert!(executor.buffer_at_sleep(n - 1).is_none());
+    }
+}
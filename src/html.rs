@@ -0,0 +1,395 @@
+use crate::delta::DeltaAnimation;
+use crate::enums::{CFRColor, CFRDirection};
+use crate::painter::CFRPainter;
+use crate::timeline::Timeline;
+use std::fmt::Write;
+
+/// Options of `to_html`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlOptions {
+    pub width: u32,
+    pub height: u32,
+    pub background: CFRColor,
+    /// Milliseconds of sleep between two frames, like the GIF frame interval.
+    pub interval: u32,
+    pub title: String,
+    /// Let `#` comment out the rest of its line, like `CommandExecutor::set_comments`.
+    pub comments: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            background: CFRColor::Black,
+            interval: 100,
+            title: "CFRS[]".to_string(),
+            comments: false,
+        }
+    }
+}
+
+/// Returns a self-contained HTML page that animates a program on a `<canvas>`.
+///
+/// The page embeds the program and a small JavaScript interpreter, so it works offline and
+/// can be shared as a single file. Clicking the canvas restarts the animation.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::html::{to_html, HtmlOptions};
+///
+/// let page = to_html("[[[[FS]]]]</script>", &HtmlOptions::default());
+/// assert!(page.starts_with("<!DOCTYPE html>"));
+/// // The program can't end the script early.
+/// assert!(page.contains(r#"const PROGRAM = "[[[[FS]]]]\u003c/script\u003e";"#));
+/// ```
+pub fn to_html(program: &str, options: &HtmlOptions) -> String {
+    let palette: Vec<String> = CFRColor::ALL
+        .iter()
+        .map(|c| format!("{:?}", c.to_rgb()))
+        .collect();
+    let moves: Vec<String> = CFRDirection::ALL
+        .iter()
+        .map(|&direction| {
+            let mut painter = CFRPainter::new();
+            painter.direction = direction;
+            (painter.x, painter.y) = (1, 1);
+            painter
+                .move_forward(3, 3)
+                .expect("The painter starts on the canvas");
+            format!("[{}, {}]", painter.x as i32 - 1, painter.y as i32 - 1)
+        })
+        .collect();
+    let scale = (512 / options.width.max(options.height).max(1)).max(1);
+
+    let mut out = String::new();
+    write!(
+        out,
+        r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; background: #222; }}
+canvas {{ width: {css_width}px; max-width: 95vw; image-rendering: pixelated; cursor: pointer; }}
+</style>
+</head>
+<body>
+<canvas id="canvas" width="{width}" height="{height}" title="Click to restart"></canvas>
+<script>
+const PROGRAM = {program};
+const WIDTH = {width}, HEIGHT = {height}, BACKGROUND = {background}, INTERVAL = {interval};
+const COMMENTS = {comments};
+const PALETTE = [{palette}];
+const MOVES = [{moves}];
+
+const canvas = document.getElementById("canvas");
+const context = canvas.getContext("2d");
+const image = context.createImageData(WIDTH, HEIGHT);
+let commands, index, blocks, x, y, direction, color, time, timer;
+
+function draw(pixel, c) {{
+  image.data.set([...PALETTE[c], 255], pixel * 4);
+}}
+
+function reset() {{
+  clearTimeout(timer);
+  for (let pixel = 0; pixel < WIDTH * HEIGHT; pixel++) draw(pixel, BACKGROUND);
+  commands = PROGRAM.split("");
+  index = 0;
+  blocks = [];
+  x = (WIDTH - 1) >> 1;
+  y = (HEIGHT - 1) >> 1;
+  direction = 0;
+  color = 0;
+  time = 0;
+  frame();
+}}
+
+// Executes one command like `CommandExecutor::step`; returns false at the end or on errors.
+function step() {{
+  if (index >= commands.length) return false;
+  switch (commands[index]) {{
+    case "C": color = (color + 1) % 8; break;
+    case "F":
+      x = (x + MOVES[direction][0] + WIDTH) % WIDTH;
+      y = (y + MOVES[direction][1] + HEIGHT) % HEIGHT;
+      draw(y * WIDTH + x, color);
+      break;
+    case "R": direction = (direction + 1) % 8; break;
+    case "S": time += 20; break;
+    case "[": blocks.push(index + 1); break;
+    case "]":
+      if (blocks.length === 0) return false;
+      commands[index] = "|";
+      index = blocks.pop();
+      return true;
+    case "|": commands[index] = "]"; break;
+    case "#": {{
+      if (!COMMENTS) break;
+      const end = commands.indexOf("\n", index);
+      index = end < 0 ? commands.length : end;
+      break;
+    }}
+  }}
+  index++;
+  return true;
+}}
+
+function frame() {{
+  let running = true;
+  while (running && time < INTERVAL) running = step();
+  time -= INTERVAL;
+  context.putImageData(image, 0, 0);
+  if (running) timer = setTimeout(frame, INTERVAL);
+}}
+
+canvas.addEventListener("click", reset);
+reset();
+</script>
+</body>
+</html>
+"##,
+        title = escape_html(&options.title),
+        css_width = options.width * scale,
+        width = options.width,
+        height = options.height,
+        program = json_string(program),
+        background = options.background as u8,
+        interval = options.interval.max(1),
+        comments = options.comments,
+        palette = palette.join(", "),
+        moves = moves.join(", "),
+    )
+    .unwrap();
+    out
+}
+
+/// Returns a self-contained HTML page with a slider to scrub through the frames of
+/// `animation`, one at a time, in either direction. Arrow keys step a frame, and the play
+/// button plays the frames at the delay of the animation.
+///
+/// The frames are embedded as the JSON of `DeltaAnimation::write_json`, and the page keeps a
+/// full frame every 64 frames so that jumping anywhere only replays a few deltas.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::delta::DeltaAnimation;
+/// use cfrs::html::to_scrubber;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(4, 4);
+/// let mut animation = DeltaAnimation::new(&frame, 100);
+/// frame.data[5] = CFRColor::Red;
+/// animation.push(&frame);
+///
+/// let page = to_scrubber(&animation, "Flower");
+/// assert!(page.contains(r#"max="1""#));
+/// assert!(page.contains(r#""deltas":[[5,5]]"#));
+/// ```
+pub fn to_scrubber(animation: &DeltaAnimation, title: &str) -> String {
+    scrubber(animation, None, title)
+}
+
+/// Like `to_scrubber`, but the page also shows the step and the last executed command of
+/// every frame, plays every frame for its own delay, and has a field to jump to a step.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::delta::DeltaAnimation;
+/// use cfrs::html::to_scrubber_with_timeline;
+/// use cfrs::timeline::Timeline;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(4, 4);
+/// let mut animation = DeltaAnimation::new(&frame, 100);
+/// let mut timeline = Timeline::new(&frame);
+/// timeline.push(&frame, 0, 100, 0, 0, None);
+/// frame.data[5] = CFRColor::Red;
+/// animation.push(&frame);
+/// timeline.push(&frame, 7, 100, 0, 6, Some('F'));
+///
+/// let page = to_scrubber_with_timeline(&animation, &timeline, "Flower");
+/// assert!(page.contains(r#""steps":[0,7]"#));
+/// ```
+pub fn to_scrubber_with_timeline(
+    animation: &DeltaAnimation,
+    timeline: &Timeline,
+    title: &str,
+) -> String {
+    scrubber(animation, Some(timeline), title)
+}
+
+fn scrubber(animation: &DeltaAnimation, timeline: Option<&Timeline>, title: &str) -> String {
+    let mut json = Vec::new();
+    animation
+        .write_json(&mut json)
+        .expect("Writing to a Vec can't fail");
+    let json = String::from_utf8(json).expect("The JSON is ASCII");
+    let timeline_json = match timeline {
+        Some(timeline) => {
+            let mut json = Vec::new();
+            timeline
+                .write_json(&mut json)
+                .expect("Writing to a Vec can't fail");
+            String::from_utf8(json).expect("The JSON is UTF-8")
+        }
+        None => "null".to_string(),
+    };
+    let scale = (512 / animation.width.max(animation.height).max(1)).max(1);
+
+    let mut out = String::new();
+    write!(
+        out,
+        r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; min-height: 100vh; display: flex; flex-direction: column; gap: 12px; align-items: center; justify-content: center; background: #222; color: #eee; font: 14px sans-serif; }}
+canvas {{ width: {css_width}px; max-width: 95vw; image-rendering: pixelated; }}
+#controls {{ display: flex; gap: 12px; align-items: center; width: {css_width}px; max-width: 95vw; }}
+#slider {{ flex: 1; }}
+</style>
+</head>
+<body>
+<canvas id="canvas" width="{width}" height="{height}"></canvas>
+<div id="controls">
+<button id="play">Play</button>
+<input id="slider" type="range" min="0" max="{last}" value="0">
+<span id="label"></span>
+<input id="step" type="number" min="0" placeholder="Step" hidden>
+</div>
+<script>
+const ANIMATION = {json};
+const TIMELINE = {timeline_json};
+const CHECKPOINT_EVERY = 64;
+
+const canvas = document.getElementById("canvas");
+const context = canvas.getContext("2d");
+const slider = document.getElementById("slider");
+const label = document.getElementById("label");
+const play = document.getElementById("play");
+const stepInput = document.getElementById("step");
+const palette = ANIMATION.palette.map((hex) => [1, 3, 5].map((i) => parseInt(hex.slice(i, i + 2), 16)));
+const count = ANIMATION.deltas.length + 1;
+const image = context.createImageData(ANIMATION.width, ANIMATION.height);
+let timer = null;
+
+function apply(pixels, delta) {{
+  for (let i = 0; i < delta.length; i += 2) pixels[delta[i]] = delta[i + 1];
+}}
+
+// Full frames at every multiple of CHECKPOINT_EVERY, as palette indices.
+const checkpoints = [];
+const pixels = Uint8Array.from(ANIMATION.keyframe, (c) => c.charCodeAt(0) - 48);
+for (let frame = 0; frame < count; frame++) {{
+  if (frame > 0) apply(pixels, ANIMATION.deltas[frame - 1]);
+  if (frame % CHECKPOINT_EVERY === 0) checkpoints.push(pixels.slice());
+}}
+
+function show(frame) {{
+  const start = Math.floor(frame / CHECKPOINT_EVERY);
+  const pixels = checkpoints[start].slice();
+  for (let f = start * CHECKPOINT_EVERY + 1; f <= frame; f++) apply(pixels, ANIMATION.deltas[f - 1]);
+  pixels.forEach((c, i) => image.data.set([...palette[c], 255], i * 4));
+  context.putImageData(image, 0, 0);
+  slider.value = frame;
+  label.textContent = `${{frame + 1}} / ${{count}}`;
+  const entry = TIMELINE && TIMELINE.frames[frame];
+  if (entry) {{
+    const command = entry.command === null ? "" : ` · ${{entry.command}} at ${{entry.offset}}`;
+    label.textContent += ` · step ${{entry.steps[1]}}${{command}}`;
+  }}
+}}
+
+function delayOf(frame) {{
+  const entry = TIMELINE && TIMELINE.frames[frame];
+  return Math.max(entry ? entry.delay : ANIMATION.delay, 10);
+}}
+
+function stop() {{
+  clearTimeout(timer);
+  timer = null;
+  play.textContent = "Play";
+}}
+
+play.addEventListener("click", () => {{
+  if (timer !== null) return stop();
+  if (Number(slider.value) === count - 1) show(0);
+  play.textContent = "Pause";
+  const tick = () => {{
+    const next = Number(slider.value) + 1;
+    if (next >= count) return stop();
+    show(next);
+    timer = setTimeout(tick, delayOf(next));
+  }};
+  timer = setTimeout(tick, delayOf(Number(slider.value)));
+}});
+slider.addEventListener("input", () => {{
+  stop();
+  show(Number(slider.value));
+}});
+document.addEventListener("keydown", (event) => {{
+  const step = {{ ArrowLeft: -1, ArrowRight: 1 }}[event.key];
+  if (step === undefined || event.target === slider) return;
+  stop();
+  show(Math.min(Math.max(Number(slider.value) + step, 0), count - 1));
+}});
+if (TIMELINE) {{
+  stepInput.hidden = false;
+  stepInput.addEventListener("change", () => {{
+    const step = Number(stepInput.value);
+    const frame = TIMELINE.frames.findIndex((entry) => entry.steps[1] >= step);
+    stop();
+    show(frame === -1 ? count - 1 : Math.min(frame, count - 1));
+  }});
+}}
+show(0);
+</script>
+</body>
+</html>
+"##,
+        title = escape_html(title),
+        css_width = animation.width * scale,
+        width = animation.width,
+        height = animation.height,
+        last = animation.deltas.len(),
+        json = json,
+        timeline_json = timeline_json,
+    )
+    .unwrap();
+    out
+}
+
+/// Quotes a string for JavaScript inside a `<script>` element.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' => {
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,358 @@
+use crate::validator::Diagnostic;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A language that diagnostics and CLI messages can be shown in.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+    /// Traditional Chinese, as used in Taiwan.
+    TraditionalChinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [
+        Language::English,
+        Language::Spanish,
+        Language::TraditionalChinese,
+    ];
+
+    /// Returns the language of the `CFRS_LANG`, `LC_ALL`, `LC_MESSAGES`, or `LANG`
+    /// environment variable, whichever is set first, or English if none is a known language.
+    pub fn from_env() -> Self {
+        ["CFRS_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns `message` in this language, or `message` itself if the catalog has no
+    /// translation of it.
+    ///
+    /// The catalog is keyed by the English messages, where `{}` stands for any text, which
+    /// is translated in turn, so that e.g. the error inside `Program stopped: {}` is
+    /// translated too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::i18n::Language;
+    ///
+    /// let spanish = Language::Spanish;
+    /// assert_eq!(
+    ///     spanish.translate("Program stopped: Unmatched ]"),
+    ///     "Programa detenido: ] sin su [",
+    /// );
+    /// assert_eq!(spanish.translate("Something new"), "Something new");
+    /// assert_eq!(Language::English.translate("Unclosed ["), "Unclosed [");
+    /// ```
+    pub fn translate(&self, message: &str) -> String {
+        if *self == Language::English {
+            return message.to_string();
+        }
+        for entry in CATALOG {
+            let Some(args) = match_template(entry.0, message) else {
+                continue;
+            };
+            let translation = match self {
+                Language::English => entry.0,
+                Language::Spanish => entry.1,
+                Language::TraditionalChinese => entry.2,
+            };
+            let mut parts = translation.split("{}");
+            let mut out = parts.next().unwrap_or_default().to_string();
+            for (arg, part) in args.iter().zip(parts) {
+                out.push_str(&self.translate(arg));
+                out.push_str(part);
+            }
+            return out;
+        }
+        message.to_string()
+    }
+
+    /// Returns `diagnostic` as its `Display` does, in this language.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::i18n::Language;
+    /// use cfrs::validator::validate;
+    ///
+    /// let diagnostic = &validate("F]", false)[0];
+    /// assert_eq!(
+    ///     Language::TraditionalChinese.diagnostic(diagnostic),
+    ///     "錯誤，位置 1：沒有對應 [ 的 ]"
+    /// );
+    /// ```
+    pub fn diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        self.translate(&diagnostic.to_string())
+    }
+}
+
+/// Returns the code of the language, e.g. `zh-TW`.
+impl Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+            Language::TraditionalChinese => "zh-TW",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// Converts a language code like `es` or `zh-TW`, or a locale like `es_ES.UTF-8`, to a
+/// `Language`.
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let locale = s.split(['.', '@']).next().unwrap_or_default();
+        let locale = locale.replace('_', "-").to_lowercase();
+        let language = locale.split('-').next().unwrap_or_default();
+        match (language, locale.as_str()) {
+            ("en" | "c" | "posix", _) => Ok(Language::English),
+            ("es", _) => Ok(Language::Spanish),
+            (_, "zh" | "zh-tw" | "zh-hant" | "zh-hk" | "zh-mo") => Ok(Language::TraditionalChinese),
+            _ => Err(format!("Unsupported language: {}", s)),
+        }
+    }
+}
+
+/// Returns the texts that stand for the `{}` of `template` in `message`, or `None` if
+/// `message` doesn't match `template`.
+fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let parts: Vec<&str> = template.split("{}").collect();
+    let (first, rest) = parts.split_first()?;
+    let mut remaining = message.strip_prefix(first)?;
+    let Some((last, middle)) = rest.split_last() else {
+        return remaining.is_empty().then(Vec::new);
+    };
+    let mut args = Vec::new();
+    for part in middle {
+        let end = remaining.find(part)?;
+        args.push(&remaining[..end]);
+        remaining = &remaining[end + part.len()..];
+    }
+    args.push(remaining.strip_suffix(last)?);
+    Some(args)
+}
+
+/// English, Spanish, and Traditional Chinese messages, with `{}` in the same order. The
+/// first matching message is used, so the generic ones come last.
+const CATALOG: &[(&str, &str, &str)] = &[
+    // Diagnostics of the validator, the lints, and lenient mode.
+    ("Unmatched ]", "] sin su [", "沒有對應 [ 的 ]"),
+    ("Unclosed [", "[ sin cerrar", "沒有關閉的 ["),
+    (
+        "Unknown command '{}' is ignored",
+        "Se ignora el comando desconocido '{}'",
+        "忽略未知的指令「{}」",
+    ),
+    (
+        "Unmatched ] is skipped",
+        "Se omite un ] sin su [",
+        "略過沒有對應 [ 的 ]",
+    ),
+    (
+        "Blocks nested deeper than {} run once",
+        "Los bloques anidados a más de {} niveles se ejecutan una vez",
+        "巢狀超過 {} 層的區塊只執行一次",
+    ),
+    (
+        "{} rotations are a no-op",
+        "{} rotaciones no hacen nada",
+        "{} 次旋轉沒有任何效果",
+    ),
+    (
+        "{} color changes are a no-op",
+        "{} cambios de color no hacen nada",
+        "{} 次換色沒有任何效果",
+    ),
+    (
+        "{} rotations are the same as {}",
+        "{} rotaciones equivalen a {}",
+        "{} 次旋轉等同於 {} 次",
+    ),
+    (
+        "{} color changes are the same as {}",
+        "{} cambios de color equivalen a {}",
+        "{} 次換色等同於 {} 次",
+    ),
+    (
+        "Commands after an unmatched ] never run",
+        "Los comandos después de un ] sin su [ nunca se ejecutan",
+        "沒有對應 [ 的 ] 之後的指令永遠不會執行",
+    ),
+    (
+        "Block never draws",
+        "El bloque nunca dibuja",
+        "這個區塊從不畫圖",
+    ),
+    // Errors of the executor and of `run_untrusted`.
+    (
+        "Nesting too deep",
+        "Anidamiento demasiado profundo",
+        "巢狀太深",
+    ),
+    (
+        "Wrap limit exceeded",
+        "Se superó el límite de vueltas por los bordes",
+        "超過繞過邊緣的次數上限",
+    ),
+    (
+        "Painter moved outside the canvas",
+        "El pintor salió del lienzo",
+        "畫筆移出了畫布",
+    ),
+    (
+        "Step limit exceeded",
+        "Se superó el límite de pasos",
+        "超過步數上限",
+    ),
+    (
+        "Unmatched ] at {}",
+        "] sin su [ en {}",
+        "位置 {} 的 ] 沒有對應的 [",
+    ),
+    (
+        "Invalid canvas size: {}",
+        "Tamaño de lienzo inválido: {}",
+        "無效的畫布大小：{}",
+    ),
+    (
+        "Program too long: {} bytes (max {})",
+        "Programa demasiado largo: {} bytes (máximo {})",
+        "程式太長：{} 位元組（上限 {}）",
+    ),
+    (
+        "Canvas too large: {} pixels (max {})",
+        "Lienzo demasiado grande: {} píxeles (máximo {})",
+        "畫布太大：{} 像素（上限 {}）",
+    ),
+    (
+        "Program did not finish within {} steps",
+        "El programa no terminó en {} pasos",
+        "程式沒有在 {} 步內結束",
+    ),
+    (
+        "Painter wrapped around the edges more than {} times",
+        "El pintor dio la vuelta por los bordes más de {} veces",
+        "畫筆繞過邊緣超過 {} 次",
+    ),
+    (
+        "Program did not finish within {}",
+        "El programa no terminó en {}",
+        "程式沒有在 {} 內結束",
+    ),
+    (
+        "Program produced more than {} frames",
+        "El programa produjo más de {} cuadros",
+        "程式產生了超過 {} 個畫格",
+    ),
+    // Messages of the CLI.
+    (
+        "Program stopped: {}",
+        "Programa detenido: {}",
+        "程式停止：{}",
+    ),
+    (
+        "Program {} failed: {}",
+        "El programa {} falló: {}",
+        "第 {} 個程式失敗：{}",
+    ),
+    (
+        "Program {} stopped",
+        "El programa {} se detuvo",
+        "第 {} 個程式已停止",
+    ),
+    (
+        "Stopped after {} steps",
+        "Detenido después de {} pasos",
+        "在 {} 步後停止",
+    ),
+    (
+        "Canvas size: {}x{}",
+        "Tamaño del lienzo: {}x{}",
+        "畫布大小：{}x{}",
+    ),
+    (
+        "Failed to open {}: {}",
+        "No se pudo abrir {}: {}",
+        "無法開啟 {}：{}",
+    ),
+    (
+        "Failed to read {}: {}",
+        "No se pudo leer {}: {}",
+        "無法讀取 {}：{}",
+    ),
+    (
+        "expected {}, got {}",
+        "se esperaba {}, se obtuvo {}",
+        "預期 {}，實際為 {}",
+    ),
+    (
+        "Interrupted; saving what has been drawn (interrupt again to quit)",
+        "Interrumpido; se guarda lo dibujado (interrumpa de nuevo para salir)",
+        "已中斷；正在儲存已畫好的部分（再次中斷即可結束）",
+    ),
+    (
+        "Unknown example {}; available: {}",
+        "Ejemplo desconocido {}; disponibles: {}",
+        "未知的範例 {}；可用的範例：{}",
+    ),
+    ("error at {}: {}", "error en {}: {}", "錯誤，位置 {}：{}"),
+    (
+        "warning at {}: {}",
+        "advertencia en {}: {}",
+        "警告，位置 {}：{}",
+    ),
+    ("{} (fixable)", "{} (corregible)", "{}（可自動修正）"),
+    // A file name or program followed by what went wrong with it.
+    ("{}: {}", "{}: {}", "{}：{}"),
+];
+
+mod tests {
+    #[test]
+    fn every_language_translates_every_message() {
+        use crate::i18n::{match_template, Language, CATALOG};
+
+        for (english, spanish, chinese) in CATALOG {
+            let count = english.matches("{}").count();
+            assert_eq!(spanish.matches("{}").count(), count, "{}", english);
+            assert_eq!(chinese.matches("{}").count(), count, "{}", english);
+            assert!(match_template(english, english).is_some(), "{}", english);
+        }
+
+        assert_eq!(
+            match_template("Program {} failed: {}", "Program 2 failed: x: y"),
+            Some(vec!["2", "x: y"])
+        );
+        assert_eq!(match_template("Unclosed [", "Unclosed [ "), None);
+        assert_eq!(
+            Language::Spanish.translate("warning at 3: 16 rotations are the same as 0 (fixable)"),
+            "advertencia en 3: 16 rotaciones equivalen a 0 (corregible)"
+        );
+        assert_eq!(
+            Language::TraditionalChinese.translate("a.cfrs: Unmatched ] at 4"),
+            "a.cfrs：位置 4 的 ] 沒有對應的 ["
+        );
+
+        for (code, language) in [
+            ("es_ES.UTF-8", Language::Spanish),
+            ("zh_TW.UTF-8", Language::TraditionalChinese),
+            ("C", Language::English),
+            ("en-US", Language::English),
+        ] {
+            assert_eq!(code.parse(), Ok(language));
+        }
+        assert!("zh_CN".parse::<Language>().is_err());
+        for language in Language::ALL {
+            assert_eq!(language.to_string().parse(), Ok(language));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use crate::limits::{run_untrusted, ResourceLimits};
+use crate::painter::CFRPainter;
+use crate::sink::{AsPixelSink, PixelSink};
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// A broken guarantee of the executor, and the step after which it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub step: u64,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "After step {}: {}", self.step, self.message)
+    }
+}
+
+/// Checks that the painter is on a `width`×`height` canvas.
+pub fn painter_in_bounds(painter: &CFRPainter, width: u32, height: u32) -> Result<(), String> {
+    if painter.x < width && painter.y < height {
+        Ok(())
+    } else {
+        Err(format!(
+            "The painter at ({}, {}) is outside the {}x{} canvas",
+            painter.x, painter.y, width, height
+        ))
+    }
+}
+
+/// Checks that the buffer has exactly one color for every pixel.
+pub fn buffer_len_matches(buffer: &CFRBuffer) -> Result<(), String> {
+    let pixels = buffer.width as u64 * buffer.height as u64;
+    if buffer.data.len() as u64 == pixels {
+        Ok(())
+    } else {
+        Err(format!(
+            "The {}x{} buffer has {} pixels",
+            buffer.width,
+            buffer.height,
+            buffer.data.len()
+        ))
+    }
+}
+
+/// Runs an executor for at most `max_steps` steps, checking after every step that
+///
+/// - the painter is on the canvas,
+/// - the canvas keeps its size,
+/// - the next command is within the program,
+/// - the sleep count grows by one for every `S` and never otherwise,
+/// - the wrap counts never decrease.
+///
+/// Works for any dialect and sink, so custom ones can be held to the same rules as the
+/// standard executor. Returns the number of executed steps.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::invariants::check_executor;
+/// use cfrs::{CFRBuffer, CommandExecutor, Dialect};
+///
+/// let mut buffer = CFRBuffer::new(5, 3);
+/// let mut executor = CommandExecutor::new("[[RFS]]".to_string(), &mut buffer);
+/// executor.set_dialect(Dialect::Repeat(3));
+/// assert_eq!(check_executor(&mut executor, 1000), Ok(43));
+/// ```
+pub fn check_executor<B: AsPixelSink>(
+    executor: &mut CommandExecutor<B>,
+    max_steps: u64,
+) -> Result<u64, Violation> {
+    let dimensions = executor.buffer().dimensions();
+    let mut steps = 0;
+    while steps < max_steps {
+        let sleeps = executor.view().sleeps;
+        let wraps = executor.wraps();
+        let Ok((slept, _)) = executor.step() else {
+            break;
+        };
+        steps += 1;
+        let violation = |message: String| Violation {
+            step: steps,
+            message,
+        };
+
+        let (width, height) = executor.buffer().dimensions();
+        if (width, height) != dimensions {
+            return Err(violation(format!(
+                "The canvas changed from {}x{} to {}x{}",
+                dimensions.0, dimensions.1, width, height
+            )));
+        }
+        painter_in_bounds(executor.painter(), width, height).map_err(violation)?;
+        let view = executor.view();
+        if view.index > executor.state().commands.len() {
+            return Err(violation(format!(
+                "The next command at {} is past the end of the program",
+                view.index
+            )));
+        }
+        if view.sleeps != sleeps + slept as u64 {
+            return Err(violation(format!(
+                "The sleep count went from {} to {}",
+                sleeps, view.sleeps
+            )));
+        }
+        let after = executor.wraps();
+        let decreased = [
+            (wraps.left, after.left),
+            (wraps.right, after.right),
+            (wraps.top, after.top),
+            (wraps.bottom, after.bottom),
+        ]
+        .iter()
+        .any(|(before, after)| after < before);
+        if decreased {
+            return Err(violation("A wrap count decreased".to_string()));
+        }
+    }
+    Ok(steps)
+}
+
+/// Runs any bytes as a program on a `width`×`height` canvas within `limits`, checking
+/// everything `check_executor` does, that neither the executor nor `run_untrusted` panics,
+/// and that both draw the same canvas. Inputs that `run_untrusted` rejects only have to be
+/// rejected without a panic.
+///
+/// This makes a fuzz target or a property test of its own.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::invariants::check_program;
+/// use cfrs::ResourceLimits;
+///
+/// let limits = ResourceLimits { max_steps: 10_000, ..Default::default() };
+/// for program in ["[[[[F]]]]", "]F", "[[[[[[[[[[[[[[[[F]]]]]]]]]]]]]]]]", "F\u{e9}#["] {
+///     assert!(check_program(program.as_bytes(), 7, 3, &limits).is_ok());
+/// }
+/// assert!(check_program(b"F", 0, 3, &limits).is_ok());
+/// ```
+pub fn check_program(
+    program: &[u8],
+    width: u32,
+    height: u32,
+    limits: &ResourceLimits,
+) -> Result<u64, Violation> {
+    let panicked = |message: &str| Violation {
+        step: 0,
+        message: message.to_string(),
+    };
+    let expected = catch_unwind(|| run_untrusted(program, width, height, limits))
+        .map_err(|_| panicked("run_untrusted panicked"))?;
+    let accepted = program.len() <= limits.max_program_len
+        && width > 0
+        && height > 0
+        && width as u64 * height as u64 <= limits.max_pixels.min(u32::MAX as u64);
+    if !accepted {
+        return Ok(0);
+    }
+
+    let commands = String::from_utf8_lossy(program).into_owned();
+    let mut executor = CommandExecutor::new(commands, CFRBuffer::new(width, height));
+    executor.set_max_wraps(limits.max_wraps);
+    let steps = catch_unwind(AssertUnwindSafe(|| {
+        check_executor(&mut executor, limits.max_steps)
+    }))
+    .map_err(|_| panicked("The executor panicked"))??;
+    let buffer = executor.into_buffer();
+    let violation = |message: String| Violation {
+        step: steps,
+        message,
+    };
+    buffer_len_matches(&buffer).map_err(violation)?;
+    if let Ok(expected) = expected {
+        if expected.data != buffer.data {
+            return Err(violation(
+                "run_untrusted drew a different canvas".to_string(),
+            ));
+        }
+    }
+    Ok(steps)
+}
+
+mod tests {
+    #[test]
+    fn catches_broken_sinks() {
+        use crate::invariants::check_executor;
+        use crate::sink::PixelSink;
+        use crate::{CFRColor, CommandExecutor};
+
+        /// A sink that grows whenever something is drawn on it.
+        struct Growing(u32);
+
+        impl PixelSink for Growing {
+            fn dimensions(&self) -> (u32, u32) {
+                (self.0, self.0)
+            }
+
+            fn set_pixel(&mut self, _: u32, _: u32, _: CFRColor) {
+                self.0 += 1;
+            }
+        }
+
+        let mut sink = Growing(4);
+        let mut executor = CommandExecutor::new("RRFF".to_string(), &mut sink);
+        let violation = check_executor(&mut executor, 100).unwrap_err();
+        assert_eq!(violation.step, 3);
+        assert_eq!(
+            violation.to_string(),
+            "After step 3: The canvas changed from 4x4 to 5x5"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::buffer::CFRBuffer;
+use crate::draw::{self, glyph};
+
+const GLYPH_SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = draw::GLYPH_WIDTH * GLYPH_SCALE;
+const GLYPH_HEIGHT: u32 = draw::GLYPH_HEIGHT * GLYPH_SCALE;
+const SPACING: u32 = GLYPH_SCALE;
+const TICK: u32 = 4;
+const PAD: u32 = 2;
+
+const MARGIN_COLOR: [u8; 3] = [255, 255, 255];
+const INK_COLOR: [u8; 3] = [0, 0, 0];
+const GRID_COLOR: [u8; 3] = [128, 128, 128];
+
+/// How `labeled` draws the canvas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LabelOptions {
+    /// Size of a canvas pixel in image pixels.
+    pub scale: u32,
+    /// Distance between ticks in canvas pixels, or `None` to fit the labels.
+    pub tick_every: Option<u32>,
+    /// Whether to draw lines between the canvas pixels.
+    pub grid: bool,
+}
+
+impl Default for LabelOptions {
+    fn default() -> Self {
+        Self {
+            scale: 4,
+            tick_every: None,
+            grid: false,
+        }
+    }
+}
+
+/// An RGBA image of a canvas with axis labels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabeledImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl LabeledImage {
+    /// Fills a rectangle, clipped to the image.
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, [r, g, b]: [u8; 3]) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                let i = (y as usize * self.width as usize + x as usize) * 4;
+                self.rgba[i..i + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+
+    fn draw_number(&mut self, x: u32, y: u32, n: u32) {
+        for (i, digit) in n.to_string().bytes().enumerate() {
+            let left = x + i as u32 * (GLYPH_WIDTH + SPACING);
+            for (row, bits) in glyph(digit as char).iter().enumerate() {
+                for column in 0..draw::GLYPH_WIDTH {
+                    if bits & (0b100 >> column) != 0 {
+                        self.fill(
+                            left + column * GLYPH_SCALE,
+                            y + row as u32 * GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            INK_COLOR,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn text_width(n: u32) -> u32 {
+    n.to_string().len() as u32 * (GLYPH_WIDTH + SPACING) - SPACING
+}
+
+/// Returns the smallest distance between ticks of 1, 2 or 5 times a power of ten that leaves
+/// room for the labels of a canvas side of `size` pixels drawn at `scale`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::labels::tick_every;
+///
+/// assert_eq!(tick_every(16, 32), 1);
+/// assert_eq!(tick_every(256, 4), 10);
+/// ```
+pub fn tick_every(size: u32, scale: u32) -> u32 {
+    let spacing = (text_width(size.saturating_sub(1)) + 2 * PAD) as u64;
+    let scale = scale.max(1) as u64;
+    let mut base = 1u64;
+    loop {
+        for step in [base, 2 * base, 5 * base] {
+            if step * scale >= spacing || step >= size as u64 {
+                return step.min(u32::MAX as u64) as u32;
+            }
+        }
+        base *= 10;
+    }
+}
+
+/// Draws the canvas enlarged, with coordinate ticks and labels in margins along the top and
+/// the left, for tutorials and documentation figures. The canvas itself is not changed.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::labels::{labeled, LabelOptions};
+/// use cfrs::CFRBuffer;
+///
+/// let image = labeled(&CFRBuffer::new(16, 16), &LabelOptions::default());
+/// assert!(image.width > 16 * 4 && image.height > 16 * 4);
+/// assert_eq!(image.rgba.len(), (image.width * image.height * 4) as usize);
+/// ```
+pub fn labeled(buffer: &CFRBuffer, options: &LabelOptions) -> LabeledImage {
+    let scale = options.scale.max(1);
+    let (columns, rows) = (buffer.width, buffer.height);
+    let step = options
+        .tick_every
+        .unwrap_or_else(|| tick_every(columns.max(rows), scale))
+        .max(1);
+    let last_label = |size: u32| size.saturating_sub(1) / step * step;
+
+    let left = PAD + text_width(last_label(rows)) + PAD + TICK;
+    let top = PAD + GLYPH_HEIGHT + PAD + TICK;
+    let right = text_width(last_label(columns)) / 2 + PAD;
+    let bottom = GLYPH_HEIGHT / 2 + PAD;
+    let width = left + columns * scale + right;
+    let height = top + rows * scale + bottom;
+    let mut image = LabeledImage {
+        width,
+        height,
+        rgba: [MARGIN_COLOR[0], MARGIN_COLOR[1], MARGIN_COLOR[2], 255]
+            .repeat(width as usize * height as usize),
+    };
+
+    for y in 0..rows {
+        for x in 0..columns {
+            let color = buffer.data[(y * columns + x) as usize].to_rgb();
+            image.fill(left + x * scale, top + y * scale, scale, scale, color);
+        }
+    }
+    if options.grid {
+        for x in 0..=columns {
+            image.fill(left + x * scale, top, 1, rows * scale, GRID_COLOR);
+        }
+        for y in 0..=rows {
+            image.fill(left, top + y * scale, columns * scale, 1, GRID_COLOR);
+        }
+    }
+
+    for x in (0..columns).step_by(step as usize) {
+        let center = left + x * scale + scale / 2;
+        image.fill(center, top - TICK, 1, TICK, INK_COLOR);
+        image.draw_number(center.saturating_sub(text_width(x) / 2), PAD, x);
+    }
+    for y in (0..rows).step_by(step as usize) {
+        let center = top + y * scale + scale / 2;
+        image.fill(left - TICK, center, TICK, 1, INK_COLOR);
+        image.draw_number(
+            left - TICK - PAD - text_width(y),
+            center.saturating_sub(GLYPH_HEIGHT / 2),
+            y,
+        );
+    }
+    image
+}
+
+/// Draws the canvases in a grid of `columns` columns, every canvas enlarged by `scale` and
+/// with its number printed under it, e.g. to compare the outputs of a generator for a range
+/// of seeds.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::labels::contact_sheet;
+/// use cfrs::CFRBuffer;
+///
+/// let cells: Vec<(u32, CFRBuffer)> = (0..5).map(|seed| (seed, CFRBuffer::new(8, 8))).collect();
+/// let sheet = contact_sheet(&cells, 3, 2);
+/// // 3 columns of 16 pixels and 2 rows of 16 pixels with their labels.
+/// assert_eq!(sheet.width, 8 + 3 * (16 + 8));
+/// assert_eq!(sheet.height, 8 + 2 * (16 + 14 + 8));
+/// ```
+pub fn contact_sheet(cells: &[(u32, CFRBuffer)], columns: u32, scale: u32) -> LabeledImage {
+    const GAP: u32 = 4 * PAD;
+    let scale = scale.max(1);
+    let columns = columns.clamp(1, (cells.len() as u32).max(1));
+    let rows = (cells.len() as u32).div_ceil(columns);
+    let canvas_width = cells.iter().map(|(_, c)| c.width).max().unwrap_or(0) * scale;
+    let canvas_height = cells.iter().map(|(_, c)| c.height).max().unwrap_or(0) * scale;
+    let cell_height = canvas_height + PAD + GLYPH_HEIGHT + PAD;
+    let width = GAP + columns * (canvas_width + GAP);
+    let height = GAP + rows * (cell_height + GAP);
+    let mut image = LabeledImage {
+        width,
+        height,
+        rgba: [MARGIN_COLOR[0], MARGIN_COLOR[1], MARGIN_COLOR[2], 255]
+            .repeat(width as usize * height as usize),
+    };
+
+    for (i, (label, canvas)) in cells.iter().enumerate() {
+        let left = GAP + i as u32 % columns * (canvas_width + GAP);
+        let top = GAP + i as u32 / columns * (cell_height + GAP);
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let color = canvas.data[(y * canvas.width + x) as usize].to_rgb();
+                image.fill(left + x * scale, top + y * scale, scale, scale, color);
+            }
+        }
+        let center = left + canvas_width / 2;
+        image.draw_number(
+            center.saturating_sub(text_width(*label) / 2),
+            top + canvas_height + PAD,
+            *label,
+        );
+    }
+    image
+}
+
+mod tests {
+    #[test]
+    fn labels_around_the_canvas() {
+        use crate::labels::{labeled, LabelOptions};
+        use crate::{CFRBuffer, CFRColor};
+
+        let mut buffer = CFRBuffer::new(12, 3);
+        buffer.data[0] = CFRColor::Red;
+        let options = LabelOptions {
+            scale: 8,
+            tick_every: Some(10),
+            grid: true,
+        };
+        let image = labeled(&buffer, &options);
+        // Left: padding, "0", padding and a tick. Top: padding, a digit, padding and a tick.
+        let (left, top) = (2 + 6 + 2 + 4, 2 + 10 + 2 + 4);
+        // Right: half of "10" and padding. Bottom: half a digit and padding.
+        assert_eq!(image.width, left + 12 * 8 + 7 + 2);
+        assert_eq!(image.height, top + 3 * 8 + 5 + 2);
+
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * image.width + x) * 4) as usize;
+            [image.rgba[i], image.rgba[i + 1], image.rgba[i + 2]]
+        };
+        assert_eq!(pixel(left + 1, top + 1), [255, 0, 0]);
+        assert_eq!(pixel(left, top + 1), [128, 128, 128]);
+        assert_eq!(pixel(left + 8 + 1, top + 1), [0, 0, 0]);
+        // The ticks of x = 0 and x = 10.
+        assert_eq!(pixel(left + 4, top - 1), [0, 0, 0]);
+        assert_eq!(pixel(left + 10 * 8 + 4, top - 1), [0, 0, 0]);
+        assert_eq!(pixel(left + 5 * 8 + 4, top - 1), [255, 255, 255]);
+        // The top left corner of the "0" label of y = 0.
+        assert_eq!(pixel(2, top + 4 - 5), [0, 0, 0]);
+    }
+}
@@ -1,9 +1,96 @@
+pub mod analysis;
+pub mod animation;
+pub mod antialias;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod badge;
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod bench;
+#[cfg(feature = "bevy_cfrs")]
+pub mod bevy_cfrs;
+pub mod bitmap;
 pub mod buffer;
+pub mod bufferfile;
+pub mod challenge;
+pub mod compare;
+pub mod concat;
+pub mod conformance;
+pub mod delta;
+pub mod doctor;
+pub mod draw;
+pub mod droste;
+#[cfg(feature = "embedded-graphics")]
+pub mod embedded;
+#[cfg(feature = "encode")]
+pub mod encode;
+#[cfg(feature = "encode")]
+pub mod encoder;
+pub mod entropy;
 pub mod enums;
+pub mod error;
+#[cfg(feature = "evcxr")]
+pub mod evcxr;
 pub mod executor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod gallery;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+#[cfg(feature = "egui")]
+pub mod gui;
+pub mod history;
+pub mod html;
+pub mod i18n;
+pub mod invariants;
+pub mod labels;
+pub mod library;
+pub mod limits;
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod metadata;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod onion;
+pub mod overlay;
 pub mod painter;
+pub mod parser;
+pub mod pixel_log;
+pub mod plot;
+pub mod preprocess;
+pub mod race;
+pub mod record;
+pub mod rle;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shared;
+pub mod sink;
+pub mod size;
+pub mod source;
+pub mod sparse;
+pub mod stats;
+pub mod stream;
+pub mod terminal;
+pub mod throttle;
+pub mod tiles;
+pub mod timeline;
+pub mod timing;
+pub mod trace;
+#[cfg(feature = "ratatui")]
+pub mod tui;
+pub mod tween;
+pub mod validator;
+pub mod verify;
+pub mod viewport;
 
+pub use analysis::{analyze, Analysis};
 pub use buffer::CFRBuffer;
 pub use enums::*;
+pub use error::CFRError;
 pub use executor::CommandExecutor;
+pub use limits::{run_untrusted, ResourceLimits, Usage};
 pub use painter::CFRPainter;
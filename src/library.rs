@@ -0,0 +1,84 @@
+/// A named example program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub program: &'static str,
+}
+
+/// The built-in example programs, for trying CFRS[] without writing a program first.
+///
+/// They draw on the default 256×256 canvas, and they all sleep, so they animate as GIFs too.
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "flower",
+        description: "The flower from the README",
+        program: "[[[[[[[[[[FS]]]]R]]RR]]RRCC]][[[[[[[[S]]]]]]]]",
+    },
+    Example {
+        name: "spiral",
+        description: "A square spiral whose sides double every other turn",
+        program: "[FS]RR[FS]RR[[FS]]RR[[FS]]RR[[[FS]]]RR[[[FS]]]RR[[[[FS]]]]RR[[[[FS]]]]RR\
+                  [[[[[FS]]]]]RR[[[[[FS]]]]]RR[[[[[[FS]]]]]]RR[[[[[[FS]]]]]]",
+    },
+    Example {
+        name: "starburst",
+        description: "Eight colored rays from the center",
+        program: "[[[CC[[[[[[FS]]]]]]RRRR[[[[[[F]]]]]]RRRRR]]]",
+    },
+    Example {
+        name: "sky",
+        description: "Stars and rays; looks best at 512×512",
+        program:
+            "[[CC[C[C[[[[[[[[[[[F]]]]]][[[R[[[[[[F]]R[[F]]]]]]]]]RS][[R]]]]R]]]RF]]][[[[[[[S]]]]]]]",
+    },
+    Example {
+        name: "frames",
+        description: "Nested colored frames",
+        program: "[[[CR[[[[[[[[[[FS]]]]FF]FF]FF]R]FF]FF]FF]FFR]FF]",
+    },
+];
+
+/// Returns the example program with the given name.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::library;
+///
+/// let spiral = library::get("spiral").unwrap();
+/// assert!(spiral.program.starts_with("[FS]"));
+/// assert!(library::get("nothing").is_none());
+/// ```
+pub fn get(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
+
+mod tests {
+    #[test]
+    fn examples_run_and_draw() {
+        use crate::library::EXAMPLES;
+        use crate::validator::validate;
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        for example in EXAMPLES {
+            assert!(
+                validate(example.program, false).is_empty(),
+                "{}",
+                example.name
+            );
+            let mut buffer = CFRBuffer::new(256, 256);
+            let mut executor = CommandExecutor::new(example.program.to_string(), &mut buffer);
+            let mut sleeps = 0;
+            while let Ok((sleep, _)) = executor.step() {
+                sleeps += sleep as u32;
+            }
+            assert!(sleeps > 0, "{}", example.name);
+            assert!(
+                buffer.data.iter().any(|&c| c != CFRColor::Black),
+                "{}",
+                example.name
+            );
+        }
+    }
+}
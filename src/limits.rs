@@ -0,0 +1,322 @@
+use crate::buffer::CFRBuffer;
+use crate::error::CFRError;
+use crate::executor::CommandExecutor;
+use std::time::{Duration, Instant};
+
+/// Limits on the resources a program may use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ResourceLimits {
+    /// Maximum program length in bytes.
+    pub max_program_len: usize,
+    /// Maximum number of canvas pixels. Values above `u32::MAX` are treated as `u32::MAX`.
+    pub max_pixels: u64,
+    /// Maximum number of executed steps.
+    pub max_steps: u64,
+    /// Maximum number of times the painter may wrap around the edges of the canvas.
+    pub max_wraps: Option<u64>,
+    /// Maximum wall time of a run. The clock is only read when this is set, since
+    /// `wasm32-unknown-unknown` has none.
+    pub max_wall_time: Option<Duration>,
+    /// Maximum number of frames an animation may capture, counted with `Meter::frame`.
+    pub max_frames: Option<u64>,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_program_len: 1 << 20,
+            max_pixels: 4096 * 4096,
+            max_steps: 100_000_000,
+            max_wraps: None,
+            max_wall_time: None,
+            max_frames: None,
+        }
+    }
+}
+
+/// The resources a run used, to bill or rate-limit it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Number of canvas pixels.
+    pub pixels: u64,
+    pub steps: u64,
+    pub sleeps: u64,
+    pub frames: u64,
+    /// The wall time since the meter started, if `ResourceLimits::max_wall_time` is set.
+    pub wall_time: Option<Duration>,
+}
+
+/// Counts the resources of a run and stops it once it exceeds its `ResourceLimits`.
+///
+/// `run_untrusted` and the HTTP API use a meter; drive one yourself to enforce the same
+/// limits in a custom loop, e.g. one that also captures frames.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::limits::Meter;
+/// use cfrs::{CFRBuffer, CFRError, CommandExecutor, ResourceLimits};
+///
+/// let limits = ResourceLimits { max_frames: Some(2), ..Default::default() };
+/// let mut meter = Meter::new(&limits);
+/// meter.check_canvas(8, 8).unwrap();
+/// let mut executor = CommandExecutor::new("[[FS]]".to_string(), CFRBuffer::new(8, 8));
+/// let mut run = || -> Result<(), CFRError> {
+///     while let Ok((sleep, _)) = executor.step() {
+///         meter.step(sleep)?;
+///         if sleep {
+///             meter.frame()?;
+///         }
+///     }
+///     Ok(())
+/// };
+/// assert_eq!(run(), Err(CFRError::FrameLimitExceeded { max: 2 }));
+/// assert_eq!((meter.usage().steps, meter.usage().frames), (12, 3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Meter {
+    limits: ResourceLimits,
+    start: Option<Instant>,
+    usage: Usage,
+}
+
+impl Meter {
+    /// Creates a meter and starts its clock, if there is a time limit.
+    pub fn new(limits: &ResourceLimits) -> Self {
+        Self {
+            limits: limits.clone(),
+            start: limits.max_wall_time.map(|_| Instant::now()),
+            usage: Usage::default(),
+        }
+    }
+
+    /// Checks the length of a program.
+    pub fn check_program(&self, program: &[u8]) -> Result<(), CFRError> {
+        if program.len() > self.limits.max_program_len {
+            return Err(CFRError::ProgramTooLong {
+                len: program.len(),
+                max: self.limits.max_program_len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the size of a canvas before it is allocated, and counts its pixels.
+    pub fn check_canvas(&mut self, width: u32, height: u32) -> Result<(), CFRError> {
+        if width == 0 || height == 0 {
+            return Err(CFRError::InvalidDimensions { width, height });
+        }
+        // Pixel indices are computed in `u32`.
+        let pixels = width as u64 * height as u64;
+        let max_pixels = self.limits.max_pixels.min(u32::MAX as u64);
+        if pixels > max_pixels {
+            return Err(CFRError::CanvasTooLarge {
+                pixels,
+                max: self.limits.max_pixels,
+            });
+        }
+        self.usage.pixels = pixels;
+        Ok(())
+    }
+
+    /// Returns the limits that the meter enforces.
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Counts an executed step, which `slept` if it was an `S`.
+    ///
+    /// The clock is read every 4096 steps, so a run may exceed its time limit by that many
+    /// steps.
+    pub fn step(&mut self, slept: bool) -> Result<(), CFRError> {
+        self.usage.steps += 1;
+        self.usage.sleeps += slept as u64;
+        if self.usage.steps > self.limits.max_steps {
+            return Err(CFRError::StepLimitExceeded {
+                max: self.limits.max_steps,
+            });
+        }
+        if self.usage.steps & 0xfff == 0 {
+            self.check_time()?;
+        }
+        Ok(())
+    }
+
+    /// Counts a captured frame.
+    pub fn frame(&mut self) -> Result<(), CFRError> {
+        self.usage.frames += 1;
+        match self.limits.max_frames {
+            Some(max) if self.usage.frames > max => Err(CFRError::FrameLimitExceeded { max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the time limit now.
+    pub fn check_time(&self) -> Result<(), CFRError> {
+        match (self.start, self.limits.max_wall_time) {
+            (Some(start), Some(max)) if start.elapsed() > max => {
+                Err(CFRError::TimeLimitExceeded { max })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the resources used so far.
+    pub fn usage(&self) -> Usage {
+        Usage {
+            wall_time: self.start.map(|start| start.elapsed()),
+            ..self.usage
+        }
+    }
+}
+
+/// Runs a program from an untrusted source on a black `width`×`height` canvas.
+///
+/// The program may be any bytes; bytes that are not commands are ignored like in
+/// `CommandExecutor`. Every limit is checked before anything is allocated or while running,
+/// so this never panics and allocates no more than the canvas and a few times the program.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::{run_untrusted, CFRError, ResourceLimits};
+///
+/// let limits = ResourceLimits::default();
+/// let buffer = run_untrusted(b"[[[[F]]]]", 16, 16, &limits).unwrap();
+/// assert_eq!(buffer.data.len(), 256);
+///
+/// let limits = ResourceLimits { max_steps: 1000, ..Default::default() };
+/// let error = run_untrusted(b"[[[[[[[[[[F]]]]]]]]]]", 16, 16, &limits).unwrap_err();
+/// assert_eq!(error, CFRError::StepLimitExceeded { max: 1000 });
+/// ```
+pub fn run_untrusted(
+    program: &[u8],
+    width: u32,
+    height: u32,
+    limits: &ResourceLimits,
+) -> Result<CFRBuffer, CFRError> {
+    run_metered(program, width, height, limits).0
+}
+
+/// Runs a program like `run_untrusted`, and also returns the resources it used, whether it
+/// finished or not.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::limits::run_metered;
+/// use cfrs::ResourceLimits;
+///
+/// let (result, usage) = run_metered(b"[[FS]]", 16, 16, &ResourceLimits::default());
+/// assert!(result.is_ok());
+/// assert_eq!((usage.pixels, usage.steps, usage.sleeps), (256, 17, 4));
+/// assert_eq!(usage.wall_time, None);
+/// ```
+pub fn run_metered(
+    program: &[u8],
+    width: u32,
+    height: u32,
+    limits: &ResourceLimits,
+) -> (Result<CFRBuffer, CFRError>, Usage) {
+    let mut meter = Meter::new(limits);
+    let result = run_with_meter(program, width, height, &mut meter);
+    (result, meter.usage())
+}
+
+fn run_with_meter(
+    program: &[u8],
+    width: u32,
+    height: u32,
+    meter: &mut Meter,
+) -> Result<CFRBuffer, CFRError> {
+    meter.check_program(program)?;
+    meter.check_canvas(width, height)?;
+
+    let commands = String::from_utf8_lossy(program).into_owned();
+    let mut executor = CommandExecutor::new(commands, CFRBuffer::new(width, height));
+    executor.set_max_wraps(meter.limits.max_wraps);
+    loop {
+        let index = executor.view().index;
+        match executor.step() {
+            Ok((sleep, _)) => meter.step(sleep)?,
+            Err("Unmatched ]") => return Err(CFRError::UnmatchedBlockEnd { index }),
+            Err("Wrap limit exceeded") => {
+                return Err(CFRError::WrapLimitExceeded {
+                    max: executor.max_wraps().unwrap_or_default(),
+                })
+            }
+            Err(_) => break,
+        }
+    }
+    Ok(executor.into_buffer())
+}
+
+mod tests {
+    #[test]
+    fn reject_before_allocating() {
+        use crate::{run_untrusted, CFRError, ResourceLimits};
+
+        let limits = ResourceLimits {
+            max_pixels: u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            run_untrusted(b"F", 0, 5, &limits).unwrap_err(),
+            CFRError::InvalidDimensions {
+                width: 0,
+                height: 5
+            }
+        );
+        assert!(matches!(
+            run_untrusted(b"F", u32::MAX, u32::MAX, &limits),
+            Err(CFRError::CanvasTooLarge { .. })
+        ));
+        assert_eq!(
+            run_untrusted(b"FF]\xff", 1, 1, &limits).unwrap_err(),
+            CFRError::UnmatchedBlockEnd { index: 2 }
+        );
+        let limits = ResourceLimits {
+            max_program_len: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            run_untrusted(b"FFF", 1, 1, &limits),
+            Err(CFRError::ProgramTooLong { len: 3, max: 2 })
+        ));
+        let limits = ResourceLimits {
+            max_wraps: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            run_untrusted(b"[[[F]]]", 4, 4, &limits).unwrap_err(),
+            CFRError::WrapLimitExceeded { max: 0 }
+        );
+    }
+
+    #[test]
+    fn meter_time_and_frames() {
+        use crate::limits::{run_metered, Meter};
+        use crate::{CFRError, ResourceLimits};
+        use std::time::Duration;
+
+        let limits = ResourceLimits {
+            max_wall_time: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let (result, usage) = run_metered(b"[[[[[[[[[[[[[F]]]]]]]]]]]]]", 64, 64, &limits);
+        assert_eq!(
+            result.unwrap_err(),
+            CFRError::TimeLimitExceeded {
+                max: Duration::ZERO
+            }
+        );
+        assert_eq!(usage.steps, 4096);
+        assert!(usage.wall_time.is_some());
+
+        let mut meter = Meter::new(&ResourceLimits::default());
+        assert!(meter.frame().is_ok());
+        assert_eq!(meter.usage().frames, 1);
+        assert_eq!(meter.usage().wall_time, None);
+    }
+}
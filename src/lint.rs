@@ -0,0 +1,253 @@
+use crate::parser::{tokenize, Token, TokenKind};
+use crate::validator::{bracket_pairs, Diagnostic, Severity};
+use std::ops::Range;
+
+/// A replacement of a byte range of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Range<usize>,
+    pub replacement: String,
+}
+
+/// A warning about a program that runs, but does something useless or surprising.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub diagnostic: Diagnostic,
+    /// A rewrite that removes the problem without changing the drawing or its animation.
+    pub fix: Option<Edit>,
+}
+
+/// Checks a program for useless or unreachable commands, assuming the standard dialect.
+///
+/// Reports runs of 8 or more `R` or `C` (a multiple of 8 is a no-op), commands after an
+/// unmatched `]` (the program stops there), and blocks that never draw. With `comments`, `#`
+/// comments are skipped, as `tokenize` does. The lints are ordered by their position.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::lint::lint;
+///
+/// let lints = lint("F RRRRRRRR F [RC] S", false);
+/// assert_eq!(lints.len(), 2);
+/// assert_eq!(lints[0].diagnostic.span, 2..10);
+/// assert_eq!(lints[0].fix.as_ref().unwrap().replacement, "");
+/// assert_eq!(lints[1].diagnostic.message, "Block never draws");
+/// assert_eq!(lints[1].fix.as_ref().unwrap().replacement, "RRCC");
+/// ```
+pub fn lint(program: &str, comments: bool) -> Vec<Lint> {
+    let tokens = tokenize(program, comments);
+    let mut lints = Vec::new();
+    lint_runs(program, &tokens, &mut lints);
+    lint_unreachable(&tokens, &mut lints);
+    lint_blocks(program, &tokens, comments, &mut lints);
+    lints.sort_by_key(|l| l.diagnostic.span.start);
+    lints
+}
+
+/// Applies the fixes of every lint until none is left, and returns the fixed program.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::lint::fix;
+///
+/// assert_eq!(fix("[[RRRR]]F RRRRRRRRRR C]FF", false), "F RR C]");
+/// ```
+pub fn fix(program: &str, comments: bool) -> String {
+    let mut program = program.to_string();
+    // Every fix either removes a block or shortens the program without adding brackets,
+    // so this terminates.
+    loop {
+        let mut edits: Vec<Edit> = lint(&program, comments)
+            .into_iter()
+            .filter_map(|l| l.fix)
+            .collect();
+        if edits.is_empty() {
+            return program;
+        }
+        edits.sort_by_key(|e| e.span.start);
+        let mut fixed = String::with_capacity(program.len());
+        let mut end = 0;
+        for edit in edits {
+            // Overlapping edits are applied on the next round.
+            if edit.span.start < end {
+                continue;
+            }
+            fixed.push_str(&program[end..edit.span.start]);
+            fixed.push_str(&edit.replacement);
+            end = edit.span.end;
+        }
+        fixed.push_str(&program[end..]);
+        program = fixed;
+    }
+}
+
+fn warning(span: Range<usize>, message: String, fix: Option<Edit>) -> Lint {
+    Lint {
+        diagnostic: Diagnostic {
+            span,
+            severity: Severity::Warning,
+            message,
+        },
+        fix,
+    }
+}
+
+/// Reports runs of 8 or more rotations or color changes, which can be shortened to the
+/// remainder of 8.
+fn lint_runs(program: &str, tokens: &[Token], lints: &mut Vec<Lint>) {
+    let mut i = 0;
+    while i < tokens.len() {
+        let command = &program[tokens[i].span.clone()];
+        let len = tokens[i..]
+            .iter()
+            .take_while(|t| t.kind == TokenKind::Command && program[t.span.clone()] == *command)
+            .count();
+        if len >= 8 && (command == "R" || command == "C") {
+            let span = tokens[i].span.start..tokens[i + len - 1].span.end;
+            let what = if command == "R" {
+                "rotations"
+            } else {
+                "color changes"
+            };
+            let message = if len % 8 == 0 {
+                format!("{} {} are a no-op", len, what)
+            } else {
+                format!("{} {} are the same as {}", len, what, len % 8)
+            };
+            let fix = Edit {
+                span: span.clone(),
+                replacement: command.repeat(len % 8),
+            };
+            lints.push(warning(span, message, Some(fix)));
+        }
+        i += len.max(1);
+    }
+}
+
+/// Reports the commands after the first unmatched `]`, where the program stops.
+fn lint_unreachable(tokens: &[Token], lints: &mut Vec<Lint>) {
+    let mut depth = 0;
+    let Some(stop) = tokens.iter().position(|t| match t.kind {
+        TokenKind::BlockStart => {
+            depth += 1;
+            false
+        }
+        TokenKind::BlockEnd if depth == 0 => true,
+        TokenKind::BlockEnd => {
+            depth -= 1;
+            false
+        }
+        _ => false,
+    }) else {
+        return;
+    };
+    let mut commands = tokens[stop + 1..].iter().filter(|t| {
+        matches!(
+            t.kind,
+            TokenKind::Command | TokenKind::BlockStart | TokenKind::BlockEnd
+        )
+    });
+    let Some(first) = commands.next() else {
+        return;
+    };
+    let last = commands.next_back().unwrap_or(first);
+    let span = first.span.start..last.span.end;
+    let fix = Edit {
+        span: span.clone(),
+        replacement: String::new(),
+    };
+    lints.push(warning(
+        span,
+        "Commands after an unmatched ] never run".to_string(),
+        Some(fix),
+    ));
+}
+
+/// Reports the outermost blocks without `F`. Blocks without `S` (and without comments, which
+/// would be lost) are replaced by the rotations and color changes they make.
+fn lint_blocks(program: &str, tokens: &[Token], comments: bool, lints: &mut Vec<Lint>) {
+    let mut reported_end = 0;
+    for (start, end) in bracket_pairs(program, comments) {
+        if start < reported_end {
+            continue;
+        }
+        let body: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| t.span.start > start && t.span.end <= end)
+            .collect();
+        let is = |command: &str| {
+            body.iter()
+                .any(|t| t.kind == TokenKind::Command && &program[t.span.clone()] == command)
+        };
+        if is("F") {
+            continue;
+        }
+        reported_end = end;
+
+        let span = start..end + 1;
+        let fix = (!is("S") && !body.iter().any(|t| t.kind == TokenKind::Comment)).then(|| {
+            // Every block runs twice, so a command nested in `depth` blocks runs 2^depth
+            // times; only the counts modulo 8 matter.
+            let (mut rotations, mut colors, mut depth) = (0, 0, 1);
+            for token in &body {
+                let times = 1 << depth.min(3);
+                match (token.kind, &program[token.span.clone()]) {
+                    (TokenKind::BlockStart, _) => depth += 1,
+                    (TokenKind::BlockEnd, _) => depth -= 1,
+                    (_, "R") => rotations += times,
+                    (_, "C") => colors += times,
+                    _ => {}
+                }
+            }
+            Edit {
+                span: span.clone(),
+                replacement: format!("{}{}", "R".repeat(rotations % 8), "C".repeat(colors % 8)),
+            }
+        });
+        lints.push(warning(span, "Block never draws".to_string(), fix));
+    }
+}
+
+mod tests {
+    #[test]
+    fn fixes_keep_the_drawing() {
+        use crate::lint::{fix, lint};
+        use crate::stats::{collect, StatsOptions};
+        use crate::{CFRBuffer, CommandExecutor};
+
+        let programs = [
+            "[[[[RRR]C]]] [[F]RRRRRRRRR] CCCCCCCCCCCCCCCCC F",
+            "[RC[S]]F # note\n[R # comment\n]F",
+            "F]FF[RRRRRRRR]",
+        ];
+        for (program, comments) in programs.iter().flat_map(|p| [(*p, false), (*p, true)]) {
+            let fixed = fix(program, comments);
+            assert!(
+                lint(&fixed, comments).iter().all(|l| l.fix.is_none()),
+                "{}",
+                fixed
+            );
+            let render = |program: &str| {
+                let mut buffer = CFRBuffer::new(16, 16);
+                let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+                executor.set_comments(comments);
+                let _ = executor.run();
+                buffer.data
+            };
+            assert_eq!(render(program), render(&fixed), "{}", fixed);
+            let options = StatsOptions {
+                comments,
+                ..Default::default()
+            };
+            let sleeps = |program: &str| collect(program, &options).sleeps;
+            assert_eq!(sleeps(program), sleeps(&fixed), "{}", fixed);
+        }
+
+        assert_eq!(fix("[[R]C]F", false), "RRRRCCF");
+        let lints = lint("[S]F", false);
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].fix.is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::painter::CFRPainter;
+use crate::parser::{tokenize, TokenKind};
+use crate::validator::{bracket_pairs, validate, Severity};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{CodeLensRequest, DocumentHighlightRequest, HoverRequest, Request as _};
+use lsp_types::{
+    CodeLens, CodeLensOptions, CodeLensParams, Command, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentHighlight, DocumentHighlightKind, DocumentHighlightParams, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+type LspResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Options of the language server.
+#[derive(Debug, Clone)]
+pub struct LspOptions {
+    /// Width of the canvas used for partial execution.
+    pub width: u32,
+    /// Height of the canvas used for partial execution.
+    pub height: u32,
+    /// Maximum number of commands executed for a hover or code lens.
+    pub max_steps: u64,
+    /// Let `#` comment out the rest of its line, like `CommandExecutor::set_comments`.
+    pub comments: bool,
+}
+
+impl Default for LspOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            max_steps: 1_000_000,
+            comments: false,
+        }
+    }
+}
+
+/// Runs a language server for CFRS programs over stdin/stdout until the client shuts it down.
+///
+/// The server provides:
+/// - diagnostics from the validator,
+/// - matching bracket highlights,
+/// - hovers showing the painter state the first time a command is reached,
+/// - a code lens summarizing the whole run.
+pub fn run_stdio(options: LspOptions) -> LspResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = LanguageServer {
+        options,
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(params) = server.handle_notification(notification)? {
+                    let notification = Notification::new(PublishDiagnostics::METHOD.into(), params);
+                    connection
+                        .sender
+                        .send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    io_threads.join()?;
+    Ok(())
+}
+
+struct LanguageServer {
+    options: LspOptions,
+    documents: HashMap<Url, String>,
+}
+
+impl LanguageServer {
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => serde_json::from_value(request.params)
+                .map(|params| serde_json::to_value(self.hover(params))),
+            DocumentHighlightRequest::METHOD => serde_json::from_value(request.params)
+                .map(|params| serde_json::to_value(self.highlight(params))),
+            CodeLensRequest::METHOD => serde_json::from_value(request.params)
+                .map(|params| serde_json::to_value(self.code_lens(params))),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported method: {}", method),
+                )
+            }
+        };
+
+        match result {
+            Ok(Ok(value)) => Response::new_ok(id, value),
+            Ok(Err(e)) | Err(e) => {
+                Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string())
+            }
+        }
+    }
+
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> LspResult<Option<PublishDiagnosticsParams>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                return Ok(Some(PublishDiagnosticsParams::new(
+                    params.text_document.uri,
+                    Vec::new(),
+                    None,
+                )));
+            }
+            _ => return Ok(None),
+        };
+
+        let text = &self.documents[&uri];
+        let diagnostics = validate(text, self.options.comments)
+            .into_iter()
+            .map(|d| lsp_types::Diagnostic {
+                range: to_range(text, d.span.start, d.span.end),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                source: Some("cfrs".to_string()),
+                message: d.message,
+                ..Default::default()
+            })
+            .collect();
+        Ok(Some(PublishDiagnosticsParams::new(uri, diagnostics, None)))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let text = self.documents.get(&position.text_document.uri)?;
+        let offset = position_to_offset(text, position.position);
+        let token = tokenize(text, self.options.comments)
+            .into_iter()
+            .find(|t| t.span.contains(&offset))?;
+        if matches!(token.kind, TokenKind::Comment | TokenKind::Invalid) {
+            return None;
+        }
+        let span = token.span;
+        let command = &text[span.clone()];
+
+        let value = match self.painter_at(text, span.start) {
+            Some((painter, steps)) => format!(
+                "**Painter before `{}`** (reached after {} steps)\n\n\
+                 - position: ({}, {})\n- direction: {}\n- color: {}",
+                command, steps, painter.x, painter.y, painter.direction, painter.color
+            ),
+            None => format!(
+                "`{}` is not reached within {} steps",
+                command, self.options.max_steps
+            ),
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(to_range(text, span.start, span.end)),
+        })
+    }
+
+    fn highlight(&self, params: DocumentHighlightParams) -> Option<Vec<DocumentHighlight>> {
+        let position = params.text_document_position_params;
+        let text = self.documents.get(&position.text_document.uri)?;
+        let offset = position_to_offset(text, position.position);
+
+        // The cursor may be on either side of the bracket.
+        let (start, end) = bracket_pairs(text, self.options.comments)
+            .into_iter()
+            .find(|&(start, end)| [start, end].iter().any(|&i| i == offset || i + 1 == offset))?;
+        Some(
+            [start, end]
+                .iter()
+                .map(|&i| DocumentHighlight {
+                    range: to_range(text, i, i + 1),
+                    kind: Some(DocumentHighlightKind::TEXT),
+                })
+                .collect(),
+        )
+    }
+
+    fn code_lens(&self, params: CodeLensParams) -> Option<Vec<CodeLens>> {
+        let text = self.documents.get(&params.text_document.uri)?;
+
+        let mut buffer = CFRBuffer::new(self.options.width, self.options.height);
+        let mut executor = CommandExecutor::new(text.clone(), &mut buffer);
+        executor.set_comments(self.options.comments);
+        let mut steps = 0;
+        let mut sleeps = 0;
+        let mut finished = false;
+        while steps < self.options.max_steps {
+            match executor.step() {
+                Ok((sleep, _)) => {
+                    steps += 1;
+                    sleeps += sleep as u64;
+                }
+                Err(_) => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        let (x, y) = executor.position();
+        let painted = buffer
+            .data
+            .iter()
+            .filter(|c| **c != CFRColor::Black)
+            .count();
+
+        let title = if finished {
+            format!(
+                "{}×{}: {} steps, {} sleeps, {} pixels painted, painter ends at ({}, {})",
+                self.options.width, self.options.height, steps, sleeps, painted, x, y
+            )
+        } else {
+            format!("Stopped after {} steps ({} pixels painted)", steps, painted)
+        };
+        Some(vec![CodeLens {
+            range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+            command: Some(Command::new(title, String::new(), None)),
+            data: None,
+        }])
+    }
+
+    /// Runs the program until it first reaches `offset`.
+    fn painter_at(&self, program: &str, offset: usize) -> Option<(CFRPainter, u64)> {
+        let mut buffer = CFRBuffer::new(self.options.width, self.options.height);
+        let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+        executor.set_comments(self.options.comments);
+        for steps in 0..self.options.max_steps {
+            if executor.view().index == offset {
+                return Some((*executor.painter(), steps));
+            }
+            executor.step().ok()?;
+        }
+        None
+    }
+}
+
+/// Converts a byte offset to an LSP position (UTF-16 columns).
+fn offset_to_position(text: &str, offset: usize) -> Position {
+    let mut line = 0;
+    let mut character = 0;
+    for (i, c) in text.char_indices() {
+        if i >= offset {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += c.len_utf16() as u32;
+        }
+    }
+    Position::new(line, character)
+}
+
+/// Converts an LSP position (UTF-16 columns) to a byte offset.
+fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut line = 0;
+    let mut character = 0;
+    for (i, c) in text.char_indices() {
+        if line == position.line && (character >= position.character || c == '\n') {
+            return i;
+        }
+        if c == '\n' {
+            line += 1;
+        } else if line == position.line {
+            character += c.len_utf16() as u32;
+        }
+    }
+    text.len()
+}
+
+fn to_range(text: &str, start: usize, end: usize) -> Range {
+    Range::new(
+        offset_to_position(text, start),
+        offset_to_position(text, end),
+    )
+}
+
+mod tests {
+    #[test]
+    fn convert_positions() {
+        use crate::lsp::{offset_to_position, position_to_offset};
+        use lsp_types::Position;
+
+        let text = "F\né[R]";
+        assert_eq!(offset_to_position(text, 4), Position::new(1, 1));
+        assert_eq!(position_to_offset(text, Position::new(1, 1)), 4);
+        assert_eq!(position_to_offset(text, Position::new(0, 5)), 1);
+        assert_eq!(position_to_offset(text, Position::new(3, 0)), text.len());
+    }
+}
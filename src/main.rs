@@ -1,77 +1,2784 @@
-use clap::Parser;
-use image::codecs::gif::{GifEncoder, Repeat};
-use image::{Delay, Frame, ImageBuffer, Rgba};
+use clap::{Args, Parser, Subcommand};
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+use cfrs::animation::Animation;
+use cfrs::antialias::Supersampled;
+use cfrs::bufferfile::BufferFile;
+use cfrs::challenge::Challenge;
+use cfrs::concat::{ConcatOptions, Separator};
+use cfrs::delta::DeltaAnimation;
+use cfrs::droste::Droste;
+use cfrs::encode::{
+    encode_png, write_indexed_gif, write_rgba_gif, Disposal, GifOptions, LoopCount,
+};
+use cfrs::encoder::{encode_frames, Encoder, EncoderOptions, EncoderRegistry};
+use cfrs::filter::{Filter, FrameFilter};
+use cfrs::gallery::GalleryEntry;
+use cfrs::i18n::Language;
+use cfrs::labels::{labeled, LabelOptions};
+use cfrs::metadata::Metadata;
+use cfrs::onion::onion_skin;
+use cfrs::overlay::{arrow_indices, arrow_palette, arrow_rgba, ChangeHighlighter, Overlay};
+use cfrs::pixel_log::PixelLog;
+use cfrs::plot::{to_gcode, to_turtle, PlotOptions, Trail};
+use cfrs::record::{Checkpoint, Recording, RecordingHeader, RecordingWriter};
+use cfrs::rle::TextFormat;
+use cfrs::shared::{run_concurrent_until, Compose, SharedCanvas};
+use cfrs::size::CanvasSize;
+use cfrs::terminal::ColorMode;
+use cfrs::timeline::Timeline;
+use cfrs::timing::{FrameClock, Timing};
+use cfrs::trace::{Trace, TraceFormat};
+use cfrs::tween::Tweener;
+use cfrs::viewport::Viewport;
+use cfrs::{CFRBuffer, CFRColor, CFRPainter, CommandExecutor, Dialect};
+
+/// Prints a message to stderr in the language of `--lang`, like `eprintln!`.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        eprintln!("{}", localize(&format!($($arg)*)))
+    };
+}
 
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    #[clap(long, default_value = "256")]
-    width: u32,
-    #[clap(long, default_value = "256")]
-    height: u32,
+    #[clap(subcommand)]
+    subcommand: Option<Command>,
+    #[clap(flatten)]
+    render: RenderArgs,
+    /// The language of errors and diagnostics: `en`, `es`, or `zh-TW`. Defaults to the
+    /// language of the `CFRS_LANG` or `LANG` environment variable.
+    #[clap(long, global = true)]
+    lang: Option<Language>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render a program to an image or animation (the default).
+    Render(Box<RenderArgs>),
+    /// Interactively append commands to a running program with a live terminal preview.
+    Repl(ReplArgs),
+    /// Run a program while saving periodic checkpoints to a `.cfrsrec` file.
+    Record(RecordArgs),
+    /// Re-render a `.cfrsrec` recording, verifying it against its checkpoints.
+    Replay(ReplayArgs),
+    /// Check the executor against the reference test vectors.
+    Conformance,
+    /// Report the enabled features, encoders, and terminal capabilities, and check that
+    /// rendering and encoding work.
+    Doctor,
+    /// Render a program file under the spec rules, on a 64×64 canvas unless given, and check
+    /// the digest of its canvas.
+    Verify(VerifyArgs),
+    /// Run a short program on a 32×32 canvas unless given, and print its canvas to the
+    /// terminal.
+    Eval(EvalArgs),
+    /// Run a program and print statistics about it.
+    Stats(StatsArgs),
+    /// Render the text of a program file with commands colored by type and by how often
+    /// they ran, as a PNG or another image, or as an HTML page.
+    RenderSource(RenderSourceArgs),
+    /// Write an SVG badge with the length, colors and canvas coverage of a program file.
+    Badge(BadgeArgs),
+    /// Render program files into a directory with thumbnails and an `index.json` manifest.
+    Gallery(GalleryArgs),
+    /// Warn about useless or unreachable commands in a program.
+    Lint(LintArgs),
+    /// Render two program files and report the pixels that differ.
+    Compare(CompareArgs),
+    /// Animate program files side by side, all advancing one command at a time.
+    Race(RaceArgs),
+    /// Score a program file against a target image or a maze.
+    Challenge(ChallengeArgs),
+    /// Expand the macros of a `.cfrst` template and print the plain program.
+    Expand(ExpandArgs),
+    /// Join program files into one program that runs them one after the other.
+    Cat(CatArgs),
+    /// Convert a program between plain CFRS and the run-length encoded text form.
+    Convert(ConvertArgs),
+    /// Print the program embedded in a PNG or GIF made by `cfrs`.
+    Extract(ExtractArgs),
+    /// Convert an image to a `.cfrsbuf` canvas, using the nearest color for every pixel.
+    Import(ImportArgs),
+    /// Run a Rhai script that generates a program, and print the program.
+    #[cfg(feature = "scripting")]
+    Gen(GenArgs),
+    /// Serve an HTTP API that renders programs on demand.
+    #[cfg(feature = "server")]
+    ServeApi(ServeApiArgs),
+    /// Render jobs read from stdin as JSON lines, writing one JSON result per job to stdout.
+    #[cfg(feature = "batch")]
+    Worker(WorkerArgs),
+    /// Run a language server for CFRS programs over stdin/stdout.
+    #[cfg(feature = "lsp")]
+    Lsp(LspArgs),
+}
+
+/// The size of the canvas of the subcommands that draw.
+#[derive(Args, Debug, Clone)]
+struct CanvasSizeArgs {
+    /// Canvas width; 256 unless the subcommand says otherwise.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+    /// Canvas height; 256 unless the subcommand says otherwise.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
+    /// Canvas size as `WxH` (e.g. `128x128`) or a preset: `spec` (64×64), `default`
+    /// (256×256), `hd` (1280×720), `fhd` (1920×1080), or `4k` (3840×2160).
+    #[clap(long, conflicts_with_all = ["width", "height"])]
+    size: Option<CanvasSize>,
+}
+
+impl CanvasSizeArgs {
+    /// Returns the size of `--size`, or of `--width` and `--height`, taking the dimensions
+    /// that are not given from `default`.
+    fn resolve(&self, default: CanvasSize) -> CanvasSize {
+        self.size.unwrap_or(CanvasSize::new(
+            self.width.unwrap_or(default.width),
+            self.height.unwrap_or(default.height),
+        ))
+    }
+
+    /// Returns whether no size was given.
+    fn is_default(&self) -> bool {
+        (self.width, self.height, self.size) == (None, None, None)
+    }
+}
+
+/// The canvas options of the subcommands that draw.
+#[derive(Args, Debug, Clone)]
+struct CanvasArgs {
+    #[clap(flatten)]
+    size: CanvasSizeArgs,
     #[clap(short, long, default_value = "black")]
     background: CFRColor,
+}
+
+impl CanvasArgs {
+    /// Returns a canvas of the resolved size, see `CanvasSizeArgs::resolve`, filled with the
+    /// background.
+    fn buffer(&self, default: CanvasSize) -> CFRBuffer {
+        let size = self.size.resolve(default);
+        let mut buffer = CFRBuffer::new(size.width, size.height);
+        buffer.data.fill(self.background);
+        buffer
+    }
+}
+
+/// The canvas size of the subcommands that don't say otherwise.
+const DEFAULT_SIZE: CanvasSize = CanvasSize::new(256, 256);
+
+#[derive(Args, Debug, Clone)]
+struct RenderArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Pick the smallest canvas that fits the drawing without wrapping around the edges,
+    /// with `--auto-size=<padding>` pixels of padding, by running the programs once
+    /// without drawing first.
+    #[clap(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        conflicts_with_all = ["width", "height", "size", "world"]
+    )]
+    auto_size: Option<u32>,
+    #[clap(long, default_value = "100")]
+    interval: u32,
+    /// How many milliseconds every command takes in animations, e.g. `F=1,S=20`. Commands
+    /// that are not listed keep their default: 20 for `S`, none for the others.
+    #[clap(long, default_value = "S=20")]
+    timing: Timing,
+    /// Capture a frame every this many drawn pixels instead of every `interval` milliseconds
+    /// of sleeps, for programs that rarely sleep. Every frame is shown for `interval`.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    frame_every_pixels: Option<u32>,
+    /// Capture a frame every this many executed commands instead, e.g. for `--format strip`.
+    #[clap(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "frame_every_pixels"
+    )]
+    every: Option<u64>,
+    /// How many times a GIF animation repeats: `infinite`, `none`, or a count.
+    #[clap(long, default_value = "infinite")]
+    loop_count: LoopCount,
+    /// GIF frame disposal method: `keep`, `background`, `previous`, or `any`.
+    #[clap(long, default_value = "keep")]
+    disposal: Disposal,
+    /// Encode every GIF frame in full instead of only the area that changed.
+    #[clap(long)]
+    full_frames: bool,
+    /// Show the trails of this many previous frames at fading intensity in every GIF frame.
+    #[clap(long)]
+    onion_skin: Option<usize>,
+    /// Add this many in-between frames per captured frame that replay the strokes in the
+    /// order they were drawn. The frame delay is divided accordingly.
+    #[clap(long, default_value = "0")]
+    tween: usize,
+    /// Another program to run on the same canvas; can be repeated.
+    #[clap(long = "program")]
+    programs: Vec<String>,
+    /// How the programs share the canvas: `sequential` runs them one after another, and
+    /// `concurrent` runs them all at once on their own threads.
+    #[clap(long, default_value = "sequential")]
+    compose: Compose,
+    /// Size of a pixel in millimeters (G-code) or turtle units (Python) for plotter output.
+    #[clap(long, default_value = "0.5")]
+    plot_scale: f64,
+    /// G-code feed rate while drawing, in millimeters per minute.
+    #[clap(long, default_value = "1500")]
+    feed_rate: f64,
+    /// How many times blocks run: `standard` (twice) or `repeat:<count>` for CFRS variants
+    /// that repeat blocks a different number of times.
+    #[clap(long, default_value = "standard")]
+    dialect: Dialect,
+    /// Skip an unmatched `]` and run blocks nested deeper than `--max-depth` once instead of
+    /// stopping, and print warnings for them and for unknown commands.
+    #[clap(long)]
+    lenient: bool,
+    /// The deepest nesting of blocks allowed.
+    #[clap(long)]
+    max_depth: Option<usize>,
+    /// Let `C` cycle from yellow to transparent, which is fully transparent in PNG and other
+    /// RGBA outputs and black in GIFs.
+    #[cfg(feature = "transparent")]
+    #[clap(long)]
+    transparent: bool,
+    /// Draw the final canvas shrunk into a region of itself, recursively:
+    /// `x,y,width,height,levels`.
+    #[clap(long)]
+    droste: Option<Droste>,
+    /// Export the canvas repeated in a grid of `COLUMNSxROWS` copies, e.g. `3x3`, to check
+    /// that a wrap-around pattern tiles seamlessly.
+    #[clap(long, value_parser = parse_grid, conflicts_with = "overlay")]
+    tile: Option<(u32, u32)>,
+    /// Export a panned and zoomed view of the canvas instead of all of it: `x,y,zoom` shows
+    /// the part from (`x`, `y`) enlarged `zoom` times, wrapping around the edges.
+    #[clap(long, conflicts_with_all = ["tiles", "overlay", "labels", "antialias"])]
+    viewport: Option<Viewport>,
+    /// Draw debug information on top of every exported frame without changing the canvas:
+    /// `painter-arrow` shows the painter's position and direction, and `changes[:fade]`
+    /// tints the pixels that changed since the previous GIF frame, fading out over `fade`
+    /// frames (3 by default).
+    #[clap(long, conflicts_with_all = ["tiles", "masks", "onion_skin"])]
+    overlay: Option<Overlay>,
+    /// Write every drawn pixel as `step,x,y,color` to a `.csv` file, or as JSON Lines to a
+    /// `.jsonl` file.
+    #[clap(long)]
+    pixel_log: Option<PathBuf>,
+    /// Write the step range, delay, and changed area of every animation frame to this JSON
+    /// file, e.g. `timeline.json`, for players that seek by step. `html-scrub` pages show
+    /// the steps too.
+    #[clap(long)]
+    timeline: Option<PathBuf>,
+    /// Write a trace of the run to this JSON file, with a span for every pass through a
+    /// block and an instant for every draw and sleep, one step per microsecond. Open it in
+    /// https://ui.perfetto.dev to see where a long program spends its steps.
+    #[clap(long)]
+    trace: Option<PathBuf>,
+    /// The format of `--trace`: `chrome`, the Chrome trace JSON that Perfetto opens.
+    #[clap(long, default_value = "chrome", requires = "trace")]
+    trace_format: TraceFormat,
+    /// Execute only the commands at these byte offsets of every program, e.g. `120..450`.
+    #[clap(long, value_parser = parse_range)]
+    range: Option<Range<usize>>,
+    /// With `--range`, first run every program without drawing up to the start of the range.
+    #[clap(long, requires = "range")]
+    fast_forward: bool,
+    /// Write one image or animation per `#scene <name>` comment of the program, to
+    /// `<name>_<number>_<scene>.<extension>` next to the output path. Every scene draws on
+    /// the canvas left by the previous ones, and animations only show its own frames.
+    /// Implies `--comments`.
+    #[clap(long, conflicts_with_all = ["range", "world", "antialias", "programs", "timeline", "trace"])]
+    split_scenes: bool,
+    /// Save the final canvas enlarged, with coordinate ticks and labels in the margins.
+    #[clap(long, conflicts_with_all = ["tiles", "masks", "overlay"])]
+    labels: bool,
+    /// With `--labels`, the size of a canvas pixel in image pixels.
+    #[clap(long, default_value = "4", requires = "labels")]
+    label_scale: u32,
+    /// With `--labels`, draw lines between the canvas pixels.
+    #[clap(long, requires = "labels")]
+    grid: bool,
+    /// Post-process every exported frame: `crt` scanlines, `invert`, `grayscale`, or
+    /// `blur[:radius]`; can be repeated to apply several in order.
+    #[clap(long = "filter", conflicts_with_all = ["tiles", "masks", "overlay", "labels", "onion_skin"])]
+    filters: Vec<Filter>,
+    /// Draw smooth lines on a canvas this many times larger, e.g. `4x`, and average it back
+    /// down for anti-aliased PNG and GIF outputs.
+    #[clap(long, value_parser = parse_factor)]
+    antialias: Option<u32>,
+    /// Continue drawing on the canvas saved in this `.cfrsbuf` file, if it exists, and save
+    /// the canvas back to it, so that many runs build up one canvas. The size of a saved
+    /// canvas replaces `--size`.
+    #[clap(long, conflicts_with = "antialias")]
+    world: Option<PathBuf>,
+    /// Output format, e.g. `png`, `gif`, `pgm`, `html`, `html-scrub`, `strip`, or `cfrsbuf`,
+    /// instead of the extension of the output path.
+    #[clap(long)]
+    format: Option<String>,
+    /// Write PNG images with an indexed palette of the used colors instead of RGBA, which
+    /// makes them several times smaller.
+    #[clap(long, default_value_t = true, action = clap::ArgAction::Set)]
+    png_indexed: bool,
+    /// Write a Deep Zoom pyramid of PNG tiles of this size: the `.dzi` descriptor to the
+    /// output path and the tiles to `<name>_files/` next to it.
+    #[clap(long)]
+    tiles: Option<u32>,
+    /// Instead of the canvas, write one black and white mask per color, showing where the
+    /// canvas has that color, to `<name>_<color>.<extension>` next to the output path.
+    #[clap(long, conflicts_with = "tiles")]
+    masks: bool,
+    /// Render a built-in example program by name instead of `command`, e.g. `spiral`.
+    #[clap(long, conflicts_with = "command")]
+    example: Option<String>,
+    /// Don't embed the program and canvas options in PNG and GIF outputs.
+    #[clap(long)]
+    no_metadata: bool,
+    /// Render the programs embedded in a PNG or GIF made by `cfrs` instead of `command`. The
+    /// canvas size, background, and dialect of the image are used unless given.
+    #[clap(long, conflicts_with_all = ["command", "example"])]
+    from_image: Option<PathBuf>,
+    /// Let `#` comment out the rest of its line. Otherwise `#` is ignored like any unknown
+    /// character, and the commands after it run.
+    #[clap(long)]
+    comments: bool,
+    #[clap(required = true)]
+    output: Option<PathBuf>,
+    #[clap(required_unless_present_any = ["example", "from_image"])]
+    command: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ReplArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Maximum width of the terminal preview in columns.
+    #[clap(long, default_value = "64")]
+    columns: u32,
+    /// Maximum number of commands executed per line; a line that runs longer is discarded.
+    #[clap(long, default_value = "10000000")]
+    max_steps: u64,
+    /// Start with a panned and zoomed preview: `x,y,zoom`.
+    #[clap(long)]
+    viewport: Option<Viewport>,
+    /// Colors of the preview: `truecolor`, `256`, or `16`. Detected from `COLORTERM` and
+    /// `TERM` by default.
+    #[clap(long)]
+    color_mode: Option<ColorMode>,
+}
+
+#[derive(Args, Debug)]
+struct RecordArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    #[clap(long, default_value = "100")]
+    interval: u32,
+    /// Number of steps between two checkpoints.
+    #[clap(long, default_value = "1000000")]
+    checkpoint_every: u64,
+    /// Continue an unfinished recording from its last checkpoint.
+    #[clap(long, conflicts_with = "command")]
+    resume: bool,
+    recording: PathBuf,
+    #[clap(required_unless_present = "resume")]
+    command: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ReplayArgs {
+    /// Start from the last checkpoint instead of re-running and verifying the whole program.
+    #[clap(long)]
+    from_checkpoint: bool,
+    recording: PathBuf,
+    output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Maximum number of executed steps.
+    #[clap(long, default_value = "100000000")]
+    max_steps: u64,
+    /// Stop once the painter has wrapped around the edges more than this many times.
+    #[clap(long)]
+    max_wraps: Option<u64>,
+    /// Print the program with the commands that were never executed highlighted.
+    #[clap(long)]
+    coverage: bool,
+    /// Print the steps and time spent in every block.
+    #[clap(long)]
+    profile: bool,
+    /// Analyze the program without running it.
+    #[clap(long, conflicts_with_all = ["coverage", "profile", "watch"])]
+    analyze: bool,
+    /// Print every command that paints the pixel at `X,Y`; can be repeated.
+    #[clap(long, value_parser = parse_point)]
+    watch: Vec<(u32, u32)>,
+    /// Let `#` comment out the rest of its line, which also counts the steps of every
+    /// `#scene`. Otherwise `#` is ignored like any unknown character.
+    #[clap(long)]
+    comments: bool,
+    command: String,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    #[clap(flatten)]
+    size: CanvasSizeArgs,
+    /// The expected digest of the canvas, as `sha256:<hex>` or `fnv1a:<hex>`. Without it,
+    /// the SHA-256 digest of the canvas is printed.
+    #[clap(long)]
+    expect_digest: Option<cfrs::verify::Digest>,
+    /// Maximum number of executed steps.
+    #[clap(long, default_value = "100000000")]
+    max_steps: u64,
+    program: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct RenderSourceArgs {
+    /// Size of a pixel of the 3×5 font in image pixels.
+    #[clap(long, default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+    /// Maximum number of executed steps.
+    #[clap(long, default_value = "100000000")]
+    max_steps: u64,
+    /// The image, or an `.html` page.
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Let `#` comment out the rest of its line. Otherwise `#` is ignored like any unknown
+    /// character, and the commands after it run.
+    #[clap(long)]
+    comments: bool,
+    program: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct BadgeArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Where to write the SVG; printed if omitted.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    program: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct GalleryArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Format of the renders: `png` or `gif`.
+    #[clap(long, default_value = "png", value_parser = ["png", "gif"])]
+    format: String,
     #[clap(long, default_value = "100")]
     interval: u32,
+    /// Also write PNG thumbnails that fit in this many pixels, as `<name>_thumb.png`.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    thumbnails: Option<u32>,
+    /// Skip programs that take more steps than this.
+    #[clap(long, default_value = "100000000")]
+    max_steps: u64,
+    /// The directory to write the renders and `index.json` to.
+    #[clap(short, long)]
+    output: PathBuf,
+    #[clap(required = true)]
+    programs: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct LintArgs {
+    /// Print the program with every fixable warning fixed instead of the warnings.
+    #[clap(long)]
+    fix: bool,
+    /// Let `#` comment out the rest of its line. Otherwise `#` is ignored like any unknown
+    /// character, and the commands after it are checked.
+    #[clap(long)]
+    comments: bool,
+    command: String,
+}
+
+#[derive(Args, Debug)]
+struct ExpandArgs {
+    /// The `.cfrst` template to expand.
+    template: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct CatArgs {
+    /// What goes between two programs: `newline`, `sleep`, or `none`.
+    #[clap(long, default_value = "newline")]
+    separator: Separator,
+    /// Bring the painter back to white and facing up before every program.
+    #[clap(long)]
+    reset: bool,
+    /// Write the program to this file instead of printing it.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    #[clap(required = true)]
+    programs: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct EvalArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Stop after this many steps.
+    #[clap(long, default_value = "1000000")]
+    steps: u64,
+    /// Print one plain character per pixel instead of colored half blocks.
+    #[clap(long)]
+    ascii: bool,
+    /// Colors of the half blocks: `truecolor`, `256`, or `16`. Detected from `COLORTERM`
+    /// and `TERM` by default.
+    #[clap(long, conflicts_with = "ascii")]
+    color_mode: Option<ColorMode>,
+    /// Skip an unmatched `]` and run blocks nested deeper than `--max-depth` once instead of
+    /// stopping, and print warnings for them and for unknown commands.
+    #[clap(long)]
+    lenient: bool,
+    /// The deepest nesting of blocks allowed.
+    #[clap(long)]
+    max_depth: Option<usize>,
+    /// Let `#` comment out the rest of its line. Otherwise `#` is ignored like any unknown
+    /// character, and the commands after it run.
+    #[clap(long)]
+    comments: bool,
+    command: String,
+}
+
+#[derive(Args, Debug)]
+struct ExtractArgs {
+    /// Print the canvas options and crate version too, not only the program.
+    #[clap(long)]
+    all: bool,
+    image: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    image: PathBuf,
+    /// The `.cfrsbuf` file to write.
     output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    /// The format of the program: `cfrs` or `rle`.
+    #[clap(long, default_value = "cfrs")]
+    from: TextFormat,
+    /// The format to print: `cfrs` or `rle`.
+    #[clap(long, default_value = "cfrs")]
+    to: TextFormat,
+    /// Let `#` comment out the rest of its line, which drops comments from `rle`. Otherwise
+    /// `#` is ignored like any unknown character.
+    #[clap(long)]
+    comments: bool,
     command: String,
 }
 
+#[derive(Args, Debug)]
+struct CompareArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Save an image of the first render with the differing pixels in red.
+    #[clap(long)]
+    diff_image: Option<PathBuf>,
+    first: PathBuf,
+    second: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct ChallengeArgs {
+    /// Stop the program after this many steps.
+    #[clap(long, default_value = "100000000")]
+    max_steps: u64,
+    /// Print the score as JSON.
+    #[clap(long)]
+    json: bool,
+    /// A target image to draw, or a text file with a maze to solve.
+    challenge: PathBuf,
+    program: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct RaceArgs {
+    #[clap(flatten)]
+    canvas: CanvasArgs,
+    /// Color of the lines between the canvases.
+    #[clap(long, default_value = "white")]
+    divider: CFRColor,
+    /// Capture a frame every this many commands of every program.
+    #[clap(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    steps_per_frame: u64,
+    #[clap(long, default_value = "100")]
+    interval: u32,
+    /// The animation, e.g. `race.gif`, or an image of the final canvases.
+    #[clap(short, long)]
+    output: PathBuf,
+    /// The program files, two or more.
+    #[clap(num_args = 2.., required = true)]
+    programs: Vec<PathBuf>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Args, Debug)]
+struct ServeApiArgs {
+    #[clap(long, default_value = "127.0.0.1:3000")]
+    addr: std::net::SocketAddr,
+    /// Maximum number of commands executed per request.
+    #[clap(long, default_value = "10000000")]
+    max_steps: u64,
+    /// Maximum render time per request in milliseconds.
+    #[clap(long, default_value = "5000")]
+    timeout: u64,
+    /// Maximum number of canvas pixels per request.
+    #[clap(long, default_value = "1048576")]
+    max_pixels: u64,
+}
+
+#[cfg(feature = "batch")]
+#[derive(Args, Debug)]
+struct WorkerArgs {
+    /// Maximum number of commands executed per job.
+    #[clap(long, default_value = "10000000")]
+    max_steps: u64,
+    /// Maximum render time per job in milliseconds.
+    #[clap(long, default_value = "5000")]
+    timeout: u64,
+    /// Maximum number of canvas pixels per job.
+    #[clap(long, default_value = "1048576")]
+    max_pixels: u64,
+    /// Maximum number of frames per GIF job.
+    #[clap(long, default_value = "500")]
+    max_frames: u64,
+}
+
+#[cfg(feature = "scripting")]
+#[derive(Args, Debug)]
+struct GenArgs {
+    /// The Rhai script that generates the program.
+    #[clap(long)]
+    script: PathBuf,
+    /// A variable for the script, e.g. `--param size=5`. Can be repeated.
+    #[clap(long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+    /// The seed of `random(n)` in the script.
+    #[clap(long, default_value = "0", conflicts_with = "seeds")]
+    seed: i64,
+    /// Take `random(n)` from the operating system's randomness instead of the seed, for a
+    /// different program on every run.
+    #[clap(long, conflicts_with_all = ["seed", "seeds", "replay_entropy"])]
+    os_entropy: bool,
+    /// Take `random(n)` from a file written by `--save-entropy`, to generate a program again.
+    #[clap(long, conflicts_with_all = ["seed", "seeds"])]
+    replay_entropy: Option<PathBuf>,
+    /// Write the numbers `random(n)` drew from to a file, for `--replay-entropy`.
+    #[clap(long, conflicts_with = "seeds")]
+    save_entropy: Option<PathBuf>,
+    /// Render the programs of a range of seeds like `0..64` into a contact sheet image
+    /// instead of printing a program.
+    #[clap(long, value_parser = parse_range, requires = "output")]
+    seeds: Option<Range<usize>>,
+    /// The contact sheet image.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Columns of the contact sheet; by default, about as many as rows.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    columns: Option<u32>,
+    #[clap(long, default_value = "64", value_parser = clap::value_parser!(u32).range(1..))]
+    width: u32,
+    #[clap(long, default_value = "64", value_parser = clap::value_parser!(u32).range(1..))]
+    height: u32,
+    /// Size of a canvas pixel in the contact sheet.
+    #[clap(long, default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+    /// Maximum number of steps of every rendered program.
+    #[clap(long, default_value = "10000000")]
+    max_steps: u64,
+}
+
+#[cfg(feature = "lsp")]
+#[derive(Args, Debug)]
+struct LspArgs {
+    /// Canvas width used to evaluate hovers and code lenses.
+    #[clap(long, default_value = "256", value_parser = clap::value_parser!(u32).range(1..))]
+    width: u32,
+    /// Canvas height used to evaluate hovers and code lenses.
+    #[clap(long, default_value = "256", value_parser = clap::value_parser!(u32).range(1..))]
+    height: u32,
+    /// Maximum number of commands executed per hover or code lens.
+    #[clap(long, default_value = "1000000")]
+    max_steps: u64,
+    /// Let `#` comment out the rest of its line. Otherwise `#` is ignored like any unknown
+    /// character, and the commands after it run.
+    #[clap(long)]
+    comments: bool,
+}
+
 fn main() {
-    let args = Cli::parse();
+    let cli = Cli::parse();
+    LANGUAGE.get_or_init(|| cli.lang.unwrap_or_else(Language::from_env));
+
+    match cli.subcommand {
+        Some(Command::Render(args)) => {
+            handle_signals();
+            render(*args)
+        }
+        Some(Command::Repl(args)) => repl(args),
+        Some(Command::Record(args)) => record(args),
+        Some(Command::Replay(args)) => replay(args),
+        Some(Command::Conformance) => conformance(),
+        Some(Command::Doctor) => doctor(),
+        Some(Command::Verify(args)) => verify(args),
+        Some(Command::Eval(args)) => eval(args),
+        Some(Command::Stats(args)) => stats(args),
+        Some(Command::RenderSource(args)) => render_source(args),
+        Some(Command::Badge(args)) => badge(args),
+        Some(Command::Gallery(args)) => gallery(args),
+        Some(Command::Challenge(args)) => challenge(args),
+        Some(Command::Compare(args)) => compare(args),
+        Some(Command::Race(args)) => race(args),
+        Some(Command::Lint(args)) => lint(args),
+        Some(Command::Expand(args)) => expand(args),
+        Some(Command::Cat(args)) => cat(args),
+        Some(Command::Convert(args)) => convert(args),
+        Some(Command::Extract(args)) => extract(args),
+        Some(Command::Import(args)) => import(args),
+        #[cfg(feature = "scripting")]
+        Some(Command::Gen(args)) => gen(args),
+        #[cfg(feature = "server")]
+        Some(Command::ServeApi(args)) => serve_api(args),
+        #[cfg(feature = "batch")]
+        Some(Command::Worker(args)) => worker(args),
+        #[cfg(feature = "lsp")]
+        Some(Command::Lsp(args)) => lsp(args),
+        None => {
+            handle_signals();
+            render(cli.render)
+        }
+    }
+}
+
+/// Takes the programs and, where they were left at their defaults, the canvas options from
+/// the metadata of `--from-image`.
+fn apply_from_image(args: &mut RenderArgs) {
+    let Some(path) = &args.from_image else {
+        return;
+    };
+    let bytes = std::fs::read(path).expect("Failed to read image");
+    let Some(metadata) = cfrs::metadata::extract(&bytes) else {
+        report!("{} has no CFRS metadata", path.display());
+        std::process::exit(2);
+    };
+    let mut programs = metadata.programs.into_iter();
+    args.command = programs.next();
+    args.programs.splice(0..0, programs);
+    if args.canvas.size.is_default() {
+        args.canvas.size.size = Some(CanvasSize::new(metadata.width, metadata.height));
+    }
+    if args.canvas.background == CFRColor::Black {
+        args.canvas.background = metadata.background;
+    }
+    if args.dialect == Dialect::Standard {
+        args.dialect = metadata.dialect;
+    }
+}
+
+/// The language of `--lang`, set once the arguments are parsed.
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Returns `message` in the language of `--lang`, or in English before the arguments are
+/// parsed.
+fn localize(message: &str) -> String {
+    LANGUAGE
+        .get()
+        .copied()
+        .unwrap_or_default()
+        .translate(message)
+}
+
+/// Set by Ctrl+C or SIGTERM, which stops a render so that it saves what it has drawn.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
 
-    let extension = args
-        .output
+/// Makes the first Ctrl+C or SIGTERM stop the render and save the frames captured so far,
+/// instead of leaving an empty or truncated output file. A second one quits right away.
+fn handle_signals() {
+    #[cfg(feature = "ctrlc")]
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        report!("Interrupted; saving what has been drawn (interrupt again to quit)");
+    })
+    .expect("Failed to handle signals");
+}
+
+/// Replaces the canvas size with the smallest one that fits the drawing of every program
+/// around the center, if `--auto-size` is given.
+fn apply_auto_size(args: &mut RenderArgs) {
+    let Some(padding) = args.auto_size.take() else {
+        return;
+    };
+    let limits = cfrs::ResourceLimits::default();
+    let (mut reach_x, mut reach_y) = (0u64, 0u64);
+    for program in programs_of(args) {
+        let mut executor = CommandExecutor::new(program, CFRBuffer::new(1, 1));
+        executor.set_dialect(args.dialect);
+        executor.set_max_depth(args.max_depth);
+        executor.set_lenient(args.lenient);
+        executor.set_comments(args.comments);
+        let dry_run = executor.dry_run(limits.max_steps);
+        if let Some(error) = dry_run.error {
+            report!("Sizing the canvas stopped early: {}", error);
+        }
+        if let Some(bounds) = dry_run.bounds {
+            reach_x = reach_x
+                .max(bounds.left.unsigned_abs())
+                .max(bounds.right.unsigned_abs());
+            reach_y = reach_y
+                .max(bounds.top.unsigned_abs())
+                .max(bounds.bottom.unsigned_abs());
+        }
+    }
+    // The painter starts at the center, so the canvas reaches as far on both sides.
+    let size = |reach: u64| reach.saturating_add(padding as u64).saturating_mul(2) + 1;
+    let (width, height) = (size(reach_x), size(reach_y));
+    if width.saturating_mul(height) > limits.max_pixels {
+        report!(
+            "The drawing needs a {}x{} canvas, which is too large",
+            width,
+            height
+        );
+        std::process::exit(2);
+    }
+    let size = CanvasSize::new(width as u32, height as u32);
+    args.canvas.size.size = Some(size);
+    report!("Canvas size: {}x{}", size.width, size.height);
+}
+
+fn render(mut args: RenderArgs) {
+    apply_from_image(&mut args);
+    apply_auto_size(&mut args);
+    if args.split_scenes {
+        render_scenes(args);
+        return;
+    }
+    render_scene(args, None);
+}
+
+/// Renders the programs to the output path, drawing only up to the end of `scene` and
+/// capturing frames only within it if given.
+fn render_scene(args: RenderArgs, scene: Option<Range<usize>>) {
+    let output = args.output.clone().expect("output is required");
+    let format = args
+        .format
+        .clone()
+        .unwrap_or_else(|| format_of(&output).to_string());
+    let embeds = matches!(format.as_str(), "png" | "gif");
+    // A part of a program, or a program drawn over a world, doesn't reproduce the image.
+    let reproducible = args.range.is_none() && args.world.is_none() && scene.is_none();
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let metadata = (embeds && !args.no_metadata && reproducible).then(|| Metadata {
+        background: args.canvas.background,
+        dialect: args.dialect,
+        ..Metadata::new(programs_of(&args), width, height)
+    });
+    render_output(args, scene);
+    if interrupted() {
+        // The image only shows a part of the program.
+        std::process::exit(130);
+    }
+    if let Some(metadata) = metadata {
+        embed_metadata(&output, &format, &metadata);
+    }
+}
+
+/// Renders every `#scene` of the program to a file of its own.
+fn render_scenes(args: RenderArgs) {
+    let output = args.output.clone().expect("output is required");
+    let format = args
+        .format
+        .clone()
+        .unwrap_or_else(|| format_of(&output).to_string());
+    if matches!(format.as_str(), "html" | "gcode" | "py") || args.compose == Compose::Concurrent {
+        report!("Scenes are only split for images and animations of sequential programs");
+        std::process::exit(2);
+    }
+    if !args.programs.is_empty() {
+        report!("Scenes are only split for a single program");
+        std::process::exit(2);
+    }
+    let program = programs_of(&args).swap_remove(0);
+    let scenes = cfrs::parser::scenes(&program);
+    if scenes.is_empty() {
+        report!("The program has no #scene markers");
+        std::process::exit(2);
+    }
+
+    let name = output
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("scene");
+    let extension = output
         .extension()
         .and_then(std::ffi::OsStr::to_str)
-        .unwrap_or("");
-    let animation = extension == "gif";
+        .unwrap_or(&format);
+    for (i, scene) in scenes.iter().enumerate() {
+        let title: String = scene
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = output.with_file_name(format!("{}_{:02}_{}.{}", name, i + 1, title, extension));
+        let mut scene_args = args.clone();
+        scene_args.comments = true;
+        scene_args.output = Some(path.clone());
+        scene_args.format = Some(format.clone());
+        render_scene(scene_args, Some(scene.span.clone()));
+        println!("{}", path.display());
+    }
+}
+
+/// Returns the program, or the example, followed by the other programs.
+fn programs_of(args: &RenderArgs) -> Vec<String> {
+    let command = match &args.example {
+        Some(name) => match cfrs::library::get(name) {
+            Some(example) => example.program.to_string(),
+            None => {
+                let names: Vec<&str> = cfrs::library::EXAMPLES.iter().map(|e| e.name).collect();
+                report!("Unknown example {}; available: {}", name, names.join(", "));
+                std::process::exit(2);
+            }
+        },
+        None => args.command.clone().expect("command is required"),
+    };
+    std::iter::once(command)
+        .chain(args.programs.iter().cloned())
+        .collect()
+}
+
+/// Adds the metadata to the PNG or GIF at `output`, if it was written.
+fn embed_metadata(output: &Path, format: &str, metadata: &Metadata) {
+    let Ok(bytes) = std::fs::read(output) else {
+        return;
+    };
+    let embedded = match format {
+        "png" => cfrs::metadata::embed_png(&bytes, metadata),
+        _ => cfrs::metadata::embed_gif(&bytes, metadata),
+    };
+    if let Some(embedded) = embedded {
+        std::fs::write(output, embedded).expect("Failed to save metadata");
+    }
+}
+
+fn render_output(args: RenderArgs, scene: Option<Range<usize>>) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let output = args.output.clone().expect("output is required");
+    let format = args
+        .format
+        .clone()
+        .unwrap_or_else(|| format_of(&output).to_string());
+
+    let animation = is_animation(&format);
+
+    let mut programs = programs_of(&args);
+    if let Some(range) = &args.range {
+        for program in &mut programs {
+            let Some(slice) = program.get(range.clone()) else {
+                report!("Range {:?} is not within the program", range);
+                std::process::exit(2);
+            };
+            if !args.fast_forward {
+                *program = slice.to_string();
+            }
+        }
+    }
+    if args.fast_forward && (format == "html" || format == "gcode" || format == "py") {
+        report!("Fast-forwarding is only supported for images and animations");
+    }
+    let images = !matches!(format.as_str(), "html" | "gcode" | "py");
+    if args.world.is_some() && (!images || args.compose == Compose::Concurrent) {
+        report!("Worlds are only supported for images and animations of sequential programs");
+        std::process::exit(2);
+    }
+    if args.timeline.is_some() && !animation {
+        report!("Timelines are only written for animations");
+    }
+    if format == "html" {
+        if !args.programs.is_empty() {
+            report!("HTML output only plays the first program");
+        }
+        if args.dialect != Dialect::Standard {
+            report!("HTML output always uses the standard dialect");
+        }
+        let options = cfrs::html::HtmlOptions {
+            width,
+            height,
+            background: args.canvas.background,
+            interval: args.interval,
+            comments: args.comments,
+            ..Default::default()
+        };
+        let page = cfrs::html::to_html(&programs[0], &options);
+        std::fs::write(&output, page).expect("Failed to save page");
+        return;
+    }
+    if format == "gcode" || format == "py" {
+        let mut trail = Trail::new(width, height);
+        for program in programs {
+            let mut executor = CommandExecutor::new(program, &mut trail);
+            executor.set_dialect(args.dialect);
+            executor.set_max_depth(args.max_depth);
+            executor.set_lenient(args.lenient);
+            executor.set_comments(args.comments);
+            let _ = executor.run();
+            executor.warnings().iter().for_each(|w| report!("{}", w));
+        }
+        let options = PlotOptions {
+            scale: args.plot_scale,
+            background: Some(args.canvas.background),
+            feed_rate: args.feed_rate,
+            ..Default::default()
+        };
+        let plot = if format == "gcode" {
+            to_gcode(&trail, &options)
+        } else {
+            to_turtle(&trail, &options)
+        };
+        std::fs::write(&output, plot).expect("Failed to save plot");
+        return;
+    }
+    if let Some(factor) = args.antialias {
+        render_antialiased(&args, programs, &output, &format, factor);
+        return;
+    }
+    let (mut buffer, mut animation, painters, timeline) = match args.compose {
+        Compose::Sequential => execute_sequential(&args, programs, animation, scene.as_ref()),
+        Compose::Concurrent => execute_concurrent(&args, &programs, animation),
+    };
+    if let Some(path) = &args.world {
+        save_world(path, &buffer);
+    }
+    if let (Some(path), Some(timeline)) = (&args.timeline, &timeline) {
+        let file = std::io::BufWriter::new(File::create(path).expect("Failed to create file"));
+        timeline.write_json(file).expect("Failed to save timeline");
+    }
+    if format == "cfrsbuf" {
+        let file = BufferFile {
+            buffer,
+            painter: painters.last().copied(),
+        };
+        let writer = std::io::BufWriter::new(File::create(&output).expect("Failed to create file"));
+        file.write(writer).expect("Failed to save canvas");
+        return;
+    }
+    if let Some(droste) = args.droste {
+        buffer = droste.apply(&buffer);
+        animation
+            .frames
+            .iter_mut()
+            .for_each(|frame| *frame = droste.apply(frame));
+    }
 
-    let mut buffer = CFRBuffer::new(args.width, args.height);
-    buffer.data.iter_mut().for_each(|c| *c = args.background);
+    if let Some((columns, rows)) = args.tile {
+        let width = buffer.width.checked_mul(columns);
+        let height = buffer.height.checked_mul(rows);
+        if width.zip(height).is_none() {
+            report!("The tiled canvas is too large");
+            std::process::exit(2);
+        }
+        buffer = buffer.repeat(columns, rows);
+        animation
+            .frames
+            .iter_mut()
+            .for_each(|frame| *frame = frame.repeat(columns, rows));
+    }
+    if let Some(viewport) = args.viewport {
+        buffer = viewport.apply(&buffer);
+        animation
+            .frames
+            .iter_mut()
+            .for_each(|frame| *frame = viewport.apply(frame));
+    }
+
+    if let Some(tile_size) = args.tiles {
+        save_tiles(&output, &buffer, tile_size);
+        return;
+    }
+    if args.masks {
+        save_masks(&output, &format, &buffer, args.png_indexed);
+        return;
+    }
+    if format == "strip" {
+        let options = EncoderOptions {
+            every: args.every,
+            ..Default::default()
+        };
+        let mut encoder = EncoderRegistry::builtin()
+            .get("strip", &options)
+            .expect("Film strips have an encoder");
+        save_encoded(&output, encoder.as_mut(), &animation);
+        return;
+    }
+    let options = GifOptions {
+        delay: args.interval / (args.tween as u32 + 1),
+        loop_count: args.loop_count,
+        disposal: args.disposal,
+        optimize: !args.full_frames,
+    };
+    if !args.filters.is_empty() {
+        save_filtered(
+            &output,
+            &format,
+            &buffer,
+            &animation,
+            &args.filters,
+            &options,
+        );
+        return;
+    }
+    if args.labels {
+        if is_animation(&format) {
+            report!("Axis labels can only be drawn on still images");
+            std::process::exit(2);
+        }
+        let options = LabelOptions {
+            scale: args.label_scale,
+            grid: args.grid,
+            ..Default::default()
+        };
+        let image = labeled(&buffer, &options);
+        image::RgbaImage::from_raw(image.width, image.height, image.rgba)
+            .expect("Labeled image has its own size")
+            .save(&output)
+            .expect("Failed to save image");
+        return;
+    }
+    if let Some(Overlay::PainterArrow) = args.overlay {
+        if painters.is_empty() {
+            report!("Concurrent programs have no single painter to draw");
+        } else {
+            save_with_arrows(&output, &format, &buffer, &animation, &painters, &options);
+            return;
+        }
+    }
+    if let Some(Overlay::Changes { fade }) = args.overlay {
+        if format != "gif" {
+            report!("Changes can only be highlighted in GIF animations");
+            std::process::exit(2);
+        }
+        let mut canvas = CFRBuffer::new(buffer.width, buffer.height);
+        canvas
+            .data
+            .iter_mut()
+            .for_each(|c| *c = args.canvas.background);
+        let mut highlighter = ChangeHighlighter::new(&canvas, fade);
+        let frames: Vec<Vec<u8>> = animation
+            .frames
+            .iter()
+            .map(|frame| highlighter.indices(frame))
+            .collect();
+        let file = File::create(&output).expect("Failed to create file");
+        write_indexed_gif(
+            file,
+            buffer.width,
+            buffer.height,
+            &highlighter.palette(),
+            &frames,
+            &options,
+        )
+        .expect("Failed to save animation");
+        return;
+    }
+    if let (Some(layers), "gif") = (args.onion_skin, format.as_str()) {
+        let skin = onion_skin(&animation.frames, layers, args.canvas.background);
+        let file = File::create(&output).expect("Failed to create file");
+        write_indexed_gif(
+            file,
+            skin.width,
+            skin.height,
+            &skin.palette,
+            &skin.frames,
+            &options,
+        )
+        .expect("Failed to save animation");
+        return;
+    }
+    save(
+        &output,
+        &format,
+        &buffer,
+        &animation,
+        timeline.as_ref(),
+        &options,
+        args.png_indexed,
+    );
+}
 
-    let mut time = 0;
+/// Runs the programs one after another on a supersampled canvas and saves an anti-aliased
+/// PNG image or GIF animation.
+fn render_antialiased(
+    args: &RenderArgs,
+    programs: Vec<String>,
+    output: &Path,
+    format: &str,
+    factor: u32,
+) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    if format != "png" && format != "gif" {
+        report!("Anti-aliasing is only supported for PNG and GIF outputs");
+        std::process::exit(2);
+    }
+    if args.compose == Compose::Concurrent {
+        report!("Anti-aliased programs always run one after another");
+    }
+    let mut canvas = Supersampled::new(width, height, factor, args.canvas.background);
+    let mut clock = FrameClock::new(args.timing, args.interval);
     let mut frames = Vec::new();
+    let mut delays = Vec::new();
+    for program in programs {
+        let mut executor = CommandExecutor::new(program, &mut canvas);
+        executor.set_dialect(args.dialect);
+        executor.set_comments(args.comments);
+        while !interrupted() {
+            let command = executor.view().next_command();
+            let Ok((_, canvas)) = executor.step() else {
+                break;
+            };
+            if let Some(delay) = command.and_then(|c| clock.tick(c)) {
+                if format == "gif" {
+                    frames.push(canvas.to_rgba_bytes());
+                    delays.push(delay);
+                }
+            }
+        }
+    }
 
-    let mut executor = CommandExecutor::new(args.command, &mut buffer);
-    while let Ok((sleep, buf)) = executor.step() {
-        if sleep && animation {
-            time += 20;
-            if time >= args.interval {
-                time -= args.interval;
-                let img = buf.to_rgba_image();
-                frames.push(img);
+    let last = canvas.to_rgba_bytes();
+    if format == "png" {
+        image::RgbaImage::from_raw(width, height, last)
+            .expect("The anti-aliased canvas has its own size")
+            .save_with_format(output, image::ImageFormat::Png)
+            .expect("Failed to save image");
+        return;
+    }
+    if frames.last() != Some(&last) {
+        frames.push(last);
+        delays.push(args.interval);
+    }
+    let options = GifOptions {
+        delay: args.interval,
+        loop_count: args.loop_count,
+        disposal: args.disposal,
+        optimize: !args.full_frames,
+    };
+    let file = std::io::BufWriter::new(File::create(output).expect("Failed to create file"));
+    write_rgba_gif(file, width, height, &frames, &delays, &options)
+        .expect("Failed to save animation");
+}
+
+/// Reads the canvas saved in a world file, or returns `None` if there is none yet.
+fn load_world(path: &Path) -> Option<CFRBuffer> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            report!("Failed to open {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+    match cfrs::bufferfile::read_buffer(std::io::BufReader::new(file)) {
+        Ok(buffer) => Some(buffer),
+        Err(e) => {
+            report!("Failed to read {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Saves the canvas to a world file, replacing it only once the new one is written so that
+/// an interrupted run leaves the world intact.
+fn save_world(path: &Path, buffer: &CFRBuffer) {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let file = File::create(&temporary).expect("Failed to create world file");
+    cfrs::bufferfile::write_buffer(std::io::BufWriter::new(file), buffer)
+        .expect("Failed to save world");
+    std::fs::rename(&temporary, path).expect("Failed to save world");
+}
+
+/// Runs the programs one after another on the same canvas, the first one only up to the end
+/// of `scene` if given.
+///
+/// Also returns the painter at every frame, followed by the painter at the end, and the
+/// timeline of the frames.
+fn execute_sequential(
+    args: &RenderArgs,
+    programs: Vec<String>,
+    animation: bool,
+    scene: Option<&Range<usize>>,
+) -> (CFRBuffer, Animation, Vec<CFRPainter>, Option<Timeline>) {
+    let mut buffer = match args.world.as_deref().and_then(load_world) {
+        Some(world) => world,
+        None => args.canvas.buffer(DEFAULT_SIZE),
+    };
+
+    let mut clock = FrameClock::new(args.timing, args.interval);
+    let mut drawn = 0;
+    let mut frames = Animation::new();
+    let mut painters = Vec::new();
+
+    let mut tweener = (args.tween > 0).then(|| Tweener::new(&buffer, args.tween));
+    let mut painter = CFRPainter::new();
+    let mut log = args.pixel_log.as_ref().map(|_| PixelLog::new());
+    let mut timeline = animation.then(|| Timeline::new(&buffer));
+    let mut trace = args.trace.as_ref().map(|_| Trace::new());
+    let mut steps = 0;
+    for (index, program) in programs.into_iter().enumerate() {
+        let mut executor = CommandExecutor::new(program, &mut buffer);
+        executor.set_dialect(args.dialect);
+        executor.set_max_depth(args.max_depth);
+        executor.set_lenient(args.lenient);
+        executor.set_comments(args.comments);
+        #[cfg(feature = "transparent")]
+        executor.set_transparent(args.transparent);
+        let scene = scene.filter(|_| index == 0);
+        let end = match (scene, &args.range, args.fast_forward) {
+            (Some(scene), _, _) => scene.end,
+            (None, Some(range), true) => {
+                let max_steps = cfrs::ResourceLimits::default().max_steps;
+                if let Err(e) = executor.fast_forward(range.start, max_steps) {
+                    report!("Failed to fast-forward to byte {}: {}", range.start, e);
+                    std::process::exit(1);
+                }
+                range.end
+            }
+            _ => usize::MAX,
+        };
+        while executor.view().index < end {
+            let offset = executor.view().index;
+            if interrupted() {
+                report!(
+                    "Stopped at byte {} of program {} after {} steps",
+                    offset,
+                    index + 1,
+                    steps
+                );
+                break;
+            }
+            let command = executor.view().next_command();
+            let draws = command == Some('F');
+            let depth = executor.view().depth();
+            let Ok(_) = executor.step() else {
+                break;
+            };
+            steps += 1;
+            if let Some(trace) = &mut trace {
+                trace.record(steps, index, offset, command, depth, &executor);
+            }
+            if let (Some(log), true) = (&mut log, draws) {
+                let painter = executor.painter();
+                log.push(steps, painter.x, painter.y, painter.color);
+            }
+            if let (Some(tweener), true) = (&mut tweener, draws) {
+                let painter = executor.painter();
+                tweener.draw(painter.x, painter.y, painter.color);
             }
+            let capture = match (args.every, args.frame_every_pixels) {
+                (Some(every), _) => (steps % every == 0).then_some(args.interval),
+                (None, Some(pixels)) if draws => {
+                    drawn += 1;
+                    (drawn % pixels == 0).then_some(args.interval)
+                }
+                (None, Some(_)) => None,
+                (None, None) => command.and_then(|c| clock.tick(c)),
+            };
+            let in_scene = scene.is_none_or(|s| offset >= s.start);
+            if let (Some(delay), true, true) = (capture, animation, in_scene) {
+                match &mut tweener {
+                    Some(tweener) => {
+                        let delay = delay / (args.tween as u32 + 1);
+                        for frame in tweener.capture() {
+                            if let Some(timeline) = &mut timeline {
+                                timeline.push(&frame, steps, delay, index, offset, command);
+                            }
+                            frames.push_frame(frame, delay);
+                            painters.push(*executor.painter());
+                        }
+                    }
+                    None => {
+                        if let Some(timeline) = &mut timeline {
+                            let frame = executor.buffer();
+                            timeline.push(frame, steps, delay, index, offset, command);
+                        }
+                        frames.push_frame(executor.buffer().clone(), delay);
+                        painters.push(*executor.painter());
+                    }
+                }
+            }
+        }
+        executor.warnings().iter().for_each(|w| report!("{}", w));
+        painter = *executor.painter();
+        if interrupted() {
+            break;
         }
     }
+    painters.push(painter);
+    if let (Some(path), Some(log)) = (&args.pixel_log, &log) {
+        save_pixel_log(path, log);
+    }
+    if let (Some(path), Some(trace)) = (&args.trace, &trace) {
+        let file = std::io::BufWriter::new(File::create(path).expect("Failed to create file"));
+        trace
+            .write(file, args.trace_format)
+            .expect("Failed to save trace");
+    }
 
-    if animation {
-        save_gif_animation(&frames, &args.output, args.interval);
+    (buffer, frames, painters, timeline)
+}
+
+/// Runs the programs at the same time on a shared canvas, capturing a frame every
+/// `interval` milliseconds of wall time.
+fn execute_concurrent(
+    args: &RenderArgs,
+    programs: &[String],
+    animation: bool,
+) -> (CFRBuffer, Animation, Vec<CFRPainter>, Option<Timeline>) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    if args.dialect != Dialect::Standard {
+        report!("Concurrent programs always use the standard dialect");
+    }
+    if args.pixel_log.is_some() {
+        report!("Pixel logs are only written for sequential programs");
+    }
+    if args.timeline.is_some() {
+        report!("Timelines are only written for sequential programs");
+    }
+    if args.trace.is_some() {
+        report!("Traces are only written for sequential programs");
+    }
+    if args.fast_forward {
+        report!("Fast-forwarding is only supported for sequential programs");
+    }
+    if args.lenient || args.max_depth.is_some() {
+        report!("Concurrent programs always run in strict mode");
+    }
+    if args.every.is_some() {
+        report!("Frames of concurrent programs are only captured by time");
+    }
+    if args.frame_every_pixels.is_some() {
+        report!("Concurrent programs are captured every interval of wall time");
+    }
+    if args.timing.color + args.timing.forward + args.timing.rotate > 0 {
+        report!("Only sleeps take time in concurrent programs");
+    }
+    if args.comments {
+        report!("Concurrent programs never skip comments");
+    }
+    let canvas = SharedCanvas::new(width, height, args.canvas.background);
+    // Only animations need the programs to take their time.
+    let sleep = Duration::from_millis(if animation {
+        args.timing.sleep as u64
     } else {
-        if extension == "jpg" {
+        0
+    });
+    let interval = Duration::from_millis(args.interval as u64);
+
+    let done = AtomicBool::new(false);
+    let (results, frames) = std::thread::scope(|s| {
+        let capture = s.spawn(|| {
+            let mut frames = Animation::new();
+            while animation && !done.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                frames.push_frame(canvas.snapshot(), args.interval);
+            }
+            frames
+        });
+        let results = run_concurrent_until(&canvas, programs, sleep, &INTERRUPTED);
+        done.store(true, Ordering::Relaxed);
+        (results, capture.join().expect("Capture thread panicked"))
+    });
+    for (i, result) in results.iter().enumerate() {
+        match result {
+            Ok(()) => {}
+            Err("Interrupted") => report!("Program {} stopped", i + 1),
+            Err(e) => report!("Program {} failed: {}", i + 1, e),
+        }
+    }
+
+    (canvas.snapshot(), frames, Vec::new(), None)
+}
+
+fn save_pixel_log(path: &Path, log: &PixelLog) {
+    let file = std::io::BufWriter::new(File::create(path).expect("Failed to create file"));
+    let result = if format_of(path) == "jsonl" {
+        log.write_jsonl(file)
+    } else {
+        log.write_csv(file)
+    };
+    result.expect("Failed to save pixel log");
+}
+
+/// Encodes the frames with `encoder` and saves them.
+fn save_encoded(output: &Path, encoder: &mut dyn Encoder, animation: &Animation) {
+    match encode_frames(encoder, &animation.frames, &animation.delays) {
+        Ok(bytes) => std::fs::write(output, bytes).expect("Failed to save animation"),
+        Err(e) => {
+            report!("{}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Saves a GIF or still image with the filters applied to every frame.
+fn save_filtered(
+    output: &Path,
+    format: &str,
+    buffer: &CFRBuffer,
+    animation: &Animation,
+    filters: &[Filter],
+    options: &GifOptions,
+) {
+    let filtered = |frame: &CFRBuffer| {
+        let mut rgba = frame.to_rgba_bytes();
+        for filter in filters {
+            filter.apply(&mut rgba, frame.width, frame.height);
+        }
+        rgba
+    };
+    if format == "gif" {
+        let frames: Vec<Vec<u8>> = animation.frames.iter().map(filtered).collect();
+        let file = std::io::BufWriter::new(File::create(output).expect("Failed to create file"));
+        write_rgba_gif(
+            file,
+            buffer.width,
+            buffer.height,
+            &frames,
+            &animation.delays,
+            options,
+        )
+        .expect("Failed to save animation");
+        return;
+    }
+    if is_animation(format) {
+        report!("Filters can only be applied to GIF animations and still images");
+        std::process::exit(2);
+    }
+    let img = image::RgbaImage::from_raw(buffer.width, buffer.height, filtered(buffer))
+        .expect("Filtered frame has the size of the canvas");
+    let result = match format {
+        "jpg" | "jpeg" => image::DynamicImage::ImageRgba8(img).to_rgb8().save(output),
+        _ => img.save(output),
+    };
+    result.expect("Failed to save image");
+}
+
+/// Saves a GIF or still image with the painter arrow on top of every frame.
+fn save_with_arrows(
+    output: &Path,
+    format: &str,
+    buffer: &CFRBuffer,
+    animation: &Animation,
+    painters: &[CFRPainter],
+    options: &GifOptions,
+) {
+    match format {
+        "gif" => {
+            let frames: Vec<Vec<u8>> = animation
+                .frames
+                .iter()
+                .zip(painters)
+                .map(|(frame, painter)| arrow_indices(frame, painter))
+                .collect();
+            let file = File::create(output).expect("Failed to create file");
+            write_indexed_gif(
+                file,
+                buffer.width,
+                buffer.height,
+                &arrow_palette(),
+                &frames,
+                options,
+            )
+            .expect("Failed to save animation");
+        }
+        format if is_animation(format) => {
+            report!("The painter arrow can only be drawn on GIF animations and still images");
+            std::process::exit(2);
+        }
+        _ => {
+            let painter = painters.last().expect("There is a painter at the end");
+            let image = image::RgbaImage::from_raw(
+                buffer.width,
+                buffer.height,
+                arrow_rgba(buffer, painter),
+            )
+            .expect("Image has the size of the canvas");
+            // Convert to RGB, which every format supports.
+            let image = image::DynamicImage::ImageRgba8(image).to_rgb8();
+            let result = match image::ImageFormat::from_extension(format) {
+                Some(image_format) => image.save_with_format(output, image_format),
+                None => image.save(output),
+            };
+            result.expect("Failed to save image");
+        }
+    }
+}
+
+fn save_tiles(output: &Path, buffer: &CFRBuffer, tile_size: u32) {
+    let tile_size = tile_size.max(1);
+    let descriptor = cfrs::tiles::dzi_descriptor(buffer.width, buffer.height, tile_size, "png");
+    std::fs::write(output, descriptor).expect("Failed to save descriptor");
+
+    let name = output
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("tiles");
+    let directory = output.with_file_name(format!("{}_files", name));
+    for tile in buffer.export_tiles(tile_size) {
+        let path = directory.join(format!("{}.png", tile.path()));
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create directory");
+        let png = encode_png(&tile.buffer).expect("Failed to encode tile");
+        std::fs::write(path, png).expect("Failed to save tile");
+    }
+}
+
+fn save_masks(output: &Path, format: &str, buffer: &CFRBuffer, png_indexed: bool) {
+    // Masks are still images, even when the output is an animation.
+    let format = if is_animation(format) { "png" } else { format };
+    let name = output
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("mask");
+    for color in CFRColor::ALL {
+        let path = output.with_file_name(format!(
+            "{}_{}.{}",
+            name,
+            color.to_string().to_lowercase(),
+            format
+        ));
+        let mask = buffer.mask(color);
+        save(
+            &path,
+            format,
+            &mask,
+            &Animation::new(),
+            None,
+            &GifOptions::default(),
+            png_indexed,
+        );
+    }
+}
+
+/// Parses a pixel position like `10,20`.
+fn parse_point(s: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid position: {}", s);
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    Ok((
+        x.trim().parse().map_err(|_| invalid())?,
+        y.trim().parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Parses a grid size like `3x3`.
+fn parse_grid(s: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid grid: {}", s);
+    let (columns, rows) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let columns: u32 = columns.parse().map_err(|_| invalid())?;
+    let rows: u32 = rows.parse().map_err(|_| invalid())?;
+    if columns == 0 || rows == 0 {
+        return Err(invalid());
+    }
+    Ok((columns, rows))
+}
+
+/// Parses a supersampling factor like `4x` or `4`, from 2 to 16.
+fn parse_factor(s: &str) -> Result<u32, String> {
+    match s.strip_suffix(['x', 'X']).unwrap_or(s).parse() {
+        Ok(factor @ 2..=16) => Ok(factor),
+        _ => Err(format!("Invalid factor: {} (expected 2x to 16x)", s)),
+    }
+}
+
+/// Parses a range of byte offsets like `120..450`.
+fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("Invalid range: {}", s))?;
+    let start = start.parse().map_err(|_| format!("Invalid range: {}", s))?;
+    let end = end.parse().map_err(|_| format!("Invalid range: {}", s))?;
+    if start > end {
+        return Err(format!("Invalid range: {}", s));
+    }
+    Ok(start..end)
+}
+
+/// Returns the extension of the output path, which selects the output format.
+fn format_of(output: &Path) -> &str {
+    output
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or_default()
+}
+
+fn is_animation(format: &str) -> bool {
+    matches!(format, "json" | "cfrsdelta" | "html-scrub")
+        || EncoderRegistry::builtin().contains(format)
+}
+
+fn save(
+    output: &Path,
+    format: &str,
+    buffer: &CFRBuffer,
+    animation: &Animation,
+    timeline: Option<&Timeline>,
+    options: &GifOptions,
+    png_indexed: bool,
+) {
+    let encoder_options = EncoderOptions {
+        loop_count: options.loop_count,
+        disposal: options.disposal,
+        optimize: options.optimize,
+        ..Default::default()
+    };
+    if let Some(mut encoder) = EncoderRegistry::builtin().get(format, &encoder_options) {
+        save_encoded(output, encoder.as_mut(), animation);
+        return;
+    }
+    match format {
+        "json" | "cfrsdelta" | "html-scrub" => {
+            let frames = &animation.frames;
+            let mut delta = DeltaAnimation::new(frames.first().unwrap_or(buffer), options.delay);
+            frames.iter().skip(1).for_each(|frame| delta.push(frame));
+            if format == "html-scrub" {
+                let page = match timeline {
+                    Some(timeline) => {
+                        cfrs::html::to_scrubber_with_timeline(&delta, timeline, "CFRS[]")
+                    }
+                    None => cfrs::html::to_scrubber(&delta, "CFRS[]"),
+                };
+                std::fs::write(output, page).expect("Failed to save page");
+                return;
+            }
+            let file =
+                std::io::BufWriter::new(File::create(output).expect("Failed to create file"));
+            let result = if format == "json" {
+                delta.write_json(file)
+            } else {
+                delta.write_binary(file)
+            };
+            result.expect("Failed to save animation");
+        }
+        "bmp" | "ff" | "pgm" => {
+            let file =
+                std::io::BufWriter::new(File::create(output).expect("Failed to create file"));
+            let result = match format {
+                "bmp" => cfrs::bitmap::write_bmp(file, buffer),
+                "ff" => cfrs::bitmap::write_farbfeld(file, buffer),
+                _ => cfrs::bitmap::write_pgm(file, buffer),
+            };
+            result.expect("Failed to save image");
+        }
+        "png" if png_indexed => {
+            let png = encode_png(buffer).expect("Failed to encode image");
+            std::fs::write(output, png).expect("Failed to save image");
+        }
+        "jpg" | "jpeg" => {
             let img = buffer.to_rgb_image();
-            img.save(args.output).expect("Failed to save image");
-        } else {
+            img.save_with_format(output, image::ImageFormat::Jpeg)
+                .expect("Failed to save image");
+        }
+        _ => {
             let img = buffer.to_rgba_image();
-            img.save(args.output).expect("Failed to save image");
+            let result = match image::ImageFormat::from_extension(format) {
+                Some(image_format) => img.save_with_format(output, image_format),
+                None => img.save(output),
+            };
+            result.expect("Failed to save image");
+        }
+    }
+}
+
+const REPL_HELP: &str = "\
+Enter CFRS commands to append them to the program. Meta-commands:
+  :reset          start over with an empty program
+  :undo           remove the last entered line
+  :save <path>    save the canvas as an image
+  :export <path>  save the program entered so far
+  :pan <dx> <dy>  move the preview by this many canvas pixels
+  :zoom <n>       zoom the preview n times, or `in` or `out`
+  :quit           exit";
+
+fn repl(args: ReplArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let new_executor = || CommandExecutor::new(String::new(), args.canvas.buffer(DEFAULT_SIZE));
+    let replay = |lines: &[String]| {
+        let mut executor = new_executor();
+        for line in lines {
+            executor.push_commands(line);
+            executor.push_commands("\n");
+        }
+        executor.run().map(|_| executor)
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut executor = new_executor();
+    let mut viewport = args.viewport.unwrap_or_default();
+    let color_mode = args.color_mode.unwrap_or_else(ColorMode::detect);
+    println!("{}", REPL_HELP);
+
+    loop {
+        print!("cfrs> ");
+        std::io::stdout().flush().expect("Failed to flush stdout");
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            break;
+        }
+        let input = input.trim();
+        let (meta, argument) = input.split_once(' ').unwrap_or((input, ""));
+        let argument = argument.trim();
+
+        let message = match meta {
+            "" => continue,
+            ":quit" | ":q" => break,
+            ":help" => REPL_HELP.to_string(),
+            ":reset" => {
+                lines.clear();
+                executor = new_executor();
+                "Reset".to_string()
+            }
+            ":undo" => match lines.pop() {
+                Some(line) => {
+                    executor = replay(&lines).expect("Previous lines always run");
+                    format!("Removed `{}`", line)
+                }
+                None => "Nothing to undo".to_string(),
+            },
+            ":pan" => {
+                let delta = argument.split_once(' ').and_then(|(dx, dy)| {
+                    dx.trim()
+                        .parse::<i64>()
+                        .ok()
+                        .zip(dy.trim().parse::<i64>().ok())
+                });
+                match delta {
+                    Some((dx, dy)) => {
+                        viewport.pan(dx, dy, width, height);
+                        format!("Viewport at {:?}", viewport.offset)
+                    }
+                    None => "Usage: :pan <dx> <dy>".to_string(),
+                }
+            }
+            ":zoom" => {
+                match argument {
+                    "in" => viewport.zoom_in(width, height),
+                    "out" => viewport.zoom_out(width, height),
+                    _ => {
+                        if let Ok(zoom) = argument.parse() {
+                            viewport.set_zoom(zoom, width, height);
+                        }
+                    }
+                }
+                format!("Zoom {}x at {:?}", viewport.zoom, viewport.offset)
+            }
+            ":save" if !argument.is_empty() => {
+                let img = viewport.apply(executor.buffer()).to_rgba_image();
+                match img.save(argument) {
+                    Ok(()) => format!("Saved canvas to {}", argument),
+                    Err(e) => format!("Failed to save canvas: {}", e),
+                }
+            }
+            ":export" if !argument.is_empty() => {
+                let program = lines.iter().map(|l| format!("{}\n", l)).collect::<String>();
+                match std::fs::write(argument, program) {
+                    Ok(()) => format!("Exported program to {}", argument),
+                    Err(e) => format!("Failed to export program: {}", e),
+                }
+            }
+            meta if meta.starts_with(':') => format!("Unknown or incomplete command: {}", input),
+            _ => {
+                lines.push(input.to_string());
+                executor.push_commands(input);
+                executor.push_commands("\n");
+                match run_line(&mut executor, args.max_steps) {
+                    Ok(()) => String::new(),
+                    Err(e) => {
+                        lines.pop();
+                        executor = replay(&lines).expect("Previous lines always run");
+                        format!("{}; line discarded", e)
+                    }
+                }
+            }
+        };
+
+        let rows = args.columns / 2;
+        print!(
+            "\x1b[2J\x1b[H{}",
+            cfrs::terminal::to_ansi_with_mode(
+                &viewport.apply(executor.buffer()),
+                args.columns,
+                rows,
+                color_mode
+            )
+        );
+        let (x, y) = executor.position();
+        println!(
+            "painter at ({}, {}) facing {}, color {}",
+            x,
+            y,
+            executor.painter().direction,
+            executor.painter().color
+        );
+        if !message.is_empty() {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Runs the commands added since the last call, or fails after `max_steps` commands.
+fn run_line(executor: &mut CommandExecutor<CFRBuffer>, max_steps: u64) -> Result<(), String> {
+    for _ in 0..=max_steps {
+        match executor.step() {
+            Ok(_) => {}
+            Err("End of commands") => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Err(format!("Line did not finish within {} steps", max_steps))
+}
+
+fn read_recording(path: &Path) -> Recording {
+    let file = File::open(path).expect("Failed to open recording");
+    Recording::read(std::io::BufReader::new(file)).expect("Failed to read recording")
+}
+
+fn record(args: RecordArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let (header, mut executor, mut steps, mut writer) = if args.resume {
+        let recording = read_recording(&args.recording);
+        if recording.is_complete() {
+            println!("{} is already complete", args.recording.display());
+            return;
+        }
+        let checkpoint = recording.checkpoints.last();
+        let (executor, steps) = match checkpoint {
+            Some(checkpoint) => (checkpoint.restore(), checkpoint.steps),
+            None => (recording.header.executor(), 0),
+        };
+        // Rewrite the file so that a record cut off by the crash does not hide new records.
+        let file = File::create(&args.recording).expect("Failed to create recording");
+        let mut writer = RecordingWriter::new(std::io::BufWriter::new(file), &recording.header)
+            .expect("Failed to write recording");
+        for checkpoint in &recording.checkpoints {
+            writer
+                .checkpoint(checkpoint)
+                .expect("Failed to write recording");
+        }
+        println!("Resuming after {} steps", steps);
+        (recording.header, executor, steps, writer)
+    } else {
+        let header = RecordingHeader {
+            program: args.command.expect("command is required"),
+            width,
+            height,
+            background: args.canvas.background,
+            interval: args.interval,
+        };
+        let file = File::create(&args.recording).expect("Failed to create recording");
+        let writer = RecordingWriter::new(std::io::BufWriter::new(file), &header)
+            .expect("Failed to write recording");
+        (header.clone(), header.executor(), 0, writer)
+    };
+
+    let checkpoint_every = args.checkpoint_every.max(1);
+    let result = loop {
+        if let Err(e) = executor.step() {
+            break e;
+        }
+        steps += 1;
+        if steps % checkpoint_every == 0 {
+            writer
+                .checkpoint(&Checkpoint::capture(&executor, steps))
+                .expect("Failed to write recording");
+        }
+    };
+    if steps % checkpoint_every != 0 {
+        writer
+            .checkpoint(&Checkpoint::capture(&executor, steps))
+            .expect("Failed to write recording");
+    }
+    writer.finish(steps).expect("Failed to write recording");
+
+    if result != "End of commands" {
+        report!("Program stopped: {}", result);
+    }
+    println!(
+        "Recorded {} steps of a {}×{} canvas",
+        steps, header.width, header.height
+    );
+}
+
+fn replay(args: ReplayArgs) {
+    let recording = read_recording(&args.recording);
+    let header = &recording.header;
+    let animation = is_animation(format_of(&args.output));
+    let start = match recording.checkpoints.last() {
+        Some(checkpoint) if args.from_checkpoint => checkpoint.clone(),
+        _ => Checkpoint::capture(&header.executor(), 0),
+    };
+
+    let mut executor = start.restore();
+    let mut steps = start.steps;
+    let mut checkpoints = recording
+        .checkpoints
+        .iter()
+        .filter(move |c| c.steps > steps);
+    let mut next = checkpoints.next();
+    let mut clock = FrameClock::new(Timing::default(), header.interval);
+    let mut frames = Animation::new();
+    loop {
+        let command = executor.view().next_command();
+        let Ok((_, buf)) = executor.step() else {
+            break;
+        };
+        steps += 1;
+        if let (true, Some(delay)) = (animation, command.and_then(|c| clock.tick(c))) {
+            frames.push_frame(buf.clone(), delay);
+        }
+        if let Some(checkpoint) = next.filter(|c| c.steps == steps) {
+            if !checkpoint.matches(&executor) {
+                report!("Replay diverges from the recording at step {}", steps);
+                std::process::exit(1);
+            }
+            next = checkpoints.next();
+        }
+    }
+
+    if let Some(total_steps) = recording.total_steps {
+        if total_steps != steps {
+            report!(
+                "Replay ran {} steps, but the recording ran {}",
+                steps,
+                total_steps
+            );
+            std::process::exit(1);
+        }
+    }
+    let options = GifOptions {
+        delay: header.interval,
+        ..Default::default()
+    };
+    save(
+        &args.output,
+        format_of(&args.output),
+        executor.buffer(),
+        &frames,
+        None,
+        &options,
+        true,
+    );
+    println!("Replayed {} steps", steps);
+}
+
+fn conformance() {
+    let outcomes = cfrs::conformance::run_all();
+    for outcome in &outcomes {
+        let status = if outcome.passed() { "ok" } else { "FAILED" };
+        println!("{} ... {}", outcome.vector.name, status);
+        if !outcome.passed() {
+            println!(
+                "  expected {:x?}, got {:x?}",
+                outcome.vector.expected, outcome.actual
+            );
+        }
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed()).count();
+    println!("{} passed, {} failed", outcomes.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn doctor() {
+    let report = cfrs::doctor::Report::collect();
+    print!("{}", report);
+    if !report.healthy() {
+        std::process::exit(1);
+    }
+}
+
+fn verify(args: VerifyArgs) {
+    let CanvasSize { width, height } = args.size.resolve(CanvasSize::new(64, 64));
+    let program = std::fs::read(&args.program).expect("Failed to read program");
+    let limits = cfrs::ResourceLimits {
+        max_steps: args.max_steps,
+        ..Default::default()
+    };
+    let Some(expected) = args.expect_digest else {
+        match cfrs::run_untrusted(&program, width, height, &limits) {
+            Ok(canvas) => println!("{}", cfrs::verify::Digest::sha256(&canvas)),
+            Err(err) => {
+                report!("{}: {}", args.program.display(), err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    };
+    let verification = cfrs::verify::verify(&program, width, height, &expected, &limits);
+    match &verification.actual {
+        Ok(actual) if verification.passed() => println!("ok {}", actual),
+        Ok(actual) => {
+            report!("expected {}, got {}", expected, actual);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            report!("{}: {}", args.program.display(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn stats(args: StatsArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    if args.analyze {
+        analyze(&args);
+        return;
+    }
+    let options = cfrs::stats::StatsOptions {
+        width,
+        height,
+        background: args.canvas.background,
+        max_steps: args.max_steps,
+        max_wraps: args.max_wraps,
+        coverage: args.coverage,
+        profile: args.profile,
+        comments: args.comments,
+    };
+    let stats = cfrs::stats::collect(&args.command, &options);
+
+    println!("steps: {}", stats.steps);
+    println!("sleeps: {}", stats.sleeps);
+    println!("painted pixels: {}", stats.painted);
+    println!("colors: {}", stats.colors);
+    println!(
+        "final position: ({}, {})",
+        stats.position.0, stats.position.1
+    );
+    println!(
+        "wraps: {} (left {}, right {}, top {}, bottom {})",
+        stats.wraps.total(),
+        stats.wraps.left,
+        stats.wraps.right,
+        stats.wraps.top,
+        stats.wraps.bottom
+    );
+    if let Some(error) = stats.error {
+        println!("stopped: {}", error);
+    }
+    for scene in &stats.scenes {
+        println!(
+            "scene {}: {} steps, {} sleeps",
+            scene.name, scene.steps, scene.sleeps
+        );
+    }
+    if let Some(counts) = &stats.coverage {
+        let (executed, total) = cfrs::stats::coverage_summary(&args.command, counts, args.comments);
+        println!("coverage: {} of {} commands executed\n", executed, total);
+        let ansi = std::io::IsTerminal::is_terminal(&std::io::stdout());
+        print!(
+            "{}",
+            cfrs::stats::annotate_coverage(&args.command, counts, args.comments, ansi)
+        );
+    }
+    if let Some(profile) = &stats.profile {
+        print!("\n{}", cfrs::stats::format_profile(&args.command, profile));
+    }
+    if !args.watch.is_empty() {
+        println!();
+        watch(&args);
+    }
+}
+
+fn watch(args: &StatsArgs) {
+    let mut buffer = args.canvas.buffer(DEFAULT_SIZE);
+    let mut executor = CommandExecutor::new(args.command.clone(), &mut buffer);
+    // Stepped by hand rather than with `run_to_watchpoint` to stop after `max_steps`.
+    for _ in 0..args.max_steps {
+        let view = executor.view();
+        let (index, draws) = (view.index, view.next_command() == Some('F'));
+        if executor.step().is_err() {
+            break;
+        }
+        let painter = executor.painter();
+        if draws && args.watch.contains(&(painter.x, painter.y)) {
+            println!(
+                "({}, {}) painted {} by the command at {}",
+                painter.x, painter.y, painter.color, index
+            );
+        }
+    }
+}
+
+fn lint(args: LintArgs) {
+    if args.fix {
+        println!("{}", cfrs::lint::fix(&args.command, args.comments));
+        return;
+    }
+    for lint in cfrs::lint::lint(&args.command, args.comments) {
+        let fixable = if lint.fix.is_some() { " (fixable)" } else { "" };
+        println!("{}", localize(&format!("{}{}", lint.diagnostic, fixable)));
+    }
+}
+
+fn expand(args: ExpandArgs) {
+    let template = std::fs::read_to_string(&args.template).expect("Failed to read template");
+    match cfrs::preprocess::expand(&template) {
+        Ok(program) => print!("{}", program),
+        Err(err) => {
+            report!("{}: {}", args.template.display(), err);
+            std::process::exit(1);
         }
     }
 }
 
-fn save_gif_animation(frames: &Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>, path: &PathBuf, interval: u32) {
-    let mut file = File::create(path).unwrap();
-    let mut encoder = GifEncoder::new(&mut file);
-    encoder.set_repeat(Repeat::Infinite).unwrap();
-    for frame_data in frames {
-        let frame = Frame::from_parts(
-            frame_data.clone(),
-            0,
-            0,
-            Delay::from_numer_denom_ms(interval, 1),
+fn eval(args: EvalArgs) {
+    let mut buffer = args.canvas.buffer(CanvasSize::new(32, 32));
+    let mut executor = CommandExecutor::new(args.command, &mut buffer);
+    executor.set_max_depth(args.max_depth);
+    executor.set_lenient(args.lenient);
+    executor.set_comments(args.comments);
+    let mut error = None;
+    for _ in 0..args.steps {
+        match executor.step() {
+            Ok(_) => {}
+            Err("End of commands") => break,
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+    if executor.view().next_command().is_some() && error.is_none() {
+        report!("Stopped after {} steps", args.steps);
+    }
+    executor.warnings().iter().for_each(|w| report!("{}", w));
+
+    if args.ascii {
+        print!(
+            "{}",
+            cfrs::terminal::to_ascii(&buffer, args.canvas.background)
         );
-        encoder.encode_frame(frame).unwrap();
+    } else {
+        let rows = buffer.height.div_ceil(2);
+        let mode = args.color_mode.unwrap_or_else(ColorMode::detect);
+        print!(
+            "{}",
+            cfrs::terminal::to_ansi_with_mode(&buffer, buffer.width, rows, mode)
+        );
+    }
+    if let Some(e) = error {
+        report!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn extract(args: ExtractArgs) {
+    let bytes = std::fs::read(&args.image).expect("Failed to read image");
+    let Some(metadata) = cfrs::metadata::extract(&bytes) else {
+        report!("{} has no CFRS metadata", args.image.display());
+        std::process::exit(1);
+    };
+    if args.all {
+        print!("{}", metadata.to_text());
+    } else {
+        for program in &metadata.programs {
+            println!("{}", program);
+        }
+    }
+}
+
+fn import(args: ImportArgs) {
+    let buffer = load_image(&args.image);
+    buffer.save(&args.output).expect("Failed to save canvas");
+}
+
+/// Reads an image as a canvas, using the nearest color for every pixel.
+fn load_image(path: &Path) -> CFRBuffer {
+    let image = image::open(path).expect("Failed to open image").to_rgb8();
+    let mut buffer = CFRBuffer::new(image.width(), image.height());
+    for (color, pixel) in buffer.data.iter_mut().zip(image.pixels()) {
+        *color = CFRColor::nearest(pixel.0);
+    }
+    buffer
+}
+
+fn cat(args: CatArgs) {
+    let parts: Vec<String> = args
+        .programs
+        .iter()
+        .map(|path| std::fs::read_to_string(path).expect("Failed to read program"))
+        .collect();
+    let options = ConcatOptions {
+        separator: args.separator,
+        reset: args.reset,
+    };
+    let program = cfrs::concat::concat(&parts, &options).unwrap_or_else(|err| {
+        report!("{}: {}", args.programs[err.part].display(), err.diagnostic);
+        std::process::exit(1);
+    });
+    match args.output {
+        Some(path) => std::fs::write(path, program).expect("Failed to write program"),
+        None => print!("{}", program),
+    }
+}
+
+fn convert(args: ConvertArgs) {
+    let program = match args.from {
+        TextFormat::Cfrs => args.command,
+        TextFormat::Rle => cfrs::rle::decode(&args.command).unwrap_or_else(|err| {
+            report!("{}", err);
+            std::process::exit(1);
+        }),
+    };
+    match args.to {
+        TextFormat::Cfrs => println!("{}", program),
+        TextFormat::Rle => println!("{}", cfrs::rle::encode(&program, args.comments)),
+    }
+}
+
+fn challenge(args: ChallengeArgs) {
+    let challenge = match format_of(&args.challenge) {
+        "png" | "gif" | "jpg" | "jpeg" | "bmp" | "webp" => {
+            Challenge::Target(load_image(&args.challenge))
+        }
+        _ => {
+            let text = std::fs::read_to_string(&args.challenge).expect("Failed to read maze");
+            Challenge::Maze(text.parse().unwrap_or_else(|e| {
+                report!("{}", e);
+                std::process::exit(2);
+            }))
+        }
+    };
+    let program = std::fs::read_to_string(&args.program).expect("Failed to read program");
+    let score = challenge.run(&program, args.max_steps);
+
+    if args.json {
+        println!("{}", score.to_json());
+    } else {
+        if let Some(matched) = score.matched {
+            println!("matched pixels: {:.2}%", matched);
+        }
+        if let Some(reached) = score.reached_goal {
+            println!("reached goal: {}", if reached { "yes" } else { "no" });
+        }
+        if let Some((x, y)) = score.wall_hit {
+            println!("hit a wall at ({}, {})", x, y);
+        }
+        println!("steps: {}", score.steps);
+        if let Some(e) = score.error {
+            println!("error: {}", e);
+        }
+        println!("{}", if score.passed() { "passed" } else { "failed" });
+    }
+    if !score.passed() {
+        std::process::exit(1);
+    }
+}
+
+fn render_source(args: RenderSourceArgs) {
+    let program = std::fs::read_to_string(&args.program).expect("Failed to read program");
+    let options = cfrs::stats::StatsOptions {
+        max_steps: args.max_steps,
+        coverage: true,
+        comments: args.comments,
+        ..Default::default()
+    };
+    let stats = cfrs::stats::collect(&program, &options);
+    if let Some(error) = stats.error {
+        report!("{}: {}", args.program.display(), error);
+    }
+    let counts = stats.coverage.unwrap_or_default();
+    if format_of(&args.output) == "html" {
+        let title = args.program.display().to_string();
+        let page = cfrs::source::to_html(&program, &counts, args.comments, &title);
+        std::fs::write(&args.output, page).expect("Failed to save page");
+        return;
     }
+    let image = cfrs::source::to_image(&program, &counts, args.comments, args.scale);
+    image::RgbaImage::from_raw(image.width, image.height, image.rgba)
+        .expect("The source image has its own size")
+        .save(&args.output)
+        .expect("Failed to save image");
+}
+
+fn badge(args: BadgeArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let program = std::fs::read_to_string(&args.program).expect("Failed to read program");
+    let options = cfrs::stats::StatsOptions {
+        width,
+        height,
+        background: args.canvas.background,
+        ..Default::default()
+    };
+    let svg = cfrs::badge::Badge::new(&program, &options).to_svg();
+    match &args.output {
+        Some(path) => std::fs::write(path, svg).expect("Failed to save badge"),
+        None => print!("{}", svg),
+    }
+}
+
+fn gallery(args: GalleryArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    std::fs::create_dir_all(&args.output).expect("Failed to create directory");
+    let options = cfrs::stats::StatsOptions {
+        width,
+        height,
+        background: args.canvas.background,
+        max_steps: args.max_steps,
+        ..Default::default()
+    };
+    let gif_options = GifOptions {
+        delay: args.interval,
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+    for path in &args.programs {
+        let program = std::fs::read_to_string(path).expect("Failed to read program");
+        let name = path
+            .file_stem()
+            .and_then(std::ffi::OsStr::to_str)
+            .unwrap_or("program");
+        let stats = cfrs::stats::collect(&program, &options);
+        let mut entry = GalleryEntry {
+            program: path.clone(),
+            image: PathBuf::from(format!("{}.{}", name, args.format)),
+            thumbnail: None,
+            width,
+            height,
+            steps: stats.steps,
+            sleeps: stats.sleeps,
+            frames: 0,
+            painted: stats.painted,
+            colors: stats.colors,
+            error: stats.error.map(str::to_string),
+        };
+        if stats.error == Some("Step limit exceeded") {
+            report!("{}: skipped after {} steps", path.display(), stats.steps);
+            entries.push(entry);
+            continue;
+        }
+
+        let mut buffer = args.canvas.buffer(DEFAULT_SIZE);
+        let mut executor = CommandExecutor::new(program, &mut buffer);
+        let mut animation = if args.format == "gif" {
+            // A failing program keeps the frames it drew; the error is in the stats.
+            let mut animation = Animation::new();
+            let mut clock = FrameClock::new(Timing::default(), args.interval);
+            loop {
+                let command = executor.view().next_command();
+                let Ok((_, canvas)) = executor.step() else {
+                    break;
+                };
+                if let Some(delay) = command.and_then(|c| clock.tick(c)) {
+                    animation.push_frame(canvas.clone(), delay);
+                }
+            }
+            animation
+        } else {
+            let _ = executor.run();
+            Animation::new()
+        };
+        if animation.frames.last().map(|f| &f.data) != Some(&buffer.data) {
+            animation.push_frame(buffer.clone(), args.interval);
+        }
+        entry.frames = animation.frames.len();
+        let format = args.format.as_str();
+        save(
+            &args.output.join(&entry.image),
+            format,
+            &buffer,
+            &animation,
+            None,
+            &gif_options,
+            true,
+        );
+        if let Some(size) = args.thumbnails {
+            let thumbnail = PathBuf::from(format!("{}_thumb.png", name));
+            let image = buffer.thumbnail(size);
+            let png = encode_png(&image).expect("Failed to encode thumbnail");
+            std::fs::write(args.output.join(&thumbnail), png).expect("Failed to save thumbnail");
+            entry.thumbnail = Some(thumbnail);
+        }
+        if let Some(error) = &entry.error {
+            report!("{}: {}", path.display(), error);
+        }
+        entries.push(entry);
+    }
+
+    let index = args.output.join("index.json");
+    let file = std::io::BufWriter::new(File::create(index).expect("Failed to create file"));
+    cfrs::gallery::write_index(file, &entries).expect("Failed to save index");
+}
+
+fn compare(args: CompareArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let render_file = |path: &Path| {
+        let program = std::fs::read_to_string(path).expect("Failed to read program");
+        let mut buffer = args.canvas.buffer(DEFAULT_SIZE);
+        if let Err(e) = CommandExecutor::new(program, &mut buffer).run() {
+            report!("{}: {}", path.display(), e);
+        }
+        buffer
+    };
+    let (first, second) = (render_file(&args.first), render_file(&args.second));
+    let comparison = cfrs::compare::compare(&first, &second);
+
+    println!(
+        "differing pixels: {} ({:.2}%)",
+        comparison.differing,
+        comparison.ratio() * 100.0
+    );
+    if let Some((left, top, right, bottom)) = comparison.bounds {
+        println!(
+            "differing area: ({}, {}) to ({}, {})",
+            left, top, right, bottom
+        );
+    }
+    if let Some(path) = &args.diff_image {
+        let bytes = cfrs::compare::diff_image(&first, &second);
+        image::RgbaImage::from_raw(width, height, bytes)
+            .expect("Diff image has the size of the canvas")
+            .save(path)
+            .expect("Failed to save image");
+    }
+    if !comparison.is_identical() {
+        std::process::exit(1);
+    }
+}
+
+fn race(args: RaceArgs) {
+    let mut executors: Vec<CommandExecutor<CFRBuffer>> = args
+        .programs
+        .iter()
+        .map(|path| {
+            let program = std::fs::read_to_string(path).expect("Failed to read program");
+            CommandExecutor::new(program, args.canvas.buffer(DEFAULT_SIZE))
+        })
+        .collect();
+    let (animation, finishes) = cfrs::race::race(
+        &mut executors,
+        args.steps_per_frame,
+        args.interval,
+        args.divider,
+    );
+    for (path, finish) in args.programs.iter().zip(&finishes) {
+        match finish.error {
+            Some(e) => println!(
+                "{}: failed after {} steps: {}",
+                path.display(),
+                finish.steps,
+                e
+            ),
+            None => println!("{}: finished after {} steps", path.display(), finish.steps),
+        }
+    }
+
+    let options = GifOptions {
+        delay: args.interval,
+        ..Default::default()
+    };
+    let last = animation.frames.last().expect("A race has a final frame");
+    save(
+        &args.output,
+        format_of(&args.output),
+        last,
+        &animation,
+        None,
+        &options,
+        true,
+    );
+}
+
+fn analyze(args: &StatsArgs) {
+    let CanvasSize { width, height } = args.canvas.size.resolve(DEFAULT_SIZE);
+    let analysis = cfrs::analysis::analyze_with(&args.command, args.comments);
+    let count = |n: Option<u64>| n.map_or("more than 2^64".to_string(), |n| n.to_string());
+
+    println!("max depth: {}", analysis.max_depth);
+    println!("blocks: {}", analysis.blocks);
+    println!("steps: {}", count(analysis.steps));
+    println!("sleeps: {}", count(analysis.sleeps));
+    match analysis.first_sleep {
+        Some(steps) => println!("first sleep: after {} steps", steps),
+        None => println!("first sleep: never"),
+    }
+    if let Some(error) = &analysis.error {
+        println!("fails: {}", error);
+    }
+    if let Some(bounds) = analysis.bounds {
+        println!(
+            "bounds: x {}..={}, y {}..={} from the start",
+            bounds.left, bounds.right, bounds.top, bounds.bottom
+        );
+    }
+    println!(
+        "fits {}x{}: {}",
+        width,
+        height,
+        if analysis.fits(width, height) {
+            "yes"
+        } else {
+            "no, the painter wraps around"
+        }
+    );
+}
+
+#[cfg(feature = "server")]
+fn serve_api(args: ServeApiArgs) {
+    let options = cfrs::server::ServerOptions {
+        max_steps: args.max_steps,
+        timeout: std::time::Duration::from_millis(args.timeout),
+        max_canvas_pixels: args.max_pixels,
+        ..Default::default()
+    };
+    println!("Listening on http://{}", args.addr);
+    tokio::runtime::Runtime::new()
+        .expect("Failed to start runtime")
+        .block_on(cfrs::server::serve(args.addr, options))
+        .expect("Server error");
+}
+
+#[cfg(feature = "batch")]
+fn worker(args: WorkerArgs) {
+    let limits = cfrs::ResourceLimits {
+        max_steps: args.max_steps,
+        max_pixels: args.max_pixels,
+        max_wall_time: Some(Duration::from_millis(args.timeout)),
+        max_frames: Some(args.max_frames),
+        ..Default::default()
+    };
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    if let Err(e) = cfrs::batch::run_worker(stdin, stdout, &limits) {
+        report!("Worker stopped: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn gen_program(args: &GenArgs, script: &str, params: Vec<(&str, rhai::Dynamic)>) {
+    use cfrs::entropy::{EntropySource, OsEntropy, Recorder, Replay, SplitMix64};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut recorded = None;
+    let source: Box<dyn EntropySource> = if args.os_entropy {
+        Box::new(OsEntropy::new())
+    } else if let Some(path) = &args.replay_entropy {
+        let log = std::fs::read_to_string(path).expect("Failed to read entropy");
+        recorded = Some(log.split_whitespace().count());
+        Box::new(log.parse::<Replay>().unwrap_or_else(|err| {
+            report!("{}: {}", path.display(), err);
+            std::process::exit(2);
+        }))
+    } else {
+        Box::new(SplitMix64::new(args.seed as u64))
+    };
+    let recorder = Rc::new(RefCell::new(Recorder::new(source)));
+    let result = cfrs::scripting::generate_with(script, params, args.seed, recorder.clone());
+    if let Some(path) = &args.save_entropy {
+        std::fs::write(path, recorder.borrow().log()).expect("Failed to save entropy");
+    }
+    let drawn = recorder.borrow().values.len();
+    if recorded.is_some_and(|recorded| drawn > recorded) {
+        report!("Warning: the script drew more random numbers than were recorded");
+    }
+    match result {
+        Ok(program) => println!("{}", program),
+        Err(err) => {
+            report!("{}: {}", args.script.display(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn gen(args: GenArgs) {
+    let script = std::fs::read_to_string(&args.script).expect("Failed to read script");
+    let params: Vec<(String, rhai::Dynamic)> = args
+        .params
+        .iter()
+        .map(|param| cfrs::scripting::parse_param(param))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|err| {
+            report!("{}", err);
+            std::process::exit(2);
+        });
+    let params: Vec<(&str, rhai::Dynamic)> = params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect();
+    let Some(seeds) = args.seeds.clone() else {
+        gen_program(&args, &script, params);
+        return;
+    };
+
+    let limits = cfrs::ResourceLimits {
+        max_steps: args.max_steps,
+        ..Default::default()
+    };
+    let cells: Vec<(u32, CFRBuffer)> = seeds
+        .map(|seed| {
+            let program = cfrs::scripting::generate_seeded(&script, params.clone(), seed as i64)
+                .unwrap_or_else(|err| {
+                    report!("seed {}: {}", seed, err);
+                    String::new()
+                });
+            let canvas = cfrs::run_untrusted(program.as_bytes(), args.width, args.height, &limits)
+                .unwrap_or_else(|err| {
+                    report!("seed {}: {}", seed, err);
+                    CFRBuffer::new(args.width, args.height)
+                });
+            (seed as u32, canvas)
+        })
+        .collect();
+    let columns = args
+        .columns
+        .unwrap_or_else(|| (cells.len() as f64).sqrt().ceil() as u32);
+    let sheet = cfrs::labels::contact_sheet(&cells, columns, args.scale);
+    let output = args.output.expect("--seeds requires --output");
+    image::RgbaImage::from_raw(sheet.width, sheet.height, sheet.rgba)
+        .expect("The contact sheet has its own size")
+        .save(&output)
+        .expect("Failed to save image");
+}
+
+#[cfg(feature = "lsp")]
+fn lsp(args: LspArgs) {
+    let options = cfrs::lsp::LspOptions {
+        width: args.width,
+        height: args.height,
+        max_steps: args.max_steps,
+        comments: args.comments,
+    };
+    cfrs::lsp::run_stdio(options).expect("Language server error");
 }
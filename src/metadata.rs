@@ -0,0 +1,366 @@
+use crate::enums::{CFRColor, Dialect};
+use std::ops::Range;
+
+/// The keyword of the PNG text chunk, and the first line of the GIF comment, that holds the
+/// metadata.
+pub const KEYWORD: &str = "cfrs";
+
+/// How an image was made, so that it can be rendered again.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::metadata::Metadata;
+///
+/// let metadata = Metadata::new(vec!["[[F]R] # ✿\n".to_string()], 64, 64);
+/// let text = metadata.to_text();
+/// assert!(text.is_ascii());
+/// assert_eq!(Metadata::parse(&text).unwrap(), metadata);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// The version of the crate that made the image.
+    pub version: String,
+    /// The programs, run one after another on the canvas.
+    pub programs: Vec<String>,
+    pub width: u32,
+    pub height: u32,
+    pub background: CFRColor,
+    pub dialect: Dialect,
+}
+
+impl Metadata {
+    /// Creates the metadata of the programs on a black canvas in the standard dialect, made
+    /// by this version of the crate.
+    pub fn new(programs: Vec<String>, width: u32, height: u32) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            programs,
+            width,
+            height,
+            background: CFRColor::Black,
+            dialect: Dialect::Standard,
+        }
+    }
+
+    /// Returns the metadata as ASCII `key: value` lines, with the programs escaped like Rust
+    /// string literals.
+    pub fn to_text(&self) -> String {
+        let dialect = match self.dialect {
+            Dialect::Standard => "standard".to_string(),
+            Dialect::Repeat(count) => format!("repeat:{}", count),
+        };
+        let mut text = format!(
+            "version: {}\nwidth: {}\nheight: {}\nbackground: {}\ndialect: {}\n",
+            self.version.escape_default(),
+            self.width,
+            self.height,
+            self.background.to_string().to_lowercase(),
+            dialect
+        );
+        for program in &self.programs {
+            text.push_str(&format!("program: {}\n", program.escape_default()));
+        }
+        text
+    }
+
+    /// Parses the output of `to_text`. Unknown keys are ignored, so newer versions can add
+    /// more.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut metadata = Metadata::new(Vec::new(), 0, 0);
+        let (mut width, mut height) = (None, None);
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            let (key, value) = line
+                .split_once(": ")
+                .ok_or_else(|| format!("Invalid metadata line: {}", line))?;
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid {}: {}", key, value))
+            };
+            match key {
+                "version" => metadata.version = unescape(value)?,
+                "width" => width = Some(number()?),
+                "height" => height = Some(number()?),
+                "background" => metadata.background = value.parse()?,
+                "dialect" => metadata.dialect = value.parse()?,
+                "program" => metadata.programs.push(unescape(value)?),
+                _ => {}
+            }
+        }
+        metadata.width = width.ok_or("The metadata has no width")?;
+        metadata.height = height.ok_or("The metadata has no height")?;
+        if metadata.programs.is_empty() {
+            return Err("The metadata has no program".to_string());
+        }
+        Ok(metadata)
+    }
+}
+
+/// Reverses `str::escape_default`.
+fn unescape(s: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid escape in {}", s);
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next().ok_or_else(invalid)? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(invalid)?;
+                let hex = rest.get(1..end).filter(|_| rest.starts_with('{'));
+                let code = hex.and_then(|h| u32::from_str_radix(h, 16).ok());
+                chars = rest[end + 1..].chars();
+                code.and_then(char::from_u32).ok_or_else(invalid)?
+            }
+            c @ ('\\' | '\'' | '"') => c,
+            _ => return Err(invalid()),
+        });
+    }
+    Ok(out)
+}
+
+/// Returns the PNG with the metadata in a `tEXt` chunk with the keyword `KEYWORD` right
+/// after the header, replacing the metadata it had, or `None` if it is not a PNG.
+pub fn embed_png(png: &[u8], metadata: &Metadata) -> Option<Vec<u8>> {
+    let chunks = png_chunks(png)?;
+    let header_end = chunks.first().filter(|(kind, _)| kind == b"IHDR")?.1.end;
+    let mut out = png[..header_end].to_vec();
+    let mut data = format!("{}\0", KEYWORD).into_bytes();
+    data.extend_from_slice(metadata.to_text().as_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(b"tEXt");
+    out.extend_from_slice(&data);
+    out.extend_from_slice(&crc32(&out[start..]).to_be_bytes());
+    for (kind, range) in &chunks[1..] {
+        if kind == b"tEXt" && png_text(png, range.clone()).is_some() {
+            continue;
+        }
+        out.extend_from_slice(&png[range.clone()]);
+    }
+    Some(out)
+}
+
+/// Returns the metadata that `embed_png` added to the PNG, if any.
+pub fn extract_png(png: &[u8]) -> Option<Metadata> {
+    png_chunks(png)?
+        .into_iter()
+        .filter(|(kind, _)| kind == b"tEXt")
+        .find_map(|(_, range)| png_text(png, range))
+        .and_then(|text| Metadata::parse(text).ok())
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Splits a PNG into its chunks: their types and byte ranges, including the length and CRC.
+/// The first range also includes the signature.
+fn png_chunks(png: &[u8]) -> Option<Vec<([u8; 4], Range<usize>)>> {
+    if !png.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    let mut chunks = Vec::new();
+    let mut start = PNG_SIGNATURE.len();
+    while start < png.len() {
+        let length = u32::from_be_bytes(png.get(start..start + 4)?.try_into().ok()?) as usize;
+        let kind: [u8; 4] = png.get(start + 4..start + 8)?.try_into().ok()?;
+        let end = start
+            .checked_add(12 + length)
+            .filter(|&end| end <= png.len())?;
+        let range_start = if chunks.is_empty() { 0 } else { start };
+        chunks.push((kind, range_start..end));
+        start = end;
+    }
+    Some(chunks)
+}
+
+/// Returns the text of a `tEXt` chunk with the keyword `KEYWORD`.
+fn png_text(png: &[u8], range: Range<usize>) -> Option<&str> {
+    let start = range.start.max(PNG_SIGNATURE.len());
+    let data = &png[start + 8..range.end - 4];
+    let text = data.strip_prefix(KEYWORD.as_bytes())?.strip_prefix(b"\0")?;
+    std::str::from_utf8(text).ok()
+}
+
+/// The CRC-32 of PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Returns the GIF with the metadata in a comment extension that starts with a line of
+/// `KEYWORD`, placed before the first frame and replacing the metadata it had, or `None` if
+/// it is not a GIF.
+pub fn embed_gif(gif: &[u8], metadata: &Metadata) -> Option<Vec<u8>> {
+    let blocks = gif_blocks(gif)?;
+    let header_end = blocks[0].1.end;
+    let mut out = gif[..header_end].to_vec();
+    out.extend_from_slice(&[0x21, 0xfe]);
+    let comment = format!("{}\n{}", KEYWORD, metadata.to_text());
+    for block in comment.as_bytes().chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    for (kind, range) in &blocks[1..] {
+        if *kind == Block::Comment && gif_comment(gif, range.clone()).is_some() {
+            continue;
+        }
+        out.extend_from_slice(&gif[range.clone()]);
+    }
+    Some(out)
+}
+
+/// Returns the metadata that `embed_gif` added to the GIF, if any.
+pub fn extract_gif(gif: &[u8]) -> Option<Metadata> {
+    gif_blocks(gif)?
+        .into_iter()
+        .filter(|(kind, _)| *kind == Block::Comment)
+        .find_map(|(_, range)| gif_comment(gif, range))
+        .and_then(|text| Metadata::parse(&text).ok())
+}
+
+/// Returns the metadata of a PNG or GIF, if any.
+pub fn extract(bytes: &[u8]) -> Option<Metadata> {
+    extract_png(bytes).or_else(|| extract_gif(bytes))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Block {
+    Header,
+    Comment,
+    Other,
+}
+
+/// Splits a GIF into its header with the global color table, its extensions and images, and
+/// its trailer, with their byte ranges.
+fn gif_blocks(gif: &[u8]) -> Option<Vec<(Block, Range<usize>)>> {
+    if !(gif.starts_with(b"GIF87a") || gif.starts_with(b"GIF89a")) {
+        return None;
+    }
+    let flags = *gif.get(10)?;
+    let mut position = 13;
+    if flags & 0x80 != 0 {
+        position += 3 << ((flags & 0x07) + 1);
+    }
+    let mut blocks = vec![(Block::Header, 0..position)];
+    // Skips sub-blocks up to and including the empty one that ends them.
+    let skip_sub_blocks = |mut position: usize| -> Option<usize> {
+        loop {
+            let size = *gif.get(position)? as usize;
+            position += 1 + size;
+            if size == 0 {
+                return Some(position);
+            }
+        }
+    };
+    loop {
+        let start = position;
+        match *gif.get(position)? {
+            0x21 => {
+                let label = *gif.get(position + 1)?;
+                position = skip_sub_blocks(position + 2)?;
+                let kind = if label == 0xfe {
+                    Block::Comment
+                } else {
+                    Block::Other
+                };
+                blocks.push((kind, start..position));
+            }
+            0x2c => {
+                let flags = *gif.get(position + 9)?;
+                position += 10;
+                if flags & 0x80 != 0 {
+                    position += 3 << ((flags & 0x07) + 1);
+                }
+                // The minimum LZW code size, then the image data.
+                position = skip_sub_blocks(position + 1)?;
+                blocks.push((Block::Other, start..position));
+            }
+            0x3b => {
+                blocks.push((Block::Other, start..position + 1));
+                break;
+            }
+            _ => return None,
+        }
+    }
+    (position <= gif.len()).then_some(blocks)
+}
+
+/// Returns the text of a comment extension that starts with a line of `KEYWORD`.
+fn gif_comment(gif: &[u8], range: Range<usize>) -> Option<String> {
+    let mut data = Vec::new();
+    let mut position = range.start + 2;
+    while position < range.end {
+        let size = gif[position] as usize;
+        data.extend_from_slice(gif.get(position + 1..position + 1 + size)?);
+        position += 1 + size;
+    }
+    let text = String::from_utf8(data).ok()?;
+    Some(text.strip_prefix(KEYWORD)?.strip_prefix('\n')?.to_string())
+}
+
+mod tests {
+    #[test]
+    fn embedding_twice_replaces_the_metadata() {
+        use crate::metadata::{crc32, embed_gif, embed_png, extract, Metadata};
+        use crate::{CFRColor, Dialect};
+
+        let mut metadata = Metadata::new(vec!["F\\\"'\t\r".to_string(), "R".to_string()], 3, 4);
+        metadata.background = CFRColor::Cyan;
+        metadata.dialect = Dialect::Repeat(3);
+        assert_eq!(Metadata::parse(&metadata.to_text()), Ok(metadata.clone()));
+        assert!(Metadata::parse("width: 1\nheight: 1\n").is_err());
+        assert!(Metadata::parse("width: 1\nheight: 1\nprogram: \\x\n").is_err());
+
+        let count = |bytes: &[u8], pattern: &[u8]| {
+            bytes
+                .windows(pattern.len())
+                .filter(|w| *w == pattern)
+                .count()
+        };
+        let placeholder = Metadata::new(vec!["C".to_string()], 1, 1);
+
+        // A 1×1 PNG.
+        let png = [
+            &b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x01\0\0\0\x01\x08\x02\0\0\0\x90\x77\x53\xde"[..],
+            b"\0\0\0\x0cIDAT\x08\xd7\x63\xf8\xcf\xc0\0\0\x03\x01\x01\0\x18\xdd\x8d\xb0",
+            b"\0\0\0\0IEND\xae\x42\x60\x82",
+        ]
+        .concat();
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(extract(&png), None);
+        let once = embed_png(&png, &placeholder).unwrap();
+        let twice = embed_png(&once, &metadata).unwrap();
+        assert_eq!(extract(&twice), Some(metadata.clone()));
+        assert_eq!(count(&twice, b"tEXtcfrs"), 1);
+        assert!(twice.ends_with(b"IEND\xae\x42\x60\x82"));
+
+        // A 1×1 GIF with a graphic control extension.
+        let gif = b"GIF89a\x01\0\x01\0\x80\0\0\xff\xff\xff\0\0\0\x21\xf9\x04\x01\0\0\0\0\x2c\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0\x3b";
+        assert_eq!(extract(gif), None);
+        let once = embed_gif(gif, &placeholder).unwrap();
+        let twice = embed_gif(&once, &metadata).unwrap();
+        assert_eq!(extract(&twice), Some(metadata));
+        assert_eq!(count(&twice, b"\x21\xfe"), 1);
+
+        assert!(embed_png(gif, &placeholder).is_none());
+        assert!(embed_gif(&png, &placeholder).is_none());
+    }
+}
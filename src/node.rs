@@ -0,0 +1,166 @@
+use crate::buffer::CFRBuffer;
+use crate::encode::encode_png;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::limits::ResourceLimits;
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::str::FromStr;
+
+/// The options of `render` and `new Executor`, all optional.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Canvas width, 256 by default.
+    pub width: Option<u32>,
+    /// Canvas height, 256 by default.
+    pub height: Option<u32>,
+    /// Background color name, `black` by default.
+    pub background: Option<String>,
+    /// Maximum number of commands `render` executes, 100 million by default.
+    pub max_steps: Option<i64>,
+}
+
+/// What a call to `Executor.step` did.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    /// Number of commands executed.
+    pub steps: u32,
+    /// Number of those commands that were `S`.
+    pub sleeps: u32,
+    /// Whether the program has ended.
+    pub done: bool,
+}
+
+/// Runs a program and returns the canvas as a PNG.
+///
+/// ```js
+/// const { render } = require("./cfrs.node");
+/// fs.writeFileSync("out.png", render("[[[[[[[[[[FS]]]]R]]RR]]RRCC]]", { width: 512 }));
+/// ```
+#[napi]
+pub fn render(program: String, options: Option<RenderOptions>) -> Result<Buffer> {
+    let options = options.unwrap_or_default();
+    let limits = ResourceLimits::default();
+    let max_steps = options
+        .max_steps
+        .map_or(limits.max_steps, |steps| steps.max(0) as u64);
+    let buffer = canvas(&options, &limits)?;
+    let mut executor = CommandExecutor::new(program, buffer);
+    let mut steps = 0;
+    loop {
+        match executor.step() {
+            Ok(_) => {}
+            Err("End of commands") => break,
+            Err(e) => return Err(Error::from_reason(e)),
+        }
+        steps += 1;
+        if steps > max_steps {
+            return Err(Error::from_reason(format!(
+                "Program exceeds {} steps",
+                max_steps
+            )));
+        }
+    }
+    let png = encode_png(executor.buffer()).map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(png.into())
+}
+
+/// An interpreter that runs a program a few commands at a time.
+///
+/// ```js
+/// const { Executor } = require("./cfrs.node");
+/// const executor = new Executor("[[[[FS]]]]", { width: 64, height: 64 });
+/// while (!executor.step(100).done) {
+///     draw(executor.frameRGBA(), executor.width, executor.height);
+/// }
+/// ```
+#[napi]
+pub struct Executor {
+    executor: CommandExecutor<'static, CFRBuffer>,
+    done: bool,
+}
+
+#[napi]
+impl Executor {
+    #[napi(constructor)]
+    pub fn new(program: String, options: Option<RenderOptions>) -> Result<Self> {
+        let buffer = canvas(&options.unwrap_or_default(), &ResourceLimits::default())?;
+        Ok(Self {
+            executor: CommandExecutor::new(program, buffer),
+            done: false,
+        })
+    }
+
+    /// Executes up to `n` commands (1 by default), stopping early at the end of the program.
+    #[napi]
+    pub fn step(&mut self, n: Option<u32>) -> Result<StepResult> {
+        let mut result = StepResult {
+            steps: 0,
+            sleeps: 0,
+            done: self.done,
+        };
+        while !self.done && result.steps < n.unwrap_or(1) {
+            match self.executor.step() {
+                Ok((sleep, _)) => {
+                    result.steps += 1;
+                    result.sleeps += sleep as u32;
+                }
+                Err("End of commands") => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Err(Error::from_reason(e));
+                }
+            }
+        }
+        result.done = self.done;
+        Ok(result)
+    }
+
+    /// Returns the canvas as RGBA bytes, `width * height * 4` in row-major order.
+    #[napi(js_name = "frameRGBA")]
+    pub fn frame_rgba(&self) -> Buffer {
+        self.executor.buffer().to_rgba_bytes().into()
+    }
+
+    /// Returns the canvas as a PNG.
+    #[napi]
+    pub fn png(&self) -> Result<Buffer> {
+        let png =
+            encode_png(self.executor.buffer()).map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(png.into())
+    }
+
+    #[napi(getter)]
+    pub fn width(&self) -> u32 {
+        self.executor.buffer().width
+    }
+
+    #[napi(getter)]
+    pub fn height(&self) -> u32 {
+        self.executor.buffer().height
+    }
+}
+
+fn canvas(options: &RenderOptions, limits: &ResourceLimits) -> Result<CFRBuffer> {
+    let width = options.width.unwrap_or(256);
+    let height = options.height.unwrap_or(256);
+    if width == 0 || height == 0 {
+        return Err(Error::from_reason("Canvas dimensions must be positive"));
+    }
+    if width as u64 * height as u64 > limits.max_pixels {
+        return Err(Error::from_reason(format!(
+            "Canvas exceeds {} pixels",
+            limits.max_pixels
+        )));
+    }
+    let background = match &options.background {
+        Some(name) => CFRColor::from_str(name).map_err(Error::from_reason)?,
+        None => CFRColor::Black,
+    };
+    let mut buffer = CFRBuffer::new(width, height);
+    buffer.data.iter_mut().for_each(|c| *c = background);
+    Ok(buffer)
+}
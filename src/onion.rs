@@ -0,0 +1,93 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+
+/// Maximum number of ghost layers, so that all faded colors fit in a 256-color palette.
+pub const MAX_LAYERS: usize = 256 / CFRColor::ALL.len() - 1;
+
+/// Onion-skinned animation frames as palette indices.
+#[derive(Debug, Clone)]
+pub struct OnionSkin {
+    pub width: u32,
+    pub height: u32,
+    /// `palette[level * CFRColor::ALL.len() + color]` is `color` faded towards the background
+    /// by `level`.
+    pub palette: Vec<[u8; 3]>,
+    pub frames: Vec<Vec<u8>>,
+}
+
+/// Composites every frame with the trails of the previous `layers` frames (ghosting).
+///
+/// Every frame shows the pixels that changed since the previous frame at full intensity,
+/// and the pixels that last changed `k` frames earlier faded towards the background by
+/// `k / (layers + 1)`. Pixels that have not changed for more than `layers` frames are
+/// hidden, so only the recent motion of the painter is visible. The first frame counts
+/// every pixel that is not the background as changed. `layers` is capped at `MAX_LAYERS`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::onion::onion_skin;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(3, 1);
+/// let mut frames = Vec::new();
+/// for x in 0..3 {
+///     frame.data[x] = CFRColor::White;
+///     frames.push(frame.clone());
+/// }
+///
+/// let skin = onion_skin(&frames, 1, CFRColor::Black);
+/// // White at full intensity, faded halfway, and the black background.
+/// assert_eq!(skin.palette[skin.frames[2][2] as usize], [255, 255, 255]);
+/// assert_eq!(skin.palette[skin.frames[2][1] as usize], [128, 128, 128]);
+/// assert_eq!(skin.palette[skin.frames[2][0] as usize], [0, 0, 0]);
+/// ```
+pub fn onion_skin(frames: &[CFRBuffer], layers: usize, background: CFRColor) -> OnionSkin {
+    let layers = layers.min(MAX_LAYERS);
+    let (width, height) = frames.first().map_or((0, 0), |f| (f.width, f.height));
+
+    let [br, bg, bb] = background.to_rgb();
+    let mut palette = Vec::with_capacity(CFRColor::ALL.len() * (layers + 1));
+    for level in 0..=layers {
+        let fade = |from: u8, to: u8| {
+            let (from, to) = (from as usize, to as usize);
+            ((from * (layers + 1 - level) + to * level + layers.div_ceil(2)) / (layers + 1)) as u8
+        };
+        for color in CFRColor::ALL {
+            let [r, g, b] = color.to_rgb();
+            palette.push([fade(r, br), fade(g, bg), fade(b, bb)]);
+        }
+    }
+
+    // How many frames ago every pixel last changed.
+    let mut ages = vec![usize::MAX; width as usize * height as usize];
+    let mut previous: Option<&CFRBuffer> = None;
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            ages.iter_mut().enumerate().for_each(|(i, age)| {
+                let before = previous.map_or(background, |p| p.data[i]);
+                *age = if frame.data[i] != before {
+                    0
+                } else {
+                    age.saturating_add(1)
+                };
+            });
+            previous = Some(frame);
+            ages.iter()
+                .zip(&frame.data)
+                .map(|(&age, &color)| match age {
+                    age if age <= layers => (age * CFRColor::ALL.len()) as u8 + color as u8,
+                    _ => background as u8,
+                })
+                .collect()
+        })
+        .collect();
+
+    OnionSkin {
+        width,
+        height,
+        palette,
+        frames,
+    }
+}
@@ -0,0 +1,266 @@
+use crate::buffer::CFRBuffer;
+use crate::compare::diff_mask;
+use crate::enums::{CFRColor, CFRDirection};
+use crate::painter::CFRPainter;
+use std::str::FromStr;
+
+/// Debug information drawn on top of exported frames, without changing the canvas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Overlay {
+    /// An arrow at the painter's position pointing in its direction.
+    PainterArrow,
+    /// The pixels that changed since the previous frame tinted with `CHANGE_COLOR`, fading
+    /// out over the next `fade` frames.
+    Changes { fade: usize },
+}
+
+/// Parses `painter-arrow`, or `changes[:fade]` with 3 frames of fading by default.
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let (name, argument) = s.split_once(':').unwrap_or((&s, ""));
+        match (name, argument) {
+            ("painter-arrow", "") => Ok(Overlay::PainterArrow),
+            ("changes", "") => Ok(Overlay::Changes { fade: 3 }),
+            ("changes", fade) => match fade.parse() {
+                Ok(fade) if fade <= MAX_FADE => Ok(Overlay::Changes { fade }),
+                _ => Err(format!("The fade must be from 0 to {}: {}", MAX_FADE, s)),
+            },
+            _ => Err(format!("Invalid overlay: {}", s)),
+        }
+    }
+}
+
+/// The color of the painter arrow, which is not one of the CFRS colors so it stands out.
+pub const ARROW_COLOR: [u8; 3] = [255, 128, 0];
+
+/// The palette of `arrow_indices`: the CFRS colors in `CFRColor::ALL` order, then
+/// `ARROW_COLOR`.
+pub fn arrow_palette() -> Vec<[u8; 3]> {
+    CFRColor::ALL
+        .iter()
+        .map(|c| c.to_rgb())
+        .chain([ARROW_COLOR])
+        .collect()
+}
+
+/// Returns the pixels of an arrow that starts at the painter and points in its direction:
+/// a shaft of three pixels and a head of four, wrapping around the edges like the painter.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::overlay::arrow;
+/// use cfrs::CFRPainter;
+///
+/// let mut painter = CFRPainter::new();
+/// painter.x = 5;
+/// painter.y = 5;
+/// let pixels = arrow(&painter, 10, 10);
+/// assert_eq!(
+///     pixels,
+///     vec![(5, 5), (5, 4), (5, 3), (6, 4), (4, 4), (7, 5), (3, 5)]
+/// );
+/// ```
+pub fn arrow(painter: &CFRPainter, width: u32, height: u32) -> Vec<(u32, u32)> {
+    let (dx, dy): (i64, i64) = match painter.direction {
+        CFRDirection::Up => (0, -1),
+        CFRDirection::UpRight => (1, -1),
+        CFRDirection::Right => (1, 0),
+        CFRDirection::DownRight => (1, 1),
+        CFRDirection::Down => (0, 1),
+        CFRDirection::DownLeft => (-1, 1),
+        CFRDirection::Left => (-1, 0),
+        CFRDirection::UpLeft => (-1, -1),
+    };
+    let tip = (2 * dx, 2 * dy);
+    let mut offsets = vec![(0, 0), (dx, dy), tip];
+    for k in 1..=2 {
+        if dx != 0 && dy != 0 {
+            // Diagonal arrows have a head along the two axes.
+            offsets.push((tip.0 - k * dx, tip.1));
+            offsets.push((tip.0, tip.1 - k * dy));
+        } else {
+            offsets.push((tip.0 - k * dx - k * dy, tip.1 - k * dy + k * dx));
+            offsets.push((tip.0 - k * dx + k * dy, tip.1 - k * dy - k * dx));
+        }
+    }
+    let (x, y) = (painter.x as i64, painter.y as i64);
+    offsets
+        .iter()
+        .map(|(ox, oy)| {
+            (
+                (x + ox).rem_euclid(width as i64) as u32,
+                (y + oy).rem_euclid(height as i64) as u32,
+            )
+        })
+        .collect()
+}
+
+/// Returns the frame as indices into `arrow_palette`, with the painter arrow on top.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::overlay::{arrow_indices, arrow_palette, ARROW_COLOR};
+/// use cfrs::{CFRBuffer, CFRPainter};
+///
+/// let mut painter = CFRPainter::new();
+/// painter.x = 2;
+/// painter.y = 2;
+/// let indices = arrow_indices(&CFRBuffer::new(5, 5), &painter);
+/// let palette = arrow_palette();
+/// assert_eq!(palette[indices[2 * 5 + 2] as usize], ARROW_COLOR);
+/// assert_eq!(palette[indices[0] as usize], [0, 0, 0]);
+/// ```
+pub fn arrow_indices(frame: &CFRBuffer, painter: &CFRPainter) -> Vec<u8> {
+    let mut indices: Vec<u8> = frame.data.iter().map(|&c| c as u8).collect();
+    if frame.width > 0 && frame.height > 0 {
+        for (x, y) in arrow(painter, frame.width, frame.height) {
+            indices[(y * frame.width + x) as usize] = CFRColor::ALL.len() as u8;
+        }
+    }
+    indices
+}
+
+/// Returns the frame as RGBA bytes with the painter arrow on top.
+pub fn arrow_rgba(frame: &CFRBuffer, painter: &CFRPainter) -> Vec<u8> {
+    let palette = arrow_palette();
+    arrow_indices(frame, painter)
+        .iter()
+        .flat_map(|&i| {
+            let [r, g, b] = palette[i as usize];
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+/// The color that changed pixels are tinted with, which is not one of the CFRS colors so it
+/// stands out.
+pub const CHANGE_COLOR: [u8; 3] = [255, 128, 0];
+
+/// The longest fade of `ChangeHighlighter`, so that all tints and a transparent color fit in
+/// a 256-color palette.
+pub const MAX_FADE: usize = 255 / CFRColor::ALL.len() - 2;
+
+/// Tints the pixels of animation frames that changed since the previous frame, so fast
+/// drawing is easy to follow.
+///
+/// A pixel that changed in the current frame is mixed 3:1 with `CHANGE_COLOR`, and the tint
+/// fades out over the next `fade` frames.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::overlay::{ChangeHighlighter, CHANGE_COLOR};
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(2, 1);
+/// let mut highlighter = ChangeHighlighter::new(&frame, 1);
+/// let palette = highlighter.palette();
+/// frame.data[1] = CFRColor::White;
+///
+/// let indices = highlighter.indices(&frame);
+/// assert_eq!(palette[indices[0] as usize], [0, 0, 0]);
+/// assert_eq!(palette[indices[1] as usize], [255, 160, 64]);
+/// // Half as strong in the next frame, and gone after that.
+/// assert_eq!(palette[highlighter.indices(&frame)[1] as usize], [255, 207, 159]);
+/// assert_eq!(palette[highlighter.indices(&frame)[1] as usize], [255, 255, 255]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChangeHighlighter {
+    fade: usize,
+    previous: CFRBuffer,
+    /// How many frames ago every pixel last changed.
+    ages: Vec<usize>,
+}
+
+impl ChangeHighlighter {
+    /// Creates a highlighter for an animation that starts on `canvas`. `fade` is capped at
+    /// `MAX_FADE`.
+    pub fn new(canvas: &CFRBuffer, fade: usize) -> Self {
+        Self {
+            fade: fade.min(MAX_FADE),
+            previous: canvas.clone(),
+            ages: vec![usize::MAX; canvas.data.len()],
+        }
+    }
+
+    /// The palette of `indices`: the CFRS colors in `CFRColor::ALL` order, followed by them
+    /// tinted for every age from 0 to `fade` frames.
+    pub fn palette(&self) -> Vec<[u8; 3]> {
+        let levels = self.fade as u32 + 1;
+        let mut palette: Vec<[u8; 3]> = CFRColor::ALL.iter().map(|c| c.to_rgb()).collect();
+        for age in 0..levels {
+            // Three quarters of the change color when new, fading linearly.
+            let weight = 3 * (levels - age);
+            let total = 4 * levels;
+            let mix = |from: u8, to: u8| {
+                ((from as u32 * (total - weight) + to as u32 * weight + total / 2) / total) as u8
+            };
+            for color in CFRColor::ALL {
+                let [r, g, b] = color.to_rgb();
+                let [hr, hg, hb] = CHANGE_COLOR;
+                palette.push([mix(r, hr), mix(g, hg), mix(b, hb)]);
+            }
+        }
+        palette
+    }
+
+    /// Returns the next frame as indices into `palette`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame does not have the dimensions of the canvas.
+    pub fn indices(&mut self, frame: &CFRBuffer) -> Vec<u8> {
+        let changed = diff_mask(&self.previous, frame);
+        self.previous.clone_from(frame);
+        let colors = CFRColor::ALL.len();
+        self.ages
+            .iter_mut()
+            .zip(changed)
+            .zip(&frame.data)
+            .map(|((age, changed), &color)| {
+                *age = if changed { 0 } else { age.saturating_add(1) };
+                if *age <= self.fade {
+                    ((*age + 1) * colors) as u8 + color as u8
+                } else {
+                    color as u8
+                }
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    #[test]
+    fn arrow_wraps_and_leaves_frame_alone() {
+        use crate::enums::CFRDirection;
+        use crate::overlay::{arrow, arrow_rgba, ChangeHighlighter, Overlay, MAX_FADE};
+        use crate::{CFRBuffer, CFRColor, CFRPainter};
+
+        let mut painter = CFRPainter::new();
+        painter.direction = CFRDirection::DownLeft;
+        let pixels = arrow(&painter, 4, 4);
+        assert_eq!(
+            pixels,
+            vec![(0, 0), (3, 1), (2, 2), (3, 2), (2, 1), (0, 2), (2, 0)]
+        );
+
+        let mut frame = CFRBuffer::new(4, 4);
+        frame.data[15] = CFRColor::Red;
+        let rgba = arrow_rgba(&frame, &painter);
+        assert_eq!(&rgba[..4], &[255, 128, 0, 255]);
+        assert_eq!(&rgba[4..8], &[0, 0, 0, 255]);
+        assert_eq!(frame.data[15], CFRColor::Red);
+
+        assert_eq!("changes".parse(), Ok(Overlay::Changes { fade: 3 }));
+        assert_eq!("Changes:0".parse(), Ok(Overlay::Changes { fade: 0 }));
+        assert!("changes:1000".parse::<Overlay>().is_err());
+        assert!("painter-arrow:1".parse::<Overlay>().is_err());
+        let highlighter = ChangeHighlighter::new(&frame, MAX_FADE);
+        assert!(highlighter.palette().len() < 256);
+    }
+}
@@ -1,5 +1,54 @@
-use crate::buffer::CFRBuffer;
+use crate::buffer::{pixel_index, CFRBuffer};
 use crate::enums::{CFRColor, CFRDirection};
+use std::ops::AddAssign;
+
+/// How many times the painter has wrapped around every edge of the canvas.
+///
+/// A diagonal move through a corner wraps around two edges at once.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Wraps {
+    pub left: u64,
+    pub right: u64,
+    pub top: u64,
+    pub bottom: u64,
+}
+
+impl Wraps {
+    /// Returns the number of wraps around all edges.
+    pub fn total(&self) -> u64 {
+        self.left
+            .saturating_add(self.right)
+            .saturating_add(self.top)
+            .saturating_add(self.bottom)
+    }
+}
+
+impl AddAssign for Wraps {
+    fn add_assign(&mut self, other: Self) {
+        self.left = self.left.saturating_add(other.left);
+        self.right = self.right.saturating_add(other.right);
+        self.top = self.top.saturating_add(other.top);
+        self.bottom = self.bottom.saturating_add(other.bottom);
+    }
+}
+
+/// Moves `coordinate` by `delta` without leaving a side of `size` pixels, which the caller
+/// makes sure of by wrapping around the edges.
+#[cfg(not(feature = "checked-math"))]
+#[inline]
+fn step(coordinate: u32, delta: i32, _size: u32) -> Result<u32, &'static str> {
+    Ok(coordinate.wrapping_add_signed(delta))
+}
+
+/// Moves `coordinate` by `delta`, or fails if the painter was not on a side of `size` pixels
+/// to begin with.
+#[cfg(feature = "checked-math")]
+fn step(coordinate: u32, delta: i32, size: u32) -> Result<u32, &'static str> {
+    match coordinate.checked_add_signed(delta) {
+        Some(moved) if moved < size => Ok(moved),
+        _ => Err("Painter moved outside the canvas"),
+    }
+}
 
 /// The CFRPainter struct represents a painter that moves around a buffer and draws points.
 /// It keeps track of the painter's direction, color, and position.
@@ -54,6 +103,8 @@ impl CFRPainter {
             CFRColor::Red => CFRColor::Magenta,
             CFRColor::Magenta => CFRColor::Yellow,
             CFRColor::Yellow => CFRColor::White,
+            #[cfg(feature = "transparent")]
+            CFRColor::Transparent => CFRColor::White,
         };
     }
 
@@ -103,10 +154,43 @@ impl CFRPainter {
     /// let mut painter = CFRPainter::new();
     /// painter.x = 128;
     /// painter.y = 128;
-    /// painter.move_forward_and_draw(&mut buffer);
+    /// painter.move_forward_and_draw(&mut buffer).unwrap();
     /// assert_eq!(buffer.data[(127 * 256 + 128) as usize], painter.color);
     /// ```
-    pub fn move_forward_and_draw(&mut self, buffer: &mut CFRBuffer) {
+    pub fn move_forward_and_draw(&mut self, buffer: &mut CFRBuffer) -> Result<(), &'static str> {
+        self.move_forward(buffer.width, buffer.height)?;
+        if buffer.data.is_empty() {
+            return Ok(());
+        }
+        let index = pixel_index(self.x, self.y, buffer.width);
+        if let Some(pixel) = buffer.data.get_mut(index) {
+            *pixel = self.color;
+        }
+        Ok(())
+    }
+
+    /// Moves the painter forward by one pixel without drawing, wrapping around the edges of a
+    /// `width`×`height` canvas.
+    ///
+    /// Returns the edges the painter wrapped around, if any. On a canvas without pixels the
+    /// painter stays where it is. With the `checked-math` feature, a painter that is not on
+    /// the canvas to begin with fails to move instead of wrapping its coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::CFRPainter;
+    ///
+    /// let mut painter = CFRPainter::new();
+    /// let wraps = painter.move_forward(16, 16).unwrap();
+    /// assert_eq!((painter.x, painter.y), (0, 15));
+    /// assert_eq!((wraps.top, wraps.total()), (1, 1));
+    /// ```
+    pub fn move_forward(&mut self, width: u32, height: u32) -> Result<Wraps, &'static str> {
+        let mut wraps = Wraps::default();
+        if width == 0 || height == 0 {
+            return Ok(wraps);
+        }
         let mut dx = 0;
         let mut dy = 0;
         match self.direction {
@@ -141,23 +225,25 @@ impl CFRPainter {
         }
 
         if self.x == 0 && dx == -1 {
-            self.x = buffer.width - 1;
-        } else if self.x == buffer.width - 1 && dx == 1 {
+            self.x = width - 1;
+            wraps.left = 1;
+        } else if self.x == width - 1 && dx == 1 {
             self.x = 0;
+            wraps.right = 1;
         } else {
-            self.x = (self.x as i32 + dx) as u32;
+            self.x = step(self.x, dx, width)?;
         }
 
         if self.y == 0 && dy == -1 {
-            self.y = buffer.height - 1;
-        } else if self.y == buffer.height - 1 && dy == 1 {
+            self.y = height - 1;
+            wraps.top = 1;
+        } else if self.y == height - 1 && dy == 1 {
             self.y = 0;
+            wraps.bottom = 1;
         } else {
-            self.y = (self.y as i32 + dy) as u32;
+            self.y = step(self.y, dy, height)?;
         }
-
-        let index = (self.y * buffer.width + self.x) as usize;
-        buffer.data[index] = self.color;
+        Ok(wraps)
     }
 }
 
@@ -220,7 +306,7 @@ mod tests {
         let mut painter = CFRPainter::new();
         painter.x = 128;
         painter.y = 0;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 128);
         assert_eq!(painter.y, 255);
     }
@@ -234,7 +320,7 @@ mod tests {
         painter.x = 128;
         painter.y = 255;
         painter.direction = CFRDirection::Down;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 128);
         assert_eq!(painter.y, 0);
     }
@@ -248,7 +334,7 @@ mod tests {
         painter.x = 0;
         painter.y = 128;
         painter.direction = CFRDirection::Left;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 255);
         assert_eq!(painter.y, 128);
     }
@@ -262,7 +348,7 @@ mod tests {
         painter.x = 255;
         painter.y = 128;
         painter.direction = CFRDirection::Right;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 0);
         assert_eq!(painter.y, 128);
     }
@@ -276,7 +362,7 @@ mod tests {
         painter.x = 0;
         painter.y = 0;
         painter.direction = CFRDirection::UpLeft;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 255);
         assert_eq!(painter.y, 255);
     }
@@ -290,7 +376,7 @@ mod tests {
         painter.x = 255;
         painter.y = 0;
         painter.direction = CFRDirection::UpRight;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 0);
         assert_eq!(painter.y, 255);
     }
@@ -304,7 +390,7 @@ mod tests {
         painter.x = 0;
         painter.y = 255;
         painter.direction = CFRDirection::DownLeft;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 255);
         assert_eq!(painter.y, 0);
     }
@@ -318,8 +404,121 @@ mod tests {
         painter.x = 255;
         painter.y = 255;
         painter.direction = CFRDirection::DownRight;
-        painter.move_forward_and_draw(&mut buffer);
+        painter.move_forward_and_draw(&mut buffer).unwrap();
         assert_eq!(painter.x, 0);
         assert_eq!(painter.y, 0);
     }
+
+    #[test]
+    fn count_wraps_through_corner() {
+        use crate::{CFRDirection, CFRPainter};
+
+        let mut painter = CFRPainter::new();
+        painter.direction = CFRDirection::UpLeft;
+        let wraps = painter.move_forward(4, 4).unwrap();
+        assert_eq!((painter.x, painter.y), (3, 3));
+        assert_eq!((wraps.left, wraps.top, wraps.total()), (1, 1, 2));
+
+        let mut total = wraps;
+        painter.direction = CFRDirection::DownRight;
+        total += painter.move_forward(4, 4).unwrap();
+        assert_eq!((total.right, total.bottom, total.total()), (1, 1, 4));
+    }
+
+    #[test]
+    fn move_on_degenerate_canvases() {
+        use crate::{CFRBuffer, CFRColor, CFRDirection, CFRPainter, CommandExecutor};
+
+        let mut painter = CFRPainter::new();
+        for direction in CFRDirection::ALL {
+            painter.direction = direction;
+            painter.move_forward(1, 1).unwrap();
+            assert_eq!((painter.x, painter.y), (0, 0));
+        }
+        painter.direction = CFRDirection::DownRight;
+        let wraps = painter.move_forward(1, 3).unwrap();
+        assert_eq!((painter.x, painter.y), (0, 1));
+        assert_eq!((wraps.left, wraps.right), (0, 1));
+
+        // Move in every direction, several times around the canvas.
+        let program = "[[[[[[FFF]R]]]]]";
+        for (width, height) in [(1, 1), (1, 5), (5, 1), (2, 1)] {
+            let mut buffer = CFRBuffer::new(width, height);
+            let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+            executor.run().unwrap();
+            assert!(
+                buffer.data.contains(&CFRColor::White),
+                "{}x{}",
+                width,
+                height
+            );
+        }
+
+        let mut empty = CFRBuffer {
+            width: 0,
+            height: 3,
+            data: Vec::new(),
+        };
+        CFRPainter::new().move_forward_and_draw(&mut empty).unwrap();
+        let mut executor = CommandExecutor::new("[[FRF]]".to_string(), &mut empty);
+        executor.run().unwrap();
+        assert_eq!(executor.wraps().total(), 0);
+    }
+
+    #[test]
+    fn extreme_dimensions() {
+        use crate::{CFRBuffer, CFRDirection, CFRPainter};
+
+        // Sides around the limits of `i32` and `u32`, where casting coordinates to `i32`
+        // would overflow, walked from every edge in every direction.
+        let sides = [
+            1,
+            2,
+            3,
+            i32::MAX as u32 - 1,
+            i32::MAX as u32,
+            1 << 31,
+            u32::MAX,
+        ];
+        for width in sides {
+            for height in sides {
+                let xs =
+                    [0, 1, width / 2, width.saturating_sub(2), width - 1].map(|x| x.min(width - 1));
+                let ys = [0, 1, height / 2, height.saturating_sub(2), height - 1]
+                    .map(|y| y.min(height - 1));
+                for (x, y) in xs.into_iter().flat_map(|x| ys.map(|y| (x, y))) {
+                    for direction in CFRDirection::ALL {
+                        let mut painter = CFRPainter::new();
+                        (painter.x, painter.y, painter.direction) = (x, y, direction);
+                        let wraps = painter.move_forward(width, height).unwrap();
+                        assert!(painter.x < width && painter.y < height);
+
+                        let (dx, dy) = direction.offset();
+                        let expected = |from: u32, delta: i64, side: u32| {
+                            (from as i64 + delta).rem_euclid(side as i64) as u32
+                        };
+                        assert_eq!(painter.x, expected(x, dx, width));
+                        assert_eq!(painter.y, expected(y, dy, height));
+                        let wrapped = (x as i64 + dx != painter.x as i64) as u64
+                            + (y as i64 + dy != painter.y as i64) as u64;
+                        assert_eq!(wraps.total(), wrapped);
+                    }
+                }
+            }
+        }
+
+        // Thin canvases draw on the right pixel from every edge.
+        for (width, height) in [(1, 1), (1, 1 << 16), (1 << 16, 1)] {
+            let mut buffer = CFRBuffer::new(width, height);
+            let mut painter = CFRPainter::new();
+            (painter.x, painter.y) = (width - 1, height - 1);
+            for direction in CFRDirection::ALL {
+                painter.direction = direction;
+                painter.move_forward_and_draw(&mut buffer).unwrap();
+                let index = (painter.y as usize) * width as usize + painter.x as usize;
+                assert_eq!(buffer.data[index], painter.color);
+                painter.change_color();
+            }
+        }
+    }
 }
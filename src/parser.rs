@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+/// The kind of a `Token`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// One of `C`, `F`, `R` or `S`.
+    Command,
+    /// `[`
+    BlockStart,
+    /// `]`
+    BlockEnd,
+    /// `#` up to (excluding) the end of the line, if comments are enabled.
+    Comment,
+    /// A character that is not a command, bracket, comment or whitespace.
+    Invalid,
+}
+
+/// A token of a program, with its byte range in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Splits a program into tokens, e.g. for syntax highlighting.
+///
+/// With `comments`, `#` starts a comment up to the end of its line, as after
+/// `CommandExecutor::set_comments`. Otherwise `#` is an `Invalid` token, and the commands
+/// after it are tokenized like the executor runs them.
+///
+/// Whitespace produces no tokens. Every other character belongs to exactly one token, and
+/// brackets are tokenized whether or not they are matched.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::parser::{tokenize, TokenKind};
+///
+/// let tokens = tokenize("[F] # draw\nx", true);
+/// let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::BlockStart,
+///         TokenKind::Command,
+///         TokenKind::BlockEnd,
+///         TokenKind::Comment,
+///         TokenKind::Invalid,
+///     ]
+/// );
+/// assert_eq!(tokens[3].span, 4..10);
+/// assert_eq!(tokenize("# F", false)[1].kind, TokenKind::Command);
+/// ```
+pub fn tokenize(program: &str, comments: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = program.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let kind = match c {
+            'C' | 'F' | 'R' | 'S' => TokenKind::Command,
+            '[' => TokenKind::BlockStart,
+            ']' => TokenKind::BlockEnd,
+            '#' if comments => {
+                let end = program[i..].find('\n').map_or(program.len(), |n| i + n);
+                while chars.next_if(|&(j, _)| j < end).is_some() {}
+                tokens.push(Token {
+                    kind: TokenKind::Comment,
+                    span: i..end,
+                });
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            _ => TokenKind::Invalid,
+        };
+        tokens.push(Token {
+            kind,
+            span: i..i + c.len_utf8(),
+        });
+    }
+
+    tokens
+}
+
+/// A part of a program that starts with a `#scene <name>` comment and lasts until the next
+/// one, or the end of the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scene {
+    pub name: String,
+    /// Byte range of the scene, starting at the `#` of its marker.
+    pub span: Range<usize>,
+}
+
+/// Returns the scenes of a program, in order, to organize long programs into parts.
+///
+/// A scene marker is a comment that starts with `#scene`, followed by the name of the scene.
+/// Commands before the first marker belong to no scene.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::parser::scenes;
+///
+/// let program = "C\n#scene intro\n[FS]\n# not a marker\n#scene  the end \nRF";
+/// let scenes = scenes(program);
+/// assert_eq!(scenes.len(), 2);
+/// assert_eq!((scenes[0].name.as_str(), scenes[0].span.clone()), ("intro", 2..35));
+/// assert_eq!((scenes[1].name.as_str(), scenes[1].span.clone()), ("the end", 35..54));
+/// ```
+pub fn scenes(program: &str) -> Vec<Scene> {
+    let mut scenes: Vec<Scene> = Vec::new();
+    for token in tokenize(program, true) {
+        let TokenKind::Comment = token.kind else {
+            continue;
+        };
+        let Some(name) = program[token.span.clone()].strip_prefix("#scene") else {
+            continue;
+        };
+        if !name.is_empty() && !name.starts_with(char::is_whitespace) {
+            continue;
+        }
+        if let Some(previous) = scenes.last_mut() {
+            previous.span.end = token.span.start;
+        }
+        scenes.push(Scene {
+            name: name.trim().to_string(),
+            span: token.span.start..program.len(),
+        });
+    }
+    scenes
+}
+
+mod tests {
+    #[test]
+    fn comment_until_end_of_input() {
+        use crate::parser::{tokenize, TokenKind};
+
+        let tokens = tokenize("F#CFRS", true);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1].kind, TokenKind::Comment);
+        assert_eq!(tokens[1].span, 1..6);
+        assert_eq!(tokenize("F#CFRS", false).len(), 6);
+    }
+
+    #[test]
+    fn multibyte_invalid_characters() {
+        use crate::parser::{tokenize, TokenKind};
+
+        let tokens = tokenize("é F", false);
+        assert_eq!(tokens[0].kind, TokenKind::Invalid);
+        assert_eq!(tokens[0].span, 0..2);
+        assert_eq!(tokens[1].span, 3..4);
+    }
+}
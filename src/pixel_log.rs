@@ -0,0 +1,138 @@
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::sink::AsPixelSink;
+use std::io::{self, Write};
+
+/// A pixel drawn by an `F` command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PixelEvent {
+    /// The number of steps executed, including this one.
+    pub step: u64,
+    pub x: u32,
+    pub y: u32,
+    pub color: CFRColor,
+}
+
+/// Every pixel drawn during a run, in order, for analysis in other tools.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::pixel_log::PixelLog;
+/// use cfrs::{CFRBuffer, CommandExecutor};
+///
+/// let mut executor = CommandExecutor::new("FCRF".to_string(), CFRBuffer::new(5, 5));
+/// let log = PixelLog::capture(&mut executor).unwrap();
+///
+/// let mut csv = Vec::new();
+/// log.write_csv(&mut csv).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "step,x,y,color\n1,2,1,white\n4,3,0,black\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PixelLog {
+    pub events: Vec<PixelEvent>,
+}
+
+impl PixelLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the program of `executor` to the end, logging every pixel it draws.
+    ///
+    /// Returns an error if the program fails, e.g. at an unmatched `]`.
+    pub fn capture<B: AsPixelSink>(
+        executor: &mut CommandExecutor<B>,
+    ) -> Result<Self, &'static str> {
+        let mut log = Self::new();
+        let mut step = 0;
+        loop {
+            let draws = executor.view().next_command() == Some('F');
+            match executor.step() {
+                Ok(_) => {}
+                Err("End of commands") => return Ok(log),
+                Err(e) => return Err(e),
+            }
+            step += 1;
+            if draws {
+                log.push(
+                    step,
+                    executor.painter().x,
+                    executor.painter().y,
+                    executor.painter().color,
+                );
+            }
+        }
+    }
+
+    /// Logs a pixel drawn at `step`.
+    pub fn push(&mut self, step: u64, x: u32, y: u32, color: CFRColor) {
+        self.events.push(PixelEvent { step, x, y, color });
+    }
+
+    /// Writes the log as CSV with a `step,x,y,color` header and lowercase color names.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "step,x,y,color")?;
+        for event in &self.events {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                event.step,
+                event.x,
+                event.y,
+                color_name(event.color)
+            )?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the log as JSON Lines, one `{"step":1,"x":2,"y":1,"color":"white"}` object per
+    /// pixel.
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for event in &self.events {
+            writeln!(
+                writer,
+                "{{\"step\":{},\"x\":{},\"y\":{},\"color\":\"{}\"}}",
+                event.step,
+                event.x,
+                event.y,
+                color_name(event.color)
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+fn color_name(color: CFRColor) -> String {
+    color.to_string().to_lowercase()
+}
+
+mod tests {
+    #[test]
+    fn logs_every_draw_in_order() {
+        use crate::pixel_log::PixelLog;
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let mut buffer = CFRBuffer::new(8, 8);
+        let mut executor = CommandExecutor::new("[[FR]] # F\nCF".to_string(), &mut buffer);
+        executor.set_comments(true);
+        let log = PixelLog::capture(&mut executor).unwrap();
+        assert_eq!(log.events.len(), 5);
+        assert!(log.events.windows(2).all(|w| w[0].step < w[1].step));
+        let last = log.events.last().unwrap();
+        assert_eq!(last.color, CFRColor::Black);
+        assert_eq!(buffer.data[(last.y * 8 + last.x) as usize], CFRColor::Black);
+
+        let mut jsonl = Vec::new();
+        log.write_jsonl(&mut jsonl).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert_eq!(jsonl.lines().count(), 5);
+        assert!(jsonl.ends_with(&format!(
+            "{{\"step\":{},\"x\":{},\"y\":{},\"color\":\"black\"}}\n",
+            last.step, last.x, last.y
+        )));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::enums::CFRColor;
+use crate::sink::{AsPixelSink, PixelSink};
+use std::fmt::Write;
+
+/// A line drawn without lifting the pen: the drawn pixels in order, every one next to the
+/// previous one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stroke {
+    pub color: CFRColor,
+    pub points: Vec<(u32, u32)>,
+}
+
+/// A part of the painter's path: a stroke drawn in `color`, or a jump with the pen lifted
+/// (where the painter wrapped around an edge or changed color) to the start of the next stroke.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathSegment {
+    pub color: CFRColor,
+    pub pen_down: bool,
+    pub points: Vec<(u32, u32)>,
+}
+
+/// A `PixelSink` that records the path of the painter as strokes instead of drawing it.
+///
+/// A new stroke starts when the color changes or when the painter wraps around an edge.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::plot::Trail;
+/// use cfrs::CommandExecutor;
+///
+/// let mut trail = Trail::new(8, 8);
+/// CommandExecutor::new("FFRRFFCF".to_string(), &mut trail).run().unwrap();
+/// let strokes = trail.into_strokes();
+/// assert_eq!(strokes.len(), 2);
+/// assert_eq!(strokes[0].points, vec![(3, 2), (3, 1), (4, 1), (5, 1)]);
+/// assert_eq!(strokes[1].points, vec![(6, 1)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Trail {
+    width: u32,
+    height: u32,
+    strokes: Vec<Stroke>,
+}
+
+impl Trail {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            strokes: Vec::new(),
+        }
+    }
+
+    pub fn strokes(&self) -> &[Stroke] {
+        &self.strokes
+    }
+
+    pub fn into_strokes(self) -> Vec<Stroke> {
+        self.strokes
+    }
+
+    /// Returns the strokes as a continuous path, with pen-up segments between them.
+    ///
+    /// Pen-up segments have two points and the color of the stroke they lead to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::plot::Trail;
+    /// use cfrs::CommandExecutor;
+    ///
+    /// let mut trail = Trail::new(8, 8);
+    /// CommandExecutor::new("FFCF".to_string(), &mut trail).run().unwrap();
+    /// let path = trail.segments();
+    /// let pen: Vec<bool> = path.iter().map(|s| s.pen_down).collect();
+    /// assert_eq!(pen, vec![true, false, true]);
+    /// assert_eq!(path[1].points, vec![(3, 1), (3, 0)]);
+    /// ```
+    pub fn segments(&self) -> Vec<PathSegment> {
+        let mut segments: Vec<PathSegment> = Vec::with_capacity(self.strokes.len() * 2);
+        for stroke in &self.strokes {
+            if let Some(previous) = segments.last() {
+                segments.push(PathSegment {
+                    color: stroke.color,
+                    pen_down: false,
+                    points: vec![*previous.points.last().unwrap(), stroke.points[0]],
+                });
+            }
+            segments.push(PathSegment {
+                color: stroke.color,
+                pen_down: true,
+                points: stroke.points.clone(),
+            });
+        }
+        segments
+    }
+}
+
+impl PixelSink for Trail {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        if let Some(stroke) = self.strokes.last_mut() {
+            let (px, py) = *stroke.points.last().unwrap();
+            if stroke.color == color && px.abs_diff(x) <= 1 && py.abs_diff(y) <= 1 {
+                stroke.points.push((x, y));
+                return;
+            }
+        }
+        self.strokes.push(Stroke {
+            color,
+            points: vec![(x, y)],
+        });
+    }
+}
+
+impl AsPixelSink for Trail {
+    type Sink = Self;
+
+    fn sink(&self) -> &Self {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+/// Options of `to_gcode` and `to_turtle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    /// Size of a pixel, in millimeters for G-code and in turtle units for turtle scripts.
+    pub scale: f64,
+    /// Strokes in this color are skipped, e.g. the background, which is the paper.
+    pub background: Option<CFRColor>,
+    /// G-code feed rate while drawing, in millimeters per minute.
+    pub feed_rate: f64,
+    /// G-code Z height with the pen lifted.
+    pub pen_up: f64,
+    /// G-code Z height with the pen on the paper.
+    pub pen_down: f64,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            scale: 0.5,
+            background: Some(CFRColor::Black),
+            feed_rate: 1500.0,
+            pen_up: 5.0,
+            pen_down: 0.0,
+        }
+    }
+}
+
+/// Converts a trail to G-code for a pen plotter.
+///
+/// The origin is the bottom-left corner of the canvas and Y points up. The strokes are
+/// grouped by color, and the plotter pauses (`M0`) to change the pen between two colors.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::plot::{to_gcode, PlotOptions, Trail};
+/// use cfrs::CommandExecutor;
+///
+/// let mut trail = Trail::new(8, 8);
+/// CommandExecutor::new("RRFFF".to_string(), &mut trail).run().unwrap();
+/// let gcode = to_gcode(&trail, &PlotOptions::default());
+/// assert!(gcode.contains("G0 X2.000 Y2.000\nG1 Z0.000 F1500\nG1 X3.000 Y2.000 F1500\n"));
+/// ```
+pub fn to_gcode(trail: &Trail, options: &PlotOptions) -> String {
+    let height = trail.height;
+    let point = |(x, y): (u32, u32)| {
+        (
+            x as f64 * options.scale,
+            (height - 1 - y) as f64 * options.scale,
+        )
+    };
+
+    let mut out = String::new();
+    writeln!(out, "G21 ; millimeters\nG90 ; absolute positions").unwrap();
+    writeln!(out, "G0 Z{:.3}", options.pen_up).unwrap();
+    for (i, (color, strokes)) in by_color(&trail.strokes, options).into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out, "M0 ; change the pen to {:?}", color).unwrap();
+        } else {
+            writeln!(out, "; pen {:?}", color).unwrap();
+        }
+        for stroke in strokes {
+            let points = simplify(&stroke.points);
+            let (x, y) = point(points[0]);
+            writeln!(out, "G0 X{:.3} Y{:.3}", x, y).unwrap();
+            writeln!(out, "G1 Z{:.3} F{}", options.pen_down, options.feed_rate).unwrap();
+            for &p in &points[1..] {
+                let (x, y) = point(p);
+                writeln!(out, "G1 X{:.3} Y{:.3} F{}", x, y, options.feed_rate).unwrap();
+            }
+            writeln!(out, "G0 Z{:.3}", options.pen_up).unwrap();
+        }
+    }
+    writeln!(out, "G0 X0 Y0").unwrap();
+    out
+}
+
+/// Converts a trail to a Python script that draws it with the `turtle` module.
+///
+/// The center of the canvas is the origin of the turtle, and a pixel is `options.scale`
+/// turtle units wide. `feed_rate` and the pen heights are not used.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::plot::{to_turtle, PlotOptions, Trail};
+/// use cfrs::CommandExecutor;
+///
+/// let mut trail = Trail::new(8, 8);
+/// CommandExecutor::new("FF".to_string(), &mut trail).run().unwrap();
+/// let options = PlotOptions { scale: 2.0, ..Default::default() };
+/// let script = to_turtle(&trail, &options);
+/// assert!(script.contains("t.goto(-1.0, 3.0)\nt.pendown()\nt.goto(-1.0, 5.0)\n"));
+/// ```
+pub fn to_turtle(trail: &Trail, options: &PlotOptions) -> String {
+    let point = |(x, y): (u32, u32)| {
+        (
+            (x as f64 + 0.5 - trail.width as f64 / 2.0) * options.scale,
+            (trail.height as f64 / 2.0 - y as f64 - 0.5) * options.scale,
+        )
+    };
+
+    let mut out = String::new();
+    writeln!(out, "import turtle\n").unwrap();
+    writeln!(out, "turtle.colormode(255)").unwrap();
+    if let Some(background) = options.background {
+        writeln!(out, "turtle.bgcolor({:?})", rgb(background)).unwrap();
+    }
+    writeln!(out, "t = turtle.Turtle()").unwrap();
+    writeln!(out, "t.hideturtle()\nt.speed(0)\nt.penup()").unwrap();
+    writeln!(out, "t.pensize({})", options.scale.max(1.0)).unwrap();
+    for (color, strokes) in by_color(&trail.strokes, options) {
+        writeln!(out, "t.pencolor({:?})", rgb(color)).unwrap();
+        for stroke in strokes {
+            let points = simplify(&stroke.points);
+            let (x, y) = point(points[0]);
+            writeln!(out, "t.goto({:?}, {:?})", x, y).unwrap();
+            if points.len() == 1 {
+                writeln!(out, "t.dot({})", options.scale.max(1.0)).unwrap();
+                continue;
+            }
+            writeln!(out, "t.pendown()").unwrap();
+            for &p in &points[1..] {
+                let (x, y) = point(p);
+                writeln!(out, "t.goto({:?}, {:?})", x, y).unwrap();
+            }
+            writeln!(out, "t.penup()").unwrap();
+        }
+    }
+    writeln!(out, "\nturtle.done()").unwrap();
+    out
+}
+
+fn rgb(color: CFRColor) -> (u8, u8, u8) {
+    let [r, g, b] = color.to_rgb();
+    (r, g, b)
+}
+
+/// Groups the strokes by color, in the order the colors are first used.
+fn by_color<'a>(strokes: &'a [Stroke], options: &PlotOptions) -> Vec<(CFRColor, Vec<&'a Stroke>)> {
+    let mut groups: Vec<(CFRColor, Vec<&Stroke>)> = Vec::new();
+    for stroke in strokes {
+        if Some(stroke.color) == options.background {
+            continue;
+        }
+        match groups.iter_mut().find(|(color, _)| *color == stroke.color) {
+            Some((_, group)) => group.push(stroke),
+            None => groups.push((stroke.color, vec![stroke])),
+        }
+    }
+    groups
+}
+
+/// Removes the points in the middle of straight runs.
+fn simplify(points: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut out: Vec<(u32, u32)> = Vec::with_capacity(points.len());
+    for &point in points {
+        if let [.., a, b] = out[..] {
+            let direction = |from: (u32, u32), to: (u32, u32)| {
+                (
+                    (to.0 as i64 - from.0 as i64).signum(),
+                    (to.1 as i64 - from.1 as i64).signum(),
+                )
+            };
+            if direction(a, b) == direction(b, point) {
+                *out.last_mut().unwrap() = point;
+                continue;
+            }
+        }
+        out.push(point);
+    }
+    out
+}
+
+mod tests {
+    #[test]
+    fn split_on_wrap_and_merge_straight_runs() {
+        use crate::plot::{to_gcode, PlotOptions, Trail};
+        use crate::CommandExecutor;
+
+        let mut trail = Trail::new(4, 4);
+        CommandExecutor::new("RRFFFRRF".to_string(), &mut trail)
+            .run()
+            .unwrap();
+        let points: Vec<_> = trail.strokes().iter().map(|s| s.points.clone()).collect();
+        assert_eq!(points, vec![vec![(2, 1), (3, 1)], vec![(0, 1), (0, 2)]]);
+
+        let gcode = to_gcode(&trail, &PlotOptions::default());
+        assert_eq!(gcode.matches("G1 Z").count(), 2);
+        assert!(!gcode.contains("M0"));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::validator::{Diagnostic, Severity};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Maximum length of an expanded program in bytes.
+pub const MAX_OUTPUT_LEN: usize = 16 << 20;
+
+/// Maximum nesting of macro uses, which stops recursive macros.
+pub const MAX_DEPTH: usize = 64;
+
+/// Expands the directives of a `.cfrst` template into a plain CFRS program.
+///
+/// - `@define name { ... }` and `@define name(a, b) { ... }` define a macro, which can use
+///   its parameters as `$a` and `$b`;
+/// - `@use name` and `@use name(FF, RR)` insert a macro;
+/// - `@repeat n { ... }` inserts its body `n` times, where `n` is a number or a parameter.
+///
+/// Macros must be defined before they are used. Everything else, including `#` comments,
+/// is copied as is.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::preprocess::expand;
+///
+/// let template = "
+/// @define side(length) { @repeat $length { F } RR }
+/// @repeat 4 { @use side(3) }
+/// ";
+/// let program = expand(template).unwrap();
+/// assert_eq!(program.split_whitespace().collect::<String>(), "FFFRR".repeat(4));
+///
+/// let error = expand("@use square").unwrap_err();
+/// assert_eq!(error.message, "Unknown macro square");
+/// assert_eq!(error.span, 0..11);
+/// ```
+pub fn expand(template: &str) -> Result<String, Diagnostic> {
+    let mut preprocessor = Preprocessor {
+        source: template,
+        macros: HashMap::new(),
+        depth: 0,
+    };
+    let mut output = String::new();
+    preprocessor.expand(0..template.len(), &HashMap::new(), &mut output)?;
+    Ok(output)
+}
+
+struct Macro<'a> {
+    params: Vec<&'a str>,
+    body: Range<usize>,
+}
+
+struct Preprocessor<'a> {
+    source: &'a str,
+    macros: HashMap<&'a str, Macro<'a>>,
+    depth: usize,
+}
+
+impl<'a> Preprocessor<'a> {
+    /// Expands `range` of the source into `output`, with `args` as the values of `$` parameters.
+    fn expand(
+        &mut self,
+        range: Range<usize>,
+        args: &HashMap<&str, String>,
+        output: &mut String,
+    ) -> Result<(), Diagnostic> {
+        let bytes = self.source.as_bytes();
+        let mut i = range.start;
+        let mut copied = range.start;
+        while i < range.end {
+            match bytes[i] {
+                b'#' => i = self.line_end(i, range.end),
+                b'@' | b'$' | b'{' | b'}' => {
+                    self.push(output, &self.source[copied..i], i..i + 1)?;
+                    i = self.directive(i, range.end, args, output)?;
+                    copied = i;
+                }
+                _ => i += 1,
+            }
+        }
+        self.push(output, &self.source[copied..range.end], copied..range.end)
+    }
+
+    /// Expands the directive, parameter, or stray brace at `start`, and returns the index after
+    /// it.
+    fn directive(
+        &mut self,
+        start: usize,
+        end: usize,
+        args: &HashMap<&str, String>,
+        output: &mut String,
+    ) -> Result<usize, Diagnostic> {
+        let bytes = self.source.as_bytes();
+        let (name, mut i) = self.ident(start + 1, end);
+        let span = start..i;
+        match (bytes[start], name) {
+            (b'$', _) => {
+                let value = self.param(name, span.clone(), args)?;
+                self.push(output, value, span)?;
+            }
+            (b'@', "define") => {
+                let (name, after) = self.ident(self.skip_spaces(i, end), end);
+                if name.is_empty() {
+                    return Err(error(start..after, "Expected a macro name after @define"));
+                }
+                i = after;
+                let mut params = Vec::new();
+                if bytes.get(i) == Some(&b'(') {
+                    let close = self.closing(i, end, b'(', b')')?;
+                    for param in self.source[i + 1..close].split(',') {
+                        let param = param.trim();
+                        if param.is_empty() || !param.bytes().all(is_ident) {
+                            return Err(error(i..close + 1, "Expected parameter names"));
+                        }
+                        params.push(param);
+                    }
+                    i = close + 1;
+                }
+                let (body, after) = self.block(i, end, start)?;
+                if self.macros.contains_key(name) {
+                    return Err(error(
+                        start..after,
+                        &format!("Macro {} is already defined", name),
+                    ));
+                }
+                self.macros.insert(name, Macro { params, body });
+                i = after;
+            }
+            (b'@', "use") => {
+                let (name, after) = self.ident(self.skip_spaces(i, end), end);
+                i = after;
+                let mut values = Vec::new();
+                if bytes.get(i) == Some(&b'(') {
+                    let close = self.closing(i, end, b'(', b')')?;
+                    for range in self.split_args(i + 1..close) {
+                        let mut value = String::new();
+                        self.expand(range, args, &mut value)?;
+                        values.push(value.trim().to_string());
+                    }
+                    i = close + 1;
+                }
+                let span = start..i;
+                let Some(m) = self.macros.get(name) else {
+                    return Err(error(span, &format!("Unknown macro {}", name)));
+                };
+                if m.params.len() != values.len() {
+                    let message = format!(
+                        "Macro {} takes {} arguments, but {} were given",
+                        name,
+                        m.params.len(),
+                        values.len()
+                    );
+                    return Err(error(span, &message));
+                }
+                if self.depth >= MAX_DEPTH {
+                    return Err(error(span, "Macros nest too deeply"));
+                }
+                let body = m.body.clone();
+                let args = m.params.iter().copied().zip(values).collect();
+                self.depth += 1;
+                self.expand(body, &args, output)?;
+                self.depth -= 1;
+            }
+            (b'@', "repeat") => {
+                let count_start = self.skip_spaces(i, end);
+                let (count, after) = if bytes.get(count_start) == Some(&b'$') {
+                    let (name, after) = self.ident(count_start + 1, end);
+                    let value = self.param(name, count_start..after, args)?;
+                    (value.trim(), after)
+                } else {
+                    self.ident(count_start, end)
+                };
+                let Ok(count) = count.parse::<usize>() else {
+                    return Err(error(start..after, "Expected a repeat count after @repeat"));
+                };
+                let (body, after) = self.block(after, end, start)?;
+                let mut expanded = String::new();
+                self.expand(body, args, &mut expanded)?;
+                if expanded.len().saturating_mul(count) > MAX_OUTPUT_LEN {
+                    return Err(error(start..after, "Expanded program is too long"));
+                }
+                self.push(output, &expanded.repeat(count), start..after)?;
+                i = after;
+            }
+            (b'@', name) => {
+                return Err(error(span, &format!("Unknown directive @{}", name)));
+            }
+            (brace, _) => {
+                let message = format!("Unexpected {}", brace as char);
+                return Err(error(start..start + 1, &message));
+            }
+        }
+        Ok(i)
+    }
+
+    /// Returns the value of the parameter `name`.
+    fn param<'b>(
+        &self,
+        name: &str,
+        span: Range<usize>,
+        args: &'b HashMap<&str, String>,
+    ) -> Result<&'b str, Diagnostic> {
+        args.get(name)
+            .map(String::as_str)
+            .ok_or_else(|| error(span, &format!("Unknown parameter ${}", name)))
+    }
+
+    /// Parses the `{ ... }` block after `i` for the directive at `directive`, and returns the
+    /// range inside the braces and the index after the closing brace.
+    fn block(
+        &self,
+        i: usize,
+        end: usize,
+        directive: usize,
+    ) -> Result<(Range<usize>, usize), Diagnostic> {
+        let open = self.skip_spaces(i, end);
+        if self.source.as_bytes().get(open) != Some(&b'{') || open >= end {
+            return Err(error(directive..open, "Expected {"));
+        }
+        let close = self.closing(open, end, b'{', b'}')?;
+        Ok((open + 1..close, close + 1))
+    }
+
+    /// Returns the index of the bracket that closes the one at `open`, skipping comments.
+    fn closing(&self, open: usize, end: usize, left: u8, right: u8) -> Result<usize, Diagnostic> {
+        let bytes = self.source.as_bytes();
+        let mut depth = 0;
+        let mut i = open;
+        while i < end {
+            match bytes[i] {
+                b'#' => {
+                    i = self.line_end(i, end);
+                    continue;
+                }
+                b if b == left => depth += 1,
+                b if b == right => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Err(error(open..open + 1, &format!("Unclosed {}", left as char)))
+    }
+
+    /// Splits macro arguments at the commas outside of parentheses.
+    fn split_args(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        if self.source[range.clone()].trim().is_empty() {
+            return Vec::new();
+        }
+        let mut ranges = Vec::new();
+        let (mut depth, mut start) = (0, range.start);
+        for i in range.clone() {
+            match self.source.as_bytes()[i] {
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                b',' if depth == 0 => {
+                    ranges.push(start..i);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        ranges.push(start..range.end);
+        ranges
+    }
+
+    /// Returns the identifier at `i` (possibly empty) and the index after it.
+    fn ident(&self, i: usize, end: usize) -> (&'a str, usize) {
+        let len = self.source.as_bytes()[i.min(end)..end]
+            .iter()
+            .take_while(|&&b| is_ident(b))
+            .count();
+        (&self.source[i.min(end)..i.min(end) + len], i.min(end) + len)
+    }
+
+    fn skip_spaces(&self, i: usize, end: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        (i..end)
+            .find(|&j| !bytes[j].is_ascii_whitespace())
+            .unwrap_or(end)
+    }
+
+    /// Returns the index after the comment at `i`, including its newline.
+    fn line_end(&self, i: usize, end: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        (i..end).find(|&j| bytes[j] == b'\n').map_or(end, |j| j + 1)
+    }
+
+    fn push(&self, output: &mut String, text: &str, span: Range<usize>) -> Result<(), Diagnostic> {
+        if output.len() + text.len() > MAX_OUTPUT_LEN {
+            return Err(error(span, "Expanded program is too long"));
+        }
+        output.push_str(text);
+        Ok(())
+    }
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn error(span: Range<usize>, message: &str) -> Diagnostic {
+    Diagnostic {
+        span,
+        severity: Severity::Error,
+        message: message.to_string(),
+    }
+}
+
+mod tests {
+    #[test]
+    fn expands_nested_macros() {
+        use crate::preprocess::expand;
+
+        let template = "# @use is ignored in comments { \n\
+            @define petal(size, turn) { [@repeat $size { F }] $turn }\n\
+            @define flower(n) { @repeat $n { @use petal(2, @use right) } }\n\
+            @define right { RR }\n\
+            @use flower(4)";
+        // `right` is defined after `flower`, but before `flower` is used.
+        let program: String = expand(template).unwrap().split('\n').skip(1).collect();
+        let program: String = program.split_whitespace().collect();
+        assert_eq!(program, "[FF]RR".repeat(4));
+
+        let errors = [
+            (
+                "@define a { F } @define a { R }",
+                "Macro a is already defined",
+            ),
+            (
+                "@define a(x) { $x } @use a",
+                "Macro a takes 1 arguments, but 0 were given",
+            ),
+            ("@define a { @use a } @use a", "Macros nest too deeply"),
+            ("@repeat 2 { F", "Unclosed {"),
+            (
+                "@repeat many { F }",
+                "Expected a repeat count after @repeat",
+            ),
+            ("F $x", "Unknown parameter $x"),
+            ("F }", "Unexpected }"),
+            ("@include x", "Unknown directive @include"),
+        ];
+        for (template, message) in errors {
+            assert_eq!(
+                expand(template).unwrap_err().message,
+                message,
+                "{}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::animation::Animation;
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::sink::AsPixelSink;
+
+/// Places the canvases next to each other, separated by one-pixel columns of `divider`.
+/// Shorter canvases are padded with `divider` at the bottom.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::race::side_by_side;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let (a, b) = (CFRBuffer::new(3, 2), CFRBuffer::new(2, 3));
+/// let joined = side_by_side([&a, &b], CFRColor::White);
+/// assert_eq!((joined.width, joined.height), (6, 3));
+/// assert_eq!(joined.data[3], CFRColor::White);
+/// assert_eq!(joined.data[2 * 6], CFRColor::White);
+/// ```
+pub fn side_by_side<'b>(
+    canvases: impl IntoIterator<Item = &'b CFRBuffer>,
+    divider: CFRColor,
+) -> CFRBuffer {
+    let canvases: Vec<&CFRBuffer> = canvases.into_iter().collect();
+    let width = canvases.iter().map(|c| c.width).sum::<u32>() + canvases.len().max(1) as u32 - 1;
+    let height = canvases.iter().map(|c| c.height).max().unwrap_or(0);
+    let mut joined = CFRBuffer {
+        width,
+        height,
+        data: vec![divider; width as usize * height as usize],
+    };
+    let mut x = 0;
+    for canvas in canvases {
+        joined.blit(canvas, x, 0);
+        x += canvas.width + 1;
+    }
+    joined
+}
+
+/// How a program in a race ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Finish {
+    /// The number of commands the program executed.
+    pub steps: u64,
+    /// Why the program stopped, if it failed.
+    pub error: Option<&'static str>,
+}
+
+/// Runs the programs of `executors` step-locked, one command each at a time, and captures
+/// their canvases side by side every `steps_per_frame` steps, each frame shown for `delay`
+/// milliseconds. Programs that finish early keep showing their final canvas, the last frame
+/// shows all of them finished, and identical frames in a row are merged.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::race::race;
+/// use cfrs::{CFRBuffer, CFRColor, CommandExecutor};
+///
+/// let mut executors = [
+///     CommandExecutor::new("[[[F]]]".to_string(), CFRBuffer::new(16, 16)),
+///     CommandExecutor::new("FFFFFFFF".to_string(), CFRBuffer::new(16, 16)),
+/// ];
+/// let (animation, finishes) = race(&mut executors, 4, 100, CFRColor::White);
+/// assert_eq!(finishes[1].steps, 8);
+/// assert!(finishes[0].steps > finishes[1].steps);
+/// assert_eq!(animation.frames[0].width, 33);
+/// ```
+pub fn race<B: AsPixelSink<Sink = CFRBuffer>>(
+    executors: &mut [CommandExecutor<B>],
+    steps_per_frame: u64,
+    delay: u32,
+    divider: CFRColor,
+) -> (Animation, Vec<Finish>) {
+    let mut finishes: Vec<Option<Finish>> = vec![None; executors.len()];
+    let mut animation = Animation::new();
+    let capture = |executors: &[CommandExecutor<B>]| {
+        side_by_side(executors.iter().map(|e| e.buffer()), divider)
+    };
+    let mut step = 0;
+    while finishes.iter().any(Option::is_none) {
+        for (executor, finish) in executors.iter_mut().zip(&mut finishes) {
+            if finish.is_some() {
+                continue;
+            }
+            match executor.step() {
+                Ok(_) => {}
+                Err(e) => {
+                    *finish = Some(Finish {
+                        steps: step,
+                        error: (e != "End of commands").then_some(e),
+                    })
+                }
+            }
+        }
+        step += 1;
+        if step % steps_per_frame.max(1) == 0 {
+            animation.push_frame(capture(executors), delay);
+        }
+    }
+    animation.push_frame(capture(executors), delay);
+    animation.dedup();
+    (animation, finishes.into_iter().flatten().collect())
+}
+
+mod tests {
+    #[test]
+    fn race_is_step_locked() {
+        use crate::race::race;
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let mut executors = [
+            CommandExecutor::new("FFFF]".to_string(), CFRBuffer::new(8, 8)),
+            CommandExecutor::new("FF[F]".to_string(), CFRBuffer::new(8, 8)),
+        ];
+        let (animation, finishes) = race(&mut executors, 2, 50, CFRColor::Red);
+        assert_eq!(finishes[0].steps, 4);
+        assert_eq!(finishes[0].error, Some("Unmatched ]"));
+        assert_eq!(finishes[1].steps, 7);
+        assert_eq!(finishes[1].error, None);
+
+        // Both drew their first two pixels in the first frame.
+        let first = &animation.frames[0];
+        let drawn = |x0: u32| {
+            (0..8)
+                .flat_map(|y| (x0..x0 + 8).map(move |x| (x, y)))
+                .filter(|&(x, y)| first.data[(y * 17 + x) as usize] == CFRColor::White)
+                .count()
+        };
+        assert_eq!((drawn(0), drawn(9)), (2, 2));
+        assert!((0..8).all(|y| first.data[(y * 17 + 8) as usize] == CFRColor::Red));
+        // Frames at steps 2, 4 and 6; the frames at step 8 and the end are the same as 6.
+        assert_eq!(animation.frames.len(), 3);
+        assert_eq!(animation.delays, vec![50, 50, 150]);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::{CFRColor, CFRDirection, Dialect};
+use crate::executor::{CommandExecutor, CommandExecutorState};
+use crate::painter::CFRPainter;
+use crate::sink::AsPixelSink;
+use std::io::{self, Read, Write};
+
+/// The first bytes of every `.cfrsrec` file.
+pub const MAGIC: &[u8; 8] = b"CFRSREC\0";
+
+/// The current version of the `.cfrsrec` format.
+pub const VERSION: u16 = 1;
+
+/// Maximum number of canvas pixels in a checkpoint, so a corrupt file fails to read instead of
+/// allocating a huge canvas.
+pub const MAX_PIXELS: usize = 1 << 28;
+
+const CHECKPOINT: u8 = b'K';
+const END: u8 = b'E';
+
+/// Everything needed to start a run: the program, the canvas and the render options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub program: String,
+    pub width: u32,
+    pub height: u32,
+    pub background: CFRColor,
+    /// Animation frame interval in milliseconds.
+    pub interval: u32,
+}
+
+impl RecordingHeader {
+    /// Creates a fresh executor for the recorded run, with the canvas filled with the background.
+    pub fn executor(&self) -> CommandExecutor<'static, CFRBuffer> {
+        let mut buffer = CFRBuffer::new(self.width, self.height);
+        buffer.data.iter_mut().for_each(|c| *c = self.background);
+        CommandExecutor::new(self.program.clone(), buffer)
+    }
+}
+
+/// A snapshot of a run after `steps` steps.
+///
+/// Recordings store executors of the standard dialect without comments; `Dialect::Repeat`
+/// counters and `CommandExecutorState::comments` are not saved.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub steps: u64,
+    pub state: CommandExecutorState,
+    pub painter: CFRPainter,
+    pub buffer: CFRBuffer,
+}
+
+impl Checkpoint {
+    /// Captures the current state of an executor that has executed `steps` steps.
+    pub fn capture<B: AsPixelSink<Sink = CFRBuffer>>(
+        executor: &CommandExecutor<B>,
+        steps: u64,
+    ) -> Self {
+        Self {
+            steps,
+            state: executor.state().clone(),
+            painter: *executor.painter(),
+            buffer: executor.buffer().clone(),
+        }
+    }
+
+    /// Creates an executor that continues the run from this checkpoint.
+    pub fn restore(&self) -> CommandExecutor<'static, CFRBuffer> {
+        CommandExecutor::from_parts(self.state.clone(), self.painter, self.buffer.clone())
+    }
+
+    /// Returns `true` if the executor is in exactly the captured state.
+    pub fn matches<B: AsPixelSink<Sink = CFRBuffer>>(&self, executor: &CommandExecutor<B>) -> bool {
+        let buffer = executor.buffer();
+        self.state.commands == executor.state().commands
+            && self.state.index == executor.view().index
+            && self.state.block_starts == executor.state().block_starts
+            && (self.painter.x, self.painter.y) == (executor.painter().x, executor.painter().y)
+            && self.painter.direction == executor.painter().direction
+            && self.painter.color == executor.painter().color
+            && (self.buffer.width, self.buffer.height) == (buffer.width, buffer.height)
+            && self.buffer.data == buffer.data
+    }
+}
+
+/// A `.cfrsrec` file: the header, the checkpoints in order, and the total number of steps if
+/// the run finished.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub header: RecordingHeader,
+    pub checkpoints: Vec<Checkpoint>,
+    pub total_steps: Option<u64>,
+}
+
+impl Recording {
+    /// Reads a recording.
+    ///
+    /// A record cut off by a crash while it was written is ignored, so a partial recording
+    /// can be resumed from its last complete checkpoint.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a .cfrsrec file".to_string()));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        if version > VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported .cfrsrec version: {}",
+                version
+            )));
+        }
+
+        let header = RecordingHeader {
+            program: read_string(&mut reader)?,
+            width: read_u32(&mut reader)?,
+            height: read_u32(&mut reader)?,
+            background: read_color(&mut reader)?,
+            interval: read_u32(&mut reader)?,
+        };
+        if header.width == 0 || header.height == 0 {
+            return Err(invalid_data(format!(
+                "Invalid canvas size: {}x{}",
+                header.width, header.height
+            )));
+        }
+
+        let mut recording = Recording {
+            header,
+            checkpoints: Vec::new(),
+            total_steps: None,
+        };
+        loop {
+            let mut tag = [0; 1];
+            if reader.read(&mut tag)? == 0 {
+                break;
+            }
+            let record = match tag[0] {
+                CHECKPOINT => read_checkpoint(&mut reader).map(|c| recording.checkpoints.push(c)),
+                END => read_u64(&mut reader).map(|steps| recording.total_steps = Some(steps)),
+                tag => return Err(invalid_data(format!("Unknown record: {}", tag))),
+            };
+            match record {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(recording)
+    }
+
+    /// Returns `true` if the recording contains the end of the run.
+    pub fn is_complete(&self) -> bool {
+        self.total_steps.is_some()
+    }
+}
+
+/// Writes a `.cfrsrec` file record by record.
+///
+/// Every record is flushed as soon as it is written, so a crash loses at most the record in
+/// progress.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::record::{Checkpoint, Recording, RecordingHeader, RecordingWriter};
+/// use cfrs::CFRColor;
+///
+/// let header = RecordingHeader {
+///     program: "[[FR]]".to_string(),
+///     width: 8,
+///     height: 8,
+///     background: CFRColor::Black,
+///     interval: 100,
+/// };
+/// let mut file = Vec::new();
+/// let mut writer = RecordingWriter::new(&mut file, &header).unwrap();
+///
+/// let mut executor = header.executor();
+/// let mut steps = 0;
+/// while executor.step().is_ok() {
+///     steps += 1;
+///     if steps % 4 == 0 {
+///         writer.checkpoint(&Checkpoint::capture(&executor, steps)).unwrap();
+///     }
+/// }
+/// writer.finish(steps).unwrap();
+///
+/// let recording = Recording::read(file.as_slice()).unwrap();
+/// assert_eq!(recording.header, header);
+/// assert_eq!(recording.checkpoints.len(), 4);
+/// assert_eq!(recording.total_steps, Some(17));
+/// ```
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Starts a new recording by writing the header.
+    pub fn new(mut writer: W, header: &RecordingHeader) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_bytes(&mut writer, header.program.as_bytes())?;
+        writer.write_all(&header.width.to_le_bytes())?;
+        writer.write_all(&header.height.to_le_bytes())?;
+        writer.write_all(&[header.background as u8])?;
+        writer.write_all(&header.interval.to_le_bytes())?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// Writes a checkpoint.
+    pub fn checkpoint(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+        let w = &mut self.writer;
+        w.write_all(&[CHECKPOINT])?;
+        w.write_all(&checkpoint.steps.to_le_bytes())?;
+        write_bytes(w, checkpoint.state.commands.as_bytes())?;
+        w.write_all(&(checkpoint.state.index as u64).to_le_bytes())?;
+        w.write_all(&(checkpoint.state.block_starts.len() as u32).to_le_bytes())?;
+        for start in &checkpoint.state.block_starts {
+            w.write_all(&(*start as u64).to_le_bytes())?;
+        }
+        w.write_all(&checkpoint.painter.x.to_le_bytes())?;
+        w.write_all(&checkpoint.painter.y.to_le_bytes())?;
+        w.write_all(&[
+            checkpoint.painter.direction as u8,
+            checkpoint.painter.color as u8,
+        ])?;
+        w.write_all(&checkpoint.buffer.width.to_le_bytes())?;
+        w.write_all(&checkpoint.buffer.height.to_le_bytes())?;
+        let data: Vec<u8> = checkpoint.buffer.data.iter().map(|c| *c as u8).collect();
+        w.write_all(&data)?;
+        w.flush()
+    }
+
+    /// Marks the run as finished after `total_steps` steps.
+    pub fn finish(&mut self, total_steps: u64) -> io::Result<()> {
+        self.writer.write_all(&[END])?;
+        self.writer.write_all(&total_steps.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+fn read_checkpoint<R: Read>(reader: &mut R) -> io::Result<Checkpoint> {
+    let steps = read_u64(reader)?;
+    let commands = read_string(reader)?;
+    let index = read_u64(reader)? as usize;
+    let count = read_u32(reader)?;
+    if count as usize > commands.len() {
+        return Err(invalid_data("Checkpoint index out of range".to_string()));
+    }
+    let block_starts = (0..count)
+        .map(|_| read_u64(reader).map(|start| start as usize))
+        .collect::<io::Result<Vec<usize>>>()?;
+    if index > commands.len() || block_starts.iter().any(|&start| start >= commands.len()) {
+        return Err(invalid_data("Checkpoint index out of range".to_string()));
+    }
+
+    let x = read_u32(reader)?;
+    let y = read_u32(reader)?;
+    let [direction, color] = read_array(reader)?;
+    let direction = *CFRDirection::ALL
+        .get(direction as usize)
+        .ok_or_else(|| invalid_data(format!("Invalid direction: {}", direction)))?;
+    let color = to_color(color)?;
+
+    let width = read_u32(reader)?;
+    let height = read_u32(reader)?;
+    let pixels = (width as usize)
+        .checked_mul(height as usize)
+        .filter(|&pixels| pixels <= MAX_PIXELS)
+        .ok_or_else(|| invalid_data(format!("The canvas is too large: {}x{}", width, height)))?;
+    let mut data = Vec::new();
+    reader.by_ref().take(pixels as u64).read_to_end(&mut data)?;
+    if data.len() != pixels {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    if x >= width.max(1) || y >= height.max(1) {
+        return Err(invalid_data("Painter out of the canvas".to_string()));
+    }
+
+    Ok(Checkpoint {
+        steps,
+        state: CommandExecutorState {
+            commands,
+            index,
+            block_starts,
+            dialect: Dialect::Standard,
+            repeats: Vec::new(),
+            comments: false,
+        },
+        painter: CFRPainter {
+            direction,
+            color,
+            x,
+            y,
+        },
+        buffer: CFRBuffer {
+            width,
+            height,
+            data: data
+                .into_iter()
+                .map(to_color)
+                .collect::<io::Result<Vec<CFRColor>>>()?,
+        },
+    })
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    read_array(reader).map(u32::from_le_bytes)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    read_array(reader).map(u64::from_le_bytes)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_u32(reader)? as u64;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|e| invalid_data(e.to_string()))
+}
+
+fn read_color<R: Read>(reader: &mut R) -> io::Result<CFRColor> {
+    let [color] = read_array(reader)?;
+    to_color(color)
+}
+
+fn to_color(index: u8) -> io::Result<CFRColor> {
+    CFRColor::ALL
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| invalid_data(format!("Invalid color: {}", index)))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+mod tests {
+    #[test]
+    fn resume_from_truncated_recording() {
+        use crate::record::{Checkpoint, Recording, RecordingHeader, RecordingWriter};
+        use crate::CFRColor;
+
+        let header = RecordingHeader {
+            program: "C[[[FFR]]]S".to_string(),
+            width: 16,
+            height: 16,
+            background: CFRColor::Blue,
+            interval: 100,
+        };
+        let mut file = Vec::new();
+        let mut writer = RecordingWriter::new(&mut file, &header).unwrap();
+        let mut executor = header.executor();
+        for steps in 1..=20 {
+            executor.step().unwrap();
+            if steps % 10 == 0 {
+                writer
+                    .checkpoint(&Checkpoint::capture(&executor, steps))
+                    .unwrap();
+            }
+        }
+        executor.run().unwrap();
+
+        // Simulate a crash in the middle of writing the second checkpoint.
+        file.truncate(file.len() - 5);
+        let recording = Recording::read(file.as_slice()).unwrap();
+        assert!(!recording.is_complete());
+        assert_eq!(recording.checkpoints.len(), 1);
+
+        let checkpoint = &recording.checkpoints[0];
+        assert_eq!(checkpoint.steps, 10);
+        let mut resumed = checkpoint.restore();
+        resumed.run().unwrap();
+        assert_eq!(resumed.position(), executor.position());
+        assert_eq!(resumed.buffer().data, executor.buffer().data);
+    }
+
+    #[test]
+    fn reject_huge_canvas() {
+        use crate::record::{Recording, RecordingHeader, RecordingWriter};
+        use crate::CFRColor;
+
+        let header = RecordingHeader {
+            program: String::new(),
+            width: 1,
+            height: 1,
+            background: CFRColor::Black,
+            interval: 100,
+        };
+        let mut file = Vec::new();
+        RecordingWriter::new(&mut file, &header).unwrap();
+        // A checkpoint of an empty program whose canvas claims u32::MAX by u32::MAX pixels.
+        file.push(b'K');
+        file.extend_from_slice(&0u64.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&0u64.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&[0; 10]);
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        let error = Recording::read(file.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
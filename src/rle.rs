@@ -0,0 +1,310 @@
+//! A run-length encoded text form of programs, e.g. `[10 F1 S1 ]4 R1 ]2`.
+//!
+//! Blocks in CFRS repeat a fixed number of times, so every program is finite and so is its
+//! encoding. A `]x∞` repeat would stand for a program without a plain CFRS form, which
+//! `decode` couldn't return, so it is rejected rather than produced.
+
+use crate::parser::{tokenize, TokenKind};
+use crate::preprocess::MAX_OUTPUT_LEN;
+use crate::validator::{Diagnostic, Severity};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A text encoding of programs for `cfrs convert`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextFormat {
+    /// Plain CFRS commands.
+    Cfrs,
+    /// The run-length encoding of `encode`.
+    Rle,
+}
+
+impl FromStr for TextFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cfrs" => Ok(TextFormat::Cfrs),
+            "rle" => Ok(TextFormat::Rle),
+            _ => Err(format!("Invalid text format: {}", s)),
+        }
+    }
+}
+
+/// Encodes a program as space-separated runs of commands, e.g. `F12 R2 C1`, with a
+/// block followed by identical copies of itself written once as `[ ... ]x3`, and runs of
+/// brackets, like the ones of nested blocks, as `[4` and `]4`.
+///
+/// Characters that do nothing are dropped, and so are `#` comments with `comments`, so
+/// `decode` returns the program with only its commands and brackets.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::rle::{decode, encode};
+///
+/// let program = "FFFFRR # turn\n[[F]R][[F]R]C";
+/// let rle = encode(program, true);
+/// assert_eq!(rle, "F4 R2 [2 F1 ] R1 ]x2 C1");
+/// assert_eq!(decode(&rle).unwrap(), "FFFFRR[[F]R][[F]R]C");
+/// ```
+pub fn encode(program: &str, comments: bool) -> String {
+    let commands: Vec<u8> = tokenize(program, comments)
+        .iter()
+        .filter(|t| {
+            matches!(
+                t.kind,
+                TokenKind::Command | TokenKind::BlockStart | TokenKind::BlockEnd
+            )
+        })
+        .map(|t| program.as_bytes()[t.span.start])
+        .collect();
+    let mut tokens = Vec::new();
+    encode_level(&commands, &mut tokens);
+    join_brackets(&tokens).join(" ")
+}
+
+/// Writes every run of `[` or `]` tokens as a single token with a count, e.g. `]3`.
+fn join_brackets(tokens: &[String]) -> Vec<String> {
+    let mut joined = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let run = match token.as_str() {
+            "[" | "]" => tokens[i..].iter().take_while(|&t| t == token).count(),
+            _ => 1,
+        };
+        joined.push(match run {
+            1 => token.clone(),
+            _ => format!("{}{}", token, run),
+        });
+        i += run;
+    }
+    joined
+}
+
+fn encode_level(commands: &[u8], tokens: &mut Vec<String>) {
+    let mut i = 0;
+    while i < commands.len() {
+        let c = commands[i];
+        if let Some(len) = (c == b'[').then(|| block_len(&commands[i..])).flatten() {
+            let block = &commands[i..i + len];
+            let mut times = 1;
+            while commands
+                .get(i + times * len..)
+                .is_some_and(|rest| rest.starts_with(block))
+            {
+                times += 1;
+            }
+            tokens.push("[".to_string());
+            encode_level(&block[1..len - 1], tokens);
+            tokens.push(match times {
+                1 => "]".to_string(),
+                _ => format!("]x{}", times),
+            });
+            i += times * len;
+        } else if c == b'[' || c == b']' {
+            // An unmatched bracket.
+            tokens.push((c as char).to_string());
+            i += 1;
+        } else {
+            let run = commands[i..].iter().take_while(|&&d| d == c).count();
+            tokens.push(format!("{}{}", c as char, run));
+            i += run;
+        }
+    }
+}
+
+/// Returns the length of the block at the start of `commands` up to its matching `]`.
+fn block_len(commands: &[u8]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in commands.iter().enumerate() {
+        match c {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decodes the output of `encode` into a plain program. A command or bracket without a
+/// count appears once, and whitespace is ignored.
+///
+/// Returns an error at an unexpected character, a repeat count after an unmatched `]`, an
+/// infinite repeat `]x∞`, or when the program would be longer than `MAX_OUTPUT_LEN`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::rle::decode;
+///
+/// assert_eq!(decode("F3 R [ C2 S ]x2").unwrap(), "FFFR[CCS][CCS]");
+/// assert_eq!(decode("[3 F ]2 R ]").unwrap(), "[[[F]]R]");
+///
+/// let error = decode("F3 Q").unwrap_err();
+/// assert_eq!(error.message, "Unexpected character");
+/// assert_eq!(error.span, 3..4);
+/// ```
+pub fn decode(text: &str) -> Result<String, Diagnostic> {
+    let mut output = String::new();
+    let mut starts = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            'C' | 'F' | 'R' | 'S' => {
+                let count = number(text, &mut chars)?.unwrap_or(1);
+                reserve(&output, 1, count, i..i + 1)?;
+                output.push_str(&c.to_string().repeat(count));
+            }
+            '[' => {
+                let count = number(text, &mut chars)?.unwrap_or(1);
+                reserve(&output, 1, count, i..i + 1)?;
+                for _ in 0..count {
+                    starts.push(output.len());
+                    output.push('[');
+                }
+            }
+            ']' => {
+                if let Some(count) = number(text, &mut chars)? {
+                    reserve(&output, 1, count, i..i + 1)?;
+                    for _ in 0..count {
+                        starts.pop();
+                        output.push(']');
+                    }
+                    continue;
+                }
+                output.push(']');
+                let start = starts.pop();
+                if chars.next_if(|&(_, c)| c == 'x').is_none() {
+                    continue;
+                }
+                let span = i..i + 2;
+                if chars.next_if(|&(_, c)| c == '∞').is_some() {
+                    let span = i..i + 2 + '∞'.len_utf8();
+                    return Err(error(span, "Infinite repeats have no plain CFRS form"));
+                }
+                let Some(start) = start else {
+                    return Err(error(span, "Repeat without a block"));
+                };
+                let count = match number(text, &mut chars)? {
+                    Some(count) if count > 0 => count,
+                    _ => return Err(error(span, "Expected a repeat count")),
+                };
+                let block = output[start..].to_string();
+                reserve(&output, block.len(), count - 1, span)?;
+                for _ in 1..count {
+                    output.push_str(&block);
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => return Err(error(i..i + c.len_utf8(), "Unexpected character")),
+        }
+    }
+    Ok(output)
+}
+
+/// Parses the digits at `chars`, if any.
+fn number(
+    text: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+) -> Result<Option<usize>, Diagnostic> {
+    let Some(&(start, _)) = chars.peek() else {
+        return Ok(None);
+    };
+    let mut end = start;
+    while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+        end = i + c.len_utf8();
+    }
+    if end == start {
+        return Ok(None);
+    }
+    text[start..end]
+        .parse()
+        .map(Some)
+        .map_err(|_| error(start..end, "Count too large"))
+}
+
+/// Checks that `count` copies of `len` bytes fit after `output`.
+fn reserve(output: &str, len: usize, count: usize, span: Range<usize>) -> Result<(), Diagnostic> {
+    match len.checked_mul(count) {
+        Some(n) if output.len() + n <= MAX_OUTPUT_LEN => Ok(()),
+        _ => Err(error(span, "Program too long")),
+    }
+}
+
+fn error(span: Range<usize>, message: &str) -> Diagnostic {
+    Diagnostic {
+        span,
+        severity: Severity::Error,
+        message: message.to_string(),
+    }
+}
+
+mod tests {
+    #[test]
+    fn round_trips_canonical_programs() {
+        use crate::rle::{decode, encode};
+
+        for program in [
+            "",
+            "[[[[[[[[[[FS]]]]R]]RR]]RRCC]]",
+            "FF]]RR[[F",
+            "[[F][F]][[F][F]]SSS",
+            "[]x",
+        ] {
+            let canonical: String = program.chars().filter(|c| "CFRS[]".contains(*c)).collect();
+            assert_eq!(
+                decode(&encode(program, false)).unwrap(),
+                canonical,
+                "{}",
+                program
+            );
+        }
+        assert_eq!(encode("[[F][F]][[F][F]]", false), "[2 F1 ]x2 ]x2");
+        assert_eq!(encode("FF]]RR[[F", false), "F2 ]2 R2 [2 F1");
+
+        assert_eq!(
+            decode("F ]x2").unwrap_err().message,
+            "Repeat without a block"
+        );
+        assert_eq!(
+            decode("[F]x0").unwrap_err().message,
+            "Expected a repeat count"
+        );
+        assert_eq!(
+            decode("F99999999999999999999999").unwrap_err().message,
+            "Count too large"
+        );
+        assert_eq!(
+            decode("[F9999]x9999").unwrap_err().message,
+            "Program too long"
+        );
+        assert_eq!(
+            decode("[F]x∞").unwrap_err().message,
+            "Infinite repeats have no plain CFRS form"
+        );
+    }
+
+    #[test]
+    fn round_trips_nested_programs() {
+        use crate::rle::{decode, encode};
+
+        let flower = "[[[[[[[[[[FS]]]]R]]RR]]RRCC]]";
+        assert_eq!(encode(flower, false), "[10 F1 S1 ]4 R1 ]2 R2 ]2 R2 C2 ]2");
+        for program in [
+            flower,
+            "[[[[F]]]][[[[F]]]]R[[[[[[C]]]]]]",
+            "[[[[[[[[[[[[[[[[FRFR]]]]]]]]]]]]]]]]",
+            "[[[]]][[S[[F]]]]]]",
+        ] {
+            let rle = encode(program, false);
+            assert_eq!(decode(&rle).unwrap(), program, "{}", rle);
+        }
+    }
+}
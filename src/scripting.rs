@@ -0,0 +1,167 @@
+use crate::entropy::{EntropySource, SplitMix64};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, INT};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Maximum number of operations a script may run, so a runaway loop fails instead of hanging.
+pub const MAX_OPERATIONS: u64 = 100_000_000;
+
+/// Maximum length of the generated program in bytes.
+pub const MAX_PROGRAM_LEN: usize = 16 << 20;
+
+/// Runs a [Rhai](https://rhai.rs) script that generates a CFRS program, and returns the
+/// program.
+///
+/// Scripts build the program with these functions, and a script that evaluates to a string
+/// appends it too:
+///
+/// - `emit(text)` appends any text, e.g. `emit("[[F]R]")`;
+/// - `forward(n)`, `rotate(n)`, `color(n)`, and `sleep(n)` append `n` `F`, `R`, `C`, or `S`;
+/// - `block(n)` and `end(n)` append `n` `[` or `]`.
+///
+/// `params` are available to the script as variables, e.g. `("size", Dynamic::from(5_i64))`.
+/// `random(n)` returns a pseudo-random integer from `0` to `n - 1`, the same sequence on every
+/// run; use `generate_seeded` for other sequences.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::scripting::generate;
+/// use rhai::Dynamic;
+///
+/// let script = r#"
+///     for side in 1..=sides {
+///         forward(side);
+///         rotate(2);
+///     }
+/// "#;
+/// let program = generate(script, vec![("sides", Dynamic::from(3_i64))]).unwrap();
+/// assert_eq!(program, "FRRFFRRFFFRR");
+/// ```
+pub fn generate(script: &str, params: Vec<(&str, Dynamic)>) -> Result<String, Box<EvalAltResult>> {
+    generate_seeded(script, params, 0)
+}
+
+/// Like `generate`, but `random(n)` starts from `seed`, which the script can also read as the
+/// variable `seed`. The same seed always generates the same program.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::scripting::generate_seeded;
+///
+/// let script = r#"forward(1 + random(8)); rotate(seed)"#;
+/// let first = generate_seeded(script, vec![], 7).unwrap();
+/// assert_eq!(first, generate_seeded(script, vec![], 7).unwrap());
+/// assert!(first.ends_with("RRRRRRR"));
+/// ```
+pub fn generate_seeded(
+    script: &str,
+    params: Vec<(&str, Dynamic)>,
+    seed: INT,
+) -> Result<String, Box<EvalAltResult>> {
+    let entropy = Rc::new(RefCell::new(SplitMix64::new(seed as u64)));
+    generate_with(script, params, seed, entropy)
+}
+
+/// Like `generate_seeded`, but `random(n)` takes its numbers from `entropy`, e.g. an
+/// `OsEntropy` in a `Recorder` to generate a new program on every run and still be able to
+/// generate it again with a `Replay`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::entropy::{Recorder, Replay, SplitMix64};
+/// use cfrs::scripting::{generate_seeded, generate_with};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let script = "forward(1 + random(8))";
+/// let recorder = Rc::new(RefCell::new(Recorder::new(SplitMix64::new(3))));
+/// let program = generate_with(script, vec![], 3, recorder.clone()).unwrap();
+/// assert_eq!(program, generate_seeded(script, vec![], 3).unwrap());
+///
+/// let replay: Replay = recorder.borrow().log().parse().unwrap();
+/// let replayed = generate_with(script, vec![], 0, Rc::new(RefCell::new(replay))).unwrap();
+/// assert_eq!(replayed, program);
+/// ```
+pub fn generate_with(
+    script: &str,
+    params: Vec<(&str, Dynamic)>,
+    seed: INT,
+    entropy: Rc<RefCell<dyn EntropySource>>,
+) -> Result<String, Box<EvalAltResult>> {
+    let program = Rc::new(RefCell::new(String::new()));
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_string_size(MAX_PROGRAM_LEN);
+    let register = |engine: &mut Engine, name: &str, command: &'static str| {
+        let program = program.clone();
+        engine.register_fn(name, move |n: INT| -> Result<(), Box<EvalAltResult>> {
+            append(&program, &command.repeat(n.max(0) as usize))
+        });
+    };
+    register(&mut engine, "forward", "F");
+    register(&mut engine, "rotate", "R");
+    register(&mut engine, "color", "C");
+    register(&mut engine, "sleep", "S");
+    register(&mut engine, "block", "[");
+    register(&mut engine, "end", "]");
+    let emitted = program.clone();
+    engine.register_fn("emit", move |text: &str| append(&emitted, text));
+    engine.register_fn("random", move |n: INT| -> INT {
+        entropy.borrow_mut().below(n.max(0) as u64) as INT
+    });
+
+    let mut scope = Scope::new();
+    scope.push("seed", seed);
+    for (name, value) in params {
+        scope.push_dynamic(name, value);
+    }
+    let result = engine.eval_with_scope::<Dynamic>(&mut scope, script)?;
+    if result.is_string() {
+        append(&program, &result.into_string().unwrap())?;
+    }
+
+    let program = program.borrow().clone();
+    Ok(program)
+}
+
+/// Parses a `name=value` script parameter: an integer, a float, `true` or `false`, or else
+/// a string.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::scripting::parse_param;
+///
+/// let (name, value) = parse_param("size=5").unwrap();
+/// assert_eq!(name, "size");
+/// assert_eq!(value.as_int(), Ok(5));
+/// ```
+pub fn parse_param(param: &str) -> Result<(String, Dynamic), String> {
+    let (name, value) = param
+        .split_once('=')
+        .ok_or_else(|| format!("Expected name=value: {}", param))?;
+    let value = if let Ok(n) = value.parse::<INT>() {
+        Dynamic::from(n)
+    } else if let Ok(x) = value.parse::<f64>() {
+        Dynamic::from(x)
+    } else if let Ok(b) = value.parse::<bool>() {
+        Dynamic::from(b)
+    } else {
+        Dynamic::from(value.to_string())
+    };
+    Ok((name.to_string(), value))
+}
+
+/// The SplitMix64 generator, which is good enough for art and easy to reproduce elsewhere.
+fn append(program: &RefCell<String>, text: &str) -> Result<(), Box<EvalAltResult>> {
+    let mut program = program.borrow_mut();
+    if program.len() + text.len() > MAX_PROGRAM_LEN {
+        return Err("Generated program is too long".into());
+    }
+    program.push_str(text);
+    Ok(())
+}
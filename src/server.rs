@@ -0,0 +1,264 @@
+use crate::buffer::CFRBuffer;
+use crate::encode::{encode_png, write_gif, GifOptions};
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::limits::{Meter, ResourceLimits, Usage};
+use axum::extract::State;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Limits enforced on every render request.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Maximum number of commands executed per request.
+    pub max_steps: u64,
+    /// Maximum wall time spent rendering a request.
+    pub timeout: Duration,
+    /// Maximum `width * height` of the canvas.
+    pub max_canvas_pixels: u64,
+    /// Maximum length of the program in bytes.
+    pub max_program_len: usize,
+    /// Maximum number of frames in a GIF response.
+    pub max_frames: usize,
+}
+
+impl ServerOptions {
+    /// Returns the limits that every render is metered against.
+    pub fn limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            max_program_len: self.max_program_len,
+            max_pixels: self.max_canvas_pixels,
+            max_steps: self.max_steps,
+            max_wall_time: Some(self.timeout),
+            max_frames: Some(self.max_frames as u64),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            max_steps: 10_000_000,
+            timeout: Duration::from_secs(5),
+            max_canvas_pixels: 1024 * 1024,
+            max_program_len: 64 * 1024,
+            max_frames: 500,
+        }
+    }
+}
+
+/// The output format of a render request.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    #[default]
+    Png,
+    Gif,
+    Json,
+}
+
+/// The body of `POST /render`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenderRequest {
+    pub program: String,
+    #[serde(default = "default_size")]
+    pub width: u32,
+    #[serde(default = "default_size")]
+    pub height: u32,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub format: RenderFormat,
+    /// Delay between GIF frames in milliseconds.
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+}
+
+fn default_size() -> u32 {
+    256
+}
+
+fn default_interval() -> u32 {
+    100
+}
+
+/// Statistics returned for `"format": "json"` requests.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderStats {
+    pub width: u32,
+    pub height: u32,
+    pub steps: u64,
+    pub sleeps: u64,
+    pub frames: usize,
+    pub wall_time_ms: u64,
+}
+
+struct RenderOutput {
+    buffer: CFRBuffer,
+    frames: Vec<CFRBuffer>,
+    stats: RenderStats,
+}
+
+/// Creates the router serving `POST /render`.
+pub fn router(options: ServerOptions) -> Router {
+    Router::new()
+        .route("/render", post(render))
+        .with_state(Arc::new(options))
+}
+
+/// Serves the render API on the given address until the process is stopped.
+pub async fn serve(addr: SocketAddr, options: ServerOptions) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(options)).await
+}
+
+async fn render(
+    State(options): State<Arc<ServerOptions>>,
+    Json(request): Json<RenderRequest>,
+) -> Response {
+    let background = match request.background.as_deref() {
+        Some(s) => match CFRColor::from_str(s) {
+            Ok(color) => color,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        },
+        None => CFRColor::Black,
+    };
+    if let Err(e) = check_request(&request, &options) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let format = request.format;
+    let interval = request.interval;
+    let timeout = options.timeout;
+    let task = tokio::task::spawn_blocking(move || execute(request, background, &options));
+    let (output, usage) = match tokio::time::timeout(timeout, task).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Err(_) => return (StatusCode::REQUEST_TIMEOUT, "Render timed out").into_response(),
+    };
+    let mut response = match output {
+        Ok(output) => respond(output, format, interval),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, e).into_response(),
+    };
+    add_usage_headers(&mut response, &usage);
+    response
+}
+
+/// Reports the resources a render used, whether it succeeded or not, so that callers can
+/// bill or rate-limit requests.
+fn add_usage_headers(response: &mut Response, usage: &Usage) {
+    let headers = response.headers_mut();
+    headers.insert("x-cfrs-steps", HeaderValue::from(usage.steps));
+    headers.insert("x-cfrs-frames", HeaderValue::from(usage.frames));
+    let wall_time = usage.wall_time.unwrap_or_default().as_millis() as u64;
+    headers.insert("x-cfrs-wall-time-ms", HeaderValue::from(wall_time));
+}
+
+fn respond(output: RenderOutput, format: RenderFormat, interval: u32) -> Response {
+    match format {
+        RenderFormat::Png => match encode_png(&output.buffer) {
+            Ok(bytes) => ([(header::CONTENT_TYPE, "image/png")], bytes).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+        RenderFormat::Gif => {
+            let options = GifOptions {
+                delay: interval,
+                ..GifOptions::default()
+            };
+            let mut bytes = Vec::new();
+            match write_gif(&mut bytes, &output.frames, &options) {
+                Ok(()) => ([(header::CONTENT_TYPE, "image/gif")], bytes).into_response(),
+                Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+            }
+        }
+        RenderFormat::Json => Json(output.stats).into_response(),
+    }
+}
+
+fn check_request(request: &RenderRequest, options: &ServerOptions) -> Result<(), String> {
+    if request.width == 0 || request.height == 0 {
+        return Err("Canvas dimensions must be positive".to_string());
+    }
+    if request.width as u64 * request.height as u64 > options.max_canvas_pixels {
+        return Err(format!(
+            "Canvas exceeds {} pixels",
+            options.max_canvas_pixels
+        ));
+    }
+    if request.program.len() > options.max_program_len {
+        return Err(format!("Program exceeds {} bytes", options.max_program_len));
+    }
+    if request.format == RenderFormat::Gif && request.interval < 20 {
+        return Err("Interval must be at least 20 ms".to_string());
+    }
+    Ok(())
+}
+
+fn execute(
+    request: RenderRequest,
+    background: CFRColor,
+    options: &ServerOptions,
+) -> (Result<RenderOutput, String>, Usage) {
+    let mut meter = Meter::new(&options.limits());
+    let output = execute_metered(request, background, &mut meter);
+    (output, meter.usage())
+}
+
+fn execute_metered(
+    request: RenderRequest,
+    background: CFRColor,
+    meter: &mut Meter,
+) -> Result<RenderOutput, String> {
+    let animation = request.format == RenderFormat::Gif;
+    meter
+        .check_canvas(request.width, request.height)
+        .map_err(|e| e.to_string())?;
+
+    let mut buffer = CFRBuffer::new(request.width, request.height);
+    buffer.data.iter_mut().for_each(|c| *c = background);
+
+    let mut time = 0;
+    let mut frames = Vec::new();
+
+    let mut executor = CommandExecutor::new(request.program, &mut buffer);
+    loop {
+        match executor.step() {
+            Ok((sleep, buf)) => {
+                meter.step(sleep).map_err(|e| e.to_string())?;
+                if sleep {
+                    time += 20;
+                    if animation && time >= request.interval {
+                        time -= request.interval;
+                        meter.frame().map_err(|e| e.to_string())?;
+                        frames.push(buf.clone());
+                    }
+                }
+            }
+            Err("End of commands") => break,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let usage = meter.usage();
+    let stats = RenderStats {
+        width: buffer.width,
+        height: buffer.height,
+        steps: usage.steps,
+        sleeps: usage.sleeps,
+        frames: frames.len(),
+        wall_time_ms: usage.wall_time.unwrap_or_default().as_millis() as u64,
+    };
+    Ok(RenderOutput {
+        buffer,
+        frames,
+        stats,
+    })
+}
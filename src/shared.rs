@@ -0,0 +1,203 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::sink::{AsPixelSink, PixelSink};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// A canvas that many executors can draw on at the same time, from different threads.
+///
+/// Every pixel is an atomic palette index, so drawing never blocks. Executors draw on a
+/// `&SharedCanvas`; when two of them paint the same pixel at once, one of the colors wins.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::shared::SharedCanvas;
+/// use cfrs::{CFRColor, CommandExecutor};
+/// use std::thread;
+///
+/// let canvas = SharedCanvas::new(16, 16, CFRColor::Black);
+/// thread::scope(|s| {
+///     s.spawn(|| CommandExecutor::new("[[[F]]]".to_string(), &canvas).run());
+///     s.spawn(|| CommandExecutor::new("RR[[[F]]]".to_string(), &canvas).run());
+/// });
+/// assert_eq!(canvas.get(7, 0), CFRColor::White);
+/// assert_eq!(canvas.get(15, 7), CFRColor::White);
+/// ```
+#[derive(Debug)]
+pub struct SharedCanvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<AtomicU8>,
+}
+
+impl SharedCanvas {
+    /// Creates a `width`×`height` canvas filled with `background`.
+    pub fn new(width: u32, height: u32, background: CFRColor) -> Self {
+        Self {
+            width,
+            height,
+            pixels: (0..width as usize * height as usize)
+                .map(|_| AtomicU8::new(background as u8))
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the color at (`x`, `y`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside the canvas.
+    pub fn get(&self, x: u32, y: u32) -> CFRColor {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel is outside the canvas"
+        );
+        let index = self.pixels[(y * self.width + x) as usize].load(Ordering::Relaxed);
+        CFRColor::ALL[index as usize]
+    }
+
+    /// Copies the canvas into a `CFRBuffer`. Pixels drawn while copying may or may not be
+    /// included.
+    pub fn snapshot(&self) -> CFRBuffer {
+        CFRBuffer {
+            width: self.width,
+            height: self.height,
+            data: self
+                .pixels
+                .iter()
+                .map(|p| CFRColor::ALL[p.load(Ordering::Relaxed) as usize])
+                .collect(),
+        }
+    }
+}
+
+impl PixelSink for &SharedCanvas {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        self.pixels[(y * self.width + x) as usize].store(color as u8, Ordering::Relaxed);
+    }
+}
+
+impl<'a> AsPixelSink for &'a SharedCanvas {
+    type Sink = &'a SharedCanvas;
+
+    fn sink(&self) -> &Self {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+/// How several programs share a canvas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compose {
+    /// The programs run one after another.
+    Sequential,
+    /// The programs run at the same time with `run_concurrent`.
+    Concurrent,
+}
+
+impl FromStr for Compose {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sequential" => Ok(Compose::Sequential),
+            "concurrent" => Ok(Compose::Concurrent),
+            _ => Err(format!("Invalid composition: {}", s)),
+        }
+    }
+}
+
+/// Runs every program on its own thread, all drawing on `canvas`, and returns their results
+/// in the same order.
+///
+/// Every `S` sleeps for `sleep`, so with a non-zero duration the programs take turns like
+/// they would when animated, instead of the first thread finishing before the others start.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::shared::{run_concurrent, SharedCanvas};
+/// use cfrs::CFRColor;
+/// use std::time::Duration;
+///
+/// let canvas = SharedCanvas::new(16, 16, CFRColor::Black);
+/// let programs = ["[[FS]]".to_string(), "]".to_string()];
+/// let results = run_concurrent(&canvas, &programs, Duration::from_millis(1));
+/// assert_eq!(results, vec![Ok(()), Err("Unmatched ]")]);
+/// assert_eq!(canvas.get(7, 3), CFRColor::White);
+/// ```
+pub fn run_concurrent(
+    canvas: &SharedCanvas,
+    programs: &[String],
+    sleep: Duration,
+) -> Vec<Result<(), &'static str>> {
+    run_concurrent_until(canvas, programs, sleep, &AtomicBool::new(false))
+}
+
+/// Like `run_concurrent`, but every program stops with `Err("Interrupted")` once `stop` is
+/// set, leaving on `canvas` what has been drawn so far.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::shared::{run_concurrent_until, SharedCanvas};
+/// use cfrs::CFRColor;
+/// use std::sync::atomic::AtomicBool;
+/// use std::time::Duration;
+///
+/// let canvas = SharedCanvas::new(16, 16, CFRColor::Black);
+/// let programs = ["[[[[[[[[[[[[[[[[F]]]]]]]]]]]]]]]]".to_string()];
+/// let results = run_concurrent_until(&canvas, &programs, Duration::ZERO, &AtomicBool::new(true));
+/// assert_eq!(results, vec![Err("Interrupted")]);
+/// ```
+pub fn run_concurrent_until(
+    canvas: &SharedCanvas,
+    programs: &[String],
+    sleep: Duration,
+    stop: &AtomicBool,
+) -> Vec<Result<(), &'static str>> {
+    thread::scope(|s| {
+        let handles: Vec<_> = programs
+            .iter()
+            .map(|program| {
+                s.spawn(move || {
+                    let mut executor = CommandExecutor::new(program.clone(), canvas);
+                    loop {
+                        if stop.load(Ordering::Relaxed) {
+                            return Err("Interrupted");
+                        }
+                        match executor.step() {
+                            Ok((true, _)) if !sleep.is_zero() => thread::sleep(sleep),
+                            Ok(_) => {}
+                            Err("End of commands") => return Ok(()),
+                            Err(e) => return Err(e),
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Executor thread panicked"))
+            .collect()
+    })
+}
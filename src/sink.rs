@@ -0,0 +1,120 @@
+use crate::buffer::{pixel_index, CFRBuffer};
+use crate::enums::CFRColor;
+use crate::sparse::SparseBuffer;
+
+/// A surface that a `CommandExecutor` draws on.
+///
+/// Implement this for your own surface, like a framebuffer, to render into it directly
+/// without copying a `CFRBuffer`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::sink::PixelSink;
+/// use cfrs::{CFRColor, CommandExecutor};
+///
+/// /// Counts the pixels drawn in every column.
+/// struct Histogram(Vec<u32>);
+///
+/// impl PixelSink for Histogram {
+///     fn dimensions(&self) -> (u32, u32) {
+///         (self.0.len() as u32, 16)
+///     }
+///
+///     fn set_pixel(&mut self, x: u32, _y: u32, _color: CFRColor) {
+///         self.0[x as usize] += 1;
+///     }
+/// }
+///
+/// let mut histogram = Histogram(vec![0; 4]);
+/// let mut executor = CommandExecutor::new("RR[[FF]]".to_string(), &mut histogram);
+/// executor.run().unwrap();
+/// assert_eq!(histogram.0, vec![2, 2, 2, 2]);
+/// ```
+pub trait PixelSink {
+    /// Returns the width and height of the surface.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Sets the pixel at (`x`, `y`), which is always inside the surface.
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor);
+}
+
+impl PixelSink for CFRBuffer {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        self.data[pixel_index(x, y, self.width)] = color;
+    }
+}
+
+impl PixelSink for SparseBuffer {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: CFRColor) {
+        self.set(x, y, color);
+    }
+}
+
+/// A `PixelSink` that a `CommandExecutor` either owns or borrows.
+///
+/// This is implemented for `&mut S` and `Box<S>` of every `PixelSink`, and for the sinks of
+/// this crate. To let an executor own a sink of your own, implement it with `Sink = Self`.
+pub trait AsPixelSink {
+    type Sink: PixelSink + ?Sized;
+
+    fn sink(&self) -> &Self::Sink;
+
+    fn sink_mut(&mut self) -> &mut Self::Sink;
+}
+
+impl<S: PixelSink + ?Sized> AsPixelSink for &mut S {
+    type Sink = S;
+
+    fn sink(&self) -> &S {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut S {
+        self
+    }
+}
+
+impl<S: PixelSink + ?Sized> AsPixelSink for Box<S> {
+    type Sink = S;
+
+    fn sink(&self) -> &S {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut S {
+        self
+    }
+}
+
+impl AsPixelSink for CFRBuffer {
+    type Sink = Self;
+
+    fn sink(&self) -> &Self {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+impl AsPixelSink for SparseBuffer {
+    type Sink = Self;
+
+    fn sink(&self) -> &Self {
+        self
+    }
+
+    fn sink_mut(&mut self) -> &mut Self {
+        self
+    }
+}
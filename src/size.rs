@@ -0,0 +1,71 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Canvas dimensions, written as `WxH` or as the name of a preset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CanvasSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CanvasSize {
+    /// Named sizes, from the small `spec` canvas to `4k`.
+    pub const PRESETS: &'static [(&'static str, CanvasSize)] = &[
+        ("spec", CanvasSize::new(64, 64)),
+        ("default", CanvasSize::new(256, 256)),
+        ("hd", CanvasSize::new(1280, 720)),
+        ("fhd", CanvasSize::new(1920, 1080)),
+        ("4k", CanvasSize::new(3840, 2160)),
+    ];
+
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl Display for CanvasSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Parses `WxH` (e.g. `128x128`) or a preset name (e.g. `hd`). Both dimensions must be
+/// positive.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use cfrs::size::CanvasSize;
+///
+/// assert_eq!(CanvasSize::from_str("128x96"), Ok(CanvasSize::new(128, 96)));
+/// assert_eq!(CanvasSize::from_str("spec"), Ok(CanvasSize::new(64, 64)));
+/// assert_eq!(CanvasSize::from_str("HD"), Ok(CanvasSize::new(1280, 720)));
+/// assert!(CanvasSize::from_str("0x10").is_err());
+/// assert!(CanvasSize::from_str("10").is_err());
+/// ```
+impl FromStr for CanvasSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        if let Some((_, size)) = Self::PRESETS.iter().find(|(preset, _)| *preset == name) {
+            return Ok(*size);
+        }
+        let dimensions = name
+            .split_once(['x', '×'])
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+        match dimensions {
+            Some((0, _) | (_, 0)) => Err(format!("Canvas dimensions must be positive: {}", s)),
+            Some((width, height)) => Ok(CanvasSize { width, height }),
+            None => {
+                let presets: Vec<&str> = Self::PRESETS.iter().map(|(name, _)| *name).collect();
+                Err(format!(
+                    "Invalid size: {} (expected WxH or one of {})",
+                    s,
+                    presets.join(", ")
+                ))
+            }
+        }
+    }
+}
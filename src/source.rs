@@ -0,0 +1,266 @@
+use crate::draw::{self, glyph};
+use crate::html::escape_html;
+use crate::labels::LabeledImage;
+use crate::parser::{tokenize, TokenKind};
+use std::fmt::Write;
+
+const BACKGROUND: [u8; 3] = [30, 30, 30];
+/// The background of the most executed commands.
+const HOT: [u8; 3] = [190, 60, 30];
+const TEXT: [u8; 3] = [171, 178, 191];
+/// Comments and commands that never ran.
+const DIM: [u8; 3] = [92, 99, 112];
+const INVALID: [u8; 3] = [224, 108, 117];
+
+/// Returns the color of a command in annotated sources.
+fn command_color(c: char) -> [u8; 3] {
+    match c {
+        'F' => [97, 175, 239],
+        'R' => [198, 120, 221],
+        'C' => [229, 192, 123],
+        'S' => [152, 195, 121],
+        '[' | ']' => [86, 182, 194],
+        _ => TEXT,
+    }
+}
+
+/// A character of an annotated source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SourceCell {
+    pub c: char,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+    /// How many times the command ran, or `None` if it is not a command.
+    pub count: Option<u64>,
+}
+
+/// Colors every character of `program` by what it is, with a background from dark to
+/// orange by how many times it ran out of the `counts` of every byte offset, on a
+/// logarithmic scale. Commands that never ran are dimmed. Returns the cells line by line.
+///
+/// `counts` are the `coverage` of `stats::collect`, and `comments` the
+/// `StatsOptions::comments` it ran with.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::source::annotate;
+/// use cfrs::stats::{collect, StatsOptions};
+///
+/// let program = "[F]\n]F # done";
+/// let options = StatsOptions { coverage: true, ..Default::default() };
+/// let lines = annotate(program, &collect(program, &options).coverage.unwrap(), false);
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines[0][1].count, Some(2));
+/// // The `F` after the unmatched `]` never ran.
+/// assert_eq!(lines[1][1].count, Some(0));
+/// assert_ne!(lines[0][1].foreground, lines[1][1].foreground);
+/// assert_eq!(lines[1][3].count, None);
+/// ```
+pub fn annotate(program: &str, counts: &[u64], comments: bool) -> Vec<Vec<SourceCell>> {
+    let mut kinds = vec![None; program.len()];
+    for token in tokenize(program, comments) {
+        kinds[token.span].fill(Some(token.kind));
+    }
+    let max = counts.iter().copied().max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in program.split('\n') {
+        let mut cells = Vec::new();
+        for (i, c) in line.char_indices() {
+            let count = counts.get(offset + i).copied().unwrap_or(0);
+            let (foreground, count) = match kinds[offset + i] {
+                Some(TokenKind::Command | TokenKind::BlockStart | TokenKind::BlockEnd) => {
+                    let color = if count > 0 { command_color(c) } else { DIM };
+                    (color, Some(count))
+                }
+                Some(TokenKind::Comment) => (DIM, None),
+                Some(TokenKind::Invalid) => (INVALID, None),
+                None => (TEXT, None),
+            };
+            let background = match count {
+                Some(count) if count > 0 => heat(count, max),
+                _ => BACKGROUND,
+            };
+            let c = match c {
+                '\t' | '\r' => ' ',
+                c => c,
+            };
+            cells.push(SourceCell {
+                c,
+                foreground,
+                background,
+                count,
+            });
+        }
+        lines.push(cells);
+        offset += line.len() + 1;
+    }
+    lines
+}
+
+/// Returns the background of a command that ran `count` of at most `max` times.
+fn heat(count: u64, max: u64) -> [u8; 3] {
+    let t = ((count as f64).ln_1p() / (max.max(1) as f64).ln_1p()).clamp(0.0, 1.0);
+    // Even commands that ran once get some color, to tell them from those that never ran.
+    let t = 0.15 + 0.85 * t;
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    [
+        mix(BACKGROUND[0], HOT[0]),
+        mix(BACKGROUND[1], HOT[1]),
+        mix(BACKGROUND[2], HOT[2]),
+    ]
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Returns a self-contained HTML page of the annotated program, where hovering a command
+/// shows how many times it ran.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::source::to_html;
+///
+/// let page = to_html("F<", &[3, 0], false, "flower.cfrs");
+/// assert!(page.contains("<title>flower.cfrs</title>"));
+/// assert!(page.contains(r#"title="ran 3 times">F</span>"#));
+/// assert!(page.contains("&lt;</span>"));
+/// ```
+pub fn to_html(program: &str, counts: &[u64], comments: bool, title: &str) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let mut out = String::new();
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ background: {}; color: {}; margin: 0; padding: 16px; }}\n\
+         pre {{ font: 14px/1.5 ui-monospace, monospace; margin: 0 0 16px; }}\n\
+         p {{ font: 12px sans-serif; color: {}; margin: 0; }}\n\
+         </style>\n</head>\n<body>\n<pre>",
+        escape_html(title),
+        hex(BACKGROUND),
+        hex(TEXT),
+        hex(DIM)
+    )
+    .unwrap();
+    for (i, line) in annotate(program, counts, comments).iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        for cell in line {
+            let text = escape_html(&cell.c.to_string());
+            if cell.foreground == TEXT && cell.background == BACKGROUND {
+                out.push_str(&text);
+                continue;
+            }
+            write!(out, "<span style=\"color: {}", hex(cell.foreground)).unwrap();
+            if cell.background != BACKGROUND {
+                write!(out, "; background: {}", hex(cell.background)).unwrap();
+            }
+            out.push('"');
+            match cell.count {
+                Some(1) => out.push_str(" title=\"ran once\""),
+                Some(0) => out.push_str(" title=\"never ran\""),
+                Some(count) => write!(out, " title=\"ran {} times\"", count).unwrap(),
+                None => {}
+            }
+            write!(out, ">{}</span>", text).unwrap();
+        }
+    }
+    write!(
+        out,
+        "</pre>\n<p>The most executed command ran {} times.</p>\n</body>\n</html>\n",
+        max
+    )
+    .unwrap();
+    out
+}
+
+/// Returns an image of the annotated program, in the 3×5 pixel font of `draw::draw_text`
+/// enlarged `scale` times.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::source::to_image;
+///
+/// let image = to_image("[F]\nFF", &[1, 2, 1, 0, 1, 1], false, 2);
+/// // Cells of 8×14 pixels, with a margin of 8 pixels.
+/// assert_eq!((image.width, image.height), (3 * 8 + 16, 2 * 14 + 16));
+/// assert_eq!(image.rgba.len(), (image.width * image.height * 4) as usize);
+/// ```
+pub fn to_image(program: &str, counts: &[u64], comments: bool, scale: u32) -> LabeledImage {
+    let scale = scale.max(1);
+    let lines = annotate(program, counts, comments);
+    let (cell_width, cell_height) = (
+        (draw::GLYPH_WIDTH + 1) * scale,
+        (draw::GLYPH_HEIGHT + 2) * scale,
+    );
+    let margin = 4 * scale;
+    let columns = lines.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let width = columns * cell_width + 2 * margin;
+    let height = lines.len() as u32 * cell_height + 2 * margin;
+    let mut image = LabeledImage {
+        width,
+        height,
+        rgba: [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2], 255].repeat((width * height) as usize),
+    };
+    let mut fill = |x: u32, y: u32, w: u32, h: u32, [r, g, b]: [u8; 3]| {
+        for y in y..y + h {
+            for x in x..x + w {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                image.rgba[i..i + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    };
+    for (row, line) in lines.iter().enumerate() {
+        let top = margin + row as u32 * cell_height;
+        for (column, cell) in line.iter().enumerate() {
+            let left = margin + column as u32 * cell_width;
+            if cell.background != BACKGROUND {
+                fill(left, top, cell_width, cell_height, cell.background);
+            }
+            for (dy, bits) in glyph(cell.c).iter().enumerate() {
+                for dx in 0..draw::GLYPH_WIDTH {
+                    if bits & (0b100 >> dx) != 0 {
+                        fill(
+                            left + dx * scale,
+                            top + (dy as u32 + 1) * scale,
+                            scale,
+                            scale,
+                            cell.foreground,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+mod tests {
+    #[test]
+    fn hotter_commands_get_warmer_backgrounds() {
+        use crate::source::{annotate, to_image};
+
+        let lines = annotate("FFF\tx", &[0, 1, 100, 0, 0], false);
+        let backgrounds: Vec<[u8; 3]> = lines[0].iter().map(|c| c.background).collect();
+        assert_eq!(backgrounds[0], backgrounds[3]);
+        assert!(backgrounds[1][0] > backgrounds[0][0]);
+        assert!(backgrounds[2][0] > backgrounds[1][0]);
+        assert_eq!(lines[0][3].c, ' ');
+
+        // A hot `F` fills its whole cell, around the glyph too.
+        let image = to_image("F", &[5], false, 1);
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * image.width + x) * 4) as usize;
+            [image.rgba[i], image.rgba[i + 1], image.rgba[i + 2]]
+        };
+        assert_eq!(pixel(4 + 3, 4), lines[0][2].background);
+        assert_ne!(pixel(4, 5), pixel(4 + 3, 4));
+        assert_eq!(pixel(0, 0), [30, 30, 30]);
+    }
+}
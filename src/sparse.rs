@@ -0,0 +1,183 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+
+/// Width and height of the chunks of a `SparseBuffer`.
+pub const CHUNK_SIZE: u32 = 256;
+
+/// A canvas stored in `CHUNK_SIZE`×`CHUNK_SIZE` chunks that are only allocated when a pixel in
+/// them is painted with a color other than the background.
+///
+/// Programs usually touch a small part of a huge canvas, so a 16384×16384 render needs a few
+/// megabytes instead of 256 MiB up front.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::sparse::SparseBuffer;
+/// use cfrs::CFRColor;
+///
+/// let mut buffer = SparseBuffer::new(16384, 16384, CFRColor::Black);
+/// assert_eq!(buffer.allocated_chunks(), 0);
+///
+/// buffer.set(10000, 20, CFRColor::Red);
+/// assert_eq!(buffer.get(10000, 20), CFRColor::Red);
+/// assert_eq!(buffer.get(0, 0), CFRColor::Black);
+/// assert_eq!(buffer.allocated_chunks(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SparseBuffer {
+    width: u32,
+    height: u32,
+    background: CFRColor,
+    chunks_x: u32,
+    chunks: Vec<Option<Box<[CFRColor]>>>,
+}
+
+impl SparseBuffer {
+    /// Creates a `width`×`height` canvas filled with `background` without allocating any chunk.
+    pub fn new(width: u32, height: u32, background: CFRColor) -> Self {
+        let chunks_x = width.div_ceil(CHUNK_SIZE);
+        let chunks_y = height.div_ceil(CHUNK_SIZE);
+        Self {
+            width,
+            height,
+            background,
+            chunks_x,
+            chunks: vec![None; chunks_x as usize * chunks_y as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn background(&self) -> CFRColor {
+        self.background
+    }
+
+    /// Returns the number of chunks that have been allocated.
+    pub fn allocated_chunks(&self) -> usize {
+        self.chunks.iter().filter(|c| c.is_some()).count()
+    }
+
+    /// Returns the color at (`x`, `y`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside the canvas.
+    pub fn get(&self, x: u32, y: u32) -> CFRColor {
+        let (chunk, offset) = self.locate(x, y);
+        self.chunks[chunk]
+            .as_ref()
+            .map_or(self.background, |c| c[offset])
+    }
+
+    /// Sets the color at (`x`, `y`), allocating its chunk if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside the canvas.
+    pub fn set(&mut self, x: u32, y: u32, color: CFRColor) {
+        let (chunk, offset) = self.locate(x, y);
+        let background = self.background;
+        match &mut self.chunks[chunk] {
+            Some(chunk) => chunk[offset] = color,
+            None if color == background => {}
+            empty => {
+                let mut chunk = vec![background; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+                chunk[offset] = color;
+                *empty = Some(chunk.into_boxed_slice());
+            }
+        }
+    }
+
+    /// Copies a region of the canvas into a `CFRBuffer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not inside the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::sparse::SparseBuffer;
+    /// use cfrs::CFRColor;
+    ///
+    /// let mut buffer = SparseBuffer::new(1000, 1000, CFRColor::Blue);
+    /// buffer.set(300, 400, CFRColor::Red);
+    /// let region = buffer.crop(299, 400, 2, 1);
+    /// assert_eq!(region.data, vec![CFRColor::Blue, CFRColor::Red]);
+    /// ```
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> CFRBuffer {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "Region is outside the canvas"
+        );
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for row in y..y + height {
+            data.extend((x..x + width).map(|column| self.get(column, row)));
+        }
+        CFRBuffer {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Copies the whole canvas into a `CFRBuffer`.
+    pub fn to_buffer(&self) -> CFRBuffer {
+        self.crop(0, 0, self.width, self.height)
+    }
+
+    fn locate(&self, x: u32, y: u32) -> (usize, usize) {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel is outside the canvas"
+        );
+        let chunk = (y / CHUNK_SIZE) * self.chunks_x + x / CHUNK_SIZE;
+        let offset = (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE;
+        (chunk as usize, offset as usize)
+    }
+}
+
+/// Runs a program on a `SparseBuffer`, like `CommandExecutor` does on a `CFRBuffer`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::sparse::{SparseBuffer, SparseExecutor};
+/// use cfrs::CFRColor;
+///
+/// let mut buffer = SparseBuffer::new(16384, 16384, CFRColor::Black);
+/// let mut executor = SparseExecutor::new("[[[[[[[F]]]]]]]".to_string(), &mut buffer);
+/// executor.run().unwrap();
+/// assert_eq!(executor.position(), (8191, 8191 - 128));
+/// assert_eq!(buffer.allocated_chunks(), 1);
+/// ```
+pub type SparseExecutor<'a> = CommandExecutor<'a, &'a mut SparseBuffer>;
+
+mod tests {
+    #[test]
+    fn matches_dense_execution() {
+        use crate::sparse::{SparseBuffer, SparseExecutor};
+        use crate::{CFRBuffer, CFRColor, CommandExecutor};
+
+        let program = "[[[[[[[[[[FS]]]]R]]RR]]RRCC]]".to_string();
+        let mut dense = CFRBuffer::new(300, 200);
+        let mut expected = CommandExecutor::new(program.clone(), &mut dense);
+        expected.run().unwrap();
+        let position = expected.position();
+
+        let mut sparse = SparseBuffer::new(300, 200, CFRColor::Black);
+        let mut executor = SparseExecutor::new(program, &mut sparse);
+        executor.run().unwrap();
+
+        assert_eq!(executor.position(), position);
+        assert_eq!(sparse.to_buffer().data, dense.data);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::painter::Wraps;
+use crate::parser::{scenes, tokenize, TokenKind};
+use crate::validator::bracket_pairs;
+use std::fmt::Write;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Options of `collect`.
+#[derive(Debug, Clone)]
+pub struct StatsOptions {
+    pub width: u32,
+    pub height: u32,
+    pub background: CFRColor,
+    /// Maximum number of executed steps.
+    pub max_steps: u64,
+    /// Stop once the painter has wrapped around the edges more than this many times.
+    pub max_wraps: Option<u64>,
+    /// Count how many times every command is executed.
+    pub coverage: bool,
+    /// Measure the steps and time spent in every block.
+    pub profile: bool,
+    /// Let `#` comment out the rest of its line, like `CommandExecutor::set_comments`.
+    /// Scenes are only counted with comments, since their markers are comments.
+    pub comments: bool,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            background: CFRColor::Black,
+            max_steps: 100_000_000,
+            max_wraps: None,
+            coverage: false,
+            profile: false,
+            comments: false,
+        }
+    }
+}
+
+/// Statistics of a run.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub steps: u64,
+    pub sleeps: u64,
+    /// Number of pixels that differ from the background at the end.
+    pub painted: usize,
+    /// Number of distinct colors other than the background at the end.
+    pub colors: usize,
+    /// Final position of the painter.
+    pub position: (u32, u32),
+    /// How many times the painter wrapped around the edges of the canvas.
+    pub wraps: Wraps,
+    /// Why the run stopped early, if it did not reach the end of the program.
+    pub error: Option<&'static str>,
+    /// With `StatsOptions::coverage`, how many times the command at every byte offset of the
+    /// program was executed. Both the jump back and the fall-through of a `]` count, and so
+    /// does a command that stopped the run with an error.
+    pub coverage: Option<Vec<u64>>,
+    /// With `StatsOptions::profile`, the whole program followed by every matched block in
+    /// source order.
+    pub profile: Option<Vec<BlockProfile>>,
+    /// The steps and sleeps of every `#scene` of the program, in order, with
+    /// `StatsOptions::comments`.
+    pub scenes: Vec<SceneStats>,
+}
+
+/// The steps and sleeps executed within a scene (see `parser::scenes`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneStats {
+    pub name: String,
+    pub span: Range<usize>,
+    pub steps: u64,
+    pub sleeps: u64,
+}
+
+/// The steps and wall time spent in a block, including its nested blocks.
+#[derive(Debug, Clone)]
+pub struct BlockProfile {
+    /// Byte range of the block, from its `[` to its `]`, or the whole program.
+    pub span: Range<usize>,
+    /// Nesting depth: 0 for the whole program, 1 for outermost blocks.
+    pub depth: usize,
+    pub steps: u64,
+    pub time: Duration,
+    parent: usize,
+}
+
+/// Runs a program and collects statistics.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::stats::{collect, StatsOptions};
+///
+/// let options = StatsOptions { coverage: true, ..Default::default() };
+/// let stats = collect("[FS]", &options);
+/// assert_eq!((stats.steps, stats.sleeps, stats.painted, stats.colors), (7, 2, 2, 1));
+/// assert_eq!(stats.coverage.unwrap(), vec![1, 2, 2, 2]);
+///
+/// let options = StatsOptions { comments: true, ..Default::default() };
+/// let stats = collect("#scene one\nFF\n#scene two\n[S]", &options);
+/// assert_eq!((stats.scenes[0].steps, stats.scenes[1].sleeps), (4, 2));
+/// ```
+pub fn collect(program: &str, options: &StatsOptions) -> Stats {
+    let mut buffer = CFRBuffer::new(options.width, options.height);
+    buffer.data.iter_mut().for_each(|c| *c = options.background);
+    let mut coverage = options.coverage.then(|| vec![0; program.len()]);
+    let mut profile = options
+        .profile
+        .then(|| Profiler::new(program, options.comments));
+    let markers = if options.comments {
+        scenes(program)
+    } else {
+        Vec::new()
+    };
+    let mut scenes: Vec<SceneStats> = markers
+        .into_iter()
+        .map(|scene| SceneStats {
+            name: scene.name,
+            span: scene.span,
+            steps: 0,
+            sleeps: 0,
+        })
+        .collect();
+
+    let mut executor = CommandExecutor::new(program.to_string(), &mut buffer);
+    executor.set_max_wraps(options.max_wraps);
+    executor.set_comments(options.comments);
+    let mut steps = 0;
+    let mut sleeps = 0;
+    let error = loop {
+        if steps >= options.max_steps {
+            break Some("Step limit exceeded");
+        }
+        let index = executor.view().index;
+        let start = profile.as_ref().map(|_| Instant::now());
+        let result = executor.step();
+        if !matches!(result, Err("End of commands")) {
+            if let Some(counts) = &mut coverage {
+                counts[index] += 1;
+            }
+            if let (Some(profiler), Some(start)) = (&mut profile, start) {
+                profiler.record(index, start.elapsed());
+            }
+        }
+        match result {
+            Ok((sleep, _)) => {
+                steps += 1;
+                sleeps += sleep as u64;
+                let scene = scenes.partition_point(|s| s.span.start <= index);
+                if let Some(scene) = scene.checked_sub(1).map(|i| &mut scenes[i]) {
+                    scene.steps += 1;
+                    scene.sleeps += sleep as u64;
+                }
+            }
+            Err("End of commands") => break None,
+            Err(e) => break Some(e),
+        }
+    };
+    let position = executor.position();
+    let wraps = executor.wraps();
+    let mut used = [false; CFRColor::ALL.len()];
+    for color in buffer.data.iter().filter(|c| **c != options.background) {
+        used[*color as usize] = true;
+    }
+
+    Stats {
+        steps,
+        sleeps,
+        painted: buffer
+            .data
+            .iter()
+            .filter(|c| **c != options.background)
+            .count(),
+        colors: used.iter().filter(|&&used| used).count(),
+        position,
+        wraps,
+        error,
+        coverage,
+        profile: profile.map(Profiler::finish),
+        scenes,
+    }
+}
+
+/// Attributes every step to the innermost block around it.
+struct Profiler {
+    blocks: Vec<BlockProfile>,
+    /// The innermost block of every byte offset.
+    owners: Vec<usize>,
+}
+
+impl Profiler {
+    fn new(program: &str, comments: bool) -> Self {
+        let mut blocks = vec![BlockProfile {
+            span: 0..program.len(),
+            depth: 0,
+            steps: 0,
+            time: Duration::ZERO,
+            parent: 0,
+        }];
+        let mut owners = vec![0; program.len()];
+        // Pairs are ordered by their `[`, so parents come before their children.
+        let mut open: Vec<usize> = Vec::new();
+        for (start, end) in bracket_pairs(program, comments) {
+            while open.last().is_some_and(|&i| blocks[i].span.end <= start) {
+                open.pop();
+            }
+            let id = blocks.len();
+            blocks.push(BlockProfile {
+                span: start..end + 1,
+                depth: open.len() + 1,
+                steps: 0,
+                time: Duration::ZERO,
+                parent: open.last().copied().unwrap_or(0),
+            });
+            owners[start..=end].iter_mut().for_each(|o| *o = id);
+            open.push(id);
+        }
+        Self { blocks, owners }
+    }
+
+    fn record(&mut self, index: usize, time: Duration) {
+        let block = &mut self.blocks[self.owners[index]];
+        block.steps += 1;
+        block.time += time;
+    }
+
+    fn finish(mut self) -> Vec<BlockProfile> {
+        for id in (1..self.blocks.len()).rev() {
+            let (steps, time, parent) = {
+                let block = &self.blocks[id];
+                (block.steps, block.time, block.parent)
+            };
+            self.blocks[parent].steps += steps;
+            self.blocks[parent].time += time;
+        }
+        self.blocks
+    }
+}
+
+/// Formats a profile as an indented tree, one line per block with its share of the steps.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::stats::{collect, format_profile, StatsOptions};
+///
+/// let program = "F[[F]R]";
+/// let options = StatsOptions { profile: true, ..Default::default() };
+/// let profile = collect(program, &options).profile.unwrap();
+/// assert_eq!(profile[0].steps, 16);
+/// assert_eq!(profile[1].steps, 15);
+/// assert_eq!(profile[2].steps, 10);
+///
+/// let summary = format_profile(program, &profile);
+/// assert!(summary.lines().nth(2).unwrap().ends_with(" 93.8%    [[F]R]"));
+/// ```
+pub fn format_profile(program: &str, profile: &[BlockProfile]) -> String {
+    let total = profile.first().map_or(0, |b| b.steps).max(1);
+    let mut out = format!("{:>12} {:>10} {:>7}  block\n", "steps", "time", "share");
+    for block in profile {
+        let source: String = program[block.span.clone()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let source = match source.char_indices().nth(40) {
+            Some((i, _)) => format!("{}...", &source[..i]),
+            None => source,
+        };
+        writeln!(
+            out,
+            "{:>12} {:>10} {:>6.1}%  {}{}",
+            block.steps,
+            format!("{:.2?}", block.time),
+            block.steps as f64 * 100.0 / total as f64,
+            "  ".repeat(block.depth),
+            if block.depth == 0 {
+                "(program)"
+            } else {
+                &source
+            },
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Returns the commands and brackets of a program as `(executed, total)`, without those in
+/// `#` comments if the program ran with `comments`.
+pub fn coverage_summary(program: &str, counts: &[u64], comments: bool) -> (usize, usize) {
+    let commands: Vec<_> = tokenize(program, comments)
+        .into_iter()
+        .filter(|t| is_command(t.kind))
+        .collect();
+    let executed = commands.iter().filter(|t| counts[t.span.start] > 0).count();
+    (executed, commands.len())
+}
+
+/// Prints the program with every command that was never executed marked.
+///
+/// With `ansi`, unexecuted commands get a red background. Otherwise, every line with
+/// unexecuted commands is followed by a line with `^` below them. `comments` is the
+/// `StatsOptions::comments` the program ran with.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::stats::{annotate_coverage, collect, StatsOptions};
+///
+/// let program = "F]\n[F]";
+/// let options = StatsOptions { coverage: true, ..Default::default() };
+/// let stats = collect(program, &options);
+/// let annotated = annotate_coverage(program, &stats.coverage.unwrap(), false, false);
+/// assert_eq!(annotated, "F]\n[F]\n^^^\n");
+/// ```
+pub fn annotate_coverage(program: &str, counts: &[u64], comments: bool, ansi: bool) -> String {
+    let unexecuted: Vec<_> = tokenize(program, comments)
+        .into_iter()
+        .filter(|t| is_command(t.kind) && counts[t.span.start] == 0)
+        .map(|t| t.span.start)
+        .collect();
+
+    let mut out = String::new();
+    let mut offset = 0;
+    for line in program.split('\n') {
+        let mut markers = String::new();
+        for (i, c) in line.char_indices() {
+            let missed = unexecuted.binary_search(&(offset + i)).is_ok();
+            if ansi && missed {
+                write!(out, "\x1b[41m{}\x1b[0m", c).unwrap();
+            } else {
+                out.push(c);
+            }
+            markers.push(if missed { '^' } else { ' ' });
+        }
+        out.push('\n');
+        if !ansi && markers.contains('^') {
+            out.push_str(markers.trim_end());
+            out.push('\n');
+        }
+        offset += line.len() + 1;
+    }
+    out
+}
+
+fn is_command(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Command | TokenKind::BlockStart | TokenKind::BlockEnd
+    )
+}
@@ -0,0 +1,71 @@
+use crate::buffer::CFRBuffer;
+use crate::executor::CommandExecutor;
+use crate::sink::AsPixelSink;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+/// Number of events that can be queued before the background thread waits for the receiver.
+pub const STREAM_CAPACITY: usize = 16;
+
+/// An event sent by `CommandExecutor::stream_frames`.
+#[derive(Debug, Clone)]
+pub enum FrameEvent {
+    /// The program executed an `S`.
+    Sleep,
+    /// A frame of the animation, captured every `interval` milliseconds of sleep.
+    Frame(CFRBuffer),
+    /// The program finished; this is the final canvas and the last event.
+    Done(CFRBuffer),
+    /// The program failed; this is the last event.
+    Error(&'static str),
+}
+
+impl<B: AsPixelSink<Sink = CFRBuffer> + Send + 'static> CommandExecutor<'static, B> {
+    /// Runs the program on a background thread and sends its frames over a channel.
+    ///
+    /// Frames are captured like the CLI does for animations: every `S` sleeps 20ms, and a
+    /// frame is sent whenever `interval` milliseconds of sleep have passed. The channel holds
+    /// at most `STREAM_CAPACITY` events, so the program only runs ahead of the receiver by a
+    /// few frames. Dropping the receiver stops the thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::stream::FrameEvent;
+    /// use cfrs::{CFRBuffer, CommandExecutor};
+    ///
+    /// let executor = CommandExecutor::new("[[FS]]".to_string(), CFRBuffer::new(8, 8));
+    /// let events: Vec<_> = executor.stream_frames(40).into_iter().collect();
+    /// let frames = events.iter().filter(|e| matches!(e, FrameEvent::Frame(_))).count();
+    /// assert_eq!(frames, 2);
+    /// assert!(matches!(events.last(), Some(FrameEvent::Done(_))));
+    /// ```
+    pub fn stream_frames(mut self, interval: u32) -> Receiver<FrameEvent> {
+        let (sender, receiver) = sync_channel(STREAM_CAPACITY);
+        thread::spawn(move || {
+            let mut time = 0;
+            loop {
+                let event = match self.step() {
+                    Ok((false, _)) => continue,
+                    Ok((true, _)) => FrameEvent::Sleep,
+                    Err("End of commands") => FrameEvent::Done(self.buffer().clone()),
+                    Err(e) => FrameEvent::Error(e),
+                };
+                let last = matches!(event, FrameEvent::Done(_) | FrameEvent::Error(_));
+                if sender.send(event).is_err() || last {
+                    return;
+                }
+
+                time += 20;
+                if time >= interval {
+                    time -= interval;
+                    let frame = FrameEvent::Frame(self.buffer().clone());
+                    if sender.send(frame).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
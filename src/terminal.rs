@@ -0,0 +1,214 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// The colors a terminal can show.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24-bit RGB colors.
+    TrueColor,
+    /// The xterm 256-color palette.
+    Ansi256,
+    /// The 8 standard and 8 bright ANSI colors.
+    Ansi16,
+}
+
+impl ColorMode {
+    /// Detects the color support of the terminal from the `COLORTERM` and `TERM` environment
+    /// variables, falling back to 16 colors when they don't tell.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        Self::detect_from(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// Like `detect`, with the values of `COLORTERM` and `TERM`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cfrs::terminal::ColorMode;
+    ///
+    /// assert_eq!(ColorMode::detect_from(Some("truecolor"), Some("xterm")), ColorMode::TrueColor);
+    /// assert_eq!(ColorMode::detect_from(None, Some("xterm-256color")), ColorMode::Ansi256);
+    /// assert_eq!(ColorMode::detect_from(None, Some("vt100")), ColorMode::Ansi16);
+    /// ```
+    pub fn detect_from(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or_default();
+        match colorterm.unwrap_or_default() {
+            "truecolor" | "24bit" => ColorMode::TrueColor,
+            _ if term.ends_with("-direct") => ColorMode::TrueColor,
+            _ if term.contains("256color") => ColorMode::Ansi256,
+            _ => ColorMode::Ansi16,
+        }
+    }
+
+    /// Writes the escape sequence that sets the foreground (or `background`) color.
+    fn write_color(self, out: &mut String, [r, g, b]: [u8; 3], background: bool) {
+        match self {
+            ColorMode::TrueColor => {
+                let code = if background { 48 } else { 38 };
+                write!(out, "\x1b[{};2;{};{};{}m", code, r, g, b).unwrap();
+            }
+            ColorMode::Ansi256 => {
+                // The nearest color of the 6×6×6 cube.
+                let level = |c: u8| (c as u32 * 5 + 127) / 255;
+                let index = 16 + 36 * level(r) + 6 * level(g) + level(b);
+                let code = if background { 48 } else { 38 };
+                write!(out, "\x1b[{};5;{}m", code, index).unwrap();
+            }
+            ColorMode::Ansi16 => {
+                // The CFRS colors are the bright ANSI colors, and black.
+                let bits = (r > 127) as u32 | ((g > 127) as u32) << 1 | ((b > 127) as u32) << 2;
+                let base = match (bits, background) {
+                    (0, false) => 30,
+                    (0, true) => 40,
+                    (_, false) => 90,
+                    (_, true) => 100,
+                };
+                write!(out, "\x1b[{}m", base + bits).unwrap();
+            }
+        }
+    }
+}
+
+/// Parses `truecolor` (or `24bit`), `256`, or `16`.
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(ColorMode::TrueColor),
+            "256" => Ok(ColorMode::Ansi256),
+            "16" => Ok(ColorMode::Ansi16),
+            _ => Err(format!("Invalid color mode: {}", s)),
+        }
+    }
+}
+
+/// Renders the buffer as ANSI truecolor art using half-block characters.
+///
+/// Every character shows two vertically stacked pixels. Large buffers are scaled down by an
+/// integer factor (nearest-neighbor) so the output fits in `max_columns` columns and
+/// `max_rows` lines.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::buffer::CFRBuffer;
+/// use cfrs::terminal::to_ansi;
+///
+/// let buffer = CFRBuffer::new(256, 256);
+/// let art = to_ansi(&buffer, 64, 32);
+/// assert_eq!(art.lines().count(), 32);
+/// ```
+pub fn to_ansi(buffer: &CFRBuffer, max_columns: u32, max_rows: u32) -> String {
+    to_ansi_with_mode(buffer, max_columns, max_rows, ColorMode::TrueColor)
+}
+
+/// Like `to_ansi`, with the colors of `mode` for terminals without truecolor support.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::terminal::{to_ansi_with_mode, ColorMode};
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(1, 2);
+/// buffer.data[0] = CFRColor::Red;
+/// let art = to_ansi_with_mode(&buffer, 80, 24, ColorMode::Ansi16);
+/// assert_eq!(art, "\x1b[91m\x1b[40m▀\x1b[0m\n");
+/// let art = to_ansi_with_mode(&buffer, 80, 24, ColorMode::Ansi256);
+/// assert_eq!(art, "\x1b[38;5;196m\x1b[48;5;16m▀\x1b[0m\n");
+/// ```
+pub fn to_ansi_with_mode(
+    buffer: &CFRBuffer,
+    max_columns: u32,
+    max_rows: u32,
+    mode: ColorMode,
+) -> String {
+    let step = buffer
+        .width
+        .div_ceil(max_columns.max(1))
+        .max(buffer.height.div_ceil(max_rows.max(1) * 2))
+        .max(1);
+
+    let mut out = String::new();
+    for y in (0..buffer.height).step_by(2 * step as usize) {
+        for x in (0..buffer.width).step_by(step as usize) {
+            let upper = buffer.data[(y * buffer.width + x) as usize];
+            mode.write_color(&mut out, upper.to_rgb(), false);
+            if y + step < buffer.height {
+                let lower = buffer.data[((y + step) * buffer.width + x) as usize];
+                mode.write_color(&mut out, lower.to_rgb(), true);
+            } else {
+                out.push_str("\x1b[49m");
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Renders the buffer as plain text with one character per pixel: `.` for `background`, and
+/// the initial of every other color, with `K` for black.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::terminal::to_ascii;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(3, 2);
+/// buffer.data[1] = CFRColor::White;
+/// buffer.data[5] = CFRColor::Magenta;
+/// assert_eq!(to_ascii(&buffer, CFRColor::Black), ".W.\n..M\n");
+/// ```
+pub fn to_ascii(buffer: &CFRBuffer, background: CFRColor) -> String {
+    let mut out = String::with_capacity((buffer.width as usize + 1) * buffer.height as usize);
+    for row in buffer.data.chunks(buffer.width.max(1) as usize) {
+        for &color in row {
+            out.push(match color {
+                c if c == background => '.',
+                CFRColor::White => 'W',
+                CFRColor::Black => 'K',
+                CFRColor::Blue => 'B',
+                CFRColor::Green => 'G',
+                CFRColor::Cyan => 'C',
+                CFRColor::Red => 'R',
+                CFRColor::Magenta => 'M',
+                CFRColor::Yellow => 'Y',
+                #[cfg(feature = "transparent")]
+                CFRColor::Transparent => 'T',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+mod tests {
+    #[test]
+    fn pairs_rows_into_half_blocks() {
+        use crate::terminal::{to_ansi, to_ansi_with_mode, ColorMode};
+        use crate::{CFRBuffer, CFRColor};
+
+        let mut buffer = CFRBuffer::new(1, 3);
+        buffer.data[0] = CFRColor::Red;
+        buffer.data[1] = CFRColor::Blue;
+        let art = to_ansi(&buffer, 80, 24);
+        assert_eq!(
+            art,
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\n\x1b[38;2;0;0;0m\x1b[49m▀\x1b[0m\n"
+        );
+
+        let art = to_ansi_with_mode(&buffer, 80, 24, ColorMode::Ansi256);
+        assert!(art.starts_with("\x1b[38;5;196m\x1b[48;5;21m▀"));
+        let art = to_ansi_with_mode(&buffer, 80, 24, ColorMode::Ansi16);
+        assert!(art.starts_with("\x1b[91m\x1b[104m▀"));
+        assert_eq!("24BIT".parse(), Ok(ColorMode::TrueColor));
+        assert!("8".parse::<ColorMode>().is_err());
+    }
+}
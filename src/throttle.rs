@@ -0,0 +1,140 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an `S` command sleeps at normal speed.
+pub const SLEEP: Duration = Duration::from_millis(20);
+
+/// Paces a program for real-time playback.
+///
+/// Call `tick` after every step and `wait` (or, in event loops that can't block, schedule the
+/// next update after `remaining`) before the next one. Every `S` takes 20ms and, with
+/// `set_commands_per_second`, every step takes a fixed time too, all divided by the speed.
+/// The throttle keeps its own clock, so time spent drawing doesn't slow playback down.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::throttle::Throttle;
+/// use cfrs::{CFRBuffer, CommandExecutor};
+///
+/// let mut executor = CommandExecutor::new("[[FS]]".to_string(), CFRBuffer::new(16, 16));
+/// let mut throttle = Throttle::new(16.0);
+/// while let Ok((sleep, _)) = executor.step() {
+///     throttle.tick(sleep);
+///     throttle.wait();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    speed: f64,
+    commands_per_second: Option<f64>,
+    start: Instant,
+    /// Program time since `start`, at normal speed.
+    time: Duration,
+}
+
+impl Throttle {
+    pub const MIN_SPEED: f64 = 0.25;
+    pub const MAX_SPEED: f64 = 16.0;
+    /// How far playback may fall behind before the throttle stops trying to catch up.
+    const MAX_LAG: Duration = Duration::from_millis(100);
+
+    /// Creates a throttle that plays at `speed` times the normal speed, clamped to
+    /// `MIN_SPEED..=MAX_SPEED`.
+    pub fn new(speed: f64) -> Self {
+        Self {
+            speed: clamp_speed(speed),
+            commands_per_second: None,
+            start: Instant::now(),
+            time: Duration::ZERO,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Changes the speed from now on, clamped to `MIN_SPEED..=MAX_SPEED`.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = clamp_speed(speed);
+        self.reset();
+    }
+
+    /// Makes every step take `1 / commands_per_second` seconds at normal speed, on top of the
+    /// sleeps, so drawing is visible between sleeps; `None` runs steps without delay.
+    pub fn set_commands_per_second(&mut self, commands_per_second: Option<f64>) {
+        self.commands_per_second = commands_per_second.filter(|&cps| cps > 0.0);
+        self.reset();
+    }
+
+    /// Starts counting from now, forgetting any pending wait.
+    pub fn reset(&mut self) {
+        self.start = Instant::now();
+        self.time = Duration::ZERO;
+    }
+
+    /// Records a step, which was a sleep if `sleep` is true.
+    pub fn tick(&mut self, sleep: bool) {
+        if sleep {
+            self.time += SLEEP;
+        }
+        if let Some(cps) = self.commands_per_second {
+            self.time += Duration::from_secs_f64(1.0 / cps);
+        }
+        // Don't rush to catch up after the caller stalled, e.g. while a window was hidden.
+        let target = self.target();
+        let elapsed = self.start.elapsed();
+        if elapsed > target + Self::MAX_LAG {
+            self.start += elapsed - target - Self::MAX_LAG;
+        }
+    }
+
+    /// Returns how long to wait before the next step.
+    pub fn remaining(&self) -> Duration {
+        self.target().saturating_sub(self.start.elapsed())
+    }
+
+    /// Blocks the thread for `remaining`.
+    pub fn wait(&self) {
+        let remaining = self.remaining();
+        if !remaining.is_zero() {
+            thread::sleep(remaining);
+        }
+    }
+
+    /// Returns the wall time since `start` at which the recorded steps are done.
+    fn target(&self) -> Duration {
+        self.time.div_f64(self.speed)
+    }
+}
+
+fn clamp_speed(speed: f64) -> f64 {
+    if speed.is_nan() {
+        1.0
+    } else {
+        speed.clamp(Throttle::MIN_SPEED, Throttle::MAX_SPEED)
+    }
+}
+
+mod tests {
+    #[test]
+    fn sleeps_scale_with_speed() {
+        use crate::throttle::Throttle;
+        use std::time::Duration;
+
+        let mut throttle = Throttle::new(0.1);
+        assert_eq!(throttle.speed(), Throttle::MIN_SPEED);
+        throttle.tick(false);
+        assert_eq!(throttle.remaining(), Duration::ZERO);
+        throttle.tick(true);
+        assert!(throttle.remaining() > Duration::from_millis(60));
+
+        throttle.set_speed(100.0);
+        assert_eq!(throttle.speed(), Throttle::MAX_SPEED);
+        assert_eq!(throttle.remaining(), Duration::ZERO);
+        throttle.set_commands_per_second(Some(1.0));
+        throttle.tick(false);
+        assert!(throttle.remaining() > Duration::from_millis(50));
+        assert!(throttle.remaining() <= Duration::from_millis(63));
+    }
+}
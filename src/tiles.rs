@@ -0,0 +1,98 @@
+use crate::buffer::CFRBuffer;
+
+/// A tile of a Deep Zoom image pyramid, as produced by `CFRBuffer::export_tiles`.
+#[derive(Debug, Clone)]
+pub struct Tile {
+    /// Pyramid level: level 0 is a single pixel, the highest level is the full canvas.
+    pub level: u32,
+    pub column: u32,
+    pub row: u32,
+    pub buffer: CFRBuffer,
+}
+
+impl Tile {
+    /// Returns the path of the tile relative to the `<name>_files` directory, e.g. `8/1_0`,
+    /// without an extension.
+    pub fn path(&self) -> String {
+        format!("{}/{}_{}", self.level, self.column, self.row)
+    }
+}
+
+/// Returns the number of the highest (full resolution) level of the pyramid of a
+/// `width`×`height` canvas.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::tiles::max_level;
+///
+/// assert_eq!(max_level(1, 1), 0);
+/// assert_eq!(max_level(256, 100), 8);
+/// assert_eq!(max_level(257, 100), 9);
+/// ```
+pub fn max_level(width: u32, height: u32) -> u32 {
+    width
+        .max(height)
+        .max(1)
+        .next_power_of_two()
+        .trailing_zeros()
+}
+
+/// Returns the Deep Zoom (`.dzi`) descriptor of a tiled `width`×`height` canvas whose tiles
+/// are stored as `format` files (e.g. `png`).
+///
+/// Viewers such as OpenSeadragon load the tiles from the `<name>_files` directory next to
+/// the `<name>.dzi` descriptor.
+pub fn dzi_descriptor(width: u32, height: u32, tile_size: u32, format: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" \
+         Format=\"{}\" Overlap=\"0\" TileSize=\"{}\">\n  \
+         <Size Width=\"{}\" Height=\"{}\"/>\n\
+         </Image>\n",
+        format, tile_size, width, height
+    )
+}
+
+/// Halves the buffer in both dimensions (rounding up), keeping the top-left pixel of every
+/// 2×2 block so the palette colors stay crisp.
+pub(crate) fn downsample(buffer: &CFRBuffer) -> CFRBuffer {
+    let width = buffer.width.div_ceil(2);
+    let height = buffer.height.div_ceil(2);
+    let mut data = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        let row = (2 * y * buffer.width) as usize;
+        data.extend((0..width).map(|x| buffer.data[row + 2 * x as usize]));
+    }
+    CFRBuffer {
+        width,
+        height,
+        data,
+    }
+}
+
+mod tests {
+    #[test]
+    fn pyramid_levels() {
+        use crate::tiles::max_level;
+        use crate::{CFRBuffer, CFRColor};
+
+        let mut buffer = CFRBuffer::new(5, 3);
+        buffer.data[4] = CFRColor::Red;
+        let tiles = buffer.export_tiles(2);
+
+        assert_eq!(max_level(5, 3), 3);
+        let level = |l: u32| tiles.iter().filter(move |t| t.level == l);
+        assert_eq!(level(3).count(), 3 * 2);
+        assert_eq!(level(2).count(), 2);
+        assert_eq!(level(1).count(), 1);
+        assert_eq!(level(0).count(), 1);
+
+        let last = level(3).find(|t| (t.column, t.row) == (2, 1)).unwrap();
+        assert_eq!((last.buffer.width, last.buffer.height), (1, 1));
+        let corner = level(3).find(|t| (t.column, t.row) == (2, 0)).unwrap();
+        assert_eq!(corner.buffer.data, vec![CFRColor::Red, CFRColor::Black]);
+        let half = level(2).find(|t| t.column == 1).unwrap();
+        assert_eq!(half.buffer.data[0], CFRColor::Red);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::html::json_string;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// The area of the canvas that changed since the previous frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where in the run a frame was captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineFrame {
+    /// The steps executed since the previous frame; the frame shows the canvas after
+    /// `steps.end` steps.
+    pub steps: Range<u64>,
+    /// How long the frame is shown, in milliseconds.
+    pub delay: u32,
+    /// The changed area, or `None` if the frame is the same as the previous one.
+    pub dirty: Option<DirtyRect>,
+    /// The index of the program that was running, for runs of several programs.
+    pub program: usize,
+    /// The byte offset of the last executed command in its program.
+    pub offset: usize,
+    /// The last executed command.
+    pub command: Option<char>,
+}
+
+/// The step range, delay and changed area of every frame of an animation, which lets
+/// players seek to a step and show the command that drew a frame.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::timeline::{DirtyRect, Timeline};
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut frame = CFRBuffer::new(4, 4);
+/// let mut timeline = Timeline::new(&frame);
+/// frame.data[6] = CFRColor::Red;
+/// timeline.push(&frame, 3, 100, 0, 2, Some('F'));
+/// timeline.push(&frame, 8, 100, 0, 7, Some('S'));
+///
+/// assert_eq!(timeline.frames[0].steps, 0..3);
+/// assert_eq!(
+///     timeline.frames[0].dirty,
+///     Some(DirtyRect { x: 2, y: 1, width: 1, height: 1 })
+/// );
+/// assert_eq!(timeline.frames[1].dirty, None);
+/// assert_eq!(timeline.frame_at_step(5), Some(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<TimelineFrame>,
+    last: Vec<CFRColor>,
+}
+
+impl Timeline {
+    /// Creates an empty timeline of an animation that starts on `canvas`.
+    pub fn new(canvas: &CFRBuffer) -> Self {
+        Self {
+            width: canvas.width,
+            height: canvas.height,
+            frames: Vec::new(),
+            last: canvas.data.clone(),
+        }
+    }
+
+    /// Appends a frame captured after `step` steps, with the last executed `command` at
+    /// `offset` in the program at index `program`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame does not have the dimensions of the canvas.
+    pub fn push(
+        &mut self,
+        frame: &CFRBuffer,
+        step: u64,
+        delay: u32,
+        program: usize,
+        offset: usize,
+        command: Option<char>,
+    ) {
+        assert_eq!(
+            (frame.width, frame.height),
+            (self.width, self.height),
+            "Frame dimensions differ from the canvas"
+        );
+        let start = self.frames.last().map_or(0, |f| f.steps.end);
+        let dirty = dirty_rect(&self.last, &frame.data, self.width);
+        self.last.clone_from(&frame.data);
+        self.frames.push(TimelineFrame {
+            steps: start.min(step)..step,
+            delay,
+            dirty,
+            program,
+            offset,
+            command,
+        });
+    }
+
+    /// Returns the index of the first frame that shows the canvas after `step` steps, or
+    /// `None` if the animation ends before it.
+    pub fn frame_at_step(&self, step: u64) -> Option<usize> {
+        let index = self.frames.partition_point(|f| f.steps.end < step);
+        (index < self.frames.len()).then_some(index)
+    }
+
+    /// Returns the time in milliseconds at which the frame at `index` is shown.
+    pub fn time_of(&self, index: usize) -> u64 {
+        self.frames[..index.min(self.frames.len())]
+            .iter()
+            .map(|f| f.delay as u64)
+            .sum()
+    }
+
+    /// Writes the timeline as JSON, with one object per frame:
+    ///
+    /// ```json
+    /// {"width":4,"height":4,"frames":[{"index":0,"time":0,"steps":[0,3],"delay":100,
+    /// "dirty":{"x":2,"y":1,"width":1,"height":1},"program":0,"offset":2,"command":"F"}]}
+    /// ```
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "{{\"width\":{},\"height\":{},\"frames\":[",
+            self.width, self.height
+        )?;
+        let mut time = 0u64;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let dirty = match frame.dirty {
+                Some(r) => format!(
+                    "{{\"x\":{},\"y\":{},\"width\":{},\"height\":{}}}",
+                    r.x, r.y, r.width, r.height
+                ),
+                None => "null".to_string(),
+            };
+            let command = match frame.command {
+                Some(c) => json_string(&c.to_string()),
+                None => "null".to_string(),
+            };
+            write!(
+                writer,
+                "{}{{\"index\":{},\"time\":{},\"steps\":[{},{}],\"delay\":{},\"dirty\":{},\"program\":{},\"offset\":{},\"command\":{}}}",
+                if i == 0 { "" } else { "," },
+                i,
+                time,
+                frame.steps.start,
+                frame.steps.end,
+                frame.delay,
+                dirty,
+                frame.program,
+                frame.offset,
+                command
+            )?;
+            time += frame.delay as u64;
+        }
+        writer.write_all(b"]}")?;
+        writer.flush()
+    }
+}
+
+/// Returns the bounding box of the pixels that differ between two canvases of `width`
+/// columns.
+fn dirty_rect(before: &[CFRColor], after: &[CFRColor], width: u32) -> Option<DirtyRect> {
+    let width = width.max(1) as usize;
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+    for (i, _) in before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+    {
+        let (x, y) = (i % width, i / width);
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x + 1);
+        bottom = bottom.max(y + 1);
+    }
+    (left != usize::MAX).then(|| DirtyRect {
+        x: left as u32,
+        y: top as u32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+mod tests {
+    #[test]
+    fn timeline_json() {
+        use crate::timeline::Timeline;
+        use crate::{CFRBuffer, CFRColor};
+
+        let mut frame = CFRBuffer::new(3, 2);
+        let mut timeline = Timeline::new(&frame);
+        frame.data[0] = CFRColor::Red;
+        frame.data[5] = CFRColor::Red;
+        timeline.push(&frame, 4, 100, 0, 3, Some('F'));
+        timeline.push(&frame, 4, 50, 1, 0, None);
+        assert_eq!(timeline.time_of(1), 100);
+        assert_eq!(timeline.frame_at_step(4), Some(0));
+        assert_eq!(timeline.frame_at_step(5), None);
+
+        let mut json = Vec::new();
+        timeline.write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            concat!(
+                r#"{"width":3,"height":2,"frames":["#,
+                r#"{"index":0,"time":0,"steps":[0,4],"delay":100,"dirty":{"x":0,"y":0,"width":3,"height":2},"program":0,"offset":3,"command":"F"},"#,
+                r#"{"index":1,"time":100,"steps":[4,4],"delay":50,"dirty":null,"program":1,"offset":0,"command":null}]}"#
+            )
+        );
+    }
+}
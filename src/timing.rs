@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+/// How long every command takes in animations, in ticks of one millisecond.
+///
+/// By default only `S` takes time, 20 ticks like the spec says, so animations advance at the
+/// pace of the sleeps. Giving `F` a duration too, e.g. `F=1,S=20`, plays programs that rarely
+/// sleep at the speed the painter moves.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::timing::Timing;
+///
+/// let timing: Timing = "F=1,S=20".parse().unwrap();
+/// assert_eq!(timing.duration('F'), 1);
+/// assert_eq!(timing.duration('S'), 20);
+/// assert_eq!(timing.duration('R'), 0);
+/// assert_eq!(Timing::default().duration('F'), 0);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timing {
+    pub color: u32,
+    pub forward: u32,
+    pub rotate: u32,
+    pub sleep: u32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            color: 0,
+            forward: 0,
+            rotate: 0,
+            sleep: 20,
+        }
+    }
+}
+
+impl Timing {
+    /// Returns the ticks `command` takes; anything but `C`, `F`, `R`, and `S` takes none.
+    pub fn duration(&self, command: char) -> u32 {
+        match command {
+            'C' => self.color,
+            'F' => self.forward,
+            'R' => self.rotate,
+            'S' => self.sleep,
+            _ => 0,
+        }
+    }
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    /// Parses comma-separated durations like `F=1,S=20`. Commands that are not listed keep
+    /// their default duration.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut timing = Timing::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let invalid = || format!("Invalid timing: {}", part);
+            let (command, ticks) = part.split_once('=').ok_or_else(invalid)?;
+            let ticks = ticks.trim().parse().map_err(|_| invalid())?;
+            let duration = match command.trim().to_uppercase().as_str() {
+                "C" => &mut timing.color,
+                "F" => &mut timing.forward,
+                "R" => &mut timing.rotate,
+                "S" => &mut timing.sleep,
+                _ => return Err(invalid()),
+            };
+            *duration = ticks;
+        }
+        Ok(timing)
+    }
+}
+
+/// Accumulates the ticks of executed commands and tells when the next frame of an animation
+/// with frames every `interval` ticks is due, and how long it is shown.
+///
+/// A frame is due once at least `interval` ticks have passed since the previous one. Its
+/// delay is all of those ticks, so a command that takes longer than `interval` makes one
+/// longer frame, and the delays always add up to the time of the program.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::timing::{FrameClock, Timing};
+///
+/// let mut clock = FrameClock::new(Timing::default(), 40);
+/// let due: Vec<Option<u32>> = "SFSS".chars().map(|c| clock.tick(c)).collect();
+/// assert_eq!(due, vec![None, None, Some(40), None]);
+/// assert_eq!(clock.elapsed(), 60);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    timing: Timing,
+    interval: u32,
+    elapsed: u64,
+    /// Ticks since the last frame.
+    pending: u64,
+}
+
+impl FrameClock {
+    pub fn new(timing: Timing, interval: u32) -> Self {
+        Self {
+            timing,
+            interval,
+            elapsed: 0,
+            pending: 0,
+        }
+    }
+
+    /// Adds the duration of the executed `command` and returns the delay of the frame that
+    /// is due, if any: the ticks since the previous frame.
+    pub fn tick(&mut self, command: char) -> Option<u32> {
+        let duration = self.timing.duration(command) as u64;
+        self.elapsed += duration;
+        self.pending += duration;
+        if duration == 0 || self.pending < self.interval as u64 {
+            return None;
+        }
+        let delay = self.pending.min(u32::MAX as u64) as u32;
+        self.pending = 0;
+        Some(delay)
+    }
+
+    /// Returns the ticks of all commands so far.
+    pub fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+}
+
+mod tests {
+    #[test]
+    fn forward_moves_take_time() {
+        use crate::timing::{FrameClock, Timing};
+
+        let timing: Timing = "f=1, s=20".parse().unwrap();
+        let mut clock = FrameClock::new(timing, 10);
+        let due: Vec<u32> = "FFFFFFFFFFRF"
+            .chars()
+            .filter_map(|c| clock.tick(c))
+            .collect();
+        assert_eq!(due, vec![10]);
+        assert_eq!(clock.elapsed(), 11);
+        assert_eq!(clock.tick('S'), Some(21));
+
+        assert!("F".parse::<Timing>().is_err());
+        assert!("X=1".parse::<Timing>().is_err());
+        assert_eq!("".parse::<Timing>(), Ok(Timing::default()));
+    }
+
+    #[test]
+    fn slow_commands_make_longer_frames() {
+        use crate::timing::{FrameClock, Timing};
+
+        let timing: Timing = "F=1,S=100".parse().unwrap();
+        let mut clock = FrameClock::new(timing, 20);
+        let delays: Vec<u32> = "FSFFSSF".chars().filter_map(|c| clock.tick(c)).collect();
+        assert_eq!(delays, vec![101, 102, 100]);
+        assert_eq!(
+            delays.iter().map(|&d| d as u64).sum::<u64>(),
+            clock.elapsed() - 1
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use crate::enums::CFRColor;
+use crate::executor::CommandExecutor;
+use crate::html::json_string;
+use crate::sink::AsPixelSink;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// The file format of a `Trace`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// The JSON of Chrome's `about:tracing`, which [Perfetto](https://ui.perfetto.dev) opens too.
+    #[default]
+    Chrome,
+}
+
+/// Converts `chrome` to a `TraceFormat`.
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chrome" | "perfetto" => Ok(TraceFormat::Chrome),
+            _ => Err(format!("Invalid trace format: {}", s)),
+        }
+    }
+}
+
+/// What happened at a step of a `Trace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEventKind {
+    /// A pass through the block whose `[` is at `offset` starts; blocks run twice, or as
+    /// often as the dialect says.
+    Begin {
+        offset: usize,
+        pass: u32,
+        /// The start of the block's source, for telling blocks apart.
+        source: String,
+    },
+    /// The innermost pass ends.
+    End,
+    /// An `F` drew a pixel.
+    Draw { x: u32, y: u32, color: CFRColor },
+    /// An `S`.
+    Sleep,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// The number of steps executed, including this one.
+    pub step: u64,
+    /// The index of the program that was running, for runs of several programs.
+    pub program: usize,
+    pub kind: TraceEventKind,
+}
+
+/// The block passes, draws, and sleeps of a run, which trace viewers show on a timeline
+/// where every step takes a microsecond, to find the blocks a long program spends its steps
+/// in.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::trace::{Trace, TraceEventKind};
+/// use cfrs::{CFRBuffer, CommandExecutor};
+///
+/// let mut executor = CommandExecutor::new("[FS]".to_string(), CFRBuffer::new(5, 5));
+/// let trace = Trace::capture(&mut executor).unwrap();
+/// let passes = trace
+///     .events
+///     .iter()
+///     .filter(|e| matches!(e.kind, TraceEventKind::Begin { .. }))
+///     .count();
+/// assert_eq!(passes, 2);
+///
+/// let mut json = Vec::new();
+/// trace.write_chrome(&mut json).unwrap();
+/// assert!(String::from_utf8(json).unwrap().starts_with(r#"{"traceEvents":["#));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    /// The passes that have not ended, innermost last, or `None` for blocks that lenient
+    /// mode skips.
+    open: Vec<Option<(usize, u32, String)>>,
+    program: usize,
+    step: u64,
+}
+
+/// How many characters of a block's source its events show.
+const SOURCE_LEN: usize = 24;
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the program of `executor` to the end, tracing every step.
+    ///
+    /// Returns an error if the program fails, e.g. at an unmatched `]`.
+    pub fn capture<B: AsPixelSink>(
+        executor: &mut CommandExecutor<B>,
+    ) -> Result<Self, &'static str> {
+        let mut trace = Self::new();
+        let mut step = 0;
+        loop {
+            let view = executor.view();
+            let (offset, command, depth) = (view.index, view.next_command(), view.depth());
+            match executor.step() {
+                Ok(_) => {}
+                Err("End of commands") => return Ok(trace),
+                Err(e) => return Err(e),
+            }
+            step += 1;
+            trace.record(step, 0, offset, command, depth, executor);
+        }
+    }
+
+    /// Traces a step of the program at index `program` that executed `command` at
+    /// `offset` with `depth` blocks open, given the executor after the step.
+    pub fn record<B: AsPixelSink>(
+        &mut self,
+        step: u64,
+        program: usize,
+        offset: usize,
+        command: Option<char>,
+        depth: usize,
+        executor: &CommandExecutor<B>,
+    ) {
+        if program != self.program {
+            self.end_all();
+            self.program = program;
+        }
+        self.step = step;
+        match command {
+            Some('[') if executor.view().depth() > depth => {
+                let source = executor.state().commands[offset..]
+                    .chars()
+                    .take(SOURCE_LEN)
+                    .map(|c| if c == '|' { ']' } else { c })
+                    .collect();
+                self.begin(offset, 1, source);
+            }
+            Some('[') => self.open.push(None),
+            Some(']') => {
+                if let Some((start, pass, source)) = self.open.pop().flatten() {
+                    self.push(TraceEventKind::End);
+                    if executor.view().index <= offset {
+                        self.begin(start, pass + 1, source);
+                    }
+                }
+            }
+            Some('F') => {
+                let painter = executor.painter();
+                self.push(TraceEventKind::Draw {
+                    x: painter.x,
+                    y: painter.y,
+                    color: painter.color,
+                });
+            }
+            Some('S') => self.push(TraceEventKind::Sleep),
+            _ => {}
+        }
+    }
+
+    fn begin(&mut self, offset: usize, pass: u32, source: String) {
+        self.push(TraceEventKind::Begin {
+            offset,
+            pass,
+            source: source.clone(),
+        });
+        self.open.push(Some((offset, pass, source)));
+    }
+
+    fn push(&mut self, kind: TraceEventKind) {
+        self.events.push(TraceEvent {
+            step: self.step,
+            program: self.program,
+            kind,
+        });
+    }
+
+    /// Ends the passes that are still open, e.g. because the run stopped early.
+    fn end_all(&mut self) {
+        while let Some(pass) = self.open.pop() {
+            if pass.is_some() {
+                self.push(TraceEventKind::End);
+            }
+        }
+    }
+
+    /// Writes the trace in `format`.
+    pub fn write<W: Write>(&self, writer: W, format: TraceFormat) -> io::Result<()> {
+        match format {
+            TraceFormat::Chrome => self.write_chrome(writer),
+        }
+    }
+
+    /// Writes the trace as Chrome trace JSON, with a thread per program, a span per block
+    /// pass, and an instant per draw and sleep. Passes that are still open end at the last
+    /// step.
+    pub fn write_chrome<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut trace = self.clone();
+        trace.end_all();
+        writer.write_all(b"{\"traceEvents\":[")?;
+        let mut programs = trace.events.iter().map(|e| e.program).collect::<Vec<_>>();
+        programs.dedup();
+        for (i, program) in programs.iter().enumerate() {
+            write!(
+                writer,
+                "{}{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"program {}\"}}}}",
+                if i == 0 { "" } else { "," },
+                program,
+                program + 1
+            )?;
+        }
+        let mut names = Vec::new();
+        for event in &trace.events {
+            let (name, category, phase, args) = match &event.kind {
+                TraceEventKind::Begin {
+                    offset,
+                    pass,
+                    source,
+                } => {
+                    let name = format!("block at {}", offset);
+                    names.push(name.clone());
+                    let args = format!(
+                        "{{\"offset\":{},\"pass\":{},\"source\":{}}}",
+                        offset,
+                        pass,
+                        json_string(source)
+                    );
+                    (name, "block", "B", Some(args))
+                }
+                TraceEventKind::End => {
+                    let name = names.pop().unwrap_or_default();
+                    (name, "block", "E", None)
+                }
+                TraceEventKind::Draw { x, y, color } => {
+                    let args = format!(
+                        "{{\"x\":{},\"y\":{},\"color\":\"{}\"}}",
+                        x,
+                        y,
+                        color.to_string().to_lowercase()
+                    );
+                    ("F".to_string(), "draw", "i", Some(args))
+                }
+                TraceEventKind::Sleep => ("S".to_string(), "sleep", "i", None),
+            };
+            write!(
+                writer,
+                "{}{{\"name\":{},\"cat\":\"{}\",\"ph\":\"{}\",{}\"ts\":{},\"pid\":1,\"tid\":{}{}}}",
+                if programs.is_empty() { "" } else { "," },
+                json_string(&name),
+                category,
+                phase,
+                if phase == "i" { "\"s\":\"t\"," } else { "" },
+                event.step,
+                event.program,
+                args.map_or(String::new(), |args| format!(",\"args\":{}", args))
+            )?;
+        }
+        writer.write_all(b"],\"displayTimeUnit\":\"ns\"}")?;
+        writer.flush()
+    }
+}
+
+mod tests {
+    #[test]
+    fn passes_nest_and_repeat() {
+        use crate::trace::{Trace, TraceEventKind};
+        use crate::{CFRBuffer, CommandExecutor, Dialect};
+
+        let begins = |trace: &Trace| -> Vec<(usize, u32)> {
+            trace
+                .events
+                .iter()
+                .filter_map(|e| match &e.kind {
+                    TraceEventKind::Begin { offset, pass, .. } => Some((*offset, *pass)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut executor = CommandExecutor::new("[[F]]".to_string(), CFRBuffer::new(8, 8));
+        let trace = Trace::capture(&mut executor).unwrap();
+        assert_eq!(
+            begins(&trace),
+            [(0, 1), (1, 1), (1, 2), (0, 2), (1, 1), (1, 2)]
+        );
+        let ends = trace
+            .events
+            .iter()
+            .filter(|e| e.kind == TraceEventKind::End);
+        assert_eq!(ends.count(), 6);
+        assert_eq!(trace.events.last().unwrap().kind, TraceEventKind::End);
+
+        let mut executor = CommandExecutor::new("[FS]".to_string(), CFRBuffer::new(8, 8));
+        executor.set_dialect(Dialect::Repeat(3));
+        let trace = Trace::capture(&mut executor).unwrap();
+        assert_eq!(begins(&trace), [(0, 1), (0, 2), (0, 3)]);
+
+        let mut executor = CommandExecutor::new("[[F]]".to_string(), CFRBuffer::new(8, 8));
+        executor.set_max_depth(Some(1));
+        executor.set_lenient(true);
+        let trace = Trace::capture(&mut executor).unwrap();
+        assert_eq!(begins(&trace), [(0, 1), (0, 2)]);
+
+        let mut executor = CommandExecutor::new("[F[S".to_string(), CFRBuffer::new(8, 8));
+        let trace = Trace::capture(&mut executor).unwrap();
+        let mut json = Vec::new();
+        trace.write_chrome(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.matches("\"ph\":\"B\"").count(), 2);
+        assert_eq!(json.matches("\"ph\":\"E\"").count(), 2);
+        assert!(json.contains(
+            r#"{"name":"S","cat":"sleep","ph":"i","s":"t","ts":4,"pid":1,"tid":0},{"name":"block at 2","cat":"block","ph":"E","ts":4,"pid":1,"tid":0}"#
+        ));
+        assert!(json.contains(r#""args":{"offset":0,"pass":1,"source":"[F[S"}"#));
+        assert!("perfetto".parse::<crate::trace::TraceFormat>().is_ok());
+    }
+}
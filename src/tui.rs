@@ -0,0 +1,135 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+use crate::viewport::Viewport;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::widgets::{Block, Widget};
+
+/// A ratatui widget that renders a `CFRBuffer` using half-block cells.
+///
+/// Every terminal cell shows two vertically stacked pixels (`▀` with the upper pixel as
+/// foreground and the lower pixel as background). The canvas is scaled with nearest-neighbor
+/// sampling to the largest size that fits the area while keeping its aspect ratio, and is
+/// centered within the area.
+///
+/// # Examples
+///
+/// ```ignore
+/// use cfrs::tui::CfrsCanvas;
+/// use ratatui::widgets::{Block, Borders};
+///
+/// terminal.draw(|frame| {
+///     let canvas = CfrsCanvas::new(&buffer).block(Block::default().borders(Borders::ALL));
+///     frame.render_widget(canvas, frame.size());
+/// })?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct CfrsCanvas<'a> {
+    buffer: &'a CFRBuffer,
+    block: Option<Block<'a>>,
+    viewport: Viewport,
+}
+
+impl<'a> CfrsCanvas<'a> {
+    /// Creates a widget that renders the given buffer.
+    pub fn new(buffer: &'a CFRBuffer) -> Self {
+        Self {
+            buffer,
+            block: None,
+            viewport: Viewport::default(),
+        }
+    }
+
+    /// Surrounds the canvas with a block (borders, title, ...).
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Shows the canvas through a viewport, e.g. one panned and zoomed with the keyboard.
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+}
+
+fn to_color(color: CFRColor) -> Color {
+    let [r, g, b] = color.to_rgb();
+    Color::Rgb(r, g, b)
+}
+
+impl Widget for CfrsCanvas<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        let view = self.viewport.apply(self.buffer);
+        let canvas = &view;
+        if area.area() == 0 || canvas.width == 0 || canvas.height == 0 {
+            return;
+        }
+
+        // Pixel resolution available in the area: one column and two rows per cell.
+        let available_width = area.width as f64;
+        let available_height = area.height as f64 * 2.0;
+        let scale =
+            (available_width / canvas.width as f64).min(available_height / canvas.height as f64);
+        let width = ((canvas.width as f64 * scale) as u16).max(1);
+        let height = ((canvas.height as f64 * scale) as u32).max(1);
+
+        let left = area.x + (area.width - width) / 2;
+        let top = area.y + (area.height - height.div_ceil(2) as u16) / 2;
+
+        let sample = |x: u16, y: u32| {
+            let sx = ((x as f64 / scale) as u32).min(canvas.width - 1);
+            let sy = ((y as f64 / scale) as u32).min(canvas.height - 1);
+            canvas.data[(sy * canvas.width + sx) as usize]
+        };
+
+        for row in 0..height.div_ceil(2) {
+            for x in 0..width {
+                let upper = row * 2;
+                let cell = buf.get_mut(left + x, top + row as u16);
+                cell.set_char('▀').set_fg(to_color(sample(x, upper)));
+                if upper + 1 < height {
+                    cell.set_bg(to_color(sample(x, upper + 1)));
+                } else {
+                    cell.set_bg(Color::Reset);
+                }
+            }
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn render_half_blocks() {
+        use crate::tui::CfrsCanvas;
+        use crate::{CFRBuffer, CFRColor};
+        use ratatui::buffer::Buffer;
+        use ratatui::layout::Rect;
+        use ratatui::style::Color;
+        use ratatui::widgets::Widget;
+
+        let mut buffer = CFRBuffer::new(2, 2);
+        buffer.data[0] = CFRColor::White;
+        buffer.data[3] = CFRColor::Red;
+
+        let area = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::empty(area);
+        CfrsCanvas::new(&buffer).render(area, &mut buf);
+
+        assert_eq!(buf.get(0, 0).symbol(), "▀");
+        assert_eq!(buf.get(0, 0).fg, Color::Rgb(255, 255, 255));
+        assert_eq!(buf.get(0, 0).bg, Color::Rgb(0, 0, 0));
+        assert_eq!(buf.get(1, 0).fg, Color::Rgb(0, 0, 0));
+        assert_eq!(buf.get(1, 0).bg, Color::Rgb(255, 0, 0));
+    }
+}
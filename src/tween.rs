@@ -0,0 +1,71 @@
+use crate::buffer::CFRBuffer;
+use crate::enums::CFRColor;
+
+/// Emits in-between frames that replay the pixels drawn between two captured frames in
+/// the order they were drawn.
+///
+/// Record every drawn pixel with `draw`, then call `capture` where a frame would be
+/// captured. With a slow frame rate, this turns a jump of many strokes into a smooth
+/// animation of the painter.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::tween::Tweener;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut tweener = Tweener::new(&CFRBuffer::new(4, 1), 1);
+/// for x in 0..4 {
+///     tweener.draw(x, 0, CFRColor::Red);
+/// }
+/// let frames = tweener.capture();
+/// assert_eq!(frames.len(), 2);
+/// // The in-between frame shows the first half of the strokes.
+/// assert_eq!(frames[0].data[1], CFRColor::Red);
+/// assert_eq!(frames[0].data[2], CFRColor::Black);
+/// assert!(frames[1].data.iter().all(|c| *c == CFRColor::Red));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tweener {
+    frame: CFRBuffer,
+    draws: Vec<(usize, CFRColor)>,
+    steps: usize,
+}
+
+impl Tweener {
+    /// Creates a `Tweener` starting from `frame`, with `steps` in-between frames per capture.
+    pub fn new(frame: &CFRBuffer, steps: usize) -> Self {
+        Self {
+            frame: frame.clone(),
+            draws: Vec::new(),
+            steps,
+        }
+    }
+
+    /// Records that the pixel at (`x`, `y`) was set to `color`.
+    pub fn draw(&mut self, x: u32, y: u32, color: CFRColor) {
+        self.draws
+            .push(((y * self.frame.width + x) as usize, color));
+    }
+
+    /// Returns the in-between frames followed by the frame with every recorded pixel drawn.
+    ///
+    /// The recorded pixels are spread evenly over the frames. Capturing without any recorded
+    /// pixels returns the same frame `steps + 1` times, so the timing of the animation is kept.
+    pub fn capture(&mut self) -> Vec<CFRBuffer> {
+        let total = self.draws.len();
+        let mut drawn = 0;
+        let frames = (1..=self.steps + 1)
+            .map(|step| {
+                let until = total * step / (self.steps + 1);
+                for &(i, color) in &self.draws[drawn..until] {
+                    self.frame.data[i] = color;
+                }
+                drawn = until;
+                self.frame.clone()
+            })
+            .collect();
+        self.draws.clear();
+        frames
+    }
+}
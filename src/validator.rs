@@ -0,0 +1,145 @@
+use crate::parser::{tokenize, TokenKind};
+use std::fmt::Display;
+use std::ops::Range;
+
+/// The severity of a `Diagnostic`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    /// The program fails to run (e.g. an unmatched `]`).
+    Error,
+    /// The program runs, but probably not as intended.
+    Warning,
+}
+
+/// A problem found in a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Byte range of the offending characters.
+    pub span: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{} at {}: {}", severity, self.span.start, self.message)
+    }
+}
+
+/// Checks a program for problems without running it.
+///
+/// Reports unmatched `]` as errors, and unclosed `[` and characters that are not commands
+/// (whitespace excluded) as warnings. With `comments`, `#` comments are skipped, as
+/// `tokenize` does.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::validator::{validate, Severity};
+///
+/// assert!(validate("[CF RS] # comment", true).is_empty());
+/// assert_eq!(validate("[CF RS] # comment", false).len(), 8);
+///
+/// let diagnostics = validate("F]x", false);
+/// assert_eq!(diagnostics.len(), 2);
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// assert_eq!(diagnostics[0].span, 1..2);
+/// assert_eq!(diagnostics[1].severity, Severity::Warning);
+/// ```
+pub fn validate(program: &str, comments: bool) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut open = Vec::new();
+
+    for token in tokenize(program, comments) {
+        match token.kind {
+            TokenKind::Command | TokenKind::Comment => {}
+            TokenKind::BlockStart => open.push(token.span),
+            TokenKind::BlockEnd => {
+                if open.pop().is_none() {
+                    diagnostics.push(Diagnostic {
+                        span: token.span,
+                        severity: Severity::Error,
+                        message: "Unmatched ]".to_string(),
+                    });
+                }
+            }
+            TokenKind::Invalid => diagnostics.push(Diagnostic {
+                message: format!(
+                    "Unknown command '{}' is ignored",
+                    &program[token.span.clone()]
+                ),
+                span: token.span,
+                severity: Severity::Warning,
+            }),
+        }
+    }
+
+    for span in open {
+        diagnostics.push(Diagnostic {
+            span,
+            severity: Severity::Warning,
+            message: "Unclosed [".to_string(),
+        });
+    }
+
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+/// Returns the byte offsets of every matched `[` and `]` pair, ordered by the `[`. With
+/// `comments`, brackets in `#` comments are skipped, as `tokenize` does.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::validator::bracket_pairs;
+///
+/// assert_eq!(bracket_pairs("[F[R]]", false), vec![(0, 5), (2, 4)]);
+/// assert_eq!(bracket_pairs("[F # ]\n]", true), vec![(0, 7)]);
+/// ```
+pub fn bracket_pairs(program: &str, comments: bool) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut open = Vec::new();
+
+    for token in tokenize(program, comments) {
+        match token.kind {
+            TokenKind::BlockStart => open.push(token.span.start),
+            TokenKind::BlockEnd => {
+                if let Some(start) = open.pop() {
+                    pairs.push((start, token.span.start));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pairs.sort();
+    pairs
+}
+
+mod tests {
+    #[test]
+    fn unclosed_and_unmatched_brackets() {
+        use crate::validator::{validate, Severity};
+
+        let diagnostics = validate("][[F]", false);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].span, 0..1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[1].span, 1..2);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn multibyte_characters() {
+        use crate::validator::validate;
+
+        let diagnostics = validate("Fé", false);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, 1..3);
+    }
+}
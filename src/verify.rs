@@ -0,0 +1,248 @@
+use crate::buffer::CFRBuffer;
+use crate::conformance;
+use crate::error::CFRError;
+use crate::limits::{run_untrusted, ResourceLimits};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A digest of a canvas, written as `sha256:<64 hex digits>` or `fnv1a:<16 hex digits>`.
+///
+/// Both hash the dimensions (little-endian) and the palette index of every pixel, so a
+/// digest doesn't depend on the image format or the colors of a theme.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Digest {
+    Sha256([u8; 32]),
+    /// The digest of `conformance::digest`.
+    Fnv1a(u64),
+}
+
+impl Digest {
+    /// Returns the SHA-256 digest of `buffer`.
+    pub fn sha256(buffer: &CFRBuffer) -> Self {
+        Digest::Sha256(sha256(&canvas_bytes(buffer)))
+    }
+
+    /// Returns the FNV-1a digest of `buffer`.
+    pub fn fnv1a(buffer: &CFRBuffer) -> Self {
+        Digest::Fnv1a(conformance::digest(buffer))
+    }
+
+    /// Returns the digest of `buffer` of the same kind as `self`.
+    pub fn same_kind_of(&self, buffer: &CFRBuffer) -> Self {
+        match self {
+            Digest::Sha256(_) => Digest::sha256(buffer),
+            Digest::Fnv1a(_) => Digest::fnv1a(buffer),
+        }
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Digest::Sha256(hash) => {
+                write!(f, "sha256:")?;
+                hash.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Digest::Fnv1a(hash) => write!(f, "fnv1a:{:016x}", hash),
+        }
+    }
+}
+
+/// Converts `sha256:<hex>` or `fnv1a:<hex>` to a `Digest`.
+impl FromStr for Digest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid digest: {}", s);
+        let (kind, hex) = s.split_once(':').ok_or_else(invalid)?;
+        match kind.to_lowercase().as_str() {
+            "sha256" if hex.len() == 64 => {
+                let mut hash = [0; 32];
+                for (i, byte) in hash.iter_mut().enumerate() {
+                    *byte = hex
+                        .get(i * 2..i * 2 + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                        .ok_or_else(invalid)?;
+                }
+                Ok(Digest::Sha256(hash))
+            }
+            "fnv1a" if hex.len() == 16 => u64::from_str_radix(hex, 16)
+                .map(Digest::Fnv1a)
+                .map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The dimensions and palette indices that a `Digest` hashes.
+fn canvas_bytes(buffer: &CFRBuffer) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + buffer.data.len());
+    bytes.extend(buffer.width.to_le_bytes());
+    bytes.extend(buffer.height.to_le_bytes());
+    bytes.extend(buffer.data.iter().map(|c| *c as u8));
+    bytes
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Returns the SHA-256 hash of `bytes`.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::verify::sha256;
+///
+/// assert_eq!(sha256(b"abc")[..4], [0xba, 0x78, 0x16, 0xbf]);
+/// ```
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((bytes.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut hash = [0; 32];
+    for (chunk, s) in hash.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_be_bytes());
+    }
+    hash
+}
+
+/// The result of `verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub expected: Digest,
+    /// The digest of the canvas, of the same kind as `expected`, or why the program failed.
+    pub actual: Result<Digest, CFRError>,
+}
+
+impl Verification {
+    /// Returns whether the program finished and drew the expected canvas.
+    pub fn passed(&self) -> bool {
+        self.actual.as_ref() == Ok(&self.expected)
+    }
+}
+
+/// Runs `program` under the spec rules, on a black `width` × `height` canvas with the
+/// standard dialect and no lenient parsing, and compares the digest of the canvas with
+/// `expected`. An unmatched `]` or exceeding `limits` fails the verification, so that
+/// a digest pins down both the image and that the program is well-formed.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::verify::{verify, Digest};
+/// use cfrs::{run_untrusted, ResourceLimits};
+///
+/// let limits = ResourceLimits::default();
+/// let canvas = run_untrusted(b"[[F]]", 64, 64, &limits).unwrap();
+/// let expected = Digest::sha256(&canvas);
+///
+/// assert!(verify(b"[[F]]", 64, 64, &expected, &limits).passed());
+/// assert!(!verify(b"[[F]]F", 64, 64, &expected, &limits).passed());
+/// assert!(verify(b"F]", 64, 64, &expected, &limits).actual.is_err());
+/// ```
+pub fn verify(
+    program: &[u8],
+    width: u32,
+    height: u32,
+    expected: &Digest,
+    limits: &ResourceLimits,
+) -> Verification {
+    Verification {
+        expected: *expected,
+        actual: run_untrusted(program, width, height, limits)
+            .map(|canvas| expected.same_kind_of(&canvas)),
+    }
+}
+
+mod tests {
+    #[test]
+    fn sha256_and_digest_strings() {
+        use crate::verify::{sha256, Digest};
+        use crate::CFRBuffer;
+        use std::str::FromStr;
+
+        let hex = |hash: [u8; 32]| {
+            hash.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        assert_eq!(
+            hex(sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex(sha256(&[b'a'; 1000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+
+        let buffer = CFRBuffer::new(4, 4);
+        for digest in [Digest::sha256(&buffer), Digest::fnv1a(&buffer)] {
+            assert_eq!(Digest::from_str(&digest.to_string()), Ok(digest));
+        }
+        assert_eq!(Digest::fnv1a(&buffer).to_string(), "fnv1a:c6a438a2986878d5");
+        assert!(Digest::from_str("sha256:abc").is_err());
+        assert!(Digest::from_str("md5:00000000000000000000000000000000").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::buffer::CFRBuffer;
+use std::str::FromStr;
+
+/// A pan and zoom of the canvas for exports and previews, which leaves the program and its
+/// canvas unchanged.
+///
+/// The view starts at `offset` and shows `1 / zoom` of the canvas in each direction,
+/// enlarged to the size of the canvas. Like the painter, it wraps around the edges.
+///
+/// # Examples
+///
+/// ```
+/// use cfrs::viewport::Viewport;
+/// use cfrs::{CFRBuffer, CFRColor};
+///
+/// let mut buffer = CFRBuffer::new(4, 4);
+/// buffer.data[5] = CFRColor::Red;
+/// let viewport: Viewport = "1,1,2".parse().unwrap();
+/// let view = viewport.apply(&buffer);
+/// assert_eq!(view.data[..2], [CFRColor::Red, CFRColor::Red]);
+/// assert_eq!(view.data[4..6], [CFRColor::Red, CFRColor::Red]);
+/// assert_eq!(view.data[2], CFRColor::Black);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Viewport {
+    /// The canvas pixel at the top-left corner of the view.
+    pub offset: (u32, u32),
+    /// How many times the view is enlarged; 0 is treated as 1.
+    pub zoom: u32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            offset: (0, 0),
+            zoom: 1,
+        }
+    }
+}
+
+impl Viewport {
+    /// The largest zoom of `zoom_in`.
+    pub const MAX_ZOOM: u32 = 64;
+
+    /// Returns the view of the buffer, which has the same size as the buffer.
+    pub fn apply(&self, buffer: &CFRBuffer) -> CFRBuffer {
+        let (width, height) = (buffer.width, buffer.height);
+        if *self == Self::default() || buffer.data.is_empty() {
+            return buffer.clone();
+        }
+        let zoom = self.zoom.max(1);
+        let mut data = Vec::with_capacity(buffer.data.len());
+        for y in 0..height {
+            let row = ((self.offset.1 as u64 + (y / zoom) as u64) % height as u64) as usize;
+            data.extend((0..width).map(|x| {
+                let column = (self.offset.0 as u64 + (x / zoom) as u64) % width as u64;
+                buffer.data[row * width as usize + column as usize]
+            }));
+        }
+        CFRBuffer {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Moves the view by `dx` and `dy` canvas pixels on a `width`×`height` canvas.
+    pub fn pan(&mut self, dx: i64, dy: i64, width: u32, height: u32) {
+        let wrap = |offset: u32, delta: i64, size: u32| {
+            (offset as i64 + delta).rem_euclid(size.max(1) as i64) as u32
+        };
+        self.offset = (
+            wrap(self.offset.0, dx, width),
+            wrap(self.offset.1, dy, height),
+        );
+    }
+
+    /// Changes the zoom, keeping the center of the view in place.
+    pub fn set_zoom(&mut self, zoom: u32, width: u32, height: u32) {
+        let zoom = zoom.max(1);
+        let old = self.zoom.max(1);
+        let shift = |size: u32| (size / old / 2) as i64 - (size / zoom / 2) as i64;
+        self.pan(shift(width), shift(height), width, height);
+        self.zoom = zoom;
+    }
+
+    /// Doubles the zoom, up to `MAX_ZOOM`.
+    pub fn zoom_in(&mut self, width: u32, height: u32) {
+        let zoom = (self.zoom.max(1) * 2).min(Self::MAX_ZOOM);
+        self.set_zoom(zoom, width, height);
+    }
+
+    /// Halves the zoom, down to showing the whole canvas.
+    pub fn zoom_out(&mut self, width: u32, height: u32) {
+        self.set_zoom(self.zoom / 2, width, height);
+    }
+
+    /// Returns how far a key press pans: an eighth of the visible part of the canvas.
+    pub fn pan_step(&self, width: u32, height: u32) -> (i64, i64) {
+        let zoom = self.zoom.max(1);
+        let step = |size: u32| (size / zoom / 8).max(1) as i64;
+        (step(width), step(height))
+    }
+}
+
+/// Converts `x,y,zoom` to a `Viewport`.
+impl FromStr for Viewport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers = s
+            .split(',')
+            .map(|n| n.trim().parse())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("Invalid viewport: {}", s))?;
+        let [x, y, zoom] = numbers[..] else {
+            return Err(format!("Expected x,y,zoom: {}", s));
+        };
+        if zoom == 0 {
+            return Err(format!("The zoom must be at least 1: {}", s));
+        }
+        Ok(Viewport {
+            offset: (x, y),
+            zoom,
+        })
+    }
+}
+
+mod tests {
+    #[test]
+    fn zoom_keeps_the_center() {
+        use crate::viewport::Viewport;
+
+        let mut viewport = Viewport::default();
+        viewport.zoom_in(64, 32);
+        assert_eq!(viewport.offset, (16, 8));
+        viewport.zoom_in(64, 32);
+        assert_eq!((viewport.offset, viewport.zoom), ((24, 12), 4));
+        viewport.pan(-30, 0, 64, 32);
+        assert_eq!(viewport.offset, (58, 12));
+        viewport.zoom_out(64, 32);
+        viewport.zoom_out(64, 32);
+        viewport.zoom_out(64, 32);
+        assert_eq!((viewport.offset, viewport.zoom), ((34, 0), 1));
+        assert!("1,2,0".parse::<Viewport>().is_err());
+    }
+}